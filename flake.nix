@@ -27,6 +27,10 @@
           buildInputs = with pkgs; [
             (rust-bin.stable.latest.default.override {
               extensions = [ "rust-src" ];
+              targets = [
+                "riscv32imc-unknown-none-elf"
+                "thumbv7em-none-eabihf"
+              ];
             })
           ];
         };
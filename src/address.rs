@@ -0,0 +1,166 @@
+//! Connection strings naming an interface on any backend.
+//!
+//! Tools that work with several backends can accept one string and hand it to whichever backend
+//! its scheme names:
+//!
+//! ```text
+//! socketcan:can0
+//! slcan:/dev/ttyACM0?bitrate=500000
+//! udp:239.0.0.1:20000
+//! pcan:PCAN_USBBUS1?bitrate=250000&listen_only=true
+//! ```
+//!
+//! An address is `scheme:path`, optionally followed by `?key=value` parameters separated by `&`.
+//! The path is everything up to the `?` and may itself contain colons. [`InterfaceAddress::parse`]
+//! splits an address without allocating and checks the parameters every backend understands
+//! (those of [`CanConfig`]); other parameters are left to the backend:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::ConfigBinding;
+//! use embedded_can_interface::address::InterfaceAddress;
+//!
+//! let address = InterfaceAddress::parse(&args.interface)?;
+//! let can = match address.scheme() {
+//!     "usb" => Backend::Usb(MyUsbCan::open_address(&address)?),
+//!     "slcan" => Backend::Serial(MySerialCan::open_address(&address)?),
+//!     other => return Err(format!("unknown backend {other}").into()),
+//! };
+//! ```
+//!
+//! | Parameter        | Value                       | [`CanConfig`] field |
+//! |------------------|-----------------------------|---------------------|
+//! | `bitrate`        | bits per second             | `bitrate`           |
+//! | `data_bitrate`   | bits per second             | `data_bitrate`      |
+//! | `listen_only`    | `true`/`false` (or `1`/`0`) | `listen_only`       |
+//! | `self_reception` | `true`/`false` (or `1`/`0`) | `self_reception`    |
+//! | `tx_queue_len`   | frames                      | `tx_queue_len`      |
+//! | `rx_queue_len`   | frames                      | `rx_queue_len`      |
+//!
+//! [`ConfigBinding::open_address`](crate::ConfigBinding::open_address) opens the path with the
+//! resulting [`CanConfig`].
+
+use crate::{Bitrate, CanConfig};
+use core::error::Error;
+use core::fmt;
+
+/// Error returned by [`InterfaceAddress::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// The address has no `scheme:` prefix.
+    MissingScheme,
+    /// The scheme contains characters other than ASCII letters, digits, `-`, `_` and `+`.
+    InvalidScheme,
+    /// A standard parameter has a value of the wrong form.
+    InvalidParameter,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingScheme => f.write_str("missing `scheme:` prefix"),
+            Self::InvalidScheme => f.write_str("invalid scheme"),
+            Self::InvalidParameter => f.write_str("invalid parameter value"),
+        }
+    }
+}
+
+impl Error for AddressError {}
+
+/// A parsed connection string; see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceAddress<'a> {
+    scheme: &'a str,
+    path: &'a str,
+    query: &'a str,
+}
+
+fn parse_bool(value: &str) -> Result<bool, AddressError> {
+    match value {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(AddressError::InvalidParameter),
+    }
+}
+
+fn parse_bitrate(value: &str) -> Result<Bitrate, AddressError> {
+    value
+        .parse()
+        .map(Bitrate::Raw)
+        .map_err(|_| AddressError::InvalidParameter)
+}
+
+fn parse_len(value: &str) -> Result<usize, AddressError> {
+    value.parse().map_err(|_| AddressError::InvalidParameter)
+}
+
+impl<'a> InterfaceAddress<'a> {
+    /// Split `address` into scheme, path and parameters, checking the standard parameters.
+    pub fn parse(address: &'a str) -> Result<Self, AddressError> {
+        let (scheme, rest) = address.split_once(':').ok_or(AddressError::MissingScheme)?;
+        if scheme.is_empty() {
+            return Err(AddressError::MissingScheme);
+        }
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+');
+        if !scheme.chars().all(valid) {
+            return Err(AddressError::InvalidScheme);
+        }
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let address = Self {
+            scheme,
+            path,
+            query,
+        };
+        address.try_config()?;
+        Ok(address)
+    }
+
+    /// The backend name before the first `:`, e.g. `socketcan`.
+    pub fn scheme(&self) -> &'a str {
+        self.scheme
+    }
+
+    /// The interface within the backend, e.g. `can0` or `/dev/ttyACM0`.
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// All parameters in order of appearance, including the standard ones.
+    ///
+    /// A parameter without `=` has an empty value. No percent-decoding is done.
+    pub fn params(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        self.query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| param.split_once('=').unwrap_or((param, "")))
+    }
+
+    /// Value of the last parameter named `key`.
+    pub fn param(&self, key: &str) -> Option<&'a str> {
+        self.params()
+            .filter(|(name, _)| *name == key)
+            .last()
+            .map(|(_, value)| value)
+    }
+
+    /// The standard parameters as a [`CanConfig`]; unset parameters keep the defaults.
+    pub fn config(&self) -> CanConfig<'static> {
+        // `parse` has checked every standard parameter.
+        self.try_config().unwrap_or_default()
+    }
+
+    fn try_config(&self) -> Result<CanConfig<'static>, AddressError> {
+        let mut config = CanConfig::default();
+        for (key, value) in self.params() {
+            match key {
+                "bitrate" => config.bitrate = Some(parse_bitrate(value)?),
+                "data_bitrate" => config.data_bitrate = Some(parse_bitrate(value)?),
+                "listen_only" => config.listen_only = Some(parse_bool(value)?),
+                "self_reception" => config.self_reception = Some(parse_bool(value)?),
+                "tx_queue_len" => config.tx_queue_len = Some(parse_len(value)?),
+                "rx_queue_len" => config.rx_queue_len = Some(parse_len(value)?),
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,52 @@
+//! Software bitrate detection.
+//!
+//! [`detect_by_listening`] implements the usual approach for controllers without hardware
+//! auto-baud: try each candidate rate in listen-only mode and accept the first one at which a frame
+//! is received without the receive error counter moving.
+
+use core::time::Duration;
+
+use crate::{AsyncRxFrameIo, Bitrate, BitrateConfig, BusState, Lifecycle, ListenOnlyControl};
+
+/// Detect the bus bitrate by listening at each candidate rate.
+///
+/// For every entry in `candidates`, in order, the interface is reset, put into listen-only mode,
+/// configured for that rate and enabled. The rate is accepted if a frame arrives within `window`
+/// and the receive error counter is still zero afterwards. Because listen-only mode never drives
+/// the bus, probing at a wrong rate does not disturb other nodes.
+///
+/// On success the interface is left running in listen-only mode at the detected rate; call
+/// [`ListenOnlyControl::set_listen_only`] to join the bus. Returns `Ok(None)` if no candidate was
+/// confirmed; the interface is then left in an unspecified state.
+///
+/// Errors from configuration calls are returned immediately. Errors from
+/// [`AsyncRxFrameIo::recv_timeout`] are treated as “no traffic seen” at that rate, since drivers
+/// report timeouts through their error type.
+pub async fn detect_by_listening<T, E>(
+    io: &mut T,
+    candidates: &[Bitrate],
+    window: Duration,
+) -> Result<Option<Bitrate>, E>
+where
+    T: Lifecycle<Error = E>
+        + ListenOnlyControl<Error = E>
+        + BitrateConfig<Error = E>
+        + BusState<Error = E>
+        + AsyncRxFrameIo<Error = E>,
+{
+    for &bitrate in candidates {
+        io.reset()?;
+        io.disable()?;
+        io.set_listen_only(true)?;
+        io.set_bitrate(bitrate)?;
+        io.enable()?;
+
+        if io.recv_timeout(window).await.is_err() {
+            continue;
+        }
+        if io.error_counters()?.rx == 0 {
+            return Ok(Some(bitrate));
+        }
+    }
+    Ok(None)
+}
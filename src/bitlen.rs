@@ -0,0 +1,168 @@
+//! On-wire frame length estimation.
+//!
+//! Bus load estimation, rate shaping, and schedule validation all need to know how long a frame
+//! occupies the bus. [`frame_bits`] computes that from a [`FrameShape`], for classic CAN and CAN FD,
+//! either without stuff bits or with the worst-case number of stuff bits.
+//!
+//! Lengths include SOF through EOF plus the 3-bit intermission (IFS), i.e. the minimum spacing from
+//! one frame start to the next.
+
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::Bitrate;
+
+/// Frame format, as far as it affects the on-wire length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Classic CAN (up to 8 data bytes).
+    Classic,
+    /// CAN FD (up to 64 data bytes).
+    Fd {
+        /// Bit rate switch: the data phase runs at the data bitrate.
+        bitrate_switch: bool,
+    },
+}
+
+/// The properties of a frame that determine its on-wire length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameShape {
+    /// Classic or FD, and whether the bitrate switches.
+    pub format: FrameFormat,
+    /// 29-bit identifier.
+    pub extended: bool,
+    /// Remote frame (classic only; carries no data bytes).
+    pub remote: bool,
+    /// Number of data bytes. FD lengths are rounded up to the next valid DLC length.
+    pub data_len: usize,
+}
+
+impl FrameShape {
+    /// Shape of a classic frame implementing [`embedded_can::Frame`].
+    pub fn classic<F: Frame>(frame: &F) -> Self {
+        Self {
+            format: FrameFormat::Classic,
+            extended: frame.is_extended(),
+            remote: frame.is_remote_frame(),
+            data_len: frame.dlc(),
+        }
+    }
+}
+
+/// How stuff bits are accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stuffing {
+    /// Ignore dynamic stuff bits (FD fixed stuff bits are still counted). This is the nominal
+    /// length.
+    None,
+    /// Assume the maximum possible number of dynamic stuff bits, as used in worst-case response
+    /// time analysis.
+    WorstCase,
+}
+
+/// Length of a frame in bits, split by the bitrate each part is sent at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitLength {
+    /// Bits sent at the nominal (arbitration) bitrate.
+    pub arbitration_bits: u32,
+    /// Bits sent at the data bitrate (zero unless the frame uses FD bitrate switching).
+    pub data_bits: u32,
+}
+
+impl BitLength {
+    /// Total number of bits, regardless of rate.
+    pub const fn total(&self) -> u32 {
+        self.arbitration_bits + self.data_bits
+    }
+
+    /// Time the frame occupies the bus at the given nominal and data bitrates.
+    ///
+    /// `data` is only used for bits in [`BitLength::data_bits`].
+    pub fn duration(&self, nominal: Bitrate, data: Bitrate) -> Duration {
+        let nanos = |bits: u32, rate: Bitrate| match rate.bits_per_second() {
+            0 => 0,
+            bps => bits as u64 * 1_000_000_000 / bps as u64,
+        };
+        Duration::from_nanos(nanos(self.arbitration_bits, nominal) + nanos(self.data_bits, data))
+    }
+}
+
+/// Round an FD payload length up to the next length representable by a DLC.
+pub const fn fd_padded_len(len: usize) -> usize {
+    match len {
+        0..=8 => len,
+        9..=12 => 12,
+        13..=16 => 16,
+        17..=20 => 20,
+        21..=24 => 24,
+        25..=32 => 32,
+        33..=48 => 48,
+        _ => 64,
+    }
+}
+
+/// Worst-case dynamic stuff bits for `bits` bits subject to stuffing.
+const fn worst_case_stuff(bits: u32) -> u32 {
+    if bits == 0 { 0 } else { (bits - 1) / 4 }
+}
+
+/// Compute the on-wire length of a frame.
+pub fn frame_bits(shape: &FrameShape, stuffing: Stuffing) -> BitLength {
+    // ACK slot + ACK delimiter + EOF + IFS.
+    const TAIL: u32 = 2 + 7 + 3;
+
+    match shape.format {
+        FrameFormat::Classic => {
+            let data = if shape.remote {
+                0
+            } else {
+                8 * shape.data_len.min(8) as u32
+            };
+            // SOF through the end of the CRC sequence is subject to stuffing.
+            let stuffed = if shape.extended { 54 } else { 34 } + data;
+            let stuff = match stuffing {
+                Stuffing::None => 0,
+                Stuffing::WorstCase => worst_case_stuff(stuffed),
+            };
+            BitLength {
+                // + CRC delimiter.
+                arbitration_bits: stuffed + stuff + 1 + TAIL,
+                data_bits: 0,
+            }
+        }
+        FrameFormat::Fd { bitrate_switch } => {
+            let len = fd_padded_len(shape.data_len) as u32;
+            // SOF, ID, (SRR, IDE, extension,) RRS, IDE/-, FDF, res, BRS.
+            let arbitration = if shape.extended { 36 } else { 17 };
+            // ESI, DLC, data.
+            let data = 1 + 4 + 8 * len;
+            let crc: u32 = if len <= 16 { 17 } else { 21 };
+            // Stuff count (4) + CRC, with a fixed stuff bit before and every 4 bits within.
+            let crc_field = 4 + crc + (4 + crc).div_ceil(4);
+
+            let (arbitration_stuff, data_stuff) = match stuffing {
+                Stuffing::None => (0, 0),
+                Stuffing::WorstCase => {
+                    let arbitration_stuff = worst_case_stuff(arbitration);
+                    let total = worst_case_stuff(arbitration + data);
+                    (arbitration_stuff, total - arbitration_stuff)
+                }
+            };
+            // The bitrate switches back at the CRC delimiter.
+            let data_phase = data + data_stuff + crc_field + 1;
+            let arbitration_phase = arbitration + arbitration_stuff + TAIL;
+            if bitrate_switch {
+                BitLength {
+                    arbitration_bits: arbitration_phase,
+                    data_bits: data_phase,
+                }
+            } else {
+                BitLength {
+                    arbitration_bits: arbitration_phase + data_phase,
+                    data_bits: 0,
+                }
+            }
+        }
+    }
+}
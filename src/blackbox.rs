@@ -0,0 +1,296 @@
+//! Flight recorder for the most recent traffic.
+//!
+//! Field failures are hard to diagnose without knowing what was on the bus just before them.
+//! [`Blackbox`] wraps an interface and keeps the last `N` frames it sent or received, with their
+//! direction and timestamp, in a fixed ring buffer. After a fault the application can
+//! [`freeze`](Blackbox::freeze) the recording and dump it, e.g. to flash or a debug log:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::blackbox::Blackbox;
+//!
+//! let mut can: Blackbox<_, _, MyFrame, 64> = Blackbox::new(driver, clock);
+//! // ... normal operation through `can` ...
+//! if fault_detected {
+//!     can.freeze();
+//!     for record in can.records() {
+//!         log_record(record);
+//!     }
+//! }
+//! ```
+
+use core::time::Duration;
+
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+};
+
+/// Whether a recorded frame was sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the bus.
+    Rx,
+    /// Handed to the driver for transmission.
+    Tx,
+}
+
+/// One recorded frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<F> {
+    /// Direction of the frame.
+    pub direction: Direction,
+    /// Time the wrapper saw the frame, on the recorder's [`Clock`].
+    pub at: Duration,
+    /// The frame itself.
+    pub frame: F,
+}
+
+/// Wrapper that records the last `N` frames passing through it.
+///
+/// Transmitted frames are recorded once the driver accepted them; failed sends are not recorded.
+#[derive(Debug)]
+pub struct Blackbox<T, C, F, const N: usize> {
+    inner: T,
+    clock: C,
+    records: [Option<Record<F>>; N],
+    /// Index the next record is written to.
+    head: usize,
+    frozen: bool,
+}
+
+impl<T, C, F, const N: usize> Blackbox<T, C, F, N> {
+    /// Wrap `inner`, timestamping records with `clock`.
+    pub fn new(inner: T, clock: C) -> Self {
+        const { assert!(N > 0, "Blackbox needs at least one record slot") };
+        Self {
+            inner,
+            clock,
+            records: core::array::from_fn(|_| None),
+            head: 0,
+            frozen: false,
+        }
+    }
+
+    /// Stop recording, preserving the current contents while traffic continues.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    /// Resume recording.
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
+    /// Returns `true` while recording is stopped.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Recorded frames, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &Record<F>> {
+        let (newer, older) = self.records.split_at(self.head);
+        older.iter().chain(newer).flatten()
+    }
+
+    /// Number of frames currently recorded.
+    pub fn len(&self) -> usize {
+        self.records.iter().flatten().count()
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.records.iter().all(Option::is_none)
+    }
+
+    /// Discard all records.
+    pub fn clear(&mut self) {
+        self.records.iter_mut().for_each(|record| *record = None);
+        self.head = 0;
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, C: Clock, F: Clone, const N: usize> Blackbox<T, C, F, N> {
+    fn record(&mut self, direction: Direction, frame: &F) {
+        if self.frozen {
+            return;
+        }
+        self.records[self.head] = Some(Record {
+            direction,
+            at: self.clock.now(),
+            frame: frame.clone(),
+        });
+        self.head = (self.head + 1) % N;
+    }
+
+    fn record_result<R>(&mut self, direction: Direction, frame: &F, result: &Result<(), R>) {
+        if result.is_ok() {
+            self.record(direction, frame);
+        }
+    }
+
+    fn record_batch<R>(
+        &mut self,
+        frames: &[(F, SendOptions)],
+        result: &Result<usize, PartialSend<R>>,
+    ) {
+        let sent = match result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        for (frame, _) in frames.iter().take(sent) {
+            self.record(Direction::Tx, frame);
+        }
+    }
+}
+
+impl<T, C, F, const N: usize> TxFrameIo for Blackbox<T, C, F, N>
+where
+    T: TxFrameIo<Frame = F>,
+    C: Clock,
+    F: Clone,
+{
+    type Frame = F;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &F) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame);
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    fn try_send(&mut self, frame: &F) -> Result<(), Self::Error> {
+        let result = self.inner.try_send(frame);
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    fn send_timeout(&mut self, frame: &F, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout);
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    fn send_with(&mut self, frame: &F, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options);
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(F, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C, F, const N: usize> RxFrameIo for Blackbox<T, C, F, N>
+where
+    T: RxFrameIo<Frame = F>,
+    C: Clock,
+    F: Clone,
+{
+    type Frame = F;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<F, Self::Error> {
+        let frame = self.inner.recv()?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn try_recv(&mut self) -> Result<F, Self::Error> {
+        let frame = self.inner.try_recv()?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout)?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, C, F, const N: usize> AsyncTxFrameIo for Blackbox<T, C, F, N>
+where
+    T: AsyncTxFrameIo<Frame = F>,
+    C: Clock,
+    F: Clone,
+{
+    type Frame = F;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &F) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame).await;
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    async fn send_timeout(&mut self, frame: &F, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    async fn send_with(&mut self, frame: &F, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options).await;
+        self.record_result(Direction::Tx, frame, &result);
+        result
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(F, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C, F, const N: usize> AsyncRxFrameIo for Blackbox<T, C, F, N>
+where
+    T: AsyncRxFrameIo<Frame = F>,
+    C: Clock,
+    F: Clone,
+{
+    type Frame = F;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<F, Self::Error> {
+        let frame = self.inner.recv().await?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout).await?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
@@ -0,0 +1,636 @@
+//! Host-side TX/RX buffering.
+//!
+//! [`Buffered`] wraps an interface with software queues, smoothing bursty traffic on controllers
+//! with few hardware mailboxes. Queue storage is pluggable through [`FrameQueue`]:
+//!
+//! - [`Ring`] borrows a caller-provided array, matching the storage shape of
+//!   [`BufferedIo::buffered`](crate::BufferedIo::buffered);
+//! - [`PriorityRing`] uses the same storage but releases frames in CAN arbitration order, so a
+//!   software TX queue does not reintroduce the priority inversion hardware mailboxes avoid;
+//! - with the `alloc` feature, `VecDeque` can be used when const-generic sizing is inconvenient;
+//! - with the `bbqueue` feature, `BbqRing` stores serialized frames in a `bbqueue` buffer, so
+//!   DMA-capable drivers can hand contiguous runs of queued frames straight to hardware.
+//!
+//! The wrapper moves frames between its queues and the driver in [`Buffered::poll`], which is also
+//! called at the start of every I/O operation. It relies on [`TxRxState::tx_free`] and
+//! [`TxRxState::rx_pending`] so it never has to interpret driver errors as “would block”; with a
+//! driver that does not count free slots, one frame is moved whenever the transmitter is idle, and
+//! with one that does not count pending frames, received frames are read from the driver on
+//! demand instead of being queued ahead.
+
+use core::cmp::Ordering;
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::{RxFrameIo, TxFrameIo, TxRxState};
+
+/// Storage for a software frame queue.
+pub trait FrameQueue<F> {
+    /// Append a frame, handing it back if the queue is full.
+    fn push(&mut self, frame: F) -> Result<(), F>;
+
+    /// Remove and return the frame at the front of the queue.
+    fn pop(&mut self) -> Option<F>;
+
+    /// Borrow the frame at the front of the queue.
+    fn peek(&self) -> Option<&F>;
+
+    /// Number of queued frames.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if no frames are queued.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if another [`FrameQueue::push`] would fail.
+    fn is_full(&self) -> bool;
+}
+
+/// FIFO ring buffer over caller-provided storage.
+///
+/// The array only provides slots; its initial contents are ignored. Frames are cloned out of the
+/// slots on [`FrameQueue::pop`], which is cheap for the `Copy` frame types most drivers use.
+#[derive(Debug)]
+pub struct Ring<'a, F, const N: usize> {
+    slots: &'a mut [F; N],
+    head: usize,
+    len: usize,
+}
+
+impl<'a, F, const N: usize> Ring<'a, F, N> {
+    /// Create an empty ring using `slots` as storage.
+    pub fn new(slots: &'a mut [F; N]) -> Self {
+        Self {
+            slots,
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<F: Clone, const N: usize> FrameQueue<F> for Ring<'_, F, N> {
+    fn push(&mut self, frame: F) -> Result<(), F> {
+        if self.len == N {
+            return Err(frame);
+        }
+        self.slots[(self.head + self.len) % N] = frame;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        if self.len == 0 {
+            return None;
+        }
+        let frame = self.slots[self.head].clone();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(frame)
+    }
+
+    fn peek(&self) -> Option<&F> {
+        (self.len > 0).then(|| &self.slots[self.head])
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+}
+
+/// Compare two frames by CAN arbitration priority; `Less` wins arbitration.
+///
+/// IDs are compared using the arbitration order of [`embedded_can::Id`]. For equal IDs a data
+/// frame wins over a remote frame, since the RTR bit is dominant in data frames.
+pub fn arbitration_cmp<F: Frame>(a: &F, b: &F) -> Ordering {
+    a.id()
+        .cmp(&b.id())
+        .then(a.is_remote_frame().cmp(&b.is_remote_frame()))
+}
+
+/// Arbitration-ordered queue over caller-provided storage.
+///
+/// [`FrameQueue::pop`] returns the queued frame that would win bus arbitration first (see
+/// [`arbitration_cmp`]). Frames that compare equal leave in the order they were pushed. Insertion
+/// and removal are `O(N)`, which is fine for the handful of frames a TX queue typically holds.
+#[derive(Debug)]
+pub struct PriorityRing<'a, F, const N: usize> {
+    slots: &'a mut [F; N],
+    len: usize,
+}
+
+impl<'a, F, const N: usize> PriorityRing<'a, F, N> {
+    /// Create an empty queue using `slots` as storage.
+    pub fn new(slots: &'a mut [F; N]) -> Self {
+        Self { slots, len: 0 }
+    }
+}
+
+impl<F: Frame + Clone, const N: usize> FrameQueue<F> for PriorityRing<'_, F, N> {
+    fn push(&mut self, frame: F) -> Result<(), F> {
+        if self.len == N {
+            return Err(frame);
+        }
+        // Insert after every frame that wins against or ties with the new one.
+        let position = self.slots[..self.len]
+            .partition_point(|queued| arbitration_cmp(queued, &frame) != Ordering::Greater);
+        self.slots[self.len] = frame;
+        self.slots[position..=self.len].rotate_right(1);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        if self.len == 0 {
+            return None;
+        }
+        self.slots[..self.len].rotate_left(1);
+        self.len -= 1;
+        Some(self.slots[self.len].clone())
+    }
+
+    fn peek(&self) -> Option<&F> {
+        self.slots[..self.len].first()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F> FrameQueue<F> for alloc::collections::VecDeque<F> {
+    fn push(&mut self, frame: F) -> Result<(), F> {
+        self.push_back(frame);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        self.pop_front()
+    }
+
+    fn peek(&self) -> Option<&F> {
+        self.front()
+    }
+
+    fn len(&self) -> usize {
+        alloc::collections::VecDeque::len(self)
+    }
+
+    fn is_full(&self) -> bool {
+        false
+    }
+}
+
+/// Serialized form of frames stored in a [`BbqRing`].
+#[cfg(feature = "bbqueue")]
+pub trait FrameLayout<F> {
+    /// Bytes taken by one frame.
+    const SIZE: usize;
+
+    /// Write `frame` into `buf`, which is [`FrameLayout::SIZE`] bytes long. Returns `false` if the
+    /// frame has no representation in this layout.
+    fn encode(frame: &F, buf: &mut [u8]) -> bool;
+
+    /// Read a frame back from `buf`, which is [`FrameLayout::SIZE`] bytes long.
+    fn decode(buf: &[u8]) -> Option<F>;
+}
+
+/// The 16-byte `can_frame` layout of Linux SocketCAN, for classic frames.
+///
+/// Bytes 0–3 hold the identifier in little-endian order, with bit 31 set for extended identifiers
+/// and bit 30 for remote frames; byte 4 holds the DLC and bytes 8–15 the data.
+#[cfg(feature = "bbqueue")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassicLayout;
+
+#[cfg(feature = "bbqueue")]
+impl<F: Frame> FrameLayout<F> for ClassicLayout {
+    const SIZE: usize = 16;
+
+    fn encode(frame: &F, buf: &mut [u8]) -> bool {
+        const EFF: u32 = 0x8000_0000;
+        const RTR: u32 = 0x4000_0000;
+
+        let data = frame.data();
+        if frame.dlc() > 8 || data.len() > 8 {
+            return false;
+        }
+        let mut can_id = match frame.id() {
+            embedded_can::Id::Standard(id) => u32::from(id.as_raw()),
+            embedded_can::Id::Extended(id) => id.as_raw() | EFF,
+        };
+        if frame.is_remote_frame() {
+            can_id |= RTR;
+        }
+        buf[..4].copy_from_slice(&can_id.to_le_bytes());
+        buf[4] = frame.dlc() as u8;
+        buf[5..8].fill(0);
+        buf[8..8 + data.len()].copy_from_slice(data);
+        buf[8 + data.len()..16].fill(0);
+        true
+    }
+
+    fn decode(buf: &[u8]) -> Option<F> {
+        let can_id = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let id = if can_id & 0x8000_0000 != 0 {
+            embedded_can::Id::Extended(embedded_can::ExtendedId::new(can_id & 0x1FFF_FFFF)?)
+        } else {
+            embedded_can::Id::Standard(embedded_can::StandardId::new((can_id & 0x7FF) as u16)?)
+        };
+        let dlc = usize::from(buf[4]);
+        if dlc > 8 {
+            return None;
+        }
+        if can_id & 0x4000_0000 != 0 {
+            F::new_remote(id, dlc)
+        } else {
+            F::new(id, &buf[8..8 + dlc])
+        }
+    }
+}
+
+/// FIFO queue storing serialized frames in a [`bbqueue::BBBuffer`] (requires the `bbqueue`
+/// feature).
+///
+/// Frames are kept back to back in the layout `L`, so [`BbqRing::read`] can hand a contiguous run
+/// of them to DMA-capable hardware without copying. The buffer size `N` must be a multiple of
+/// `L::SIZE` holding at least two frames; the queue holds up to `N / L::SIZE - 1` frames, since
+/// the bip-buffer needs one frame of slack to wrap around.
+///
+/// ```rust,ignore
+/// static TX: BBBuffer<{ 16 * 33 }> = BBBuffer::new();
+///
+/// let tx = BbqRing::<_, ClassicLayout, _>::new(&TX)?;
+/// let mut can = Buffered::new(driver, tx, rx);
+/// // Later, in the driver's DMA path:
+/// if let Some(grant) = can.tx_queue_mut().read() {
+///     start_dma(grant.buf());
+///     // ... once the transfer completes:
+///     let frames = grant.frames();
+///     grant.release(frames);
+/// }
+/// ```
+#[cfg(feature = "bbqueue")]
+pub struct BbqRing<'a, F, L, const N: usize> {
+    producer: bbqueue::Producer<'a, N>,
+    consumer: bbqueue::Consumer<'a, N>,
+    /// Decoded copy of the front frame, for [`FrameQueue::peek`].
+    front: Option<F>,
+    len: usize,
+    layout: core::marker::PhantomData<L>,
+}
+
+#[cfg(feature = "bbqueue")]
+impl<'a, F, L: FrameLayout<F>, const N: usize> BbqRing<'a, F, L, N> {
+    /// Create an empty queue using `buffer` as storage.
+    ///
+    /// Fails if `buffer` was already split.
+    pub fn new(buffer: &'a bbqueue::BBBuffer<N>) -> Result<Self, bbqueue::Error> {
+        const { assert!(L::SIZE > 0 && N.is_multiple_of(L::SIZE) && N / L::SIZE >= 2) };
+        let (producer, consumer) = buffer.try_split()?;
+        Ok(Self {
+            producer,
+            consumer,
+            front: None,
+            len: 0,
+            layout: core::marker::PhantomData,
+        })
+    }
+
+    /// Largest number of frames the queue holds.
+    pub const fn capacity(&self) -> usize {
+        N / L::SIZE - 1
+    }
+
+    /// Borrow the longest contiguous run of queued frames, in their serialized form.
+    ///
+    /// Returns `None` if the queue is empty. Frames stay queued until released through the grant.
+    pub fn read(&mut self) -> Option<BbqGrant<'_, 'a, F, L, N>> {
+        let grant = self.consumer.read().ok()?;
+        Some(BbqGrant { grant, ring: self })
+    }
+
+    fn refresh_front(&mut self) {
+        self.front = match self.consumer.read() {
+            // Dropping the grant releases nothing.
+            Ok(grant) => L::decode(&grant[..L::SIZE]),
+            Err(_) => None,
+        };
+    }
+}
+
+#[cfg(feature = "bbqueue")]
+impl<F, L: FrameLayout<F>, const N: usize> FrameQueue<F> for BbqRing<'_, F, L, N> {
+    /// Also hands the frame back if `L` cannot encode it.
+    fn push(&mut self, frame: F) -> Result<(), F> {
+        if self.is_full() {
+            return Err(frame);
+        }
+        let Ok(mut grant) = self.producer.grant_exact(L::SIZE) else {
+            return Err(frame);
+        };
+        if !L::encode(&frame, grant.buf()) {
+            return Err(frame);
+        }
+        grant.commit(L::SIZE);
+        if self.len == 0 {
+            self.front = Some(frame);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        let frame = self.front.take()?;
+        if let Ok(grant) = self.consumer.read() {
+            grant.release(L::SIZE);
+        }
+        self.len -= 1;
+        if self.len > 0 {
+            self.refresh_front();
+        }
+        Some(frame)
+    }
+
+    fn peek(&self) -> Option<&F> {
+        self.front.as_ref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+}
+
+/// Contiguous run of serialized frames borrowed from a [`BbqRing`].
+///
+/// Dropping the grant leaves the frames queued.
+#[cfg(feature = "bbqueue")]
+pub struct BbqGrant<'r, 'a, F, L: FrameLayout<F>, const N: usize> {
+    grant: bbqueue::GrantR<'a, N>,
+    ring: &'r mut BbqRing<'a, F, L, N>,
+}
+
+#[cfg(feature = "bbqueue")]
+impl<F, L: FrameLayout<F>, const N: usize> BbqGrant<'_, '_, F, L, N> {
+    /// The frames, `L::SIZE` bytes each.
+    pub fn buf(&self) -> &[u8] {
+        self.grant.buf()
+    }
+
+    /// Number of frames in [`BbqGrant::buf`].
+    pub fn frames(&self) -> usize {
+        self.grant.len() / L::SIZE
+    }
+
+    /// Remove the first `frames` frames from the queue; more than [`BbqGrant::frames`] removes all
+    /// of them.
+    pub fn release(self, frames: usize) {
+        let frames = frames.min(self.frames());
+        self.grant.release(frames * L::SIZE);
+        self.ring.len -= frames;
+        if frames > 0 {
+            self.ring.refresh_front();
+        }
+    }
+}
+
+/// Error returned by [`Buffered`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferedError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// [`TxFrameIo::try_send`] found the software TX queue full.
+    Full,
+}
+
+impl<E> fmt::Display for BufferedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Full => f.write_str("software TX queue full"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for BufferedError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<E> From<E> for BufferedError<E> {
+    fn from(error: E) -> Self {
+        BufferedError::Io(error)
+    }
+}
+
+/// Interface wrapper with software TX and RX queues.
+///
+/// Frames leave the TX queue in the order the queue releases them: FIFO for [`Ring`] and
+/// `VecDeque`, arbitration order for [`PriorityRing`]. Received frames are moved into the RX queue
+/// during [`Buffered::poll`]; when the RX queue is full, remaining frames are left in the driver.
+#[derive(Debug)]
+pub struct Buffered<T, TQ, RQ> {
+    inner: T,
+    tx: TQ,
+    rx: RQ,
+}
+
+impl<T, TQ, RQ> Buffered<T, TQ, RQ> {
+    /// Wrap `inner` using `tx` and `rx` as queue storage.
+    pub fn new(inner: T, tx: TQ, rx: RQ) -> Self {
+        Self { inner, tx, rx }
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Mutably borrow the TX queue, e.g. to hand queued frames to DMA-capable hardware.
+    pub fn tx_queue_mut(&mut self) -> &mut TQ {
+        &mut self.tx
+    }
+
+    /// Mutably borrow the RX queue.
+    pub fn rx_queue_mut(&mut self) -> &mut RQ {
+        &mut self.rx
+    }
+
+    /// Number of frames waiting in the software TX queue.
+    pub fn tx_queued<F>(&self) -> usize
+    where
+        TQ: FrameQueue<F>,
+    {
+        self.tx.len()
+    }
+
+    /// Number of frames waiting in the software RX queue.
+    pub fn rx_queued<F>(&self) -> usize
+    where
+        RQ: FrameQueue<F>,
+    {
+        self.rx.len()
+    }
+
+    /// Unwrap into the interface and queue storage. Queued frames remain in the queues.
+    pub fn into_parts(self) -> (T, TQ, RQ) {
+        (self.inner, self.tx, self.rx)
+    }
+}
+
+impl<T, TQ, RQ, F, E> Buffered<T, TQ, RQ>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E> + TxRxState<Error = E>,
+    TQ: FrameQueue<F>,
+    RQ: FrameQueue<F>,
+{
+    /// Move queued TX frames into free driver slots and pending RX frames into the RX queue.
+    pub fn poll(&mut self) -> Result<(), E> {
+        self.flush_tx()?;
+        let mut pending = self.inner.rx_pending()?.unwrap_or(0);
+        while pending > 0 && !self.rx.is_full() {
+            let frame = self.inner.try_recv()?;
+            // Cannot fail: checked `is_full` above.
+            let _ = self.rx.push(frame);
+            pending -= 1;
+        }
+        Ok(())
+    }
+
+    fn flush_tx(&mut self) -> Result<(), E> {
+        let mut free = crate::tx_capacity(&self.inner)?;
+        while free > 0 {
+            let Some(frame) = self.tx.peek() else {
+                break;
+            };
+            self.inner.try_send(frame)?;
+            self.tx.pop();
+            free -= 1;
+        }
+        Ok(())
+    }
+}
+
+impl<T, TQ, RQ, F, E> TxFrameIo for Buffered<T, TQ, RQ>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E> + TxRxState<Error = E>,
+    TQ: FrameQueue<F>,
+    RQ: FrameQueue<F>,
+    F: Clone,
+{
+    type Frame = F;
+    type Error = BufferedError<E>;
+
+    /// Queue `frame`, blocking on the driver only while the software queue is full.
+    fn send(&mut self, frame: &F) -> Result<(), Self::Error> {
+        self.poll()?;
+        while self.tx.is_full() {
+            if let Some(oldest) = self.tx.pop() {
+                self.inner.send(&oldest)?;
+            }
+        }
+        self.enqueue(frame)
+    }
+
+    /// Queue `frame`, returning [`BufferedError::Full`] if the software queue is full.
+    fn try_send(&mut self, frame: &F) -> Result<(), Self::Error> {
+        self.poll()?;
+        if self.tx.is_full() {
+            return Err(BufferedError::Full);
+        }
+        self.enqueue(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &F, timeout: Duration) -> Result<(), Self::Error> {
+        self.poll()?;
+        while self.tx.is_full() {
+            if let Some(oldest) = self.tx.pop() {
+                self.inner.send_timeout(&oldest, timeout)?;
+            }
+        }
+        self.enqueue(frame)
+    }
+}
+
+impl<T, TQ, RQ, F, E> Buffered<T, TQ, RQ>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E> + TxRxState<Error = E>,
+    TQ: FrameQueue<F>,
+    RQ: FrameQueue<F>,
+    F: Clone,
+{
+    fn enqueue(&mut self, frame: &F) -> Result<(), BufferedError<E>> {
+        self.tx
+            .push(frame.clone())
+            .map_err(|_| BufferedError::Full)?;
+        self.flush_tx()?;
+        Ok(())
+    }
+}
+
+impl<T, TQ, RQ, F, E> RxFrameIo for Buffered<T, TQ, RQ>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E> + TxRxState<Error = E>,
+    TQ: FrameQueue<F>,
+    RQ: FrameQueue<F>,
+{
+    type Frame = F;
+    type Error = BufferedError<E>;
+
+    fn recv(&mut self) -> Result<F, Self::Error> {
+        self.poll()?;
+        match self.rx.pop() {
+            Some(frame) => Ok(frame),
+            None => Ok(self.inner.recv()?),
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<F, Self::Error> {
+        self.poll()?;
+        match self.rx.pop() {
+            Some(frame) => Ok(frame),
+            None => Ok(self.inner.try_recv()?),
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        self.poll()?;
+        match self.rx.pop() {
+            Some(frame) => Ok(frame),
+            None => Ok(self.inner.recv_timeout(timeout)?),
+        }
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.poll()?;
+        if self.rx.is_empty() {
+            self.inner.wait_not_empty()?;
+        }
+        Ok(())
+    }
+}
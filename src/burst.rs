@@ -0,0 +1,112 @@
+//! Paced transmission of frame sequences.
+//!
+//! Firmware download protocols and some older ECUs cannot take frames back to back and need a
+//! minimum gap between them. [`Burst`] sends a sequence of frames with a fixed inter-frame delay
+//! measured on a [`Clock`]:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::burst::Burst;
+//!
+//! let burst = Burst::new(&clock, Duration::from_millis(2));
+//! burst.send(&mut can, blocks.iter().map(to_frame))?;
+//! ```
+//!
+//! The blocking [`Burst::send`] spins on the clock between frames. With the `embedded-hal-async`
+//! feature, [`Burst::send_async`] awaits an `embedded_hal_async::delay::DelayNs` instead, so the
+//! executor can run other tasks during the gaps.
+
+use core::time::Duration;
+
+use crate::{Clock, PartialSend, TxFrameIo};
+
+/// Sends frame sequences with a minimum delay between consecutive frames.
+///
+/// The delay runs from the moment the driver accepted a frame to the submission of the next one;
+/// no delay follows the last frame. Errors are reported like [`TxFrameIo::send_all`]: sending
+/// stops at the first failure, which is returned with the number of frames already sent.
+#[derive(Debug, Clone, Copy)]
+pub struct Burst<C> {
+    clock: C,
+    gap: Duration,
+}
+
+impl<C: Clock> Burst<C> {
+    /// Pace frames `gap` apart on `clock`.
+    pub fn new(clock: C, gap: Duration) -> Self {
+        Self { clock, gap }
+    }
+
+    /// Delay between consecutive frames.
+    pub fn gap(&self) -> Duration {
+        self.gap
+    }
+
+    /// Change the delay between consecutive frames.
+    pub fn set_gap(&mut self, gap: Duration) {
+        self.gap = gap;
+    }
+
+    /// Borrow the clock.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Send `frames` in order through `io`, blocking on each and spinning on the clock between
+    /// them, and return how many were sent.
+    pub fn send<T, I>(&self, io: &mut T, frames: I) -> Result<usize, PartialSend<T::Error>>
+    where
+        T: TxFrameIo,
+        I: IntoIterator<Item = T::Frame>,
+    {
+        let mut sent = 0;
+        let mut next_at = None;
+        for frame in frames {
+            if let Some(next_at) = next_at {
+                self.clock.spin_until(next_at);
+            }
+            io.send(&frame)
+                .map_err(|error| PartialSend { sent, error })?;
+            sent += 1;
+            next_at = Some(self.clock.now() + self.gap);
+        }
+        Ok(sent)
+    }
+
+    /// Send `frames` in order through `io`, awaiting `delay` between them, and return how many
+    /// were sent.
+    ///
+    /// The clock is still used to measure the gap, so time spent inside the driver counts towards
+    /// it. Requires the `embedded-hal-async` feature.
+    #[cfg(feature = "embedded-hal-async")]
+    pub async fn send_async<T, I, D>(
+        &self,
+        io: &mut T,
+        frames: I,
+        delay: &mut D,
+    ) -> Result<usize, PartialSend<T::Error>>
+    where
+        T: crate::AsyncTxFrameIo,
+        I: IntoIterator<Item = T::Frame>,
+        D: embedded_hal_async::delay::DelayNs,
+    {
+        let mut sent = 0;
+        let mut next_at: Option<Duration> = None;
+        for frame in frames {
+            if let Some(next_at) = next_at {
+                let remaining = next_at.saturating_sub(self.clock.now());
+                if !remaining.is_zero() {
+                    let micros = remaining.as_nanos().div_ceil(1_000);
+                    delay
+                        .delay_us(u32::try_from(micros).unwrap_or(u32::MAX))
+                        .await;
+                }
+            }
+            io.send(&frame)
+                .await
+                .map_err(|error| PartialSend { sent, error })?;
+            sent += 1;
+            next_at = Some(self.clock.now() + self.gap);
+        }
+        Ok(sent)
+    }
+}
@@ -0,0 +1,282 @@
+//! Software bus load estimation.
+//!
+//! [`LoadMonitor`] wraps an interface, accounts for the nominal on-wire length (see
+//! [`crate::bitlen`]) of every frame it sends or receives, and reports utilization over a sliding
+//! window through [`BusLoad`]. It only sees frames that pass through it, so for an accurate figure
+//! the wrapped interface should receive all bus traffic (no restrictive acceptance filters) and
+//! self-reception should be off to avoid counting transmitted frames twice.
+
+use core::convert::Infallible;
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::bitlen::{FrameShape, Stuffing, frame_bits};
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Bitrate, BusLoad, Clock, PartialSend, RxFrameIo, SendOptions,
+    TxFrameIo,
+};
+
+fn nominal_bits<F: Frame>(frame: &F) -> u32 {
+    frame_bits(&FrameShape::classic(frame), Stuffing::None).total()
+}
+
+/// Software bus load estimator.
+///
+/// The measurement window is `N` buckets of `bucket` length each; older traffic drops out one
+/// bucket at a time. For example `LoadMonitor<_, _, 10>` with 100 ms buckets reports the load over
+/// the last second.
+#[derive(Debug)]
+pub struct LoadMonitor<T, C, const N: usize> {
+    inner: T,
+    clock: C,
+    bits_per_second: u32,
+    bucket: Duration,
+    buckets: [u32; N],
+    current: usize,
+    bucket_start: Duration,
+}
+
+impl<T, C: Clock, const N: usize> LoadMonitor<T, C, N> {
+    /// Wrap `inner`, which runs at `bitrate`, using buckets of `bucket` length.
+    pub fn new(inner: T, clock: C, bitrate: Bitrate, bucket: Duration) -> Self {
+        const { assert!(N > 0, "LoadMonitor needs at least one bucket") };
+        let bucket_start = clock.now();
+        Self {
+            inner,
+            clock,
+            bits_per_second: bitrate.bits_per_second(),
+            bucket,
+            buckets: [0; N],
+            current: 0,
+            bucket_start,
+        }
+    }
+
+    /// Account for `bits` of bus time observed now.
+    ///
+    /// The I/O trait implementations call this automatically; use it directly to feed in traffic
+    /// observed by other means.
+    pub fn record_bits(&mut self, bits: u32) {
+        self.advance(self.clock.now());
+        self.buckets[self.current] = self.buckets[self.current].saturating_add(bits);
+    }
+
+    fn stale_buckets(&self, now: Duration) -> usize {
+        if self.bucket.is_zero() {
+            return 0;
+        }
+        let elapsed = now.saturating_sub(self.bucket_start);
+        let stale = elapsed.as_nanos() / self.bucket.as_nanos();
+        usize::try_from(stale).unwrap_or(usize::MAX)
+    }
+
+    fn advance(&mut self, now: Duration) {
+        let stale = self.stale_buckets(now);
+        if stale == 0 {
+            return;
+        }
+        for _ in 0..stale.min(N) {
+            self.current = (self.current + 1) % N;
+            self.buckets[self.current] = 0;
+        }
+        self.bucket_start += self.bucket * u32::try_from(stale).unwrap_or(u32::MAX);
+    }
+
+    /// Utilization over the window, in percent.
+    pub fn load_percent(&self) -> f32 {
+        let stale = self.stale_buckets(self.clock.now()).min(N);
+        let bits: u64 = (0..N - stale)
+            .map(|age| self.buckets[(self.current + N - age) % N] as u64)
+            .sum();
+        let window = self.bucket.as_secs_f32() * N as f32;
+        if window == 0.0 || self.bits_per_second == 0 {
+            return 0.0;
+        }
+        (bits as f32 * 100.0 / (self.bits_per_second as f32 * window)).min(100.0)
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record_batch<F: Frame, E>(
+        &mut self,
+        frames: &[(F, SendOptions)],
+        result: &Result<usize, PartialSend<E>>,
+    ) {
+        let sent = match result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        let bits = frames.iter().take(sent).fold(0u32, |bits, (frame, _)| {
+            bits.saturating_add(nominal_bits(frame))
+        });
+        self.record_bits(bits);
+    }
+}
+
+impl<T, C: Clock, const N: usize> BusLoad for LoadMonitor<T, C, N> {
+    type Error = Infallible;
+
+    fn bus_load(&self) -> Result<f32, Self::Error> {
+        Ok(self.load_percent())
+    }
+}
+
+impl<T, C, const N: usize> TxFrameIo for LoadMonitor<T, C, N>
+where
+    T: TxFrameIo,
+    T::Frame: Frame,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C, const N: usize> RxFrameIo for LoadMonitor<T, C, N>
+where
+    T: RxFrameIo,
+    T::Frame: Frame,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv()?;
+        self.record_bits(nominal_bits(&frame));
+        Ok(frame)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.try_recv()?;
+        self.record_bits(nominal_bits(&frame));
+        Ok(frame)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout)?;
+        self.record_bits(nominal_bits(&frame));
+        Ok(frame)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, C, const N: usize> AsyncTxFrameIo for LoadMonitor<T, C, N>
+where
+    T: AsyncTxFrameIo,
+    T::Frame: Frame,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await?;
+        self.record_bits(nominal_bits(frame));
+        Ok(())
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C, const N: usize> AsyncRxFrameIo for LoadMonitor<T, C, N>
+where
+    T: AsyncRxFrameIo,
+    T::Frame: Frame,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv().await?;
+        self.record_bits(nominal_bits(&frame));
+        Ok(frame)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout).await?;
+        self.record_bits(nominal_bits(&frame));
+        Ok(frame)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
@@ -0,0 +1,430 @@
+//! Adapter for the STM32 bxCAN driver of the [`bxcan`] crate (requires the `bxcan` feature).
+//!
+//! - [`Can`], [`Tx`], [`Rx0`] and [`Rx1`] implement [`TxFrameIo`]/[`RxFrameIo`] as far as each
+//!   half allows; [`Rx`] reads both receive FIFOs.
+//! - [`SplitTxRx`] splits a [`Can`] into a [`Tx`] and an [`Rx`].
+//! - [`FilterConfig`] installs one 32-bit mask filter bank per filter.
+//! - [`TxRxState`] reports mailbox and FIFO state, so [`Can`] works with
+//!   [`Buffered`](crate::buffered::Buffered).
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::{FilterConfig, SplitTxRx, TxFrameIo};
+//!
+//! let mut can = bxcan::Can::builder(can1).set_bit_timing(0x001c_0003).enable();
+//! can.set_filters(&filters)?;
+//! let (mut tx, mut rx) = can.split();
+//! tx.send(&frame)?;
+//! ```
+//!
+//! The driver has no timer, so `send_timeout`/`recv_timeout` block like `send`/`recv`, and the
+//! blocking operations busy-wait on the mailbox and FIFO registers.
+
+use core::convert::Infallible;
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use bxcan::filter::{Mask32, MasterFilters};
+use bxcan::{Can, Fifo, FilterOwner, Frame, Instance, OverrunError, Rx0, Rx1, Tx};
+
+use crate::{
+    ConstFilterConfig, FilterConfig, FrameTypes, Id, IdMask, IdMaskFilter, IdTypes, PendingMask,
+    RxFrameIo, SplitTxRx, TxFrameIo, TxRxState,
+};
+
+/// Error returned by the bxCAN adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BxcanError {
+    /// No mailbox is free for the frame (non-blocking send), or no frame is waiting (non-blocking
+    /// receive).
+    WouldBlock,
+    /// A receive FIFO overflowed and frames were lost. The frames still in the FIFO can be read.
+    Overrun,
+}
+
+impl fmt::Display for BxcanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WouldBlock => f.write_str("operation would block"),
+            Self::Overrun => f.write_str("receive FIFO overrun"),
+        }
+    }
+}
+
+impl Error for BxcanError {}
+
+impl From<OverrunError> for BxcanError {
+    fn from(_: OverrunError) -> Self {
+        BxcanError::Overrun
+    }
+}
+
+fn classify<E: Into<BxcanError>>(error: nb::Error<E>) -> BxcanError {
+    match error {
+        nb::Error::WouldBlock => BxcanError::WouldBlock,
+        nb::Error::Other(error) => error.into(),
+    }
+}
+
+impl From<Infallible> for BxcanError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// Register offsets within `bxcan::RegisterBlock`, whose fields are private to the driver.
+const TSR: usize = 0x08;
+const RF0R: usize = 0x0C;
+const RF1R: usize = 0x10;
+/// TME0..TME2: transmit mailbox `n` is empty.
+const TSR_TME_SHIFT: u32 = 26;
+/// FMP: number of frames pending in the FIFO.
+const RFR_FMP: u32 = 0b11;
+
+fn read_register<I: Instance>(offset: usize) -> u32 {
+    // SAFETY: `Instance` guarantees that `REGISTERS` points at the peripheral's register block
+    // while the instance is owned. TSR and RFxR are status registers whose reads have no side
+    // effects, so reading them does not interfere with the driver.
+    unsafe {
+        I::REGISTERS
+            .cast::<u8>()
+            .add(offset)
+            .cast::<u32>()
+            .read_volatile()
+    }
+}
+
+fn empty_mailboxes<I: Instance>() -> u32 {
+    (read_register::<I>(TSR) >> TSR_TME_SHIFT) & 0b111
+}
+
+fn fifo_pending<I: Instance>(fifo: Fifo) -> usize {
+    let offset = match fifo {
+        Fifo::Fifo0 => RF0R,
+        Fifo::Fifo1 => RF1R,
+    };
+    (read_register::<I>(offset) & RFR_FMP) as usize
+}
+
+fn rx_pending<I: Instance>() -> usize {
+    fifo_pending::<I>(Fifo::Fifo0) + fifo_pending::<I>(Fifo::Fifo1)
+}
+
+/// Queue `frame` only if a mailbox is free.
+///
+/// With all three mailboxes busy, `transmit` would abort the lowest-priority pending frame to
+/// make room; the adapter reports [`BxcanError::WouldBlock`] instead, so frames are never dropped.
+fn try_transmit<I: Instance>(tx: &mut Tx<I>, frame: &Frame) -> Result<(), BxcanError> {
+    if empty_mailboxes::<I>() == 0 {
+        return Err(BxcanError::WouldBlock);
+    }
+    tx.transmit(frame).map(|_| ()).map_err(classify)
+}
+
+fn block<T>(mut op: impl FnMut() -> Result<T, BxcanError>) -> Result<T, BxcanError> {
+    loop {
+        match op() {
+            Err(BxcanError::WouldBlock) => continue,
+            result => return result,
+        }
+    }
+}
+
+/// `try_send` reports [`BxcanError::WouldBlock`] when no mailbox is free, and also while a frame of
+/// equal or higher priority is pending: the driver keeps frames of equal priority in order by
+/// queueing a frame next to pending ones only if it wins arbitration against all of them.
+impl<I: Instance> TxFrameIo for Tx<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn send(&mut self, frame: &Frame) -> Result<(), BxcanError> {
+        block(|| try_transmit(self, frame))
+    }
+
+    fn try_send(&mut self, frame: &Frame) -> Result<(), BxcanError> {
+        try_transmit(self, frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Frame, _timeout: Duration) -> Result<(), BxcanError> {
+        self.send(frame)
+    }
+}
+
+impl<I: Instance> RxFrameIo for Rx0<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn recv(&mut self) -> Result<Frame, BxcanError> {
+        block(|| self.try_recv())
+    }
+
+    fn try_recv(&mut self) -> Result<Frame, BxcanError> {
+        self.receive().map_err(classify)
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<Frame, BxcanError> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), BxcanError> {
+        while fifo_pending::<I>(Fifo::Fifo0) == 0 {}
+        Ok(())
+    }
+}
+
+impl<I: Instance> RxFrameIo for Rx1<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn recv(&mut self) -> Result<Frame, BxcanError> {
+        block(|| self.try_recv())
+    }
+
+    fn try_recv(&mut self) -> Result<Frame, BxcanError> {
+        self.receive().map_err(classify)
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<Frame, BxcanError> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), BxcanError> {
+        while fifo_pending::<I>(Fifo::Fifo1) == 0 {}
+        Ok(())
+    }
+}
+
+/// Receive half reading both FIFOs, as returned by [`SplitTxRx::split`].
+///
+/// FIFO 0 is drained before FIFO 1, like [`Can::receive`].
+pub struct Rx<I> {
+    rx0: Rx0<I>,
+    rx1: Rx1<I>,
+}
+
+impl<I: Instance> Rx<I> {
+    /// Combine the two FIFO halves returned by [`Can::split`].
+    pub fn new(rx0: Rx0<I>, rx1: Rx1<I>) -> Self {
+        Self { rx0, rx1 }
+    }
+
+    /// Mutably borrow the FIFO 0 half.
+    pub fn rx0(&mut self) -> &mut Rx0<I> {
+        &mut self.rx0
+    }
+
+    /// Mutably borrow the FIFO 1 half.
+    pub fn rx1(&mut self) -> &mut Rx1<I> {
+        &mut self.rx1
+    }
+
+    /// Unwrap into the FIFO halves.
+    pub fn into_parts(self) -> (Rx0<I>, Rx1<I>) {
+        (self.rx0, self.rx1)
+    }
+}
+
+impl<I: Instance> RxFrameIo for Rx<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn recv(&mut self) -> Result<Frame, BxcanError> {
+        block(|| self.try_recv())
+    }
+
+    fn try_recv(&mut self) -> Result<Frame, BxcanError> {
+        match self.rx0.try_recv() {
+            Err(BxcanError::WouldBlock) => self.rx1.try_recv(),
+            result => result,
+        }
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<Frame, BxcanError> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), BxcanError> {
+        while rx_pending::<I>() == 0 {}
+        Ok(())
+    }
+}
+
+impl<I: Instance> SplitTxRx for Can<I> {
+    type Tx = Tx<I>;
+    type Rx = Rx<I>;
+
+    fn split(self) -> (Self::Tx, Self::Rx) {
+        let (tx, rx0, rx1) = Can::split(self);
+        (tx, Rx::new(rx0, rx1))
+    }
+}
+
+/// See the [`Tx`] implementation for when `try_send` reports [`BxcanError::WouldBlock`].
+impl<I: Instance> TxFrameIo for Can<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn send(&mut self, frame: &Frame) -> Result<(), BxcanError> {
+        let (tx, _, _) = self.split_by_ref();
+        tx.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Frame) -> Result<(), BxcanError> {
+        let (tx, _, _) = self.split_by_ref();
+        tx.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Frame, _timeout: Duration) -> Result<(), BxcanError> {
+        self.send(frame)
+    }
+}
+
+impl<I: Instance> RxFrameIo for Can<I> {
+    type Frame = Frame;
+    type Error = BxcanError;
+
+    fn recv(&mut self) -> Result<Frame, BxcanError> {
+        block(|| self.try_recv())
+    }
+
+    fn try_recv(&mut self) -> Result<Frame, BxcanError> {
+        self.receive().map_err(classify)
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<Frame, BxcanError> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), BxcanError> {
+        while rx_pending::<I>() == 0 {}
+        Ok(())
+    }
+}
+
+/// Mailbox `n` of the controller is slot `n` of [`TxRxState::pending_tx`].
+///
+/// `tx_free` is 1 while all mailboxes are empty and 0 otherwise: the driver only queues a frame
+/// next to pending ones if it has higher priority than all of them, so only an idle transmitter is
+/// sure to accept any frame.
+impl<I: Instance> TxRxState for Can<I> {
+    type Error = BxcanError;
+
+    fn is_transmitter_idle(&self) -> Result<bool, BxcanError> {
+        Ok(empty_mailboxes::<I>() == 0b111)
+    }
+
+    fn tx_free(&self) -> Result<Option<usize>, BxcanError> {
+        Ok(Some(usize::from(empty_mailboxes::<I>() == 0b111)))
+    }
+
+    fn pending_tx(&self) -> Result<Option<PendingMask>, BxcanError> {
+        Ok(Some(PendingMask(!empty_mailboxes::<I>() & 0b111)))
+    }
+
+    fn rx_pending(&self) -> Result<Option<usize>, BxcanError> {
+        Ok(Some(rx_pending::<I>()))
+    }
+}
+
+/// Error returned when a filter list does not fit the bxCAN filter banks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BxcanFilterError {
+    /// More filters were given than filter banks are assigned to the instance.
+    TooManyFilters,
+    /// A filter's identifier and mask have different widths.
+    MixedIdTypes,
+    /// A filter uses [`IdTypes::Both`], which the banks cannot express: they compare standard and
+    /// extended identifiers at different bit positions.
+    UnsupportedIdTypes,
+}
+
+impl fmt::Display for BxcanFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyFilters => f.write_str("more filters than filter banks"),
+            Self::MixedIdTypes => f.write_str("filter identifier and mask have different widths"),
+            Self::UnsupportedIdTypes => {
+                f.write_str("filter banks cannot match both identifier types")
+            }
+        }
+    }
+}
+
+impl Error for BxcanFilterError {}
+
+fn mask32(filter: &IdMaskFilter) -> Result<Mask32, BxcanFilterError> {
+    if filter.id_types == IdTypes::Both {
+        return Err(BxcanFilterError::UnsupportedIdTypes);
+    }
+    let mut bank = match (filter.id, filter.mask) {
+        (Id::Standard(id), IdMask::Standard(mask)) => {
+            let id = bxcan::StandardId::new(id.as_raw());
+            let mask = bxcan::StandardId::new(mask & 0x7FF);
+            let (Some(id), Some(mask)) = (id, mask) else {
+                return Err(BxcanFilterError::MixedIdTypes);
+            };
+            Mask32::frames_with_std_id(id, mask)
+        }
+        (Id::Extended(id), IdMask::Extended(mask)) => {
+            let id = bxcan::ExtendedId::new(id.as_raw());
+            let mask = bxcan::ExtendedId::new(mask & 0x1FFF_FFFF);
+            let (Some(id), Some(mask)) = (id, mask) else {
+                return Err(BxcanFilterError::MixedIdTypes);
+            };
+            Mask32::frames_with_ext_id(id, mask)
+        }
+        _ => return Err(BxcanFilterError::MixedIdTypes),
+    };
+    match filter.frame_types {
+        FrameTypes::Both => {}
+        FrameTypes::Data => {
+            bank.data_frames_only();
+        }
+        FrameTypes::Remote => {
+            bank.remote_frames_only();
+        }
+    }
+    Ok(bank)
+}
+
+/// Installs filter `n` in bank `n`, in 32-bit mask mode, routing accepted frames to FIFO 0.
+///
+/// The banks filter exactly, including [`FrameTypes`]. An empty list accepts every frame. The
+/// list is checked before any bank is changed, so a rejected list leaves the previous filters in
+/// place. Chips whose banks are shared with a slave instance only count the banks assigned to
+/// this one.
+impl<I: FilterOwner> FilterConfig for Can<I> {
+    type Error = BxcanFilterError;
+    type FiltersHandle<'a>
+        = MasterFilters<'a, I>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        let mut banks = self.modify_filters();
+        if filters.len().max(1) > usize::from(banks.num_banks()) {
+            return Err(BxcanFilterError::TooManyFilters);
+        }
+        for filter in filters {
+            mask32(filter)?;
+        }
+        banks.clear();
+        if filters.is_empty() {
+            banks.enable_bank(0, Fifo::Fifo0, Mask32::accept_all());
+        }
+        for (index, filter) in filters.iter().enumerate() {
+            banks.enable_bank(index as u8, Fifo::Fifo0, mask32(filter)?);
+        }
+        Ok(())
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        Can::modify_filters(self)
+    }
+}
+
+/// One filter per bank. On chips whose banks are split with a slave instance, fewer may be
+/// available at runtime.
+impl<I: FilterOwner> ConstFilterConfig for Can<I> {
+    const MAX_FILTERS: usize = I::NUM_FILTER_BANKS as usize;
+}
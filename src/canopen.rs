@@ -0,0 +1,244 @@
+//! CANopen COB-ID helpers.
+//!
+//! CANopen (CiA 301) splits an 11-bit identifier, the COB-ID, into a 4-bit function code and a
+//! 7-bit node ID. The predefined connection set assigns every node its emergency, PDO, SDO and
+//! heartbeat identifiers from that split, so even applications without a CANopen stack need to
+//! build and take apart COB-IDs:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::canopen::{CobId, FunctionCode, NodeId};
+//!
+//! let node = NodeId::new(5).unwrap();
+//! let request = MyFrame::new(CobId::sdo_rx(node), &upload_request).unwrap();
+//! match CobId::from_id(frame.id()).and_then(|id| Some((id.function()?, id.node()))) {
+//!     Some((FunctionCode::Heartbeat, Some(node))) => nodes.alive(node, frame.data()[0]),
+//!     Some((FunctionCode::SyncEmcy, Some(node))) => report_emergency(node, frame.data()),
+//!     _ => {}
+//! }
+//! ```
+//!
+//! SDO directions are named from the server (the node) as in CiA 301: the node transmits SDO
+//! responses on [`CobId::sdo_tx`] and receives requests on [`CobId::sdo_rx`].
+
+use embedded_can::{Id, StandardId};
+
+/// Function code of a COB-ID in the predefined connection set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum FunctionCode {
+    /// Network management (node ID 0).
+    Nmt = 0,
+    /// SYNC with node ID 0, emergency (EMCY) otherwise.
+    SyncEmcy = 1,
+    /// TIME stamp (node ID 0).
+    Time = 2,
+    /// Transmit PDO 1.
+    Tpdo1 = 3,
+    /// Receive PDO 1.
+    Rpdo1 = 4,
+    /// Transmit PDO 2.
+    Tpdo2 = 5,
+    /// Receive PDO 2.
+    Rpdo2 = 6,
+    /// Transmit PDO 3.
+    Tpdo3 = 7,
+    /// Receive PDO 3.
+    Rpdo3 = 8,
+    /// Transmit PDO 4.
+    Tpdo4 = 9,
+    /// Receive PDO 4.
+    Rpdo4 = 10,
+    /// SDO sent by the server (node).
+    SdoTx = 11,
+    /// SDO received by the server (node).
+    SdoRx = 12,
+    /// NMT error control: heartbeat and node guarding.
+    Heartbeat = 14,
+}
+
+impl FunctionCode {
+    /// The function code with raw value `code`, or `None` if it is not part of the predefined
+    /// connection set.
+    pub const fn from_raw(code: u8) -> Option<Self> {
+        Some(match code {
+            0 => Self::Nmt,
+            1 => Self::SyncEmcy,
+            2 => Self::Time,
+            3 => Self::Tpdo1,
+            4 => Self::Rpdo1,
+            5 => Self::Tpdo2,
+            6 => Self::Rpdo2,
+            7 => Self::Tpdo3,
+            8 => Self::Rpdo3,
+            9 => Self::Tpdo4,
+            10 => Self::Rpdo4,
+            11 => Self::SdoTx,
+            12 => Self::SdoRx,
+            14 => Self::Heartbeat,
+            _ => return None,
+        })
+    }
+}
+
+/// A CANopen node ID, 1–127.
+///
+/// Node ID 0 is not a node: it addresses all nodes in NMT commands and appears in the COB-IDs of
+/// the broadcast objects (NMT, SYNC, TIME).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct NodeId(u8);
+
+impl NodeId {
+    /// The node ID `id`, or `None` outside 1–127.
+    pub const fn new(id: u8) -> Option<Self> {
+        match id {
+            1..=127 => Some(Self(id)),
+            _ => None,
+        }
+    }
+
+    /// The raw node ID.
+    pub const fn as_raw(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<NodeId> for u8 {
+    fn from(id: NodeId) -> Self {
+        id.0
+    }
+}
+
+/// An 11-bit COB-ID: function code (bits 10–7) and node ID (bits 6–0).
+///
+/// The per-node constructors take a [`NodeId`]; [`CobId::new`] takes any raw node ID (truncated to
+/// 7 bits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct CobId(u16);
+
+impl CobId {
+    /// Network management commands.
+    pub const NMT: Self = Self(0x000);
+    /// SYNC.
+    pub const SYNC: Self = Self(0x080);
+    /// TIME stamp.
+    pub const TIME: Self = Self(0x100);
+
+    /// COB-ID from a raw function code (truncated to 4 bits) and node ID.
+    pub const fn new(function_code: u8, node_id: u8) -> Self {
+        Self(((function_code as u16 & 0xF) << 7) | (node_id as u16 & 0x7F))
+    }
+
+    /// COB-ID of `function` for `node_id`.
+    pub const fn of(function: FunctionCode, node_id: NodeId) -> Self {
+        Self::new(function as u8, node_id.0)
+    }
+
+    /// Emergency object of `node_id`.
+    pub const fn emcy(node_id: NodeId) -> Self {
+        Self::of(FunctionCode::SyncEmcy, node_id)
+    }
+
+    /// Transmit PDO `pdo` (1–4) of `node_id`, or `None` for other PDO numbers.
+    pub const fn tpdo(pdo: u8, node_id: NodeId) -> Option<Self> {
+        match pdo {
+            1..=4 => Some(Self::new(1 + 2 * pdo, node_id.0)),
+            _ => None,
+        }
+    }
+
+    /// Receive PDO `pdo` (1–4) of `node_id`, or `None` for other PDO numbers.
+    pub const fn rpdo(pdo: u8, node_id: NodeId) -> Option<Self> {
+        match pdo {
+            1..=4 => Some(Self::new(2 + 2 * pdo, node_id.0)),
+            _ => None,
+        }
+    }
+
+    /// SDO responses sent by `node_id`.
+    pub const fn sdo_tx(node_id: NodeId) -> Self {
+        Self::of(FunctionCode::SdoTx, node_id)
+    }
+
+    /// SDO requests to `node_id`.
+    pub const fn sdo_rx(node_id: NodeId) -> Self {
+        Self::of(FunctionCode::SdoRx, node_id)
+    }
+
+    /// Heartbeat (and node guarding) of `node_id`.
+    pub const fn heartbeat(node_id: NodeId) -> Self {
+        Self::of(FunctionCode::Heartbeat, node_id)
+    }
+
+    /// View a raw 11-bit identifier, or `None` if `raw` has more than 11 bits.
+    pub const fn from_raw(raw: u16) -> Option<Self> {
+        if raw > 0x7FF { None } else { Some(Self(raw)) }
+    }
+
+    /// View `id`, or `None` for an extended identifier.
+    pub fn from_id(id: impl Into<Id>) -> Option<Self> {
+        match id.into() {
+            Id::Standard(id) => Some(id.into()),
+            Id::Extended(_) => None,
+        }
+    }
+
+    /// The raw 11-bit identifier.
+    pub const fn as_raw(&self) -> u16 {
+        self.0
+    }
+
+    /// Raw function code, 0–15.
+    pub const fn function_code(&self) -> u8 {
+        (self.0 >> 7) as u8
+    }
+
+    /// Function code, or `None` outside the predefined connection set.
+    pub const fn function(&self) -> Option<FunctionCode> {
+        FunctionCode::from_raw(self.function_code())
+    }
+
+    /// Raw node ID, 0–127.
+    pub const fn node_id(&self) -> u8 {
+        (self.0 & 0x7F) as u8
+    }
+
+    /// Node ID, or `None` for node ID 0 (the broadcast objects).
+    pub const fn node(&self) -> Option<NodeId> {
+        NodeId::new(self.node_id())
+    }
+
+    /// PDO number (1–4) and direction (`true` for transmit) of a PDO COB-ID.
+    pub const fn pdo(&self) -> Option<(u8, bool)> {
+        match self.function_code() {
+            code @ 3..=10 => Some(((code - 1) / 2, code % 2 == 1)),
+            _ => None,
+        }
+    }
+}
+
+impl From<StandardId> for CobId {
+    fn from(id: StandardId) -> Self {
+        Self(id.as_raw())
+    }
+}
+
+impl From<CobId> for StandardId {
+    fn from(id: CobId) -> Self {
+        // SAFETY: every constructor keeps the value within 11 bits.
+        unsafe { StandardId::new_unchecked(id.0) }
+    }
+}
+
+impl From<CobId> for Id {
+    fn from(id: CobId) -> Self {
+        Id::Standard(id.into())
+    }
+}
+
+impl From<CobId> for crate::Id {
+    fn from(id: CobId) -> Self {
+        crate::Id::Standard(id.into())
+    }
+}
@@ -0,0 +1,286 @@
+//! Change detection on received frames.
+//!
+//! Most traffic on a body or powertrain bus is periodic and repeats the same payload for long
+//! stretches. [`OnChange`] forwards a received frame only when its content differs from the
+//! previous frame with the same ID, so event-driven applications see each change once instead of
+//! every repetition.
+//!
+//! Signals such as alive counters and checksums change in every frame; a don't-care mask excludes
+//! them from the comparison.
+
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::frame_eq::FrameEq;
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+#[derive(Debug)]
+struct Entry<F> {
+    id: Id,
+    frame: F,
+    /// Value of the use counter when the entry was last hit, for replacement.
+    used: u32,
+}
+
+/// Receive wrapper that drops frames whose content is unchanged from the previous frame with the
+/// same ID.
+///
+/// The first frame of every ID is forwarded. State is kept for up to `N` IDs; when the table is
+/// full the least recently seen entry is replaced, so an ID that falls out of the table has its
+/// next frame forwarded again. Transmission is passed through unchanged.
+#[derive(Debug)]
+pub struct OnChange<R, F, const N: usize> {
+    inner: R,
+    /// Bits compared in each of the first 8 data bytes.
+    mask: [u8; 8],
+    seen: [Option<Entry<F>>; N],
+    uses: u32,
+    suppressed: u32,
+}
+
+impl<R, F, const N: usize> OnChange<R, F, N> {
+    /// Wrap `inner`, comparing all data bits.
+    pub fn new(inner: R) -> Self {
+        Self::with_mask(inner, [0xFF; 8])
+    }
+
+    /// Wrap `inner`, comparing only the data bits set in `mask`.
+    ///
+    /// `mask[i]` applies to data byte `i`; bytes beyond the eighth (CAN FD) are always compared
+    /// in full. A change in length or frame type always counts as a change.
+    pub fn with_mask(inner: R, mask: [u8; 8]) -> Self {
+        const { assert!(N > 0, "OnChange needs at least one ID slot") };
+        Self {
+            inner,
+            mask,
+            seen: core::array::from_fn(|_| None),
+            uses: 0,
+            suppressed: 0,
+        }
+    }
+
+    /// Replace the don't-care mask; see [`OnChange::with_mask`].
+    pub fn set_mask(&mut self, mask: [u8; 8]) {
+        self.mask = mask;
+    }
+
+    /// Number of frames dropped as unchanged.
+    pub fn suppressed(&self) -> u32 {
+        self.suppressed
+    }
+
+    /// Forget the last frame of `id`, so its next frame is forwarded.
+    pub fn forget(&mut self, id: impl Into<Id>) {
+        let id = id.into();
+        for entry in &mut self.seen {
+            if entry.as_ref().is_some_and(|entry| entry.id == id) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Forget all per-ID state.
+    pub fn clear(&mut self) {
+        self.seen.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R, F: Frame + Clone, const N: usize> OnChange<R, F, N> {
+    fn unchanged(&self, a: &F, b: &F) -> bool {
+        FrameEq::new().mask(&self.mask).content_eq(a, b)
+    }
+
+    /// Record `frame` and return `true` if it should be delivered.
+    fn admit(&mut self, frame: &F) -> bool {
+        self.uses = self.uses.wrapping_add(1);
+        let id = frame.id();
+        if let Some(index) = self
+            .seen
+            .iter()
+            .position(|e| e.as_ref().is_some_and(|e| e.id == id))
+        {
+            let unchanged = self.seen[index]
+                .as_ref()
+                .is_some_and(|entry| self.unchanged(&entry.frame, frame));
+            if let Some(entry) = &mut self.seen[index] {
+                entry.used = self.uses;
+                if unchanged {
+                    self.suppressed = self.suppressed.saturating_add(1);
+                    return false;
+                }
+                entry.frame = frame.clone();
+            }
+            return true;
+        }
+        let uses = self.uses;
+        let slot = match self.seen.iter().position(Option::is_none) {
+            Some(free) => free,
+            None => (0..N)
+                .max_by_key(|&index| {
+                    self.seen[index]
+                        .as_ref()
+                        .map_or(0, |entry| uses.wrapping_sub(entry.used))
+                })
+                .unwrap_or(0),
+        };
+        self.seen[slot] = Some(Entry {
+            id,
+            frame: frame.clone(),
+            used: uses,
+        });
+        true
+    }
+}
+
+impl<R: TxFrameIo, F, const N: usize> TxFrameIo for OnChange<R, F, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<R, F, const N: usize> RxFrameIo for OnChange<R, F, N>
+where
+    R: RxFrameIo<Frame = F>,
+    F: Frame + Clone,
+{
+    type Frame = F;
+    type Error = R::Error;
+
+    fn recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.try_recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Receive the next changed frame.
+    ///
+    /// Without a clock the timeout applies to each underlying receive, so a steady stream of
+    /// unchanged frames can extend the call beyond `timeout`.
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv_timeout(timeout)?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Wait until the driver has a frame; it may still turn out to be unchanged.
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<R: AsyncTxFrameIo, F, const N: usize> AsyncTxFrameIo for OnChange<R, F, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<R, F, const N: usize> AsyncRxFrameIo for OnChange<R, F, N>
+where
+    R: AsyncRxFrameIo<Frame = F>,
+    F: Frame + Clone,
+{
+    type Frame = F;
+    type Error = R::Error;
+
+    async fn recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv().await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// See [`RxFrameIo::recv_timeout`] on this type: the timeout applies per underlying receive.
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv_timeout(timeout).await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
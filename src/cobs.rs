@@ -0,0 +1,566 @@
+//! COBS framing, and CAN over a serial byte stream.
+//!
+//! Consistent Overhead Byte Stuffing rewrites a packet so it contains no zero bytes, at a cost of
+//! one byte per 254, which frees zero to mark packet boundaries on a byte stream. A receiver that
+//! joins mid-stream or loses a byte resynchronises at the next zero. [`encode`] and
+//! [`decode_in_place`] implement it.
+//!
+//! `CobsCan` (feature `embedded-io`) carries [`codec`](crate::codec) frames in COBS packets over
+//! any `embedded_io` stream, turning a UART into a [`FrameIo`](crate::FrameIo). That covers
+//! bridges where a second processor owns the CAN controller:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::cobs::CobsCan;
+//!
+//! // Application processor: the CAN controller is on the other end of `uart`.
+//! let mut can: CobsCan<_, MyFrame> = CobsCan::new(uart);
+//! can.send(&request)?;
+//! let response = can.recv()?;
+//!
+//! // Bridge processor: forward in both directions.
+//! if let Ok(frame) = link.try_recv() { twai.send(&frame)?; }
+//! if let Ok(frame) = twai.try_recv() { link.send(&frame)?; }
+//! ```
+//!
+//! Each frame is one packet followed by a zero byte. `try_send` and `try_recv` only move the bytes
+//! the stream reports ready (`embedded_io::WriteReady` and `ReadReady`), so a packet may go out
+//! over several calls; `CobsCan::flush` finishes it. A stream has no notion of time, so
+//! `send_timeout` and `recv_timeout` ignore their timeout and block like `send` and `recv`.
+
+use core::error::Error;
+use core::fmt;
+
+use crate::codec::MAX_ENCODED_LEN;
+#[cfg(feature = "embedded-io")]
+use crate::{
+    Disconnected, RxFrameIo, TxFrameIo,
+    codec::{DecodeError, WireFrame},
+};
+#[cfg(feature = "embedded-io")]
+use core::time::Duration;
+#[cfg(feature = "embedded-io")]
+use embedded_can::Frame;
+
+/// Longest COBS encoding of `len` bytes.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Longest packet carrying one frame, including the zero delimiter.
+pub const MAX_PACKET_LEN: usize = max_encoded_len(MAX_ENCODED_LEN) + 1;
+
+/// Error returned by [`decode_in_place`] for data that is not valid COBS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CobsError;
+
+impl fmt::Display for CobsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid COBS data")
+    }
+}
+
+impl Error for CobsError {}
+
+/// COBS-encode `src` into `dst`, returning the encoded length. No delimiter is appended.
+///
+/// # Panics
+///
+/// If `dst` is shorter than [`max_encoded_len`] of `src.len()`.
+pub fn encode(src: &[u8], dst: &mut [u8]) -> usize {
+    assert!(
+        dst.len() >= max_encoded_len(src.len()),
+        "COBS output buffer too short"
+    );
+    let mut code_at = 0;
+    let mut out = 1;
+    let mut code = 1u8;
+    for &byte in src {
+        if byte != 0 {
+            dst[out] = byte;
+            out += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            dst[code_at] = code;
+            code_at = out;
+            out += 1;
+            code = 1;
+        }
+    }
+    dst[code_at] = code;
+    out
+}
+
+/// Decode the COBS packet `buf` (without its delimiter) in place, returning the decoded length.
+pub fn decode_in_place(buf: &mut [u8]) -> Result<usize, CobsError> {
+    let mut read = 0;
+    let mut out = 0;
+    while read < buf.len() {
+        let code = usize::from(buf[read]);
+        if code == 0 {
+            return Err(CobsError);
+        }
+        let start = read + 1;
+        let end = start + code - 1;
+        if end > buf.len() || buf[start..end].contains(&0) {
+            return Err(CobsError);
+        }
+        buf.copy_within(start..end, out);
+        out += end - start;
+        read = end;
+        if code != 0xFF && read < buf.len() {
+            buf[out] = 0;
+            out += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Error returned by `CobsCan` (feature `embedded-io`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CobsCanError<E> {
+    /// A non-blocking receive found no complete packet, or a non-blocking send found the stream
+    /// still busy with an earlier packet.
+    WouldBlock,
+    /// The stream reached its end.
+    Closed,
+    /// A packet was not a valid encoded frame; it was dropped.
+    Corrupt,
+    /// A packet held a frame the frame type cannot represent; it was dropped.
+    Unsupported,
+    /// The stream reported an error.
+    Io(E),
+}
+
+impl<E> fmt::Display for CobsCanError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WouldBlock => f.write_str("operation would block"),
+            Self::Closed => f.write_str("stream closed"),
+            Self::Corrupt => f.write_str("corrupt frame packet"),
+            Self::Unsupported => f.write_str("frame type cannot represent received frame"),
+            Self::Io(_) => f.write_str("stream error"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for CobsCanError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E: embedded_io::Error> Disconnected for CobsCanError<E> {
+    fn is_disconnected(&self) -> bool {
+        use embedded_io::ErrorKind;
+        match self {
+            Self::Closed => true,
+            Self::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::NotConnected
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// [`FrameIo`](crate::FrameIo) over a COBS-framed `embedded_io` stream (feature `embedded-io`);
+/// see the [module documentation](self).
+///
+/// `F` is the frame type handed to and returned from the traits. Sending needs
+/// `embedded_io::WriteReady` and receiving `embedded_io::ReadReady`, so that `try_send` and
+/// `try_recv` do not block.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+pub struct CobsCan<S, F> {
+    stream: S,
+    tx: [u8; MAX_PACKET_LEN],
+    /// Bytes of `tx` already written; the packet is complete when this reaches `tx_len`.
+    tx_pos: usize,
+    tx_len: usize,
+    rx: [u8; MAX_PACKET_LEN],
+    rx_len: usize,
+    /// Dropping an overlong packet until the next delimiter.
+    discarding: bool,
+    /// Frame read by `wait_not_empty` and not yet returned.
+    peeked: Option<F>,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<S, F> CobsCan<S, F> {
+    /// Carry frames over `stream`.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            tx: [0; MAX_PACKET_LEN],
+            tx_pos: 0,
+            tx_len: 0,
+            rx: [0; MAX_PACKET_LEN],
+            rx_len: 0,
+            discarding: false,
+            peeked: None,
+        }
+    }
+
+    /// Borrow the stream.
+    pub fn inner(&self) -> &S {
+        &self.stream
+    }
+
+    /// Mutably borrow the stream.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Unwrap, returning the stream. Buffered bytes of a partly sent or received packet are lost;
+    /// call [`CobsCan::flush`] first to finish sending.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<S: embedded_io::Write + embedded_io::WriteReady, F: Frame> CobsCan<S, F> {
+    /// Write the rest of a partly sent packet and flush the stream, blocking until done.
+    pub fn flush(&mut self) -> Result<(), CobsCanError<S::Error>> {
+        self.write_pending(true)?;
+        self.stream.flush().map_err(CobsCanError::Io)
+    }
+
+    fn write_frame(&mut self, frame: &F, block: bool) -> Result<(), CobsCanError<S::Error>> {
+        if !self.write_pending(block)? {
+            return Err(CobsCanError::WouldBlock);
+        }
+        let mut raw = [0; MAX_ENCODED_LEN];
+        let len = WireFrame::from_frame(frame).encode(&mut raw);
+        let len = encode(&raw[..len], &mut self.tx);
+        self.tx[len] = 0;
+        self.tx_len = len + 1;
+        self.tx_pos = 0;
+        if block {
+            self.flush()
+        } else {
+            self.write_pending(false).map(drop)
+        }
+    }
+
+    /// Write the buffered packet, returning `false` if bytes remain because the stream was not
+    /// ready and `block` is unset.
+    fn write_pending(&mut self, block: bool) -> Result<bool, CobsCanError<S::Error>> {
+        while self.tx_pos < self.tx_len {
+            if !block && !self.stream.write_ready().map_err(CobsCanError::Io)? {
+                return Ok(false);
+            }
+            let written = self
+                .stream
+                .write(&self.tx[self.tx_pos..self.tx_len])
+                .map_err(CobsCanError::Io)?;
+            if written == 0 {
+                return Err(CobsCanError::Closed);
+            }
+            self.tx_pos += written;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<S: embedded_io::Read + embedded_io::ReadReady, F: Frame> CobsCan<S, F> {
+    fn read_frame(&mut self, block: bool) -> Result<F, CobsCanError<S::Error>> {
+        if let Some(frame) = self.peeked.take() {
+            return Ok(frame);
+        }
+        loop {
+            if let Some(end) = self.rx[..self.rx_len].iter().position(|&b| b == 0) {
+                let result = self.take_packet(end);
+                self.rx.copy_within(end + 1..self.rx_len, 0);
+                self.rx_len -= end + 1;
+                match result {
+                    Some(result) => return result,
+                    None => continue,
+                }
+            }
+            if self.rx_len == self.rx.len() {
+                self.rx_len = 0;
+                self.discarding = true;
+            }
+            if !block && !self.stream.read_ready().map_err(CobsCanError::Io)? {
+                return Err(CobsCanError::WouldBlock);
+            }
+            let read = self
+                .stream
+                .read(&mut self.rx[self.rx_len..])
+                .map_err(CobsCanError::Io)?;
+            if read == 0 {
+                return Err(CobsCanError::Closed);
+            }
+            self.rx_len += read;
+        }
+    }
+
+    /// Decode the packet in `rx[..end]`; `None` for an empty or discarded packet.
+    fn take_packet(&mut self, end: usize) -> Option<Result<F, CobsCanError<S::Error>>> {
+        if core::mem::take(&mut self.discarding) || end == 0 {
+            return None;
+        }
+        let Ok(len) = decode_in_place(&mut self.rx[..end]) else {
+            return Some(Err(CobsCanError::Corrupt));
+        };
+        Some(match WireFrame::decode(&self.rx[..len]) {
+            Ok((wire, used)) if used == len => wire.to_frame().ok_or(CobsCanError::Unsupported),
+            Ok(_) | Err(DecodeError::Incomplete | DecodeError::Invalid) => {
+                Err(CobsCanError::Corrupt)
+            }
+            Err(DecodeError::UnsupportedVersion(_) | DecodeError::Unsupported { .. }) => {
+                Err(CobsCanError::Unsupported)
+            }
+        })
+    }
+}
+
+/// [`TxFrameIo::send`] writes the whole packet and flushes the stream.
+///
+/// [`TxFrameIo::try_send`] never blocks: it fails with [`CobsCanError::WouldBlock`] while an
+/// earlier packet cannot be finished, and otherwise accepts the frame and writes as much of it as
+/// the stream is ready for. The rest goes out on the next send or [`CobsCan::flush`].
+///
+/// The stream cannot time out, so [`TxFrameIo::send_timeout`] ignores its timeout and behaves like
+/// [`TxFrameIo::send`].
+#[cfg(feature = "embedded-io")]
+impl<S, F> TxFrameIo for CobsCan<S, F>
+where
+    S: embedded_io::Write + embedded_io::WriteReady,
+    F: Frame,
+{
+    type Frame = F;
+    type Error = CobsCanError<S::Error>;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.write_frame(frame, true)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.write_frame(frame, false)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, _timeout: Duration) -> Result<(), Self::Error> {
+        self.write_frame(frame, true)
+    }
+}
+
+/// The stream cannot time out, so [`RxFrameIo::recv_timeout`] ignores its timeout and behaves like
+/// [`RxFrameIo::recv`].
+#[cfg(feature = "embedded-io")]
+impl<S, F> RxFrameIo for CobsCan<S, F>
+where
+    S: embedded_io::Read + embedded_io::ReadReady,
+    F: Frame,
+{
+    type Frame = F;
+    type Error = CobsCanError<S::Error>;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.read_frame(true)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.read_frame(false)
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.read_frame(true)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        if self.peeked.is_none() {
+            let frame = self.read_frame(true)?;
+            self.peeked = Some(frame);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(src: &[u8]) {
+        let mut encoded = [0xAA; 600];
+        let len = encode(src, &mut encoded);
+        assert!(len <= max_encoded_len(src.len()));
+        assert!(!encoded[..len].contains(&0), "zero in encoding of {src:?}");
+        assert_eq!(decode_in_place(&mut encoded[..len]), Ok(src.len()));
+        assert_eq!(&encoded[..src.len()], src);
+    }
+
+    #[test]
+    fn encodings() {
+        let mut buf = [0; 8];
+        assert_eq!(encode(&[], &mut buf), 1);
+        assert_eq!(buf[0], 0x01);
+        assert_eq!(encode(&[0x11, 0x00], &mut buf), 3);
+        assert_eq!(buf[..3], [0x02, 0x11, 0x01]);
+        assert_eq!(encode(&[0x00, 0x00], &mut buf), 3);
+        assert_eq!(buf[..3], [0x01, 0x01, 0x01]);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&[]);
+        round_trip(&[0]);
+        round_trip(&[0, 0, 0]);
+        round_trip(&[1, 2, 0, 0]);
+        let mut data = [0; 520];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i % 255) as u8 + 1;
+        }
+        for len in [253, 254, 255, 508, 509, 510] {
+            round_trip(&data[..len]);
+            // The same run followed by zeros, which must not be lost at a block boundary.
+            let mut with_zeros = data;
+            with_zeros[len..len + 2].fill(0);
+            round_trip(&with_zeros[..len + 2]);
+        }
+    }
+
+    #[test]
+    fn long_runs_split() {
+        let data = [0x42; 255];
+        let mut encoded = [0; 300];
+        let len = encode(&data, &mut encoded);
+        assert_eq!(len, 257);
+        assert_eq!((encoded[0], encoded[255], encoded[256]), (0xFF, 0x02, 0x42));
+        let len = encode(&data[..254], &mut encoded);
+        assert_eq!(len, 256);
+        assert_eq!((encoded[0], encoded[255]), (0xFF, 0x01));
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert_eq!(decode_in_place(&mut [0x00]), Err(CobsError));
+        assert_eq!(decode_in_place(&mut [0x03, 0x11]), Err(CobsError));
+        assert_eq!(decode_in_place(&mut [0x03, 0x11, 0x00]), Err(CobsError));
+    }
+
+    #[cfg(feature = "embedded-io")]
+    mod stream {
+        use core::convert::Infallible;
+
+        use super::*;
+        use crate::testdata::TestFrame;
+
+        /// In-memory byte stream: what is written is read back.
+        struct Pipe {
+            buf: [u8; 4096],
+            read: usize,
+            written: usize,
+        }
+
+        impl Pipe {
+            fn new() -> Self {
+                Self {
+                    buf: [0; 4096],
+                    read: 0,
+                    written: 0,
+                }
+            }
+
+            fn push(&mut self, bytes: &[u8]) {
+                self.buf[self.written..self.written + bytes.len()].copy_from_slice(bytes);
+                self.written += bytes.len();
+            }
+        }
+
+        impl embedded_io::ErrorType for Pipe {
+            type Error = Infallible;
+        }
+
+        impl embedded_io::Read for Pipe {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+                let len = buf.len().min(self.written - self.read);
+                buf[..len].copy_from_slice(&self.buf[self.read..self.read + len]);
+                self.read += len;
+                Ok(len)
+            }
+        }
+
+        impl embedded_io::ReadReady for Pipe {
+            fn read_ready(&mut self) -> Result<bool, Infallible> {
+                Ok(self.read < self.written)
+            }
+        }
+
+        impl embedded_io::Write for Pipe {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+                self.push(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> Result<(), Infallible> {
+                Ok(())
+            }
+        }
+
+        impl embedded_io::WriteReady for Pipe {
+            fn write_ready(&mut self) -> Result<bool, Infallible> {
+                Ok(true)
+            }
+        }
+
+        fn frames() -> [TestFrame; 3] {
+            [
+                TestFrame::standard(0x123, &[0, 1, 0, 0]),
+                TestFrame::extended(0x1000_0000, &[0; 8]),
+                TestFrame::standard(0x7FF, &[0xFF; 64]),
+            ]
+        }
+
+        #[test]
+        fn loopback() {
+            let mut can = CobsCan::<_, TestFrame>::new(Pipe::new());
+            for frame in frames() {
+                can.send(&frame).unwrap();
+            }
+            assert!(can.wait_not_empty().is_ok());
+            for frame in frames() {
+                assert_eq!(can.try_recv(), Ok(frame));
+            }
+            assert_eq!(can.try_recv(), Err(CobsCanError::WouldBlock));
+            assert_eq!(can.recv(), Err(CobsCanError::Closed));
+        }
+
+        #[test]
+        fn corrupt_packets_dropped() {
+            let mut can = CobsCan::<_, TestFrame>::new(Pipe::new());
+            // Truncated COBS block.
+            can.inner_mut().push(&[0x05, 0x01, 0x02, 0x00]);
+            // Valid COBS, but not an encoded frame.
+            can.inner_mut().push(&[0x03, 0x12, 0x34, 0x00]);
+            // Empty packets between delimiters are skipped.
+            can.inner_mut().push(&[0x00, 0x00]);
+            let [frame, ..] = frames();
+            can.send(&frame).unwrap();
+            assert_eq!(can.try_recv(), Err(CobsCanError::Corrupt));
+            assert_eq!(can.try_recv(), Err(CobsCanError::Corrupt));
+            assert_eq!(can.try_recv(), Ok(frame));
+        }
+
+        #[test]
+        fn overlong_packet_dropped() {
+            let mut can = CobsCan::<_, TestFrame>::new(Pipe::new());
+            can.inner_mut().push(&[0x01; 3 * MAX_PACKET_LEN]);
+            can.inner_mut().push(&[0x00]);
+            let [_, frame, _] = frames();
+            can.send(&frame).unwrap();
+            assert_eq!(can.try_recv(), Ok(frame));
+            assert_eq!(can.try_recv(), Err(CobsCanError::WouldBlock));
+        }
+    }
+}
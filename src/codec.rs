@@ -0,0 +1,581 @@
+//! Canonical binary encoding of frames.
+//!
+//! Transports that carry frames as bytes (the [`stream`](crate::stream) capture, recorded logs
+//! fed to [`Replay`](crate::replay::Replay), links between processors) share this one versioned
+//! format rather than each inventing its own. [`WireFrame`] holds everything the format can
+//! express; [`encode_frame`] and [`decode_frame`] convert directly from and to a frame type:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::codec::{self, MAX_ENCODED_LEN};
+//!
+//! let mut buf = [0; MAX_ENCODED_LEN];
+//! let len = codec::encode_frame(&frame, Some(clock.now()), &mut buf);
+//! link.write_all(&buf[..len])?;
+//!
+//! // Replay a recorded capture.
+//! let mut rx = Replay::paced(codec::decode_frames::<MyFrame>(&capture), clock);
+//! ```
+//!
+//! # Format (version 1)
+//!
+//! | Bytes | Content |
+//! |-------|---------|
+//! | 1 | `0xA0` plus the format version |
+//! | 1 | flags, below |
+//! | 2 or 4 | identifier, little-endian; 4 bytes if extended |
+//! | 1 | DLC, 0–15 |
+//! | 1 | number of data bytes, 0–64; 0 for a remote frame |
+//! | 0 or 8 | timestamp in microseconds, little-endian, if present |
+//! | 0–64 | data |
+//!
+//! | Flag | Meaning |
+//! |------|---------|
+//! | `0x01` | extended identifier |
+//! | `0x02` | remote frame |
+//! | `0x04` | CAN FD frame |
+//! | `0x08` | bitrate switch (BRS) |
+//! | `0x10` | error state indicator (ESI) |
+//! | `0x20` | timestamp present |
+//! | `0x40` | frame sent by the producer rather than received |
+//! | `0x80` | reserved, zero |
+//!
+//! A classic frame with 8 data bytes and a timestamp takes 22 bytes. The timestamp's epoch is the
+//! producer's clock. Decoders reject other versions with [`DecodeError::UnsupportedVersion`]; the
+//! fixed upper bits of the first byte let stream decoders resynchronise after lost bytes.
+
+use core::error::Error;
+use core::fmt;
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+pub use crate::blackbox::Direction;
+use crate::frame_eq::{FdFlags, dlc_to_len};
+
+/// Version of the format written by this module.
+pub const VERSION: u8 = 1;
+
+/// Longest encoding: an extended identifier, a timestamp and 64 data bytes.
+pub const MAX_ENCODED_LEN: usize = 2 + 4 + 2 + 8 + 64;
+
+const MAGIC: u8 = 0xA0;
+const EXTENDED: u8 = 0x01;
+const REMOTE: u8 = 0x02;
+const FD: u8 = 0x04;
+const BRS: u8 = 0x08;
+const ESI: u8 = 0x10;
+const TIMESTAMP: u8 = 0x20;
+const TX: u8 = 0x40;
+const RESERVED: u8 = 0x80;
+
+/// Error returned when decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer ends within the encoding.
+    Incomplete,
+    /// The buffer does not start with an encoded frame.
+    Invalid,
+    /// An encoding of a format version this module does not read.
+    UnsupportedVersion(u8),
+    /// A well-formed encoding of `len` bytes that the frame type cannot represent, e.g. a CAN FD
+    /// frame decoded into a classic frame type.
+    Unsupported {
+        /// Length of the encoding.
+        len: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incomplete => f.write_str("incomplete encoded frame"),
+            Self::Invalid => f.write_str("invalid encoded frame"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported frame encoding version {version}")
+            }
+            Self::Unsupported { .. } => f.write_str("frame type cannot represent encoded frame"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// A frame with everything the format carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireFrame {
+    /// Identifier.
+    pub id: Id,
+    /// Remote frame.
+    pub remote: bool,
+    /// CAN FD frame.
+    pub fd: bool,
+    /// Bitrate switch (BRS) of a CAN FD frame.
+    pub bitrate_switch: bool,
+    /// Error state indicator (ESI) of a CAN FD frame.
+    pub error_state_indicator: bool,
+    /// Whether the producer sent or received the frame; [`Direction::Rx`] unless it recorded a sent
+    /// frame.
+    pub direction: Direction,
+    /// Timestamp on the producer's clock, if it recorded one.
+    pub timestamp: Option<Duration>,
+    dlc: u8,
+    len: u8,
+    data: [u8; 64],
+}
+
+impl WireFrame {
+    /// Capture `frame`; a frame with more than 8 data bytes is marked as CAN FD.
+    pub fn from_frame<F: Frame>(frame: &F) -> Self {
+        let remote = frame.is_remote_frame();
+        let payload = if remote { &[][..] } else { frame.data() };
+        let payload = &payload[..payload.len().min(64)];
+        let mut data = [0; 64];
+        data[..payload.len()].copy_from_slice(payload);
+        let dlc = match frame.dlc() {
+            dlc @ 0..=15 => dlc as u8,
+            // A byte count rather than a DLC code.
+            _ => len_to_dlc(payload.len()),
+        };
+        Self {
+            id: frame.id(),
+            remote,
+            fd: payload.len() > 8,
+            bitrate_switch: false,
+            error_state_indicator: false,
+            direction: Direction::Rx,
+            timestamp: None,
+            dlc,
+            len: payload.len() as u8,
+            data,
+        }
+    }
+
+    /// Capture `frame` with its CAN FD flags.
+    pub fn from_fd_frame<F: Frame + FdFlags>(frame: &F) -> Self {
+        Self {
+            fd: frame.is_fd(),
+            bitrate_switch: frame.bitrate_switch(),
+            error_state_indicator: frame.error_state_indicator(),
+            ..Self::from_frame(frame)
+        }
+    }
+
+    /// Set the timestamp.
+    pub fn with_timestamp(mut self, at: Duration) -> Self {
+        self.timestamp = Some(at);
+        self
+    }
+
+    /// Set the direction.
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// The DLC, 0–15.
+    pub fn dlc(&self) -> u8 {
+        self.dlc
+    }
+
+    /// The data bytes; empty for a remote frame.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+
+    /// Convert to a frame type, or `None` if it cannot represent the frame.
+    ///
+    /// CAN FD flags are not carried over; frame types that have them read them from the fields.
+    pub fn to_frame<F: Frame>(&self) -> Option<F> {
+        if self.remote {
+            F::new_remote(self.id, usize::from(self.dlc))
+        } else {
+            F::new(self.id, self.data())
+        }
+    }
+
+    /// Length of the encoding.
+    pub fn encoded_len(&self) -> usize {
+        let id = if matches!(self.id, Id::Extended(_)) {
+            4
+        } else {
+            2
+        };
+        let timestamp = if self.timestamp.is_some() { 8 } else { 0 };
+        2 + id + 2 + timestamp + self.payload().len()
+    }
+
+    /// Data bytes written: none for a remote frame, even if fields were changed after capture.
+    fn payload(&self) -> &[u8] {
+        if self.remote { &[] } else { self.data() }
+    }
+
+    /// Encode into `buf`, returning the length of the encoding.
+    pub fn encode(&self, buf: &mut [u8; MAX_ENCODED_LEN]) -> usize {
+        let data = self.payload();
+        let mut flags = 0;
+        for (set, flag) in [
+            (self.remote, REMOTE),
+            (!self.remote && (self.fd || data.len() > 8), FD),
+            (self.bitrate_switch, BRS),
+            (self.error_state_indicator, ESI),
+            (self.timestamp.is_some(), TIMESTAMP),
+            (self.direction == Direction::Tx, TX),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        buf[0] = MAGIC | VERSION;
+        let mut at = 2;
+        match self.id {
+            Id::Standard(id) => {
+                buf[at..at + 2].copy_from_slice(&id.as_raw().to_le_bytes());
+                at += 2;
+            }
+            Id::Extended(id) => {
+                flags |= EXTENDED;
+                buf[at..at + 4].copy_from_slice(&id.as_raw().to_le_bytes());
+                at += 4;
+            }
+        }
+        buf[1] = flags;
+        buf[at] = self.dlc;
+        buf[at + 1] = data.len() as u8;
+        at += 2;
+        if let Some(timestamp) = self.timestamp {
+            let micros = u64::try_from(timestamp.as_micros()).unwrap_or(u64::MAX);
+            buf[at..at + 8].copy_from_slice(&micros.to_le_bytes());
+            at += 8;
+        }
+        buf[at..at + data.len()].copy_from_slice(data);
+        at + data.len()
+    }
+
+    /// Decode the encoding at the start of `buf`, returning it and its length.
+    pub fn decode(buf: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let magic = *buf.first().ok_or(DecodeError::Incomplete)?;
+        if magic & 0xF0 != MAGIC {
+            return Err(DecodeError::Invalid);
+        }
+        if magic & 0x0F != VERSION {
+            return Err(DecodeError::UnsupportedVersion(magic & 0x0F));
+        }
+        let flags = *buf.get(1).ok_or(DecodeError::Incomplete)?;
+        if flags & RESERVED != 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let bytes = |at: usize, len: usize| buf.get(at..at + len).ok_or(DecodeError::Incomplete);
+        let (id, mut at) = if flags & EXTENDED != 0 {
+            let raw = u32::from_le_bytes(bytes(2, 4)?.try_into().unwrap());
+            (
+                Id::Extended(ExtendedId::new(raw).ok_or(DecodeError::Invalid)?),
+                6,
+            )
+        } else {
+            let raw = u16::from_le_bytes(bytes(2, 2)?.try_into().unwrap());
+            (
+                Id::Standard(StandardId::new(raw).ok_or(DecodeError::Invalid)?),
+                4,
+            )
+        };
+        let lengths = bytes(at, 2)?;
+        let (dlc, len) = (lengths[0], lengths[1]);
+        at += 2;
+        let remote = flags & REMOTE != 0;
+        let fd = flags & FD != 0;
+        if dlc > 15 || len > 64 || (remote && (len != 0 || fd)) || (!fd && len > 8) {
+            return Err(DecodeError::Invalid);
+        }
+        let timestamp = if flags & TIMESTAMP != 0 {
+            let micros = u64::from_le_bytes(bytes(at, 8)?.try_into().unwrap());
+            at += 8;
+            Some(Duration::from_micros(micros))
+        } else {
+            None
+        };
+        let payload = bytes(at, usize::from(len))?;
+        let mut data = [0; 64];
+        data[..payload.len()].copy_from_slice(payload);
+        let frame = Self {
+            id,
+            remote,
+            fd,
+            bitrate_switch: flags & BRS != 0,
+            error_state_indicator: flags & ESI != 0,
+            direction: if flags & TX != 0 {
+                Direction::Tx
+            } else {
+                Direction::Rx
+            },
+            timestamp,
+            dlc,
+            len,
+            data,
+        };
+        Ok((frame, at + payload.len()))
+    }
+}
+
+/// Smallest DLC code covering `len` data bytes.
+fn len_to_dlc(len: usize) -> u8 {
+    (0..=15u8)
+        .find(|&dlc| dlc_to_len(usize::from(dlc)) >= len)
+        .unwrap_or(15)
+}
+
+/// Encode `frame` with an optional timestamp into `buf`, returning the length of the encoding.
+pub fn encode_frame<F: Frame>(
+    frame: &F,
+    timestamp: Option<Duration>,
+    buf: &mut [u8; MAX_ENCODED_LEN],
+) -> usize {
+    let mut wire = WireFrame::from_frame(frame);
+    wire.timestamp = timestamp;
+    wire.encode(buf)
+}
+
+/// Decode the frame at the start of `buf`, returning it, its timestamp and the length of the
+/// encoding.
+pub fn decode_frame<F: Frame>(buf: &[u8]) -> Result<(F, Option<Duration>, usize), DecodeError> {
+    let (wire, len) = WireFrame::decode(buf)?;
+    let frame = wire.to_frame().ok_or(DecodeError::Unsupported { len })?;
+    Ok((frame, wire.timestamp, len))
+}
+
+/// Decode the encodings in `buf`; see [`WireFrames`].
+pub fn decode_wire_frames(buf: &[u8]) -> WireFrames<'_> {
+    WireFrames { buf, skipped: 0 }
+}
+
+/// Decode the frames in `buf` as `(timestamp, frame)` pairs, the input of
+/// [`Replay`](crate::replay::Replay); see [`Frames`].
+pub fn decode_frames<F: Frame>(buf: &[u8]) -> Frames<'_, F> {
+    Frames {
+        wire: decode_wire_frames(buf),
+        _frame: PhantomData,
+    }
+}
+
+/// Iterator over the encoded frames in a byte buffer.
+///
+/// Bytes that do not start an encoding of the supported version are skipped and counted by
+/// [`WireFrames::skipped`]. Iteration stops at an encoding cut off by the end of the buffer, which
+/// [`WireFrames::remaining`] returns so it can be completed with the next chunk of a stream.
+#[derive(Debug, Clone)]
+pub struct WireFrames<'a> {
+    buf: &'a [u8],
+    skipped: usize,
+}
+
+impl<'a> WireFrames<'a> {
+    /// The bytes not decoded yet: after iteration, the start of an incomplete encoding, if any.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Number of bytes skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl Iterator for WireFrames<'_> {
+    type Item = WireFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match WireFrame::decode(self.buf) {
+                Ok((frame, len)) => {
+                    self.buf = &self.buf[len..];
+                    return Some(frame);
+                }
+                Err(DecodeError::Incomplete) => return None,
+                Err(_) => {
+                    self.buf = &self.buf[1..];
+                    self.skipped += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over the frames in a byte buffer, as `(timestamp, frame)`.
+///
+/// Like [`WireFrames`], also skipping frames the frame type cannot represent. Frames without a
+/// timestamp have a timestamp of zero.
+#[derive(Debug, Clone)]
+pub struct Frames<'a, F> {
+    wire: WireFrames<'a>,
+    _frame: PhantomData<F>,
+}
+
+impl<'a, F> Frames<'a, F> {
+    /// See [`WireFrames::remaining`].
+    pub fn remaining(&self) -> &'a [u8] {
+        self.wire.remaining()
+    }
+
+    /// Number of bytes skipped so far, including frames the frame type cannot represent.
+    pub fn skipped(&self) -> usize {
+        self.wire.skipped()
+    }
+}
+
+impl<F: Frame> Iterator for Frames<'_, F> {
+    type Item = (Duration, F);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let wire = self.wire.next()?;
+            match wire.to_frame() {
+                Some(frame) => return Some((wire.timestamp.unwrap_or_default(), frame)),
+                None => self.wire.skipped += wire.encoded_len(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::TestFrame;
+
+    fn encode(frame: &WireFrame) -> ([u8; MAX_ENCODED_LEN], usize) {
+        let mut buf = [0; MAX_ENCODED_LEN];
+        let len = frame.encode(&mut buf);
+        assert_eq!(len, frame.encoded_len());
+        (buf, len)
+    }
+
+    fn round_trip(frame: WireFrame) {
+        let (buf, len) = encode(&frame);
+        assert_eq!(WireFrame::decode(&buf[..len]), Ok((frame, len)));
+        for cut in 0..len {
+            assert_eq!(WireFrame::decode(&buf[..cut]), Err(DecodeError::Incomplete));
+        }
+    }
+
+    fn standard() -> WireFrame {
+        WireFrame::from_frame(&TestFrame::standard(0x123, &[1, 2, 3]))
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(standard());
+        round_trip(WireFrame::from_frame(&TestFrame::standard(0x7FF, &[])));
+        round_trip(WireFrame::from_frame(&TestFrame::extended(
+            0x1ABC_DEF0,
+            &[0xFF; 8],
+        )));
+        let remote = TestFrame::new_remote(StandardId::new(0x456).unwrap(), 4).unwrap();
+        let remote = WireFrame::from_frame(&remote);
+        assert!(remote.remote && remote.data().is_empty() && remote.dlc() == 4);
+        round_trip(remote);
+        let data: [u8; 64] = core::array::from_fn(|i| i as u8);
+        let fd = WireFrame::from_frame(&TestFrame::extended(0x42, &data));
+        assert!(fd.fd && fd.dlc() == 15);
+        round_trip(fd);
+        round_trip(WireFrame {
+            bitrate_switch: true,
+            error_state_indicator: true,
+            ..WireFrame::from_frame(&TestFrame::standard(0x10, &[0; 12]))
+        });
+        round_trip(
+            standard()
+                .with_timestamp(Duration::from_micros(1_234_567))
+                .with_direction(Direction::Tx),
+        );
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = TestFrame::extended(0x1234, &[9; 20]);
+        let at = Duration::from_micros(42);
+        let mut buf = [0; MAX_ENCODED_LEN];
+        let len = encode_frame(&frame, Some(at), &mut buf);
+        assert_eq!(decode_frame(&buf[..len]), Ok((frame, Some(at), len)));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let (buf, len) = encode(&standard());
+        let decode = |edit: &dyn Fn(&mut [u8])| {
+            let mut buf = buf;
+            edit(&mut buf);
+            WireFrame::decode(&buf[..len]).map(|_| ())
+        };
+        assert_eq!(decode(&|_| {}), Ok(()));
+        assert_eq!(decode(&|b| b[0] = 0x50), Err(DecodeError::Invalid));
+        assert_eq!(
+            decode(&|b| b[0] = MAGIC | 2),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+        assert_eq!(decode(&|b| b[1] |= RESERVED), Err(DecodeError::Invalid));
+        // Identifier too wide for a standard identifier.
+        assert_eq!(decode(&|b| b[3] = 0x08), Err(DecodeError::Invalid));
+        // DLC above 15.
+        assert_eq!(decode(&|b| b[4] = 16), Err(DecodeError::Invalid));
+        // More than 8 data bytes without the FD flag, more than 64 with it.
+        assert_eq!(decode(&|b| b[5] = 9), Err(DecodeError::Invalid));
+        assert_eq!(
+            decode(&|b| {
+                b[1] |= FD;
+                b[5] = 65;
+            }),
+            Err(DecodeError::Invalid)
+        );
+
+        let remote = TestFrame::new_remote(StandardId::new(0x456).unwrap(), 4).unwrap();
+        let (buf, len) = encode(&WireFrame::from_frame(&remote));
+        let decode = |edit: &dyn Fn(&mut [u8])| {
+            let mut buf = buf;
+            edit(&mut buf);
+            WireFrame::decode(&buf[..len]).map(|_| ())
+        };
+        assert_eq!(decode(&|_| {}), Ok(()));
+        assert_eq!(decode(&|b| b[1] |= FD), Err(DecodeError::Invalid));
+        assert_eq!(decode(&|b| b[5] = 1), Err(DecodeError::Invalid));
+    }
+
+    #[test]
+    fn unrepresentable_frame() {
+        // A remote frame with an FD length code, which `TestFrame` cannot build.
+        let mut buf = [MAGIC | VERSION, REMOTE, 0x23, 0x01, 12, 0];
+        assert_eq!(
+            decode_frame::<TestFrame>(&buf),
+            Err(DecodeError::Unsupported { len: 6 })
+        );
+        buf[4] = 8;
+        assert!(decode_frame::<TestFrame>(&buf).is_ok());
+    }
+
+    #[test]
+    fn resynchronises_after_garbage() {
+        let first = standard();
+        let second = WireFrame::from_frame(&TestFrame::extended(0x1000, &[7; 8]))
+            .with_timestamp(Duration::from_millis(5));
+        let mut stream = [0; 3 * MAX_ENCODED_LEN];
+        let mut at = 0;
+        let mut push = |bytes: &[u8]| {
+            stream[at..at + bytes.len()].copy_from_slice(bytes);
+            at += bytes.len();
+        };
+        // Bytes outside the magic range, then a magic byte with a reserved flag.
+        push(&[0x00, 0x55, 0xFF, MAGIC | VERSION, RESERVED]);
+        let (buf, len) = encode(&first);
+        push(&buf[..len]);
+        push(&[0x13, 0x37]);
+        let (buf, len) = encode(&second);
+        push(&buf[..len]);
+        // The start of a third frame.
+        push(&buf[..3]);
+
+        let mut frames = decode_wire_frames(&stream[..at]);
+        assert_eq!(frames.next(), Some(first));
+        assert_eq!(frames.next(), Some(second));
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.skipped(), 7);
+        assert_eq!(frames.remaining(), &buf[..3]);
+    }
+}
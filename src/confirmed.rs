@@ -0,0 +1,614 @@
+//! On-bus delivery confirmation via self-reception.
+//!
+//! A successful [`TxFrameIo::send`] only means the driver accepted the frame. When the interface
+//! echoes transmitted frames back on the receive path (see [`SelfReceptionControl`]), seeing the
+//! echo proves the frame actually won arbitration and was acknowledged. [`ConfirmedTx`] waits for
+//! that echo, which works even on controllers that do not expose TX-complete interrupts.
+//!
+//! [`ConfirmedTx::send_verified`] builds on this with retries and a fallback to
+//! [`TxRxState::is_transmitter_idle`] for backends without self-reception, reporting which
+//! mechanism confirmed delivery through [`Delivery`].
+//!
+//! [`SelfReceptionControl`]: crate::SelfReceptionControl
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::buffered::FrameQueue;
+use crate::frame_eq::FrameEq;
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+    TxRxState,
+};
+
+/// Error returned by [`ConfirmedTx::send_confirmed`] and [`ConfirmedTx::send_verified`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// Delivery was not confirmed before the timeout elapsed (on every attempt).
+    Timeout,
+}
+
+impl<E> fmt::Display for ConfirmError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Timeout => f.write_str("delivery not confirmed within the timeout"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ConfirmError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// How [`ConfirmedTx::send_verified`] confirmed delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The frame was observed on the receive path via self-reception.
+    Echoed {
+        /// Number of transmission attempts, including the successful one.
+        attempts: u32,
+    },
+    /// The transmitter drained its queue, so the frame left the controller.
+    ///
+    /// Weaker than [`Delivery::Echoed`]: it is used when the interface does not echo frames.
+    TxComplete {
+        /// Number of transmission attempts, including the successful one.
+        attempts: u32,
+    },
+}
+
+/// Retry policy for [`ConfirmedTx::send_verified`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of transmission attempts (at least one is always made).
+    pub max_attempts: u32,
+    /// Time allowed for each attempt to be sent and confirmed.
+    pub attempt_timeout: Duration,
+    /// Consecutive attempts that must find the transmitter idle without an echo before the
+    /// interface is assumed not to echo frames (at least one).
+    pub echo_misses: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            attempt_timeout: Duration::from_millis(50),
+            echo_misses: 3,
+        }
+    }
+}
+
+fn is_echo<F: Frame>(sent: &F, received: &F) -> bool {
+    FrameEq::new().eq(sent, received)
+}
+
+/// Wrapper providing [`ConfirmedTx::send_confirmed`] and [`ConfirmedTx::send_verified`].
+///
+/// Self-reception must be enabled on the wrapped interface. Frames received while waiting for an
+/// echo are kept in the side queue `Q` and returned by subsequent receive calls in order; if the
+/// side queue overflows, the oldest frames are dropped and counted in [`ConfirmedTx::dropped`].
+#[derive(Debug)]
+pub struct ConfirmedTx<T, C, Q> {
+    inner: T,
+    clock: C,
+    pending: Q,
+    dropped: u32,
+    echo: Option<bool>,
+    /// Consecutive attempts that went unechoed while echo support was unknown.
+    misses: u32,
+}
+
+impl<T, C, Q> ConfirmedTx<T, C, Q> {
+    /// Wrap `inner`, using `pending` to hold frames received while waiting for echoes.
+    pub fn new(inner: T, clock: C, pending: Q) -> Self {
+        Self {
+            inner,
+            clock,
+            pending,
+            dropped: 0,
+            echo: None,
+            misses: 0,
+        }
+    }
+
+    /// Whether the wrapped interface echoes transmitted frames, if known yet.
+    ///
+    /// [`ConfirmedTx::send_verified`] learns this from the first echo, or from
+    /// [`RetryPolicy::echo_misses`] consecutive attempts that get a TX-complete indication without
+    /// one.
+    pub fn echo_supported(&self) -> Option<bool> {
+        self.echo
+    }
+
+    /// Override echo detection, e.g. after enabling or disabling self-reception.
+    pub fn set_echo_supported(&mut self, echo: Option<bool>) {
+        self.echo = echo;
+        self.misses = 0;
+    }
+
+    /// Number of received frames dropped because the side queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface and the side queue.
+    pub fn into_parts(self) -> (T, Q) {
+        (self.inner, self.pending)
+    }
+
+    fn stash<F>(&mut self, frame: F)
+    where
+        Q: FrameQueue<F>,
+    {
+        if let Err(frame) = self.pending.push(frame) {
+            self.pending.pop();
+            self.dropped = self.dropped.saturating_add(1);
+            let _ = self.pending.push(frame);
+        }
+    }
+}
+
+impl<T, C, Q, F, E> ConfirmedTx<T, C, Q>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    Q: FrameQueue<F>,
+    F: Frame,
+{
+    /// Send `frame` and wait up to `timeout` for its echo on the receive path.
+    pub fn send_confirmed(&mut self, frame: &F, timeout: Duration) -> Result<(), ConfirmError<E>> {
+        let deadline = self.clock.now() + timeout;
+        self.inner
+            .send_timeout(frame, timeout)
+            .map_err(ConfirmError::Io)?;
+        match self.wait_echo(frame, deadline)? {
+            true => Ok(()),
+            false => Err(ConfirmError::Timeout),
+        }
+    }
+
+    /// Wait until `deadline` for the echo of `frame`, stashing other frames.
+    fn wait_echo(&mut self, frame: &F, deadline: Duration) -> Result<bool, ConfirmError<E>> {
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            match self.inner.recv_timeout(remaining) {
+                Ok(received) if is_echo(frame, &received) => return Ok(true),
+                Ok(received) => self.stash(received),
+                Err(_) if self.clock.now() >= deadline => return Ok(false),
+                Err(e) => return Err(ConfirmError::Io(e)),
+            }
+        }
+    }
+}
+
+impl<T, C, Q, F, E> ConfirmedTx<T, C, Q>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E> + TxRxState<Error = E>,
+    C: Clock,
+    Q: FrameQueue<F>,
+    F: Frame,
+{
+    /// Send `frame` and confirm delivery by the best mechanism the interface supports.
+    ///
+    /// Each attempt waits for the echo of `frame`. Until an echo has been seen once, an attempt
+    /// that times out but finds the transmitter idle confirms delivery as
+    /// [`Delivery::TxComplete`]; after [`RetryPolicy::echo_misses`] such attempts in a row the
+    /// interface is taken not to echo frames, and from then on attempts poll
+    /// [`TxRxState::is_transmitter_idle`] instead of waiting for an echo.
+    ///
+    /// An attempt that is not confirmed within [`RetryPolicy::attempt_timeout`] is retried, up to
+    /// [`RetryPolicy::max_attempts`], after which [`ConfirmError::Timeout`] is returned. A frame is
+    /// only sent again once the transmitter is idle, so a copy still waiting in the controller is
+    /// not duplicated; the attempt keeps waiting for that copy instead. Before sending again,
+    /// frames already received are checked for a late echo of the previous copy, which confirms
+    /// that copy rather than the next one.
+    pub fn send_verified(
+        &mut self,
+        frame: &F,
+        policy: &RetryPolicy,
+    ) -> Result<Delivery, ConfirmError<E>> {
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempts = 0;
+        for window in 1..=max_attempts {
+            let last = window == max_attempts;
+            let deadline = self.clock.now() + policy.attempt_timeout;
+            let send = if attempts == 0 {
+                true
+            } else if self.inner.is_transmitter_idle().map_err(ConfirmError::Io)? {
+                if self.echo == Some(false) {
+                    return Ok(Delivery::TxComplete { attempts });
+                }
+                if self.take_echo(frame) {
+                    return Ok(self.echoed(attempts));
+                }
+                true
+            } else {
+                false
+            };
+            if send {
+                match self.inner.send_timeout(frame, policy.attempt_timeout) {
+                    Ok(()) => attempts += 1,
+                    Err(e) if last => return Err(ConfirmError::Io(e)),
+                    Err(_) => continue,
+                }
+            }
+            if self.echo == Some(false) {
+                loop {
+                    if self.inner.is_transmitter_idle().map_err(ConfirmError::Io)? {
+                        return Ok(Delivery::TxComplete { attempts });
+                    }
+                    if self.clock.now() >= deadline {
+                        break;
+                    }
+                }
+                continue;
+            }
+            if self.wait_echo(frame, deadline)? {
+                return Ok(self.echoed(attempts));
+            }
+            if self.echo.is_none() && self.inner.is_transmitter_idle().map_err(ConfirmError::Io)? {
+                self.misses = self.misses.saturating_add(1);
+                if self.misses >= policy.echo_misses.max(1) {
+                    self.echo = Some(false);
+                }
+                return Ok(Delivery::TxComplete { attempts });
+            }
+        }
+        Err(ConfirmError::Timeout)
+    }
+
+    fn echoed(&mut self, attempts: u32) -> Delivery {
+        self.echo = Some(true);
+        self.misses = 0;
+        Delivery::Echoed { attempts }
+    }
+
+    /// Look for the echo of `frame` among the frames already received, stashing the others.
+    ///
+    /// A receive error is taken to mean that nothing more is pending.
+    fn take_echo(&mut self, frame: &F) -> bool {
+        while let Ok(received) = self.inner.try_recv() {
+            if is_echo(frame, &received) {
+                return true;
+            }
+            self.stash(received);
+        }
+        false
+    }
+}
+
+impl<T, C, Q, F, E> ConfirmedTx<T, C, Q>
+where
+    T: AsyncTxFrameIo<Frame = F, Error = E> + AsyncRxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    Q: FrameQueue<F>,
+    F: Frame,
+{
+    /// Async version of [`ConfirmedTx::send_confirmed`].
+    pub async fn send_confirmed_async(
+        &mut self,
+        frame: &F,
+        timeout: Duration,
+    ) -> Result<(), ConfirmError<E>> {
+        let deadline = self.clock.now() + timeout;
+        self.inner
+            .send_timeout(frame, timeout)
+            .await
+            .map_err(ConfirmError::Io)?;
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Err(ConfirmError::Timeout);
+            }
+            match self.inner.recv_timeout(remaining).await {
+                Ok(received) if is_echo(frame, &received) => return Ok(()),
+                Ok(received) => self.stash(received),
+                Err(_) if self.clock.now() >= deadline => return Err(ConfirmError::Timeout),
+                Err(e) => return Err(ConfirmError::Io(e)),
+            }
+        }
+    }
+}
+
+impl<T: TxFrameIo, C, Q> TxFrameIo for ConfirmedTx<T, C, Q> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<T, C, Q> RxFrameIo for ConfirmedTx<T, C, Q>
+where
+    T: RxFrameIo,
+    Q: FrameQueue<T::Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        match self.pending.pop() {
+            Some(frame) => Ok(frame),
+            None => self.inner.recv(),
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        match self.pending.pop() {
+            Some(frame) => Ok(frame),
+            None => self.inner.try_recv(),
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        match self.pending.pop() {
+            Some(frame) => Ok(frame),
+            None => self.inner.recv_timeout(timeout),
+        }
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        if self.pending.is_empty() {
+            self.inner.wait_not_empty()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsyncTxFrameIo, C, Q> AsyncTxFrameIo for ConfirmedTx<T, C, Q> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<T, C, Q> AsyncRxFrameIo for ConfirmedTx<T, C, Q>
+where
+    T: AsyncRxFrameIo,
+    Q: FrameQueue<T::Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        match self.pending.pop() {
+            Some(frame) => Ok(frame),
+            None => self.inner.recv().await,
+        }
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        match self.pending.pop() {
+            Some(frame) => Ok(frame),
+            None => self.inner.recv_timeout(timeout).await,
+        }
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        if self.pending.is_empty() {
+            self.inner.wait_not_empty().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::collections::VecDeque;
+
+    use super::*;
+    use crate::buffered::Ring;
+    use crate::fuzz::FuzzClock;
+    use crate::testdata::TestFrame;
+
+    /// Controller that transmits (and echoes) frames as soon as they are sent, or holds them.
+    struct Bus<'c> {
+        clock: &'c FuzzClock,
+        /// Whether sent frames leave the controller.
+        transmits: bool,
+        echo: bool,
+        /// Whether echoes only arrive after a blocking receive has given up.
+        late: bool,
+        queued: usize,
+        rx: VecDeque<TestFrame>,
+        sent: u32,
+    }
+
+    impl<'c> Bus<'c> {
+        fn new(clock: &'c FuzzClock) -> Self {
+            Self {
+                clock,
+                transmits: true,
+                echo: true,
+                late: false,
+                queued: 0,
+                rx: VecDeque::new(),
+                sent: 0,
+            }
+        }
+    }
+
+    impl TxFrameIo for Bus<'_> {
+        type Frame = TestFrame;
+        type Error = ();
+
+        fn send(&mut self, frame: &TestFrame) -> Result<(), ()> {
+            self.sent += 1;
+            if !self.transmits {
+                self.queued += 1;
+            } else if self.echo {
+                self.rx.push_back(*frame);
+            }
+            Ok(())
+        }
+
+        fn try_send(&mut self, frame: &TestFrame) -> Result<(), ()> {
+            self.send(frame)
+        }
+
+        fn send_timeout(&mut self, frame: &TestFrame, _timeout: Duration) -> Result<(), ()> {
+            self.send(frame)
+        }
+    }
+
+    impl RxFrameIo for Bus<'_> {
+        type Frame = TestFrame;
+        type Error = ();
+
+        fn recv(&mut self) -> Result<TestFrame, ()> {
+            self.try_recv()
+        }
+
+        fn try_recv(&mut self) -> Result<TestFrame, ()> {
+            self.rx.pop_front().ok_or(())
+        }
+
+        fn recv_timeout(&mut self, timeout: Duration) -> Result<TestFrame, ()> {
+            match self.rx.pop_front() {
+                Some(frame) if !self.late => Ok(frame),
+                frame => {
+                    self.rx.extend(frame);
+                    self.clock.advance(timeout);
+                    Err(())
+                }
+            }
+        }
+
+        fn wait_not_empty(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl TxRxState for Bus<'_> {
+        type Error = ();
+
+        fn is_transmitter_idle(&self) -> Result<bool, ()> {
+            Ok(self.queued == 0)
+        }
+    }
+
+    fn frame() -> TestFrame {
+        TestFrame::standard(0x123, &[1, 2, 3])
+    }
+
+    #[test]
+    fn echoed() {
+        let clock = FuzzClock::new();
+        let mut slots = [frame(); 4];
+        let mut tx = ConfirmedTx::new(Bus::new(&clock), &clock, Ring::new(&mut slots));
+        let delivery = tx.send_verified(&frame(), &RetryPolicy::default());
+        assert_eq!(delivery, Ok(Delivery::Echoed { attempts: 1 }));
+        assert_eq!(tx.echo_supported(), Some(true));
+    }
+
+    #[test]
+    fn queued_copy_not_duplicated() {
+        let clock = FuzzClock::new();
+        let mut slots = [frame(); 4];
+        let mut bus = Bus::new(&clock);
+        bus.transmits = false;
+        let mut tx = ConfirmedTx::new(bus, &clock, Ring::new(&mut slots));
+        let delivery = tx.send_verified(&frame(), &RetryPolicy::default());
+        assert_eq!(delivery, Err(ConfirmError::Timeout));
+        assert_eq!(tx.inner().sent, 1);
+    }
+
+    #[test]
+    fn late_echo_confirms_its_own_attempt() {
+        let clock = FuzzClock::new();
+        let mut slots = [frame(); 4];
+        let mut bus = Bus::new(&clock);
+        bus.late = true;
+        let mut tx = ConfirmedTx::new(bus, &clock, Ring::new(&mut slots));
+        tx.set_echo_supported(Some(true));
+        let delivery = tx.send_verified(&frame(), &RetryPolicy::default());
+        assert_eq!(delivery, Ok(Delivery::Echoed { attempts: 1 }));
+        assert_eq!(tx.inner().sent, 1);
+    }
+
+    #[test]
+    fn no_echo_after_consecutive_misses() {
+        let clock = FuzzClock::new();
+        let mut slots = [frame(); 4];
+        let mut bus = Bus::new(&clock);
+        bus.echo = false;
+        let mut tx = ConfirmedTx::new(bus, &clock, Ring::new(&mut slots));
+        let policy = RetryPolicy::default();
+        for echo in [None, None, Some(false)] {
+            let delivery = tx.send_verified(&frame(), &policy);
+            assert_eq!(delivery, Ok(Delivery::TxComplete { attempts: 1 }));
+            assert_eq!(tx.echo_supported(), echo);
+        }
+        assert_eq!(tx.inner().sent, 3);
+    }
+}
@@ -0,0 +1,173 @@
+//! Sharing one interface between threads and interrupt handlers with `critical-section`.
+//!
+//! [`CsCan`] lives in a `static` and guards an interface with a critical section, so a task and
+//! an interrupt handler can both use it: each call through a [`CsHandle`] takes the lock for
+//! just that call. The platform's critical-section implementation decides what the lock is
+//! (masking interrupts on a single core, a spinlock across cores, a mutex on a host).
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::cs_can::CsCan;
+//!
+//! static CAN: CsCan<MyCan> = CsCan::new();
+//!
+//! let mut can = CAN.init(driver).unwrap();
+//! can.try_send(&frame)?;
+//!
+//! #[interrupt]
+//! fn CAN_RX() {
+//!     let mut can = CAN.handle().unwrap();
+//!     while let Ok(frame) = can.try_recv() {
+//!         handle(frame);
+//!     }
+//! }
+//! ```
+//!
+//! The lock is held for the whole of each call, including the waiting in blocking methods such as
+//! [`RxFrameIo::recv`], and nothing else can take it meanwhile. Use the `try_` methods, or a
+//! driver in nonblocking mode (see [`BlockingControl`](crate::BlockingControl)), to keep
+//! critical sections short. Calling into the interface again from within a call (e.g. from a
+//! driver callback) panics.
+//!
+//! Requires the `critical-section` feature.
+
+use core::cell::RefCell;
+use core::time::Duration;
+
+use critical_section::Mutex;
+
+use crate::{PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+/// An interface shared through a critical section, for use in a `static`.
+///
+/// The cell starts empty; [`CsCan::init`] moves the interface in.
+pub struct CsCan<T> {
+    inner: Mutex<RefCell<Option<T>>>,
+}
+
+impl<T> core::fmt::Debug for CsCan<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CsCan").finish_non_exhaustive()
+    }
+}
+
+impl<T> CsCan<T> {
+    /// Create an empty cell.
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Store `value` and return a handle to it.
+    ///
+    /// Returns `Err(value)` if the cell was already initialized.
+    pub fn init(&self, value: T) -> Result<CsHandle<'_, T>, T> {
+        critical_section::with(|cs| {
+            let mut slot = self.inner.borrow_ref_mut(cs);
+            if slot.is_some() {
+                return Err(value);
+            }
+            *slot = Some(value);
+            Ok(CsHandle { can: self })
+        })
+    }
+
+    /// A handle to the interface, or `None` before [`CsCan::init`].
+    ///
+    /// Handles are cheap to create and copy; each context can get its own.
+    pub fn handle(&self) -> Option<CsHandle<'_, T>> {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).is_some())
+            .then_some(CsHandle { can: self })
+    }
+}
+
+impl<T> Default for CsCan<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Access to the interface in a [`CsCan`]; every method call takes the critical section.
+///
+/// Implements the blocking I/O traits for the shared interface. For anything else, such as
+/// configuration traits, use [`CsHandle::lock`].
+pub struct CsHandle<'a, T> {
+    can: &'a CsCan<T>,
+}
+
+impl<T> Clone for CsHandle<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CsHandle<'_, T> {}
+
+impl<T> core::fmt::Debug for CsHandle<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CsHandle").finish_non_exhaustive()
+    }
+}
+
+impl<T> CsHandle<'_, T> {
+    /// Run `f` on the interface inside the critical section.
+    ///
+    /// # Panics
+    /// Panics if called from within another call on the same interface.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        critical_section::with(|cs| {
+            let mut slot = self.can.inner.borrow_ref_mut(cs);
+            // A handle only exists once the cell holds a value, and values are never removed.
+            f(slot.as_mut().expect("CsCan handle to an empty cell"))
+        })
+    }
+}
+
+impl<T: TxFrameIo> TxFrameIo for CsHandle<'_, T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.lock(|can| can.send(frame))
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.lock(|can| can.try_send(frame))
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.lock(|can| can.send_timeout(frame, timeout))
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.lock(|can| can.send_with(frame, options))
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.lock(|can| can.send_batch(frames))
+    }
+}
+
+impl<T: RxFrameIo> RxFrameIo for CsHandle<'_, T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.lock(|can| can.recv())
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.lock(|can| can.try_recv())
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.lock(|can| can.recv_timeout(timeout))
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.lock(|can| can.wait_not_empty())
+    }
+}
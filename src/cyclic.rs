@@ -0,0 +1,478 @@
+//! Periodic transmission with bandwidth reservation.
+//!
+//! [`CyclicScheduler`] holds up to `N` frames, each with its own period, and sends the ones that
+//! are due whenever [`CyclicScheduler::poll`] is called. Driving it is left to the application: a
+//! timer interrupt, a main loop, or an async task sleeping until [`CyclicScheduler::next_due`].
+//!
+//! An optional [`BandwidthBudget`] makes the scheduler reject entries at configuration time when
+//! the periodic load, plus a reserve for event-driven traffic, would exceed a target bus load.
+//! Frame lengths are taken at their worst-case bit-stuffed size (see [`crate::bitlen`]), so an
+//! accepted schedule is guaranteed to fit on the bus.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::cyclic::{BandwidthBudget, CyclicScheduler};
+//!
+//! let budget = BandwidthBudget::new(Bitrate::Kbps500, 70.0).with_event_reserve(10.0);
+//! let mut schedule = CyclicScheduler::<MyFrame, 8>::with_budget(budget);
+//! let status = schedule.add(status_frame, Duration::from_millis(10), clock.now())?;
+//!
+//! loop {
+//!     schedule.poll(&mut can, clock.now())?;
+//! }
+//! ```
+//!
+//! Where the driver can transmit periodically on its own, the schedule does not need a poll loop
+//! at all. [`PeriodicTx`] is the interface shared by both: [`SoftwareCyclic`] runs a
+//! [`CyclicScheduler`] over any [`TxFrameIo`], and offloading drivers (such as the Linux
+//! broadcast manager behind `socketcan::BcmSocket`) implement it directly.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::bitlen::{FrameShape, Stuffing, frame_bits};
+use crate::{Bitrate, TxFrameIo};
+
+/// Bus load limits enforced by [`CyclicScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandwidthBudget {
+    /// Nominal bitrate of the bus.
+    pub bitrate: Bitrate,
+    /// Maximum bus load, in percent, that periodic and reserved event traffic may use together.
+    pub max_load: f32,
+    /// Bus load, in percent, set aside for event-driven (non-periodic) traffic.
+    pub event_reserve: f32,
+}
+
+impl BandwidthBudget {
+    /// Allow up to `max_load` percent of `bitrate`, with no event reserve.
+    pub const fn new(bitrate: Bitrate, max_load: f32) -> Self {
+        Self {
+            bitrate,
+            max_load,
+            event_reserve: 0.0,
+        }
+    }
+
+    /// Set aside `percent` of the bus for event-driven traffic.
+    pub const fn with_event_reserve(mut self, percent: f32) -> Self {
+        self.event_reserve = percent;
+        self
+    }
+
+    /// Bus load, in percent, caused by sending `bits` every `period`.
+    pub fn load_of(&self, bits: u32, period: Duration) -> f32 {
+        let bits_per_second = bits as f32 / period.as_secs_f32();
+        bits_per_second * 100.0 / self.bitrate.bits_per_second() as f32
+    }
+}
+
+/// Error returned when configuring a [`CyclicScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScheduleError {
+    /// All `N` entries are in use.
+    Full,
+    /// The period was zero.
+    ZeroPeriod,
+    /// The handle does not refer to a scheduled entry.
+    UnknownEntry,
+    /// Adding the entry would exceed [`BandwidthBudget::max_load`].
+    OverSubscribed {
+        /// Total load, in percent, the schedule would have needed (including the event reserve).
+        required: f32,
+        /// The configured limit.
+        limit: f32,
+    },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("all schedule entries in use"),
+            Self::ZeroPeriod => f.write_str("period is zero"),
+            Self::UnknownEntry => f.write_str("unknown schedule entry"),
+            Self::OverSubscribed { required, limit } => write!(
+                f,
+                "schedule needs {required:.1} % bus load, limit is {limit:.1} %"
+            ),
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// Handle to an entry in a [`CyclicScheduler`].
+///
+/// Slots are reused once an entry is removed; the generation makes a handle to the removed entry
+/// fail with [`ScheduleError::UnknownEntry`] instead of reaching the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntryHandle {
+    pub(crate) slot: usize,
+    pub(crate) generation: u32,
+}
+
+#[derive(Debug)]
+struct Entry<F> {
+    frame: F,
+    period: Duration,
+    next_due: Duration,
+    load: f32,
+}
+
+/// Sends up to `N` frames at fixed periods.
+#[derive(Debug)]
+pub struct CyclicScheduler<F, const N: usize> {
+    entries: [Option<Entry<F>>; N],
+    /// Generation of each slot, bumped when its entry is removed.
+    generations: [u32; N],
+    budget: Option<BandwidthBudget>,
+}
+
+impl<F, const N: usize> Default for CyclicScheduler<F, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, const N: usize> CyclicScheduler<F, N> {
+    /// Create an empty schedule without bandwidth accounting.
+    pub fn new() -> Self {
+        Self {
+            entries: core::array::from_fn(|_| None),
+            generations: [0; N],
+            budget: None,
+        }
+    }
+
+    /// Create an empty schedule that rejects entries exceeding `budget`.
+    pub fn with_budget(budget: BandwidthBudget) -> Self {
+        Self {
+            budget: Some(budget),
+            ..Self::new()
+        }
+    }
+
+    /// The bandwidth budget, if any.
+    pub fn budget(&self) -> Option<&BandwidthBudget> {
+        self.budget.as_ref()
+    }
+
+    /// Bus load, in percent, reserved by the periodic entries (excluding the event reserve).
+    ///
+    /// Only tracked when a budget is configured; returns `0.0` otherwise.
+    pub fn periodic_load(&self) -> f32 {
+        self.entries.iter().flatten().map(|entry| entry.load).sum()
+    }
+
+    /// Remove an entry, returning its frame.
+    pub fn remove(&mut self, handle: EntryHandle) -> Result<F, ScheduleError> {
+        let slot = self.slot(handle)?;
+        let entry = self.entries[slot]
+            .take()
+            .ok_or(ScheduleError::UnknownEntry)?;
+        self.generations[slot] = self.generations[slot].wrapping_add(1);
+        Ok(entry.frame)
+    }
+
+    /// The slot of a live entry.
+    fn slot(&self, handle: EntryHandle) -> Result<usize, ScheduleError> {
+        match self.entries.get(handle.slot) {
+            Some(Some(_)) if self.generations[handle.slot] == handle.generation => Ok(handle.slot),
+            _ => Err(ScheduleError::UnknownEntry),
+        }
+    }
+
+    /// Replace the frame sent by an entry, keeping its period and phase.
+    ///
+    /// The new frame is accounted against the budget like a fresh [`CyclicScheduler::add`].
+    pub fn set_frame(&mut self, handle: EntryHandle, frame: F) -> Result<F, ScheduleError>
+    where
+        F: Frame,
+    {
+        let slot = self.slot(handle)?;
+        let Some(period) = self.entries[slot].as_ref().map(|entry| entry.period) else {
+            return Err(ScheduleError::UnknownEntry);
+        };
+        let load = self.admit(&frame, period, Some(slot))?;
+        let Some(entry) = self.entries[slot].as_mut() else {
+            return Err(ScheduleError::UnknownEntry);
+        };
+        entry.load = load;
+        Ok(core::mem::replace(&mut entry.frame, frame))
+    }
+
+    /// Schedule `frame` every `period`, first sending it at `now`.
+    pub fn add(
+        &mut self,
+        frame: F,
+        period: Duration,
+        now: Duration,
+    ) -> Result<EntryHandle, ScheduleError>
+    where
+        F: Frame,
+    {
+        if period.is_zero() {
+            return Err(ScheduleError::ZeroPeriod);
+        }
+        let slot = self
+            .entries
+            .iter()
+            .position(Option::is_none)
+            .ok_or(ScheduleError::Full)?;
+        let load = self.admit(&frame, period, None)?;
+        self.entries[slot] = Some(Entry {
+            frame,
+            period,
+            next_due: now,
+            load,
+        });
+        Ok(EntryHandle {
+            slot,
+            generation: self.generations[slot],
+        })
+    }
+
+    /// Check `frame` against the budget, ignoring the entry at `replacing`.
+    fn admit(
+        &self,
+        frame: &F,
+        period: Duration,
+        replacing: Option<usize>,
+    ) -> Result<f32, ScheduleError>
+    where
+        F: Frame,
+    {
+        let Some(budget) = self.budget else {
+            return Ok(0.0);
+        };
+        let bits = frame_bits(&FrameShape::classic(frame), Stuffing::WorstCase).total();
+        let load = budget.load_of(bits, period);
+        let others: f32 = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| Some(index) != replacing)
+            .filter_map(|(_, entry)| entry.as_ref().map(|entry| entry.load))
+            .sum();
+        let required = others + load + budget.event_reserve;
+        if required > budget.max_load {
+            return Err(ScheduleError::OverSubscribed {
+                required,
+                limit: budget.max_load,
+            });
+        }
+        Ok(load)
+    }
+
+    /// Earliest time at which an entry becomes due, or `None` if the schedule is empty.
+    pub fn next_due(&self) -> Option<Duration> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|entry| entry.next_due)
+            .min()
+    }
+
+    /// Send every entry that is due at `now`, returning how many frames were sent.
+    ///
+    /// Frames are handed to [`TxFrameIo::try_send`]. If the driver rejects one, the error is
+    /// returned and that entry (and any later ones) stay due for the next poll. An entry that has
+    /// fallen more than a period behind is resynchronized to `now` instead of sending a burst.
+    pub fn poll<T>(&mut self, io: &mut T, now: Duration) -> Result<usize, T::Error>
+    where
+        T: TxFrameIo<Frame = F>,
+    {
+        let mut sent = 0;
+        for entry in self.entries.iter_mut().flatten() {
+            if entry.next_due > now {
+                continue;
+            }
+            io.try_send(&entry.frame)?;
+            sent += 1;
+            entry.next_due += entry.period;
+            if entry.next_due <= now {
+                entry.next_due = now + entry.period;
+            }
+        }
+        Ok(sent)
+    }
+}
+
+/// Error returned when changing a [`PeriodicTx`] schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CyclicError<E> {
+    /// The schedule rejected the change.
+    Schedule(ScheduleError),
+    /// The driver reported an error.
+    Io(E),
+}
+
+impl<E> fmt::Display for CyclicError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Schedule(_) => f.write_str("schedule change rejected"),
+            Self::Io(_) => f.write_str("driver error"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for CyclicError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Schedule(e) => Some(e),
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+impl<E> From<ScheduleError> for CyclicError<E> {
+    fn from(error: ScheduleError) -> Self {
+        Self::Schedule(error)
+    }
+}
+
+/// Periodic transmission, scheduled in software or offloaded to the driver.
+///
+/// Offloading implementations transmit without being polled: their [`PeriodicTx::poll`] sends
+/// nothing and [`PeriodicTx::next_due`] returns `None`, so a loop written for the software
+/// scheduler simply stops waking up.
+pub trait PeriodicTx {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Send `frame` every `period`, first at `now`.
+    fn add(
+        &mut self,
+        frame: Self::Frame,
+        period: Duration,
+        now: Duration,
+    ) -> Result<EntryHandle, CyclicError<Self::Error>>;
+
+    /// Replace the frame sent by an entry, keeping its period and phase.
+    fn set_frame(
+        &mut self,
+        handle: EntryHandle,
+        frame: Self::Frame,
+    ) -> Result<Self::Frame, CyclicError<Self::Error>>;
+
+    /// Stop an entry, returning its frame.
+    fn remove(&mut self, handle: EntryHandle) -> Result<Self::Frame, CyclicError<Self::Error>>;
+
+    /// Send the entries due at `now`, returning how many frames were sent.
+    fn poll(&mut self, now: Duration) -> Result<usize, Self::Error>;
+
+    /// Earliest time at which [`PeriodicTx::poll`] has work to do, if any.
+    fn next_due(&self) -> Option<Duration>;
+}
+
+/// [`PeriodicTx`] in software: a [`CyclicScheduler`] sending through a [`TxFrameIo`].
+#[derive(Debug)]
+pub struct SoftwareCyclic<T, F, const N: usize> {
+    inner: T,
+    schedule: CyclicScheduler<F, N>,
+}
+
+impl<T, F, const N: usize> SoftwareCyclic<T, F, N> {
+    /// Send the entries of `schedule` through `inner`.
+    pub fn new(inner: T, schedule: CyclicScheduler<F, N>) -> Self {
+        Self { inner, schedule }
+    }
+
+    /// Borrow the schedule.
+    pub fn schedule(&self) -> &CyclicScheduler<F, N> {
+        &self.schedule
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface and the schedule.
+    pub fn into_parts(self) -> (T, CyclicScheduler<F, N>) {
+        (self.inner, self.schedule)
+    }
+}
+
+impl<T, F, const N: usize> PeriodicTx for SoftwareCyclic<T, F, N>
+where
+    T: TxFrameIo<Frame = F>,
+    F: Frame,
+{
+    type Frame = F;
+    type Error = T::Error;
+
+    fn add(
+        &mut self,
+        frame: F,
+        period: Duration,
+        now: Duration,
+    ) -> Result<EntryHandle, CyclicError<T::Error>> {
+        Ok(self.schedule.add(frame, period, now)?)
+    }
+
+    fn set_frame(&mut self, handle: EntryHandle, frame: F) -> Result<F, CyclicError<T::Error>> {
+        Ok(self.schedule.set_frame(handle, frame)?)
+    }
+
+    fn remove(&mut self, handle: EntryHandle) -> Result<F, CyclicError<T::Error>> {
+        Ok(self.schedule.remove(handle)?)
+    }
+
+    fn poll(&mut self, now: Duration) -> Result<usize, T::Error> {
+        self.schedule.poll(&mut self.inner, now)
+    }
+
+    fn next_due(&self) -> Option<Duration> {
+        self.schedule.next_due()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::TestFrame;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn stale_handle_rejected_after_slot_reuse() {
+        let mut schedule = CyclicScheduler::<TestFrame, 1>::new();
+        let first = schedule
+            .add(TestFrame::standard(0x100, &[1]), PERIOD, Duration::ZERO)
+            .unwrap();
+        assert_eq!(schedule.remove(first), Ok(TestFrame::standard(0x100, &[1])));
+        let second = schedule
+            .add(TestFrame::standard(0x200, &[2]), PERIOD, Duration::ZERO)
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            schedule.set_frame(first, TestFrame::standard(0x300, &[3])),
+            Err(ScheduleError::UnknownEntry)
+        );
+        assert_eq!(schedule.remove(first), Err(ScheduleError::UnknownEntry));
+        assert_eq!(
+            schedule.set_frame(second, TestFrame::standard(0x300, &[3])),
+            Ok(TestFrame::standard(0x200, &[2]))
+        );
+    }
+
+    #[test]
+    fn out_of_range_handle_rejected() {
+        let mut schedule = CyclicScheduler::<TestFrame, 1>::new();
+        let handle = EntryHandle {
+            slot: 1,
+            generation: 0,
+        };
+        assert_eq!(schedule.remove(handle), Err(ScheduleError::UnknownEntry));
+    }
+}
@@ -0,0 +1,368 @@
+//! Recent-operation context for error reports.
+//!
+//! “The bus died” is rarely actionable on its own; the operations leading up to the failure
+//! usually are. [`DebugCtx`] wraps an interface and remembers its last `N` operations (direction,
+//! identifier, and whether they succeeded), at the cost of a small fixed buffer and no clock,
+//! logger or allocation. When an error is escalated, [`DebugCtx::escalate`] attaches the history
+//! to it, so it appears wherever the error is printed:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::debug_ctx::DebugCtx;
+//!
+//! let mut can: DebugCtx<_, 16> = DebugCtx::new(driver);
+//! if let Err(e) = can.send(&frame) {
+//!     // driver error: bus off
+//!     // last operations (oldest first):
+//!     //   #41 tx 123 ok
+//!     //   #42 rx 7E8 ok
+//!     //   #43 tx 123 failed
+//!     return Err(can.escalate(e).into());
+//! }
+//! ```
+//!
+//! [`DebugCtx::expect`] panics with the same report. Unlike a [`Blackbox`](crate::blackbox), which
+//! keeps whole frames for offline analysis, the context also records failed operations.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+pub use crate::blackbox::Direction;
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+/// One recorded operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpRecord {
+    /// Sequence number, counting every operation since the wrapper was created (wrapping).
+    pub seq: u32,
+    /// Send or receive.
+    pub direction: Direction,
+    /// Identifier of the frame sent or received; `None` for a failed receive.
+    pub id: Option<Id>,
+    /// Whether the operation succeeded.
+    pub ok: bool,
+}
+
+impl fmt::Display for OpRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Tx => "tx",
+            Direction::Rx => "rx",
+        };
+        write!(f, "#{} {direction} ", self.seq)?;
+        match self.id {
+            Some(Id::Standard(id)) => write!(f, "{:03X}", id.as_raw())?,
+            Some(Id::Extended(id)) => write!(f, "{:08X}", id.as_raw())?,
+            None => f.write_str("-")?,
+        }
+        f.write_str(if self.ok { " ok" } else { " failed" })
+    }
+}
+
+/// Operation history, oldest first, as kept by [`DebugCtx`] and [`WithContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct History<const N: usize> {
+    records: [Option<OpRecord>; N],
+    /// Index the next record is written to.
+    head: usize,
+}
+
+impl<const N: usize> History<N> {
+    const fn new() -> Self {
+        Self {
+            records: [None; N],
+            head: 0,
+        }
+    }
+
+    fn push(&mut self, record: OpRecord) {
+        self.records[self.head] = Some(record);
+        self.head = (self.head + 1) % N;
+    }
+
+    /// Recorded operations, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &OpRecord> {
+        let (newer, older) = self.records.split_at(self.head);
+        older.iter().chain(newer).flatten()
+    }
+
+    /// The most recent operation.
+    pub fn last(&self) -> Option<&OpRecord> {
+        self.records[(self.head + N - 1) % N].as_ref()
+    }
+}
+
+/// One operation per line, indented, after a heading.
+impl<const N: usize> fmt::Display for History<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("last operations (oldest first):")?;
+        if self.last().is_none() {
+            return f.write_str(" none");
+        }
+        self.iter().try_for_each(|record| write!(f, "\n  {record}"))
+    }
+}
+
+/// An error with the operation history at the time it was escalated.
+///
+/// `Display` shows the error followed by the history; [`source`](Error::source) is the error.
+#[derive(Debug, Clone)]
+pub struct WithContext<E, const N: usize> {
+    /// The escalated error.
+    pub error: E,
+    /// Operations leading up to it.
+    pub history: History<N>,
+}
+
+impl<E, const N: usize> WithContext<E, N> {
+    /// Unwrap, returning the error.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Display, const N: usize> fmt::Display for WithContext<E, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n{}", self.error, self.history)
+    }
+}
+
+impl<E: Error + 'static, const N: usize> Error for WithContext<E, N> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Wrapper that remembers the last `N` operations on an interface; see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct DebugCtx<T, const N: usize> {
+    inner: T,
+    history: History<N>,
+    seq: u32,
+}
+
+impl<T, const N: usize> DebugCtx<T, N> {
+    /// Wrap `inner`.
+    pub fn new(inner: T) -> Self {
+        const { assert!(N > 0, "DebugCtx needs at least one record slot") };
+        Self {
+            inner,
+            history: History::new(),
+            seq: 0,
+        }
+    }
+
+    /// The recorded operations.
+    pub fn history(&self) -> &History<N> {
+        &self.history
+    }
+
+    /// Attach the current history to `error`.
+    pub fn escalate<E>(&self, error: E) -> WithContext<E, N> {
+        WithContext {
+            error,
+            history: self.history,
+        }
+    }
+
+    /// Unwrap `result`, or panic with `msg`, the error and the history.
+    #[track_caller]
+    pub fn expect<R, E: fmt::Display>(&self, result: Result<R, E>, msg: &str) -> R {
+        match result {
+            Ok(value) => value,
+            Err(error) => panic!("{msg}: {}", self.escalate(error)),
+        }
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&mut self, direction: Direction, id: Option<Id>, ok: bool) {
+        self.history.push(OpRecord {
+            seq: self.seq,
+            direction,
+            id,
+            ok,
+        });
+        self.seq = self.seq.wrapping_add(1);
+    }
+
+    fn record_send<F: Frame, E>(&mut self, frame: &F, result: &Result<(), E>) {
+        self.record(Direction::Tx, Some(frame.id()), result.is_ok());
+    }
+
+    fn record_recv<F: Frame, E>(&mut self, result: &Result<F, E>) {
+        let id = result.as_ref().ok().map(Frame::id);
+        self.record(Direction::Rx, id, result.is_ok());
+    }
+
+    fn record_batch<F: Frame, E>(
+        &mut self,
+        frames: &[(F, SendOptions)],
+        result: &Result<usize, PartialSend<E>>,
+    ) {
+        let sent = match result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        for (frame, _) in frames.iter().take(sent) {
+            self.record(Direction::Tx, Some(frame.id()), true);
+        }
+        if let (Err(_), Some((frame, _))) = (result, frames.get(sent)) {
+            self.record(Direction::Tx, Some(frame.id()), false);
+        }
+    }
+}
+
+impl<T, const N: usize> TxFrameIo for DebugCtx<T, N>
+where
+    T: TxFrameIo<Frame: Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame);
+        self.record_send(frame, &result);
+        result
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.try_send(frame);
+        self.record_send(frame, &result);
+        result
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout);
+        self.record_send(frame, &result);
+        result
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options);
+        self.record_send(frame, &result);
+        result
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, const N: usize> RxFrameIo for DebugCtx<T, N>
+where
+    T: RxFrameIo<Frame: Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv();
+        self.record_recv(&result);
+        result
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.try_recv();
+        self.record_recv(&result);
+        result
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout);
+        self.record_recv(&result);
+        result
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, const N: usize> AsyncTxFrameIo for DebugCtx<T, N>
+where
+    T: AsyncTxFrameIo<Frame: Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame).await;
+        self.record_send(frame, &result);
+        result
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.record_send(frame, &result);
+        result
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options).await;
+        self.record_send(frame, &result);
+        result
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, const N: usize> AsyncRxFrameIo for DebugCtx<T, N>
+where
+    T: AsyncRxFrameIo<Frame: Frame>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv().await;
+        self.record_recv(&result);
+        result
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout).await;
+        self.record_recv(&result);
+        result
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
@@ -0,0 +1,252 @@
+//! Suppression of duplicated received frames.
+//!
+//! Some nodes retransmit the same frame several times in quick succession (“babbling”), for
+//! example after a lost acknowledgement or because of a firmware bug. [`Dedup`] drops a received
+//! frame when it is identical to the last frame delivered with the same ID and arrives within a
+//! configurable window.
+
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::frame_eq::FrameEq;
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+};
+
+#[derive(Debug)]
+struct Entry<F> {
+    id: Id,
+    frame: F,
+    at: Duration,
+}
+
+/// Receive wrapper that drops identical consecutive frames per ID within a time window.
+///
+/// The window is measured from the last *delivered* frame, so a node that repeats a frame forever
+/// still gets one copy through per window. State is kept for up to `N` IDs; when the table is full,
+/// the entry that was delivered longest ago is replaced. Transmission is passed through unchanged.
+#[derive(Debug)]
+pub struct Dedup<R, C, F, const N: usize> {
+    inner: R,
+    clock: C,
+    window: Duration,
+    seen: [Option<Entry<F>>; N],
+    suppressed: u32,
+}
+
+impl<R, C, F, const N: usize> Dedup<R, C, F, N> {
+    /// Wrap `inner`, suppressing duplicates that arrive within `window` of the previous copy.
+    pub fn new(inner: R, clock: C, window: Duration) -> Self {
+        const { assert!(N > 0, "Dedup needs at least one ID slot") };
+        Self {
+            inner,
+            clock,
+            window,
+            seen: core::array::from_fn(|_| None),
+            suppressed: 0,
+        }
+    }
+
+    /// Change the suppression window.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Number of frames dropped as duplicates.
+    pub fn suppressed(&self) -> u32 {
+        self.suppressed
+    }
+
+    /// Forget all per-ID state.
+    pub fn clear(&mut self) {
+        self.seen.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R, C: Clock, F: Frame + Clone, const N: usize> Dedup<R, C, F, N> {
+    /// Record `frame` and return `true` if it should be delivered.
+    fn admit(&mut self, frame: &F) -> bool {
+        let now = self.clock.now();
+        let id = frame.id();
+        if let Some(entry) = self.seen.iter_mut().flatten().find(|entry| entry.id == id) {
+            if FrameEq::new().content_eq(&entry.frame, frame)
+                && now.saturating_sub(entry.at) < self.window
+            {
+                self.suppressed = self.suppressed.saturating_add(1);
+                return false;
+            }
+            entry.frame = frame.clone();
+            entry.at = now;
+            return true;
+        }
+        let slot = match self.seen.iter().position(Option::is_none) {
+            Some(free) => free,
+            None => (0..N)
+                .min_by_key(|&index| self.seen[index].as_ref().map(|entry| entry.at))
+                .unwrap_or(0),
+        };
+        self.seen[slot] = Some(Entry {
+            id,
+            frame: frame.clone(),
+            at: now,
+        });
+        true
+    }
+}
+
+impl<R: TxFrameIo, C, F, const N: usize> TxFrameIo for Dedup<R, C, F, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<R, C, F, const N: usize> RxFrameIo for Dedup<R, C, F, N>
+where
+    R: RxFrameIo<Frame = F>,
+    C: Clock,
+    F: Frame + Clone,
+{
+    type Frame = F;
+    type Error = R::Error;
+
+    fn recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.try_recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Receive the next non-duplicate frame.
+    ///
+    /// `timeout` bounds the whole call: duplicates dropped along the way do not restart it.
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            let frame = self.inner.recv_timeout(remaining)?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Wait until the driver has a frame; it may still turn out to be a duplicate.
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<R: AsyncTxFrameIo, C, F, const N: usize> AsyncTxFrameIo for Dedup<R, C, F, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<R, C, F, const N: usize> AsyncRxFrameIo for Dedup<R, C, F, N>
+where
+    R: AsyncRxFrameIo<Frame = F>,
+    C: Clock,
+    F: Frame + Clone,
+{
+    type Frame = F;
+    type Error = R::Error;
+
+    async fn recv(&mut self) -> Result<F, Self::Error> {
+        loop {
+            let frame = self.inner.recv().await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<F, Self::Error> {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            let frame = self.inner.recv_timeout(remaining).await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
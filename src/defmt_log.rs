@@ -0,0 +1,317 @@
+//! Frame logging over `defmt` (feature `defmt`).
+//!
+//! [`DefmtLogger`] wraps an interface and logs every frame it sends and receives, so firmware gets
+//! one consistent trace instead of `defmt::info!` calls scattered over the call sites:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::defmt_log::{DefmtLogger, Verbosity};
+//!
+//! let mut can = DefmtLogger::new(twai);
+//! can.set_verbosity(Verbosity::Errors); // quiet until a field report needs more
+//! ```
+//!
+//! Lines carry the target's `defmt::timestamp!`, the direction, the identifier (3 hex digits for
+//! standard, 8 for extended), and the payload as hex bytes, or `R` and the DLC for a remote frame:
+//!
+//! ```text
+//! 0.004211 DEBUG tx 123 [01, 02, AB]
+//! 0.004968 DEBUG rx 18FEF100 [FF, FF, 00, 00, 00, 00, 00, 00]
+//! 0.005102 DEBUG rx 7DF R8
+//! ```
+//!
+//! Frames are logged at DEBUG. Failed sends are logged at WARN, failed receives at DEBUG, since
+//! `try_recv` and `recv_timeout` fail routinely when no frame arrives.
+
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+/// What [`DefmtLogger`] logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, defmt::Format)]
+pub enum Verbosity {
+    /// Nothing.
+    Off,
+    /// Failed operations only.
+    Errors,
+    /// Sent frames and failed operations.
+    Tx,
+    /// Received frames and failed operations.
+    Rx,
+    /// Every frame and failed operation.
+    #[default]
+    All,
+}
+
+impl Verbosity {
+    const fn logs_tx(self) -> bool {
+        matches!(self, Self::Tx | Self::All)
+    }
+
+    const fn logs_rx(self) -> bool {
+        matches!(self, Self::Rx | Self::All)
+    }
+
+    const fn logs_errors(self) -> bool {
+        !matches!(self, Self::Off)
+    }
+}
+
+/// Wrapper that logs frames over `defmt`; see the [module documentation](self).
+#[derive(Debug)]
+pub struct DefmtLogger<T> {
+    inner: T,
+    verbosity: Verbosity,
+}
+
+impl<T> DefmtLogger<T> {
+    /// Wrap `inner`, logging everything.
+    pub const fn new(inner: T) -> Self {
+        Self::with_verbosity(inner, Verbosity::All)
+    }
+
+    /// Wrap `inner`, logging what `verbosity` selects.
+    pub const fn with_verbosity(inner: T, verbosity: Verbosity) -> Self {
+        Self { inner, verbosity }
+    }
+
+    /// What is logged.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Change what is logged.
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn log_send<F: Frame, E: defmt::Format>(&self, frame: &F, result: &Result<(), E>) {
+        match result {
+            Ok(()) if self.verbosity.logs_tx() => log_frame("tx", frame),
+            Err(error) if self.verbosity.logs_errors() => log_send_error(frame, error),
+            _ => {}
+        }
+    }
+
+    fn log_batch<F: Frame, E: defmt::Format>(
+        &self,
+        frames: &[(F, SendOptions)],
+        result: &Result<usize, PartialSend<E>>,
+    ) {
+        let sent = match result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        if self.verbosity.logs_tx() {
+            for (frame, _) in frames.iter().take(sent) {
+                log_frame("tx", frame);
+            }
+        }
+        if let Err(partial) = result
+            && self.verbosity.logs_errors()
+        {
+            match frames.get(sent) {
+                Some((frame, _)) => log_send_error(frame, &partial.error),
+                None => defmt::warn!("tx batch failed after {=usize}: {}", sent, partial.error),
+            }
+        }
+    }
+
+    fn log_recv<F: Frame, E: defmt::Format>(&self, result: &Result<F, E>) {
+        match result {
+            Ok(frame) if self.verbosity.logs_rx() => log_frame("rx", frame),
+            Err(error) if self.verbosity.logs_errors() => defmt::debug!("rx failed: {}", error),
+            _ => {}
+        }
+    }
+}
+
+fn log_frame<F: Frame>(direction: &str, frame: &F) {
+    match (frame.id(), frame.is_remote_frame()) {
+        (Id::Standard(id), false) => defmt::debug!(
+            "{=str} {=u16:03X} {=[u8]:02X}",
+            direction,
+            id.as_raw(),
+            frame.data()
+        ),
+        (Id::Extended(id), false) => defmt::debug!(
+            "{=str} {=u32:08X} {=[u8]:02X}",
+            direction,
+            id.as_raw(),
+            frame.data()
+        ),
+        (Id::Standard(id), true) => defmt::debug!(
+            "{=str} {=u16:03X} R{=usize}",
+            direction,
+            id.as_raw(),
+            frame.dlc()
+        ),
+        (Id::Extended(id), true) => defmt::debug!(
+            "{=str} {=u32:08X} R{=usize}",
+            direction,
+            id.as_raw(),
+            frame.dlc()
+        ),
+    }
+}
+
+fn log_send_error<F: Frame, E: defmt::Format>(frame: &F, error: &E) {
+    match frame.id() {
+        Id::Standard(id) => defmt::warn!("tx {=u16:03X} failed: {}", id.as_raw(), error),
+        Id::Extended(id) => defmt::warn!("tx {=u32:08X} failed: {}", id.as_raw(), error),
+    }
+}
+
+impl<T> TxFrameIo for DefmtLogger<T>
+where
+    T: TxFrameIo<Frame: Frame, Error: defmt::Format>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame);
+        self.log_send(frame, &result);
+        result
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.try_send(frame);
+        self.log_send(frame, &result);
+        result
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout);
+        self.log_send(frame, &result);
+        result
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options);
+        self.log_send(frame, &result);
+        result
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.log_batch(frames, &result);
+        result
+    }
+}
+
+impl<T> RxFrameIo for DefmtLogger<T>
+where
+    T: RxFrameIo<Frame: Frame, Error: defmt::Format>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv();
+        self.log_recv(&result);
+        result
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.try_recv();
+        self.log_recv(&result);
+        result
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout);
+        self.log_recv(&result);
+        result
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T> AsyncTxFrameIo for DefmtLogger<T>
+where
+    T: AsyncTxFrameIo<Frame: Frame, Error: defmt::Format>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame).await;
+        self.log_send(frame, &result);
+        result
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.log_send(frame, &result);
+        result
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options).await;
+        self.log_send(frame, &result);
+        result
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.log_batch(frames, &result);
+        result
+    }
+}
+
+impl<T> AsyncRxFrameIo for DefmtLogger<T>
+where
+    T: AsyncRxFrameIo<Frame: Frame, Error: defmt::Format>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv().await;
+        self.log_recv(&result);
+        result
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout).await;
+        self.log_recv(&result);
+        result
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
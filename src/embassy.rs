@@ -0,0 +1,81 @@
+//! Adapter for the buffered CAN driver of [`embassy_stm32`] (requires the `embassy` feature).
+//!
+//! Implements:
+//!
+//! - [`AsyncTxFrameIo`] for [`BufferedCanSender`];
+//! - [`AsyncRxFrameIo`] for [`BufferedCanReceiver`];
+//! - [`SplitTxRx`] for [`BufferedCan`], splitting it into a sender and a receiver.
+//!
+//! These types exist for both the bxCAN and the FDCAN peripherals, so the same code serves either
+//! family; frames are classic CAN frames ([`Frame`]).
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::{AsyncRxFrameIo, AsyncTxFrameIo, SplitTxRx};
+//!
+//! let can = can.buffered(TX_BUF.init(TxBuf::new()), RX_BUF.init(RxBuf::new()));
+//! let (mut tx, mut rx) = can.split();
+//! let frame = rx.recv().await?;
+//! tx.send(&frame).await?;
+//! ```
+//!
+//! `embassy-stm32` builds only for STM32 targets with a chip feature selected; the `cargo
+//! check-embassy` and `cargo check-embassy-fdcan` aliases check this module for a bxCAN and an
+//! FDCAN chip.
+
+use core::convert::Infallible;
+use core::future::poll_fn;
+use core::time::Duration;
+
+use embassy_stm32::can::enums::BusError;
+use embassy_stm32::can::{BufferedCan, BufferedCanReceiver, BufferedCanSender, Frame};
+
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, SplitTxRx};
+
+/// Frames are queued in the driver's TX buffer; `send` waits only while it is full. There is no
+/// timer, so `send_timeout` acts like `send`.
+impl AsyncTxFrameIo for BufferedCanSender {
+    type Frame = Frame;
+    type Error = Infallible;
+
+    async fn send(&mut self, frame: &Frame) -> Result<(), Self::Error> {
+        self.write(*frame).await;
+        Ok(())
+    }
+
+    async fn send_timeout(&mut self, frame: &Frame, _timeout: Duration) -> Result<(), Self::Error> {
+        self.send(frame).await
+    }
+}
+
+/// Bus errors are queued alongside frames and returned in order. The reception timestamp is
+/// dropped. There is no timer, so `recv_timeout` acts like `recv`.
+impl AsyncRxFrameIo for BufferedCanReceiver {
+    type Frame = Frame;
+    type Error = BusError;
+
+    async fn recv(&mut self) -> Result<Frame, Self::Error> {
+        self.receive().await.map(|envelope| envelope.frame)
+    }
+
+    async fn recv_timeout(&mut self, _timeout: Duration) -> Result<Frame, Self::Error> {
+        self.recv().await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| self.poll_ready_to_receive(cx)).await;
+        Ok(())
+    }
+}
+
+/// The halves keep the peripheral running after the [`BufferedCan`] is dropped. Filters are no
+/// longer reachable through them, so configure them before splitting.
+impl<const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize> SplitTxRx
+    for BufferedCan<'_, TX_BUF_SIZE, RX_BUF_SIZE>
+{
+    type Tx = BufferedCanSender;
+    type Rx = BufferedCanReceiver;
+
+    fn split(self) -> (Self::Tx, Self::Rx) {
+        (self.writer(), self.reader())
+    }
+}
@@ -0,0 +1,70 @@
+//! Interface enumeration (requires the `std` feature).
+//!
+//! Tools that present a device picker need to know which interfaces exist before opening one with
+//! [`BuilderBinding::open`](crate::BuilderBinding::open). Backends implement [`ListInterfaces`];
+//! on Linux, [`socketcan_interfaces`] lists SocketCAN network devices directly.
+
+use std::string::String;
+use std::vec::Vec;
+
+/// An interface that can be opened by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceDescriptor {
+    /// Name to pass to [`BuilderBinding::open`](crate::BuilderBinding::open) (e.g. `can0`).
+    pub name: String,
+    /// Short backend identifier (e.g. `"socketcan"`).
+    pub backend: &'static str,
+    /// Whether the interface is administratively up, when the backend can tell.
+    pub up: Option<bool>,
+}
+
+/// Enumerate interfaces a backend can open.
+///
+/// This is typically implemented alongside [`BuilderBinding`](crate::BuilderBinding) on the same
+/// driver type.
+pub trait ListInterfaces {
+    /// Error returned by the backend.
+    type Error;
+
+    /// List the interfaces currently available to this backend.
+    fn list() -> Result<Vec<InterfaceDescriptor>, Self::Error>;
+}
+
+/// List SocketCAN network devices (`can*`, `vcan*`, `slcan*`, …).
+///
+/// Devices are identified by their link type (`ARPHRD_CAN`) in `/sys/class/net`, which avoids
+/// shelling out to `ip link` or depending on a netlink library.
+#[cfg(target_os = "linux")]
+pub fn socketcan_interfaces() -> std::io::Result<Vec<InterfaceDescriptor>> {
+    use std::fs;
+
+    const ARPHRD_CAN: u32 = 280;
+    const IFF_UP: u32 = 0x1;
+
+    let read_number = |path: std::path::PathBuf| -> Option<u32> {
+        let text = fs::read_to_string(path).ok()?;
+        let text = text.trim();
+        match text.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        }
+    };
+
+    let mut interfaces = Vec::new();
+    for entry in fs::read_dir("/sys/class/net")? {
+        let path = entry?.path();
+        if read_number(path.join("type")) != Some(ARPHRD_CAN) {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        interfaces.push(InterfaceDescriptor {
+            name: name.into(),
+            backend: "socketcan",
+            up: read_number(path.join("flags")).map(|flags| flags & IFF_UP != 0),
+        });
+    }
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(interfaces)
+}
@@ -0,0 +1,179 @@
+//! Trait-object support (requires the `alloc` feature).
+//!
+//! The blocking traits are already dyn-compatible, so `Box<dyn TxFrameIo<Frame = F, Error = E>>`
+//! works directly; this module implements the traits for `Box<T>` so such boxes can be passed
+//! wherever an interface is expected.
+//!
+//! The async traits use `async fn` and therefore cannot be made into trait objects.
+//! [`ErasedAsyncTxFrameIo`] and [`ErasedAsyncRxFrameIo`] are dyn-compatible mirrors that return
+//! boxed futures. Every async interface implements them, and `Box<dyn ErasedAsync…>` implements the
+//! async traits again, so host-side tools can select a backend at runtime:
+//!
+//! ```rust,ignore
+//! let rx: Box<dyn ErasedAsyncRxFrameIo<Frame = MyFrame, Error = MyError>> = Box::new(backend);
+//! let frame = AsyncRxFrameIo::recv(&mut rx).await?;
+//! ```
+
+use alloc::boxed::Box;
+use core::future::Future;
+use core::pin::Pin;
+use core::time::Duration;
+
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+/// A boxed, type-erased future.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+impl<T: TxFrameIo + ?Sized> TxFrameIo for Box<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        (**self).send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        (**self).try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        (**self).send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        (**self).send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        (**self).send_batch(frames)
+    }
+}
+
+impl<T: RxFrameIo + ?Sized> RxFrameIo for Box<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        (**self).recv()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        (**self).try_recv()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        (**self).recv_timeout(timeout)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        (**self).wait_not_empty()
+    }
+}
+
+/// Dyn-compatible form of [`AsyncTxFrameIo`].
+pub trait ErasedAsyncTxFrameIo {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// See [`AsyncTxFrameIo::send`].
+    fn send<'a>(&'a mut self, frame: &'a Self::Frame) -> BoxFuture<'a, Result<(), Self::Error>>;
+
+    /// See [`AsyncTxFrameIo::send_timeout`].
+    fn send_timeout<'a>(
+        &'a mut self,
+        frame: &'a Self::Frame,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+}
+
+impl<T: AsyncTxFrameIo> ErasedAsyncTxFrameIo for T {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send<'a>(&'a mut self, frame: &'a Self::Frame) -> BoxFuture<'a, Result<(), Self::Error>> {
+        Box::pin(AsyncTxFrameIo::send(self, frame))
+    }
+
+    fn send_timeout<'a>(
+        &'a mut self,
+        frame: &'a Self::Frame,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        Box::pin(AsyncTxFrameIo::send_timeout(self, frame, timeout))
+    }
+}
+
+impl<F, E> AsyncTxFrameIo for Box<dyn ErasedAsyncTxFrameIo<Frame = F, Error = E> + '_> {
+    type Frame = F;
+    type Error = E;
+
+    async fn send(&mut self, frame: &F) -> Result<(), E> {
+        ErasedAsyncTxFrameIo::send(&mut **self, frame).await
+    }
+
+    async fn send_timeout(&mut self, frame: &F, timeout: Duration) -> Result<(), E> {
+        ErasedAsyncTxFrameIo::send_timeout(&mut **self, frame, timeout).await
+    }
+}
+
+/// Dyn-compatible form of [`AsyncRxFrameIo`].
+pub trait ErasedAsyncRxFrameIo {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// See [`AsyncRxFrameIo::recv`].
+    fn recv(&mut self) -> BoxFuture<'_, Result<Self::Frame, Self::Error>>;
+
+    /// See [`AsyncRxFrameIo::recv_timeout`].
+    fn recv_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<Self::Frame, Self::Error>>;
+
+    /// See [`AsyncRxFrameIo::wait_not_empty`].
+    fn wait_not_empty(&mut self) -> BoxFuture<'_, Result<(), Self::Error>>;
+}
+
+impl<T: AsyncRxFrameIo> ErasedAsyncRxFrameIo for T {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> BoxFuture<'_, Result<Self::Frame, Self::Error>> {
+        Box::pin(AsyncRxFrameIo::recv(self))
+    }
+
+    fn recv_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<Self::Frame, Self::Error>> {
+        Box::pin(AsyncRxFrameIo::recv_timeout(self, timeout))
+    }
+
+    fn wait_not_empty(&mut self) -> BoxFuture<'_, Result<(), Self::Error>> {
+        Box::pin(AsyncRxFrameIo::wait_not_empty(self))
+    }
+}
+
+impl<F, E> AsyncRxFrameIo for Box<dyn ErasedAsyncRxFrameIo<Frame = F, Error = E> + '_> {
+    type Frame = F;
+    type Error = E;
+
+    async fn recv(&mut self) -> Result<F, E> {
+        ErasedAsyncRxFrameIo::recv(&mut **self).await
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<F, E> {
+        ErasedAsyncRxFrameIo::recv_timeout(&mut **self, timeout).await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), E> {
+        ErasedAsyncRxFrameIo::wait_not_empty(&mut **self).await
+    }
+}
@@ -0,0 +1,169 @@
+//! Backend-independent error classification.
+//!
+//! Every driver has its own error type, but generic code mostly needs to know which of a few
+//! cases it is looking at: retry later, give up waiting, reopen the device, or fail. [`ErrorKind`]
+//! names those cases.
+//!
+//! With the `std` feature, [`Error`] is the error type of the std backends (SocketCAN, UDP
+//! multicast): a [`std::io::Error`] together with its [`ErrorKind`]. It implements
+//! [`std::error::Error`] and converts from and to `io::Error`, so `?` works with `anyhow`, `eyre`
+//! or plain `io::Result`:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::error::ErrorKind;
+//!
+//! match can.recv_timeout(Duration::from_millis(100)) {
+//!     Ok(frame) => handle(frame),
+//!     Err(e) if e.kind() == ErrorKind::Timeout => {}
+//!     Err(e) => return Err(e.into()),
+//! }
+//! ```
+//!
+//! The crate's own error types implement [`core::error::Error`], with the driver error they wrap
+//! as [`source`](core::error::Error::source), when the wrapped error does. Middleware that wants
+//! to report errors, including in `no_std`, can require the same of the interface, e.g.
+//! `T: TxFrameIo<Error: core::error::Error>`, and print the whole chain with [`Report`]:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::error::Report;
+//!
+//! if let Err(e) = isotp.send_message(&request) {
+//!     // "driver error: device disconnected"
+//!     log::warn!("request failed: {}", Report::new(&e));
+//! }
+//! ```
+
+#[cfg(feature = "std")]
+use std::io;
+
+/// Category of a driver error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// A non-blocking call could not complete immediately (queue empty or full).
+    WouldBlock,
+    /// A timeout elapsed.
+    Timeout,
+    /// The device or link has gone away; see [`Reconnectable`](crate::Reconnectable).
+    Disconnected,
+    /// The interface does not support the frame or operation.
+    Unsupported,
+    /// Any other error.
+    Other,
+}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::WouldBlock => "operation would block",
+            Self::Timeout => "timed out",
+            Self::Disconnected => "device disconnected",
+            Self::Unsupported => "unsupported",
+            Self::Other => "other error",
+        })
+    }
+}
+
+/// Displays an error followed by its [`source`](core::error::Error::source) chain, separated by
+/// `: `.
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'a> {
+    error: &'a (dyn core::error::Error + 'a),
+}
+
+impl<'a> Report<'a> {
+    /// Report `error` and its sources.
+    pub fn new(error: &'a (dyn core::error::Error + 'a)) -> Self {
+        Self { error }
+    }
+}
+
+impl core::fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.error)?;
+        let mut source = self.error.source();
+        while let Some(error) = source {
+            write!(f, ": {error}")?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
+
+/// Error of the std backends: an [`io::Error`] and its [`ErrorKind`].
+///
+/// `Display` and [`source`](std::error::Error::source) are those of the I/O error.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    io: io::Error,
+}
+
+#[cfg(feature = "std")]
+impl Error {
+    /// Category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Borrow the underlying I/O error.
+    pub fn io(&self) -> &io::Error {
+        &self.io
+    }
+
+    /// Unwrap, returning the underlying I/O error.
+    pub fn into_io(self) -> io::Error {
+        self.io
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Self {
+        use crate::Disconnected;
+
+        let kind = match io.kind() {
+            io::ErrorKind::WouldBlock => ErrorKind::WouldBlock,
+            io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            io::ErrorKind::Unsupported => ErrorKind::Unsupported,
+            _ if io.is_disconnected() => ErrorKind::Disconnected,
+            _ => ErrorKind::Other,
+        };
+        Self { kind, io }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::ErrorKind> for Error {
+    fn from(kind: io::ErrorKind) -> Self {
+        io::Error::from(kind).into()
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        error.io
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.io.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.io.source()
+    }
+}
+
+#[cfg(feature = "std")]
+impl crate::Disconnected for Error {
+    fn is_disconnected(&self) -> bool {
+        self.kind == ErrorKind::Disconnected
+    }
+}
@@ -0,0 +1,320 @@
+//! Adapter for the TWAI controller of `esp-hal` (requires the `esp` feature).
+//!
+//! The feature enables `esp-hal` with its `unstable` feature (TWAI is an unstable driver); the
+//! chip is selected by the application's own `esp-hal` dependency as usual.
+//!
+//! - Blocking: [`Twai`], [`TwaiTx`] and [`TwaiRx`] implement the [`nb`]-style traits of
+//!   [`crate::nb_io`]; wrap them in [`FromNb`](crate::nb_io::FromNb) for [`TxFrameIo`] and
+//!   [`RxFrameIo`](crate::RxFrameIo).
+//! - Async: `Twai<'_, Async>` and `TwaiTx<'_, Async>` implement [`AsyncTxFrameIo`]; wrap
+//!   `Twai<'_, Async>` or `TwaiRx<'_, Async>` in [`AsyncTwaiRx`] for [`AsyncRxFrameIo`].
+//! - Filters: [`TwaiConfiguration`] implements [`FilterConfig`], so filters are installed before
+//!   [`TwaiConfiguration::start`].
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::FilterConfig;
+//! use embedded_can_interface::nb_io::FromNb;
+//!
+//! let mut config = TwaiConfiguration::new(peripherals.TWAI0, rx, tx, BaudRate::B500K, TwaiMode::Normal);
+//! config.set_filters(&filters)?;
+//! let mut can = FromNb::new(config.start());
+//! ```
+//!
+//! [`TxFrameIo`]: crate::TxFrameIo
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use esp_hal::twai::filter::{
+    DualExtendedFilter, DualStandardFilter, SingleExtendedFilter, SingleStandardFilter,
+};
+use esp_hal::twai::{
+    EspTwaiError, EspTwaiFrame, ExtendedId, StandardId, Twai, TwaiConfiguration, TwaiRx, TwaiTx,
+};
+use esp_hal::{Async, DriverMode};
+
+use crate::nb_io::{NbRxFrameIo, NbTxFrameIo};
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, ConstFilterConfig, FilterConfig, FrameTypes, Id, IdMask,
+    IdMaskFilter, SplitTxRx,
+};
+
+impl<Dm: DriverMode> NbTxFrameIo for Twai<'_, Dm> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    fn transmit(&mut self, frame: &EspTwaiFrame) -> nb::Result<(), EspTwaiError> {
+        Twai::transmit(self, frame)
+    }
+}
+
+impl<Dm: DriverMode> NbRxFrameIo for Twai<'_, Dm> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    fn receive(&mut self) -> nb::Result<EspTwaiFrame, EspTwaiError> {
+        Twai::receive(self)
+    }
+}
+
+impl<Dm: DriverMode> NbTxFrameIo for TwaiTx<'_, Dm> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    fn transmit(&mut self, frame: &EspTwaiFrame) -> nb::Result<(), EspTwaiError> {
+        TwaiTx::transmit(self, frame)
+    }
+}
+
+impl<Dm: DriverMode> NbRxFrameIo for TwaiRx<'_, Dm> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    fn receive(&mut self) -> nb::Result<EspTwaiFrame, EspTwaiError> {
+        TwaiRx::receive(self)
+    }
+}
+
+impl<'d, Dm: DriverMode> SplitTxRx for Twai<'d, Dm> {
+    type Tx = TwaiTx<'d, Dm>;
+    type Rx = TwaiRx<'d, Dm>;
+
+    fn split(self) -> (Self::Tx, Self::Rx) {
+        let (rx, tx) = Twai::split(self);
+        (tx, rx)
+    }
+}
+
+/// The driver has no timer, so `send_timeout` waits like `send`.
+impl AsyncTxFrameIo for Twai<'_, Async> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    async fn send(&mut self, frame: &EspTwaiFrame) -> Result<(), EspTwaiError> {
+        self.transmit_async(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &EspTwaiFrame,
+        _timeout: Duration,
+    ) -> Result<(), EspTwaiError> {
+        self.transmit_async(frame).await
+    }
+}
+
+/// The driver has no timer, so `send_timeout` waits like `send`.
+impl AsyncTxFrameIo for TwaiTx<'_, Async> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    async fn send(&mut self, frame: &EspTwaiFrame) -> Result<(), EspTwaiError> {
+        self.transmit_async(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &EspTwaiFrame,
+        _timeout: Duration,
+    ) -> Result<(), EspTwaiError> {
+        self.transmit_async(frame).await
+    }
+}
+
+/// Async TWAI receivers: `Twai<'_, Async>` and `TwaiRx<'_, Async>`.
+pub trait ReceiveAsync {
+    /// Receive the next frame.
+    async fn receive_frame(&mut self) -> Result<EspTwaiFrame, EspTwaiError>;
+}
+
+impl ReceiveAsync for Twai<'_, Async> {
+    async fn receive_frame(&mut self) -> Result<EspTwaiFrame, EspTwaiError> {
+        self.receive_async().await
+    }
+}
+
+impl ReceiveAsync for TwaiRx<'_, Async> {
+    async fn receive_frame(&mut self) -> Result<EspTwaiFrame, EspTwaiError> {
+        self.receive_async().await
+    }
+}
+
+/// [`AsyncRxFrameIo`] for an async TWAI receiver.
+///
+/// The driver moves frames from the hardware FIFO into its own queue from the interrupt handler
+/// and cannot report whether that queue is empty, so
+/// [`wait_not_empty`](AsyncRxFrameIo::wait_not_empty) receives a frame and holds it for the next
+/// `recv`. The driver has no timer, so `recv_timeout` waits like `recv`.
+#[derive(Debug)]
+pub struct AsyncTwaiRx<R> {
+    inner: R,
+    peeked: Option<EspTwaiFrame>,
+}
+
+impl<R> AsyncTwaiRx<R> {
+    /// Wrap an async receiver.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            peeked: None,
+        }
+    }
+
+    /// Borrow the wrapped receiver.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped receiver.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped receiver.
+    ///
+    /// A frame already read by [`AsyncRxFrameIo::wait_not_empty`] is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ReceiveAsync> AsyncRxFrameIo for AsyncTwaiRx<R> {
+    type Frame = EspTwaiFrame;
+    type Error = EspTwaiError;
+
+    async fn recv(&mut self) -> Result<EspTwaiFrame, EspTwaiError> {
+        match self.peeked.take() {
+            Some(frame) => Ok(frame),
+            None => self.inner.receive_frame().await,
+        }
+    }
+
+    async fn recv_timeout(&mut self, _timeout: Duration) -> Result<EspTwaiFrame, EspTwaiError> {
+        self.recv().await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), EspTwaiError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.inner.receive_frame().await?);
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a filter list does not fit the TWAI acceptance filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwaiFilterError {
+    /// More than two filters were given.
+    TooManyFilters,
+    /// Two filters of different identifier types were given; dual-filter mode needs both
+    /// standard or both extended.
+    MixedIdTypes,
+}
+
+impl fmt::Display for TwaiFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyFilters => f.write_str("the TWAI filter holds at most two filters"),
+            Self::MixedIdTypes => f.write_str("dual filters need the same identifier type"),
+        }
+    }
+}
+
+impl Error for TwaiFilterError {}
+
+fn rtr(frame_types: FrameTypes) -> (bool, bool) {
+    match frame_types {
+        FrameTypes::Both => (false, false),
+        FrameTypes::Data => (false, true),
+        FrameTypes::Remote => (true, true),
+    }
+}
+
+fn standard(filter: &IdMaskFilter) -> Option<(StandardId, StandardId, bool, bool)> {
+    let (Id::Standard(id), IdMask::Standard(mask)) = (filter.id, filter.mask) else {
+        return None;
+    };
+    let mask = StandardId::new(mask & 0x7FF)?;
+    let (rtr_code, rtr_mask) = rtr(filter.frame_types);
+    Some((id.into(), mask, rtr_code, rtr_mask))
+}
+
+fn extended(filter: &IdMaskFilter) -> Option<(ExtendedId, ExtendedId, bool, bool)> {
+    let (Id::Extended(id), IdMask::Extended(mask)) = (filter.id, filter.mask) else {
+        return None;
+    };
+    let mask = ExtendedId::new(mask & 0x1FFF_FFFF)?;
+    let (rtr_code, rtr_mask) = rtr(filter.frame_types);
+    Some((id.into(), mask, rtr_code, rtr_mask))
+}
+
+/// Installs up to two filters, applied by [`TwaiConfiguration::start`].
+///
+/// The TWAI acceptance filter is not exact, so the controller may deliver frames the list
+/// rejects; wrap the interface in [`SoftwareFilter`](crate::filter::SoftwareFilter) when exact
+/// filtering matters:
+///
+/// - a single filter also accepts frames of the other identifier type whose bits happen to
+///   match;
+/// - two extended filters compare only the 16 most significant identifier bits and ignore
+///   [`FrameTypes`].
+///
+/// An empty list accepts every frame, and [`IdTypes`](crate::IdTypes) is not supported by the
+/// hardware. Filters whose identifier and mask widths differ are rejected as
+/// [`TwaiFilterError::MixedIdTypes`].
+impl<Dm: DriverMode> FilterConfig for TwaiConfiguration<'_, Dm> {
+    type Error = TwaiFilterError;
+    type FiltersHandle<'a>
+        = &'a mut Self
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        let any = StandardId::ZERO;
+        match filters {
+            [] => self.set_filter(SingleStandardFilter::new_from_code_mask(
+                any, any, false, false, [0; 2], [0; 2],
+            )),
+            [filter] => {
+                if let Some((id, mask, rtr_code, rtr_mask)) = standard(filter) {
+                    self.set_filter(SingleStandardFilter::new_from_code_mask(
+                        id, mask, rtr_code, rtr_mask, [0; 2], [0; 2],
+                    ));
+                } else {
+                    let (id, mask, rtr_code, rtr_mask) =
+                        extended(filter).ok_or(TwaiFilterError::MixedIdTypes)?;
+                    self.set_filter(SingleExtendedFilter::new_from_code_mask(
+                        id, mask, rtr_code, rtr_mask,
+                    ));
+                }
+            }
+            [first, second] => {
+                if let (Some(a), Some(b)) = (standard(first), standard(second)) {
+                    self.set_filter(DualStandardFilter::new_from_code_mask(
+                        a.0, a.1, a.2, a.3, 0, 0, b.0, b.1, b.2, b.3,
+                    ));
+                } else {
+                    let a = extended(first).ok_or(TwaiFilterError::MixedIdTypes)?;
+                    let b = extended(second).ok_or(TwaiFilterError::MixedIdTypes)?;
+                    let high = |id: ExtendedId| (id.as_raw() >> 13) as u16;
+                    self.set_filter(DualExtendedFilter::new_from_code_mask(
+                        [high(a.0), high(b.0)],
+                        [high(a.1), high(b.1)],
+                    ));
+                }
+            }
+            _ => return Err(TwaiFilterError::TooManyFilters),
+        }
+        Ok(())
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        self
+    }
+}
+
+/// The acceptance filter holds two filters in dual-filter mode.
+impl<Dm: DriverMode> ConstFilterConfig for TwaiConfiguration<'_, Dm> {
+    const MAX_FILTERS: usize = 2;
+}
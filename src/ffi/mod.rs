@@ -0,0 +1,421 @@
+//! Adapters for vendor CAN libraries (requires the `ffi-backend` feature).
+//!
+//! Bench hardware from PEAK, Vector, Kvaser and others is driven through the vendor's C library
+//! rather than an OS network stack. Those libraries differ in naming but share a shape: open a
+//! channel, write and read fixed-size message structs from queues, set a coarse acceptance range
+//! and poll a status word. [`VendorApi`] captures that shape, and [`VendorCan`] maps any
+//! implementation onto [`TxFrameIo`], [`RxFrameIo`], [`FilterConfig`] and [`BusState`], using the
+//! caller's frame type.
+//!
+//! Acceptance filters are always applied in software as well, so vendor libraries that can only
+//! express ID ranges (or nothing at all) still deliver exactly the frames the filter list accepts.
+//!
+//! Implementations:
+//! - [`pcan`]: PEAK-System PCAN-Basic (`pcan` feature).
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+use std::time::Instant;
+
+use embedded_can::{ExtendedId, Frame, StandardId};
+
+use crate::{
+    BusState, Disconnected, ErrorCounters, ErrorState, FilterConfig, FilterList, Id, IdMask,
+    IdMaskFilter, Reconnectable, RxFrameIo, TxFrameIo,
+};
+
+#[cfg(feature = "pcan")]
+pub mod pcan;
+
+/// A classic CAN message in the vendor-neutral form exchanged with a [`VendorApi`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VendorMessage {
+    /// Frame identifier.
+    pub id: embedded_can::Id,
+    /// Remote transmission request.
+    pub remote: bool,
+    /// Data length code (0–8).
+    pub dlc: u8,
+    /// Payload; bytes beyond `dlc` are ignored.
+    pub data: [u8; 8],
+}
+
+impl VendorMessage {
+    /// Convert a frame, or return `None` if it does not fit a classic CAN message.
+    pub fn from_frame<F: Frame>(frame: &F) -> Option<Self> {
+        let dlc = u8::try_from(frame.dlc()).ok().filter(|dlc| *dlc <= 8)?;
+        let mut data = [0; 8];
+        if !frame.is_remote_frame() {
+            data.get_mut(..frame.data().len())?
+                .copy_from_slice(frame.data());
+        }
+        Some(Self {
+            id: frame.id(),
+            remote: frame.is_remote_frame(),
+            dlc,
+            data,
+        })
+    }
+
+    /// Convert to the caller's frame type.
+    pub fn to_frame<F: Frame>(&self) -> Option<F> {
+        if self.remote {
+            F::new_remote(self.id, usize::from(self.dlc))
+        } else {
+            F::new(self.id, self.data.get(..usize::from(self.dlc))?)
+        }
+    }
+}
+
+impl Frame for VendorMessage {
+    fn new(id: impl Into<embedded_can::Id>, data: &[u8]) -> Option<Self> {
+        let mut bytes = [0; 8];
+        bytes.get_mut(..data.len())?.copy_from_slice(data);
+        Some(Self {
+            id: id.into(),
+            remote: false,
+            dlc: data.len() as u8,
+            data: bytes,
+        })
+    }
+
+    fn new_remote(id: impl Into<embedded_can::Id>, dlc: usize) -> Option<Self> {
+        Some(Self {
+            id: id.into(),
+            remote: true,
+            dlc: u8::try_from(dlc).ok().filter(|dlc| *dlc <= 8)?,
+            data: [0; 8],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, embedded_can::Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> embedded_can::Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        usize::from(self.dlc)
+    }
+
+    fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            &self.data[..usize::from(self.dlc.min(8))]
+        }
+    }
+}
+
+/// The operations a vendor library has to provide for [`VendorCan`].
+///
+/// Reads and writes are non-blocking and report empty/full queues as
+/// [`nb::Error::WouldBlock`]; [`VendorCan`] adds the blocking and timeout variants on top.
+pub trait VendorApi {
+    /// Error reported by the vendor library.
+    type Error;
+
+    /// Queue `message` for transmission.
+    fn write(&mut self, message: &VendorMessage) -> nb::Result<(), Self::Error>;
+
+    /// Take the next message from the receive queue.
+    fn read(&mut self) -> nb::Result<VendorMessage, Self::Error>;
+
+    /// Narrow hardware acceptance to (at least) the frames `filters` accept; an empty list accepts
+    /// everything.
+    ///
+    /// Libraries that cannot express ID/mask pairs should open up to a superset (e.g. a bounding
+    /// ID range) or accept everything, since [`VendorCan`] filters exactly in software.
+    fn set_acceptance(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error>;
+
+    /// Current fault-confinement state.
+    fn bus_state(&self) -> Result<ErrorState, Self::Error>;
+
+    /// Transmit/receive error counters, or `None` if the library does not expose them.
+    fn error_counters(&self) -> Result<Option<ErrorCounters>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Reset the channel, clearing its queues and leaving bus-off.
+    fn reset(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Error returned by [`VendorCan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiError<E> {
+    /// A non-blocking call found the queue empty (receive) or full (transmit).
+    WouldBlock,
+    /// A timeout elapsed.
+    Timeout,
+    /// The frame cannot be represented as a classic CAN message.
+    Unsupported,
+    /// The vendor library reported an error.
+    Vendor(E),
+}
+
+impl<E> fmt::Display for FfiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WouldBlock => f.write_str("operation would block"),
+            Self::Timeout => f.write_str("timed out"),
+            Self::Unsupported => f.write_str("frame cannot be sent as a classic CAN message"),
+            Self::Vendor(_) => f.write_str("vendor library error"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for FfiError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Vendor(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<E: Disconnected> Disconnected for FfiError<E> {
+    fn is_disconnected(&self) -> bool {
+        matches!(self, Self::Vendor(e) if e.is_disconnected())
+    }
+}
+
+/// [`FrameIo`](crate::FrameIo) adapter over a vendor library channel.
+///
+/// `F` is the frame type handed to and returned from the traits. Blocking calls poll the vendor
+/// queues, sleeping for the poll interval (1 ms by default) between attempts.
+#[derive(Debug)]
+pub struct VendorCan<V, F> {
+    api: V,
+    filters: FilterList,
+    /// Set when the filter list may have changed without reaching the vendor library.
+    filters_stale: bool,
+    /// Accepted message read by `wait_not_empty` and not yet returned.
+    peeked: Option<VendorMessage>,
+    poll_interval: Duration,
+    _frame: core::marker::PhantomData<fn() -> F>,
+}
+
+impl<V: VendorApi, F> VendorCan<V, F> {
+    /// Wrap an opened vendor channel.
+    pub fn new(api: V) -> Self {
+        Self {
+            api,
+            filters: FilterList::new(),
+            filters_stale: false,
+            peeked: None,
+            poll_interval: Duration::from_millis(1),
+            _frame: core::marker::PhantomData,
+        }
+    }
+
+    /// Set how long blocking calls sleep between polls of the vendor queues.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Borrow the vendor channel.
+    pub fn inner(&self) -> &V {
+        &self.api
+    }
+
+    /// Mutably borrow the vendor channel.
+    pub fn inner_mut(&mut self) -> &mut V {
+        &mut self.api
+    }
+
+    /// Unwrap, returning the vendor channel.
+    pub fn into_inner(self) -> V {
+        self.api
+    }
+
+    fn sync_filters(&mut self) -> Result<(), FfiError<V::Error>> {
+        if self.filters_stale {
+            self.api
+                .set_acceptance(&self.filters)
+                .map_err(FfiError::Vendor)?;
+            self.filters_stale = false;
+        }
+        Ok(())
+    }
+
+    fn accepts(&self, message: &VendorMessage) -> bool {
+        self.filters.is_empty() || self.filters.iter().any(|filter| filter.accepts(message))
+    }
+
+    /// Read the next message that passes the software filters.
+    fn read_accepted(&mut self) -> Result<VendorMessage, FfiError<V::Error>> {
+        self.sync_filters()?;
+        loop {
+            let message = self.api.read().map_err(|e| match e {
+                nb::Error::WouldBlock => FfiError::WouldBlock,
+                nb::Error::Other(e) => FfiError::Vendor(e),
+            })?;
+            if self.accepts(&message) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Poll `op` until it stops reporting `WouldBlock` or `deadline` passes.
+    fn poll_until<R>(
+        &mut self,
+        deadline: Option<Instant>,
+        mut op: impl FnMut(&mut Self) -> Result<R, FfiError<V::Error>>,
+    ) -> Result<R, FfiError<V::Error>> {
+        loop {
+            match op(self) {
+                Err(FfiError::WouldBlock) => {}
+                result => return result,
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(FfiError::Timeout);
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+impl<V: VendorApi, F: Frame> TxFrameIo for VendorCan<V, F> {
+    type Frame = F;
+    type Error = FfiError<V::Error>;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.poll_until(None, |can| can.try_send(frame))
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let message = VendorMessage::from_frame(frame).ok_or(FfiError::Unsupported)?;
+        self.api.write(&message).map_err(|e| match e {
+            nb::Error::WouldBlock => FfiError::WouldBlock,
+            nb::Error::Other(e) => FfiError::Vendor(e),
+        })
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let deadline = Instant::now() + timeout;
+        self.poll_until(Some(deadline), |can| can.try_send(frame))
+    }
+}
+
+impl<V: VendorApi, F: Frame> RxFrameIo for VendorCan<V, F> {
+    type Frame = F;
+    type Error = FfiError<V::Error>;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.poll_until(None, Self::try_recv)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let message = match self.peeked.take() {
+                Some(message) => message,
+                None => self.read_accepted()?,
+            };
+            // Messages the frame type cannot represent are dropped like filtered ones.
+            if let Some(frame) = message.to_frame() {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let deadline = Instant::now() + timeout;
+        self.poll_until(Some(deadline), Self::try_recv)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        if self.peeked.is_none() {
+            let message = self.poll_until(None, Self::read_accepted)?;
+            self.peeked = Some(message);
+        }
+        Ok(())
+    }
+}
+
+impl<V: VendorApi, F> FilterConfig for VendorCan<V, F> {
+    type Error = FfiError<V::Error>;
+
+    type FiltersHandle<'a>
+        = &'a mut FilterList
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.filters = filters.iter().copied().collect();
+        self.filters_stale = true;
+        self.sync_filters()
+    }
+
+    /// Edit the filter list in place.
+    ///
+    /// Software filtering uses the edited list immediately; the vendor library is updated on the
+    /// next receive call.
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        self.filters_stale = true;
+        &mut self.filters
+    }
+}
+
+impl<V: VendorApi, F> BusState for VendorCan<V, F> {
+    type Error = FfiError<V::Error>;
+
+    fn bus_state(&self) -> Result<ErrorState, Self::Error> {
+        self.api.bus_state().map_err(FfiError::Vendor)
+    }
+
+    /// Returns zeroed counters if the vendor library does not expose them.
+    fn error_counters(&self) -> Result<ErrorCounters, Self::Error> {
+        self.api
+            .error_counters()
+            .map(Option::unwrap_or_default)
+            .map_err(FfiError::Vendor)
+    }
+
+    fn recover_bus_off(&mut self) -> Result<(), Self::Error> {
+        self.api.reset().map_err(FfiError::Vendor)
+    }
+}
+
+/// Reopens the vendor channel, then reinstalls the filter list and drops any peeked message.
+impl<V, F> Reconnectable for VendorCan<V, F>
+where
+    V: VendorApi + Reconnectable<Error = <V as VendorApi>::Error>,
+{
+    type Error = FfiError<<V as VendorApi>::Error>;
+
+    fn reopen(&mut self) -> Result<(), Self::Error> {
+        self.peeked = None;
+        self.api.reopen().map_err(FfiError::Vendor)?;
+        self.filters_stale = true;
+        self.sync_filters()
+    }
+}
+
+/// Inclusive raw identifier range covering the identifiers of the filter's own type that `filter`
+/// accepts, and whether that type is extended.
+///
+/// Useful for vendor libraries whose acceptance filter is an ID range. Filters with
+/// [`IdTypes::Both`](crate::IdTypes::Both) also accept identifiers of the other type, which the
+/// range does not cover.
+pub fn bounding_range(filter: &IdMaskFilter) -> (u32, u32, bool) {
+    match (filter.id, filter.mask) {
+        (Id::Standard(id), IdMask::Standard(mask)) => {
+            let (id, mask) = (u32::from(id.as_raw()), u32::from(mask) & 0x7FF);
+            (id & mask, (id | !mask) & 0x7FF, false)
+        }
+        (Id::Extended(id), IdMask::Extended(mask)) => {
+            let (id, mask) = (id.as_raw(), mask & 0x1FFF_FFFF);
+            (id & mask, (id | !mask) & 0x1FFF_FFFF, true)
+        }
+        // Mismatched widths match nothing in software, so any range of the right width will do.
+        (Id::Standard(_), _) => (0, u32::from(StandardId::MAX.as_raw()), false),
+        (Id::Extended(_), _) => (0, ExtendedId::MAX.as_raw(), true),
+    }
+}
@@ -0,0 +1,381 @@
+//! PEAK-System PCAN-Basic backend (requires the `pcan` feature).
+//!
+//! The PCAN-Basic library is loaded at runtime (`PCANBasic.dll` on Windows, `libpcanbasic.so` on
+//! Linux, `libPCBUSB.dylib` on macOS), so the crate builds without the vendor SDK installed and
+//! only fails when a channel is opened on a machine without it.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::ffi::pcan::{Pcan, baud, channel};
+//! use embedded_can_interface::ffi::VendorCan;
+//!
+//! let pcan = Pcan::open(channel::USB1, baud::K500)?;
+//! let mut can: VendorCan<_, MyFrame> = VendorCan::new(pcan);
+//! can.send(&frame)?;
+//! ```
+//!
+//! PCAN-Basic filters by identifier range, so each [`IdMaskFilter`] is installed as its bounding
+//! range (see [`bounding_range`]) and [`VendorCan`](super::VendorCan) discards the extra frames.
+//! The library does not report error counters.
+
+use core::fmt;
+
+use embedded_can::{ExtendedId, Id, StandardId};
+use libloading::Library;
+
+use super::{VendorApi, VendorMessage, bounding_range};
+use crate::{Disconnected, ErrorState, IdMaskFilter, IdTypes, Reconnectable};
+
+/// PCAN-Basic channel handles (`TPCANHandle`).
+pub mod channel {
+    /// First PCAN-USB channel.
+    pub const USB1: u16 = 0x51;
+    /// Second PCAN-USB channel.
+    pub const USB2: u16 = 0x52;
+    /// Third PCAN-USB channel.
+    pub const USB3: u16 = 0x53;
+    /// Fourth PCAN-USB channel.
+    pub const USB4: u16 = 0x54;
+    /// First PCAN-PCI channel.
+    pub const PCI1: u16 = 0x41;
+    /// Second PCAN-PCI channel.
+    pub const PCI2: u16 = 0x42;
+}
+
+/// PCAN-Basic bit rate codes (`TPCANBaudrate`, BTR0/BTR1 values for the SJA1000).
+pub mod baud {
+    /// 1 Mbit/s.
+    pub const M1: u16 = 0x0014;
+    /// 800 kbit/s.
+    pub const K800: u16 = 0x0016;
+    /// 500 kbit/s.
+    pub const K500: u16 = 0x001C;
+    /// 250 kbit/s.
+    pub const K250: u16 = 0x011C;
+    /// 125 kbit/s.
+    pub const K125: u16 = 0x031C;
+    /// 100 kbit/s.
+    pub const K100: u16 = 0x432F;
+    /// 50 kbit/s.
+    pub const K50: u16 = 0x472F;
+    /// 20 kbit/s.
+    pub const K20: u16 = 0x532F;
+    /// 10 kbit/s.
+    pub const K10: u16 = 0x672F;
+}
+
+const ERROR_OK: u32 = 0x00000;
+const ERROR_XMTFULL: u32 = 0x00001;
+const ERROR_BUSLIGHT: u32 = 0x00004;
+const ERROR_BUSHEAVY: u32 = 0x00008;
+const ERROR_BUSOFF: u32 = 0x00010;
+const ERROR_QRCVEMPTY: u32 = 0x00020;
+const ERROR_QXMTFULL: u32 = 0x00080;
+/// Bits holding the handle errors (`ERROR_NODRIVER` to `ERROR_ILLCLIENT`), which are values
+/// rather than flags.
+const ERROR_HANDLE_MASK: u32 = 0x01C00;
+const ERROR_ILLHW: u32 = 0x01400;
+const ERROR_BUSPASSIVE: u32 = 0x40000;
+const ERROR_INITIALIZE: u32 = 0x4000000;
+
+const MESSAGE_STANDARD: u8 = 0x00;
+const MESSAGE_RTR: u8 = 0x01;
+const MESSAGE_EXTENDED: u8 = 0x02;
+const MESSAGE_ERRFRAME: u8 = 0x40;
+const MESSAGE_STATUS: u8 = 0x80;
+
+const MODE_STANDARD: u8 = 0x00;
+const MODE_EXTENDED: u8 = 0x02;
+
+const PARAM_MESSAGE_FILTER: u8 = 0x04;
+const FILTER_CLOSE: u32 = 0x00;
+const FILTER_OPEN: u32 = 0x01;
+
+/// `TPCANMsg`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Msg {
+    id: u32,
+    msg_type: u8,
+    len: u8,
+    data: [u8; 8],
+}
+
+/// `TPCANTimestamp`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Timestamp {
+    millis: u32,
+    millis_overflow: u16,
+    micros: u16,
+}
+
+type InitializeFn = unsafe extern "system" fn(u16, u16, u8, u32, u16) -> u32;
+type HandleFn = unsafe extern "system" fn(u16) -> u32;
+type ReadFn = unsafe extern "system" fn(u16, *mut Msg, *mut Timestamp) -> u32;
+type WriteFn = unsafe extern "system" fn(u16, *mut Msg) -> u32;
+type FilterFn = unsafe extern "system" fn(u16, u32, u32, u8) -> u32;
+type SetValueFn = unsafe extern "system" fn(u16, u8, *mut core::ffi::c_void, u32) -> u32;
+
+/// Error returned by [`Pcan`].
+#[derive(Debug)]
+pub enum PcanError {
+    /// The PCAN-Basic library or one of its functions could not be loaded.
+    Load(libloading::Error),
+    /// A PCAN-Basic call returned this `TPCANStatus` error code.
+    Status(u32),
+}
+
+impl fmt::Display for PcanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(e) => write!(f, "failed to load PCAN-Basic: {e}"),
+            Self::Status(code) => write!(f, "PCAN-Basic error {code:#07x}"),
+        }
+    }
+}
+
+impl std::error::Error for PcanError {}
+
+/// An invalid hardware handle or an uninitialized channel, which is what PCAN-Basic reports once
+/// the adapter has been unplugged.
+impl Disconnected for PcanError {
+    fn is_disconnected(&self) -> bool {
+        matches!(self, Self::Status(code)
+            if code & ERROR_HANDLE_MASK == ERROR_ILLHW || code & ERROR_INITIALIZE != 0)
+    }
+}
+
+fn check(status: u32) -> Result<(), PcanError> {
+    match status {
+        ERROR_OK => Ok(()),
+        code => Err(PcanError::Status(code)),
+    }
+}
+
+/// An initialized PCAN-Basic channel; uninitialized on drop.
+pub struct Pcan {
+    channel: u16,
+    baud: u16,
+    initialize: InitializeFn,
+    uninitialize: HandleFn,
+    reset: HandleFn,
+    get_status: HandleFn,
+    read: ReadFn,
+    write: WriteFn,
+    filter_messages: FilterFn,
+    set_value: SetValueFn,
+    // Keeps the function pointers above valid; must outlive them.
+    _library: Library,
+}
+
+impl fmt::Debug for Pcan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pcan")
+            .field("channel", &self.channel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pcan {
+    /// Name of the PCAN-Basic library on this platform.
+    pub const LIBRARY: &'static str = if cfg!(windows) {
+        "PCANBasic.dll"
+    } else if cfg!(target_os = "macos") {
+        "libPCBUSB.dylib"
+    } else {
+        "libpcanbasic.so"
+    };
+
+    /// Load PCAN-Basic and initialize `channel` (see [`channel`]) at `baud` (see [`baud`]).
+    pub fn open(channel: u16, baud: u16) -> Result<Self, PcanError> {
+        // SAFETY: loading PCAN-Basic runs no initialization routines with preconditions.
+        let library = unsafe { Library::new(Self::LIBRARY) }.map_err(PcanError::Load)?;
+        Self::open_with(library, channel, baud)
+    }
+
+    /// Initialize `channel` using an already loaded PCAN-Basic library (e.g. from a non-standard
+    /// path).
+    pub fn open_with(library: Library, channel: u16, baud: u16) -> Result<Self, PcanError> {
+        // SAFETY: the symbol types match the PCAN-Basic API declarations in `PCANBasic.h`, and
+        // the library is kept alive alongside the copied function pointers.
+        unsafe {
+            let initialize: InitializeFn =
+                *library.get(b"CAN_Initialize\0").map_err(PcanError::Load)?;
+            let uninitialize: HandleFn = *library
+                .get(b"CAN_Uninitialize\0")
+                .map_err(PcanError::Load)?;
+            let reset: HandleFn = *library.get(b"CAN_Reset\0").map_err(PcanError::Load)?;
+            let get_status: HandleFn = *library.get(b"CAN_GetStatus\0").map_err(PcanError::Load)?;
+            let read: ReadFn = *library.get(b"CAN_Read\0").map_err(PcanError::Load)?;
+            let write: WriteFn = *library.get(b"CAN_Write\0").map_err(PcanError::Load)?;
+            let filter_messages: FilterFn = *library
+                .get(b"CAN_FilterMessages\0")
+                .map_err(PcanError::Load)?;
+            let set_value: SetValueFn = *library.get(b"CAN_SetValue\0").map_err(PcanError::Load)?;
+            // Hardware type, I/O port and interrupt only apply to non-plug-and-play channels.
+            check(initialize(channel, baud, 0, 0, 0))?;
+            Ok(Self {
+                channel,
+                baud,
+                initialize,
+                uninitialize,
+                reset,
+                get_status,
+                read,
+                write,
+                filter_messages,
+                set_value,
+                _library: library,
+            })
+        }
+    }
+
+    /// The channel handle.
+    pub fn channel(&self) -> u16 {
+        self.channel
+    }
+
+    /// Raw `TPCANStatus` of the channel.
+    pub fn status(&self) -> u32 {
+        // SAFETY: the channel was initialized in `open_with`.
+        unsafe { (self.get_status)(self.channel) }
+    }
+
+    fn set_message_filter(&mut self, mut value: u32) -> Result<(), PcanError> {
+        // SAFETY: PCAN_MESSAGE_FILTER takes a 4-byte value.
+        check(unsafe {
+            (self.set_value)(
+                self.channel,
+                PARAM_MESSAGE_FILTER,
+                (&raw mut value).cast(),
+                4,
+            )
+        })
+    }
+}
+
+impl Drop for Pcan {
+    fn drop(&mut self) {
+        // SAFETY: the channel was initialized in `open_with` and is not used afterwards.
+        unsafe { (self.uninitialize)(self.channel) };
+    }
+}
+
+/// Uninitializes the channel and initializes it again at the same baud rate.
+impl Reconnectable for Pcan {
+    type Error = PcanError;
+
+    fn reopen(&mut self) -> Result<(), Self::Error> {
+        // SAFETY: uninitializing a channel that is already gone only returns an error, and
+        // `initialize` takes the same arguments as in `open_with`.
+        unsafe {
+            (self.uninitialize)(self.channel);
+            check((self.initialize)(self.channel, self.baud, 0, 0, 0))
+        }
+    }
+}
+
+impl VendorApi for Pcan {
+    type Error = PcanError;
+
+    fn write(&mut self, message: &VendorMessage) -> nb::Result<(), Self::Error> {
+        let (id, mut msg_type) = match message.id {
+            Id::Standard(id) => (u32::from(id.as_raw()), MESSAGE_STANDARD),
+            Id::Extended(id) => (id.as_raw(), MESSAGE_EXTENDED),
+        };
+        if message.remote {
+            msg_type |= MESSAGE_RTR;
+        }
+        let mut msg = Msg {
+            id,
+            msg_type,
+            len: message.dlc,
+            data: message.data,
+        };
+        // SAFETY: `msg` is a valid `TPCANMsg` for the duration of the call.
+        match unsafe { (self.write)(self.channel, &mut msg) } {
+            ERROR_OK => Ok(()),
+            status if status & (ERROR_XMTFULL | ERROR_QXMTFULL) != 0 => Err(nb::Error::WouldBlock),
+            status => Err(nb::Error::Other(PcanError::Status(status))),
+        }
+    }
+
+    fn read(&mut self) -> nb::Result<VendorMessage, Self::Error> {
+        loop {
+            let mut msg = Msg::default();
+            let mut timestamp = Timestamp::default();
+            // SAFETY: both out-pointers are valid for the duration of the call.
+            match unsafe { (self.read)(self.channel, &mut msg, &mut timestamp) } {
+                ERROR_OK => {}
+                ERROR_QRCVEMPTY => return Err(nb::Error::WouldBlock),
+                status => return Err(nb::Error::Other(PcanError::Status(status))),
+            }
+            // Status and error frames report bus events, not traffic; `bus_state` covers them.
+            if msg.msg_type & (MESSAGE_STATUS | MESSAGE_ERRFRAME) != 0 {
+                continue;
+            }
+            let id = if msg.msg_type & MESSAGE_EXTENDED != 0 {
+                ExtendedId::new(msg.id).map(Id::Extended)
+            } else {
+                u16::try_from(msg.id)
+                    .ok()
+                    .and_then(StandardId::new)
+                    .map(Id::Standard)
+            };
+            let Some(id) = id else { continue };
+            return Ok(VendorMessage {
+                id,
+                remote: msg.msg_type & MESSAGE_RTR != 0,
+                dlc: msg.len.min(8),
+                data: msg.data,
+            });
+        }
+    }
+
+    fn set_acceptance(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        if filters.is_empty() {
+            return self.set_message_filter(FILTER_OPEN);
+        }
+        // `CAN_FilterMessages` widens the current filter, so start from a closed one.
+        self.set_message_filter(FILTER_CLOSE)?;
+        for filter in filters {
+            let (from, to, extended) = bounding_range(filter);
+            let mode = if extended {
+                MODE_EXTENDED
+            } else {
+                MODE_STANDARD
+            };
+            // SAFETY: plain value arguments on an initialized channel.
+            check(unsafe { (self.filter_messages)(self.channel, from, to, mode) })?;
+            if filter.id_types == IdTypes::Both {
+                // Open the other identifier type fully; software filtering does the rest.
+                let (mode, to) = if extended {
+                    (MODE_STANDARD, 0x7FF)
+                } else {
+                    (MODE_EXTENDED, 0x1FFF_FFFF)
+                };
+                // SAFETY: as above.
+                check(unsafe { (self.filter_messages)(self.channel, 0, to, mode) })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn bus_state(&self) -> Result<ErrorState, Self::Error> {
+        let status = self.status();
+        Ok(if status & ERROR_BUSOFF != 0 {
+            ErrorState::BusOff
+        } else if status & ERROR_BUSPASSIVE != 0 {
+            ErrorState::ErrorPassive
+        } else if status & (ERROR_BUSHEAVY | ERROR_BUSLIGHT) != 0 {
+            ErrorState::ErrorWarning
+        } else {
+            ErrorState::ErrorActive
+        })
+    }
+
+    fn reset(&mut self) -> Result<(), Self::Error> {
+        // SAFETY: the channel was initialized in `open_with`.
+        check(unsafe { (self.reset)(self.channel) })
+    }
+}
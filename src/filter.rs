@@ -0,0 +1,716 @@
+//! Reasoning about acceptance filters.
+//!
+//! Filter compilers, gateways and components that share one set of hardware filter banks all need
+//! to answer the same questions about [`IdMaskFilter`]s: does this filter accept an ID, does one
+//! filter make another redundant, can two filters accept the same frame, and what is the smallest
+//! set of filters accepting exactly the same IDs. This module provides those operations.
+//!
+//! Multiplexed messages carry different signals under one identifier, selected by a mux byte in
+//! the payload. A [`FrameFilter`] combines an ID filter with a [`DataFilter`] on such a byte;
+//! [`SoftwareFilter`] applies frame filters to any receive interface.
+//!
+//! A filter compares the identifier bits selected by its mask. Unless its [`IdTypes`] say otherwise
+//! it only matches identifiers of its own width, and a filter whose ID and mask widths differ
+//! matches nothing. The algebra is exact for filters of a single identifier type; for
+//! [`IdTypes::Both`] filters it errs on the side of reporting “not subsumed” and “overlapping”.
+
+use core::ops::RangeInclusive;
+use core::time::Duration;
+
+use embedded_can::{Frame, StandardId};
+
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, FilterConfig, FilterSet, FilterSetFull, FrameFilterConfig,
+    FrameTypes, Id, IdMask, IdMaskFilter, IdTypes, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+};
+
+/// Identifier types as a bit set.
+const STANDARD: u8 = 1;
+const EXTENDED: u8 = 2;
+
+/// Frame types as a bit set.
+const DATA: u8 = 1;
+const REMOTE: u8 = 2;
+
+/// A filter reduced to raw parts, or `None` if it matches nothing.
+#[derive(Clone, Copy)]
+struct Raw {
+    id_types: u8,
+    frame_types: u8,
+    /// Identifier bits, already masked.
+    id: u32,
+    mask: u32,
+}
+
+fn raw(filter: &IdMaskFilter) -> Option<Raw> {
+    let (width, id, mask) = match (filter.id, filter.mask) {
+        (Id::Standard(id), IdMask::Standard(mask)) => {
+            (STANDARD, u32::from(id.as_raw()), u32::from(mask) & 0x7FF)
+        }
+        (Id::Extended(id), IdMask::Extended(mask)) => (EXTENDED, id.as_raw(), mask & 0x1FFF_FFFF),
+        _ => return None,
+    };
+    Some(Raw {
+        id_types: match filter.id_types {
+            IdTypes::SameAsFilter => width,
+            IdTypes::Both => STANDARD | EXTENDED,
+        },
+        frame_types: match filter.frame_types {
+            FrameTypes::Both => DATA | REMOTE,
+            FrameTypes::Data => DATA,
+            FrameTypes::Remote => REMOTE,
+        },
+        id: id & mask,
+        mask,
+    })
+}
+
+fn id_raw(id: Id) -> (u8, u32) {
+    match id {
+        Id::Standard(id) => (STANDARD, u32::from(id.as_raw())),
+        Id::Extended(id) => (EXTENDED, id.as_raw()),
+    }
+}
+
+impl Id {
+    /// Raw identifier value (11 or 29 bits).
+    pub fn as_raw(&self) -> u32 {
+        match self {
+            Id::Standard(id) => u32::from(id.as_raw()),
+            Id::Extended(id) => id.as_raw(),
+        }
+    }
+
+    /// Raw identifier value with only the bits `mask` compares, e.g. the part of an identifier
+    /// that selects a routing table entry.
+    pub fn masked(&self, mask: &IdMask) -> u32 {
+        self.as_raw() & mask.bits()
+    }
+}
+
+impl IdMask {
+    /// Mask comparing the `len` most significant bits of a standard identifier, like a subnet
+    /// prefix. `len` is capped at 11.
+    pub const fn standard_prefix(len: u32) -> Self {
+        IdMask::Standard(prefix(len, 11) as u16)
+    }
+
+    /// Mask comparing the `len` most significant bits of an extended identifier. `len` is capped at
+    /// 29.
+    pub const fn extended_prefix(len: u32) -> Self {
+        IdMask::Extended(prefix(len, 29))
+    }
+
+    /// Width in bits of the identifiers the mask applies to: 11 or 29.
+    pub const fn width(&self) -> u32 {
+        match self {
+            IdMask::Standard(_) => 11,
+            IdMask::Extended(_) => 29,
+        }
+    }
+
+    /// Mask bits, limited to the identifier width.
+    pub const fn bits(&self) -> u32 {
+        match *self {
+            IdMask::Standard(mask) => mask as u32 & 0x7FF,
+            IdMask::Extended(mask) => mask & 0x1FFF_FFFF,
+        }
+    }
+
+    /// Number of identifier bits compared.
+    pub const fn compared_bits(&self) -> u32 {
+        self.bits().count_ones()
+    }
+
+    /// Length of the prefix if the mask compares a run of most significant bits and nothing else,
+    /// as built by [`IdMask::standard_prefix`] and [`IdMask::extended_prefix`].
+    pub const fn prefix_len(&self) -> Option<u32> {
+        let len = self.compared_bits();
+        if self.bits() == prefix(len, self.width()) {
+            Some(len)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if `self` is at most as strict as `other`: both apply to the same identifier
+    /// width and every bit `self` compares is also compared by `other`.
+    ///
+    /// A filter with mask `self` then accepts every identifier that a filter with the same `id`
+    /// and mask `other` accepts.
+    pub const fn covers(&self, other: &IdMask) -> bool {
+        self.width() == other.width() && self.bits() & !other.bits() == 0
+    }
+}
+
+/// `len` (capped at `width`) ones at the top of a `width`-bit value.
+const fn prefix(len: u32, width: u32) -> u32 {
+    let len = if len < width { len } else { width };
+    let all = (1 << width) - 1;
+    all & !(all >> len)
+}
+
+impl IdMaskFilter {
+    /// Number of identifiers the filter accepts, counting both widths for [`IdTypes::Both`].
+    ///
+    /// A filter comparing no bits of a standard identifier accepts all 2048; the count is the same
+    /// for both frame types, which are not counted separately.
+    pub fn accepted_ids(&self) -> u32 {
+        let Some(f) = raw(self) else {
+            return 0;
+        };
+        let mut count = 0;
+        if f.id_types & STANDARD != 0 && f.id & !0x7FF == 0 {
+            count += 1 << (11 - (f.mask & 0x7FF).count_ones());
+        }
+        if f.id_types & EXTENDED != 0 {
+            count += 1 << (29 - f.mask.count_ones());
+        }
+        count
+    }
+
+    /// The raw identifiers of the filter's own width it accepts, if they form one contiguous
+    /// range; this is the case when its mask is a [prefix](IdMask::prefix_len).
+    pub fn id_range(&self) -> Option<RangeInclusive<u32>> {
+        let f = raw(self)?;
+        self.mask.prefix_len()?;
+        let all = (1 << self.mask.width()) - 1;
+        Some(f.id..=f.id | (all & !f.mask))
+    }
+
+    /// Returns `true` if the filter accepts identifier `id`, regardless of frame type.
+    pub fn matches(&self, id: impl Into<Id>) -> bool {
+        let (width, id) = id_raw(id.into());
+        raw(self).is_some_and(|f| f.id_types & width != 0 && id & f.mask == f.id)
+    }
+
+    /// Returns `true` if the filter accepts `frame`, taking its frame type into account.
+    pub fn accepts<F: Frame>(&self, frame: &F) -> bool {
+        let frame_type = if frame.is_remote_frame() {
+            REMOTE
+        } else {
+            DATA
+        };
+        self.matches(frame.id()) && raw(self).is_some_and(|f| f.frame_types & frame_type != 0)
+    }
+
+    /// Returns `true` if every frame `other` accepts is also accepted by `self`, making `other`
+    /// redundant next to `self`.
+    pub fn subsumes(&self, other: &IdMaskFilter) -> bool {
+        let Some(b) = raw(other) else {
+            return true;
+        };
+        raw(self).is_some_and(|a| {
+            b.id_types & !a.id_types == 0
+                && b.frame_types & !a.frame_types == 0
+                && a.mask & !b.mask == 0
+                && b.id & a.mask == a.id
+        })
+    }
+
+    /// Returns `true` if at least one frame is accepted by both filters.
+    pub fn overlaps(&self, other: &IdMaskFilter) -> bool {
+        match (raw(self), raw(other)) {
+            (Some(a), Some(b)) => {
+                a.id_types & b.id_types != 0
+                    && a.frame_types & b.frame_types != 0
+                    && (a.id ^ b.id) & a.mask & b.mask == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Combine two filters into one accepting exactly the union of their frames, if such a
+    /// filter exists.
+    ///
+    /// This is the case when one subsumes the other, when they differ only in frame type, or when
+    /// both compare the same bits and their identifiers differ in exactly one of them.
+    pub fn merge(&self, other: &IdMaskFilter) -> Option<IdMaskFilter> {
+        if self.subsumes(other) {
+            return Some(*self);
+        }
+        if other.subsumes(self) {
+            return Some(*other);
+        }
+        let (a, b) = (raw(self)?, raw(other)?);
+        if a.id_types != b.id_types || a.mask != b.mask {
+            return None;
+        }
+        if a.id == b.id {
+            // Same identifiers; neither subsumes the other, so one is data-only and one
+            // remote-only.
+            return Some(self.with_frame_types(FrameTypes::Both));
+        }
+        let diff = a.id ^ b.id;
+        if a.frame_types != b.frame_types || diff.count_ones() != 1 {
+            return None;
+        }
+        Some(IdMaskFilter {
+            mask: match self.mask {
+                IdMask::Standard(mask) => IdMask::Standard(mask & !(diff as u16)),
+                IdMask::Extended(mask) => IdMask::Extended(mask & !diff),
+            },
+            ..*self
+        })
+    }
+}
+
+/// Reduce `filters` in place to a smaller set accepting exactly the same identifiers.
+///
+/// Redundant filters are dropped and pairs that [`IdMaskFilter::merge`] can combine are merged
+/// until no further reduction applies. The reduced set occupies the front of the slice; its length
+/// is returned and the order of the remaining entries is unspecified.
+///
+/// Pairwise merging finds the minimal set for the common cases (duplicates, nested filters,
+/// adjacent ID blocks) but is not guaranteed to be globally minimal. A non-empty set is never
+/// reduced to an empty one, since drivers may treat an empty list as “accept everything”.
+pub fn minimize(filters: &mut [IdMaskFilter]) -> usize {
+    let mut len = filters.len();
+    while let Some((i, j, merged)) = find_merge(&filters[..len]) {
+        filters[i] = merged;
+        filters.swap(j, len - 1);
+        len -= 1;
+    }
+    len
+}
+
+/// First pair of filters that can be merged, with the merged filter.
+fn find_merge(filters: &[IdMaskFilter]) -> Option<(usize, usize, IdMaskFilter)> {
+    filters.iter().enumerate().find_map(|(i, a)| {
+        filters[i + 1..]
+            .iter()
+            .enumerate()
+            .find_map(|(offset, b)| a.merge(b).map(|merged| (i, i + 1 + offset, merged)))
+    })
+}
+
+/// Condition on one payload byte: `data[offset] & mask == value & mask`.
+///
+/// Frames too short to have a byte at `offset` (including remote frames) do not match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DataFilter {
+    /// Index of the compared byte.
+    pub offset: u8,
+    /// Bits of the byte to compare.
+    pub mask: u8,
+    /// Expected value of the compared bits.
+    pub value: u8,
+}
+
+impl DataFilter {
+    /// Match the bits in `mask` of byte `offset` against `value`.
+    pub const fn new(offset: u8, mask: u8, value: u8) -> Self {
+        Self {
+            offset,
+            mask,
+            value,
+        }
+    }
+
+    /// Match byte `offset` exactly against `value`.
+    pub const fn byte(offset: u8, value: u8) -> Self {
+        Self::new(offset, 0xFF, value)
+    }
+
+    /// Returns `true` if `data` satisfies the condition.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.get(usize::from(self.offset))
+            .is_some_and(|byte| (byte ^ self.value) & self.mask == 0)
+    }
+}
+
+/// An ID filter, optionally narrowed by a condition on the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameFilter {
+    /// Identifier (and frame type) condition.
+    pub id: IdMaskFilter,
+    /// Payload condition, if any.
+    pub data: Option<DataFilter>,
+}
+
+impl FrameFilter {
+    /// Filter on the payload of frames accepted by `id`.
+    pub const fn new(id: IdMaskFilter, data: DataFilter) -> Self {
+        Self {
+            id,
+            data: Some(data),
+        }
+    }
+
+    /// Returns `true` if the filter accepts `frame`.
+    pub fn accepts<F: Frame>(&self, frame: &F) -> bool {
+        self.id.accepts(frame) && self.data.is_none_or(|data| data.matches(frame.data()))
+    }
+}
+
+impl From<IdMaskFilter> for FrameFilter {
+    fn from(id: IdMaskFilter) -> Self {
+        Self { id, data: None }
+    }
+}
+
+/// Receive wrapper that applies up to `N` [`FrameFilter`]s in software.
+///
+/// Frames no filter accepts are dropped; with no filters installed every frame is delivered.
+/// [`SoftwareFilter::install_id_filters`] additionally programs the ID part of the filters into
+/// the wrapped driver's [`FilterConfig`], so the hardware rejects most unwanted traffic and only
+/// the payload conditions are left to software. Transmission is passed through unchanged.
+#[derive(Debug)]
+pub struct SoftwareFilter<R, const N: usize> {
+    inner: R,
+    filters: [FrameFilter; N],
+    len: usize,
+    rejected: u32,
+}
+
+impl<R, const N: usize> SoftwareFilter<R, N> {
+    /// Placeholder for unused slots.
+    const UNUSED: FrameFilter = FrameFilter {
+        id: IdMaskFilter::standard(StandardId::ZERO, 0),
+        data: None,
+    };
+
+    /// Wrap `inner` with no filters installed.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            filters: [Self::UNUSED; N],
+            len: 0,
+            rejected: 0,
+        }
+    }
+
+    /// The installed filters.
+    pub fn filters(&self) -> &[FrameFilter] {
+        &self.filters[..self.len]
+    }
+
+    /// Number of frames dropped because no filter accepted them.
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    /// Program the ID part of the installed filters into the wrapped driver.
+    ///
+    /// The ID filters are minimized first (see [`minimize`]), as several frame filters often share
+    /// one identifier. With no filters installed, the driver's filters are cleared.
+    pub fn install_id_filters(&mut self) -> Result<(), R::Error>
+    where
+        R: FilterConfig,
+    {
+        let mut ids = FilterSet::<N>::new();
+        for filter in self.filters() {
+            // Cannot overflow: there are at most `N` filters.
+            let _ = ids.add(filter.id);
+        }
+        ids.minimize();
+        self.inner.set_filters(&ids)
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn admit<F: Frame>(&mut self, frame: &F) -> bool {
+        let accepted = self.len == 0 || self.filters().iter().any(|f| f.accepts(frame));
+        if !accepted {
+            self.rejected = self.rejected.saturating_add(1);
+        }
+        accepted
+    }
+}
+
+impl<R, const N: usize> FrameFilterConfig for SoftwareFilter<R, N> {
+    type Error = FilterSetFull;
+
+    fn set_frame_filters(&mut self, filters: &[FrameFilter]) -> Result<(), Self::Error> {
+        let target = self.filters.get_mut(..filters.len()).ok_or(FilterSetFull)?;
+        target.copy_from_slice(filters);
+        self.len = filters.len();
+        Ok(())
+    }
+}
+
+impl<R: TxFrameIo, const N: usize> TxFrameIo for SoftwareFilter<R, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<R, const N: usize> RxFrameIo for SoftwareFilter<R, N>
+where
+    R: RxFrameIo,
+    R::Frame: Frame,
+{
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let frame = self.inner.recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let frame = self.inner.try_recv()?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Receive the next accepted frame.
+    ///
+    /// Without a clock the timeout applies to each underlying receive, so a steady stream of
+    /// rejected frames can extend the call beyond `timeout`.
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let frame = self.inner.recv_timeout(timeout)?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Wait until the driver has a frame; it may still be rejected.
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<R: AsyncTxFrameIo, const N: usize> AsyncTxFrameIo for SoftwareFilter<R, N> {
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<R, const N: usize> AsyncRxFrameIo for SoftwareFilter<R, N>
+where
+    R: AsyncRxFrameIo,
+    R::Frame: Frame,
+{
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let frame = self.inner.recv().await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// See [`RxFrameIo::recv_timeout`] on this type: the timeout applies per underlying receive.
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let frame = self.inner.recv_timeout(timeout).await?;
+            if self.admit(&frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_can::Frame;
+
+    use super::*;
+    use crate::testdata::TestFrame;
+
+    fn filter(id: u16, mask: u16) -> IdMaskFilter {
+        IdMaskFilter::standard(StandardId::new(id).unwrap(), mask)
+    }
+
+    fn accepts(filters: &[IdMaskFilter], id: u16, remote: bool) -> bool {
+        let id = StandardId::new(id).unwrap();
+        let frame = if remote {
+            TestFrame::new_remote(id, 0)
+        } else {
+            TestFrame::new(id, &[])
+        };
+        filters.iter().any(|f| f.accepts(&frame.unwrap()))
+    }
+
+    #[test]
+    fn subsumes_nested() {
+        let outer = filter(0x100, 0x700);
+        let inner = filter(0x123, 0x7FF);
+        assert!(outer.subsumes(&inner));
+        assert!(!inner.subsumes(&outer));
+        assert!(outer.subsumes(&outer));
+        assert!(!outer.with_frame_types(FrameTypes::Data).subsumes(&inner));
+        assert!(outer.subsumes(&inner.with_frame_types(FrameTypes::Remote)));
+    }
+
+    #[test]
+    fn overlaps() {
+        let outer = filter(0x100, 0x700);
+        assert!(outer.overlaps(&filter(0x123, 0x7FF)));
+        assert!(!outer.overlaps(&filter(0x223, 0x7FF)));
+        // Each accepts IDs the other does not, but both accept 0x101.
+        assert!(filter(0x001, 0x00F).overlaps(&filter(0x100, 0x700)));
+        let data = outer.with_frame_types(FrameTypes::Data);
+        assert!(!data.overlaps(&outer.with_frame_types(FrameTypes::Remote)));
+    }
+
+    #[test]
+    fn merge_duplicates_and_nested() {
+        let outer = filter(0x100, 0x700);
+        let inner = filter(0x123, 0x7FF);
+        assert_eq!(outer.merge(&outer), Some(outer));
+        assert_eq!(outer.merge(&inner), Some(outer));
+        assert_eq!(inner.merge(&outer), Some(outer));
+    }
+
+    #[test]
+    fn merge_adjacent_blocks() {
+        assert_eq!(
+            filter(0x120, 0x7F0).merge(&filter(0x130, 0x7F0)),
+            Some(filter(0x120, 0x7E0))
+        );
+        // Two differing bits would also accept 0x100 and 0x130.
+        assert_eq!(filter(0x110, 0x7F0).merge(&filter(0x120, 0x7F0)), None);
+        // Different masks, neither nested.
+        assert_eq!(filter(0x120, 0x7F0).merge(&filter(0x130, 0x7F8)), None);
+        // Frame types must agree for identifiers to be combined.
+        let data = filter(0x120, 0x7F0).with_frame_types(FrameTypes::Data);
+        assert_eq!(data.merge(&filter(0x130, 0x7F0)), None);
+    }
+
+    #[test]
+    fn merge_data_and_remote() {
+        let id = filter(0x123, 0x7FF);
+        let data = id.with_frame_types(FrameTypes::Data);
+        let remote = id.with_frame_types(FrameTypes::Remote);
+        assert_eq!(data.merge(&remote), Some(id));
+        let other = filter(0x122, 0x7FF).with_frame_types(FrameTypes::Remote);
+        assert_eq!(data.merge(&other), None);
+    }
+
+    #[test]
+    fn minimize_common_cases() {
+        let mut filters = [filter(0x123, 0x7FF); 3];
+        assert_eq!(minimize(&mut filters), 1);
+
+        let mut filters = [filter(0x123, 0x7FF), filter(0x100, 0x700)];
+        assert_eq!(minimize(&mut filters), 1);
+        assert_eq!(filters[0], filter(0x100, 0x700));
+
+        let mut filters = [0x102, 0x100, 0x103, 0x101].map(|id| filter(id, 0x7FF));
+        assert_eq!(minimize(&mut filters), 1);
+        assert_eq!(filters[0].id_range(), Some(0x100..=0x103));
+
+        let mut filters: [IdMaskFilter; 0] = [];
+        assert_eq!(minimize(&mut filters), 0);
+    }
+
+    #[test]
+    fn minimize_preserves_accepted_set() {
+        // Small xorshift generator: sets of clustered filters, so that many of them merge.
+        let mut state = 0x2545_F491_u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        let mut reduced_sets = 0;
+        for _ in 0..200 {
+            let mut filters = [filter(0, 0); 8];
+            let len = next() as usize % filters.len() + 1;
+            for f in &mut filters[..len] {
+                let frame_types = [FrameTypes::Both, FrameTypes::Data, FrameTypes::Remote];
+                *f = filter(
+                    0x100 | (next() as u16 & 0x0F),
+                    0x7F0 | (next() as u16 & 0x0F),
+                )
+                .with_frame_types(frame_types[next() as usize % 3]);
+            }
+            let original = filters;
+            let reduced = minimize(&mut filters[..len]);
+            assert!(reduced >= 1 && reduced <= len);
+            reduced_sets += usize::from(reduced < len);
+            for id in 0..=0x7FF {
+                for remote in [false, true] {
+                    assert_eq!(
+                        accepts(&filters[..reduced], id, remote),
+                        accepts(&original[..len], id, remote),
+                        "{:?} -> {:?} differ at {id:#x}",
+                        &original[..len],
+                        &filters[..reduced],
+                    );
+                }
+            }
+        }
+        assert!(reduced_sets > 50, "only {reduced_sets} sets were reduced");
+    }
+}
@@ -0,0 +1,153 @@
+//! Semantic frame comparison.
+//!
+//! Two frames that a protocol considers “the same” are not always equal field by field. A frame
+//! type may keep bytes past the DLC in its buffer, an alive counter may change in every frame, and
+//! the data bytes of a remote frame carry no meaning. [`FrameEq`] is the crate's single definition
+//! of frame equality, with switches for these cases; the wrappers that look for repeated frames
+//! ([`Dedup`](crate::dedup::Dedup), [`OnChange`](crate::change::OnChange),
+//! [`ConfirmedTx`](crate::confirmed::ConfirmedTx)) use it too:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::frame_eq::FrameEq;
+//!
+//! // Byte 7 holds an alive counter in the upper nibble.
+//! const MASK: [u8; 8] = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+//! assert!(FrameEq::new().mask(&MASK).eq(&received, &expected));
+//! ```
+//!
+//! Frame types that know about CAN FD can implement [`FdFlags`] to have the format and the BRS and
+//! ESI bits compared as well, with [`FrameEq::eq_fd`].
+
+use embedded_can::Frame;
+
+/// CAN FD flags of a frame type that can represent them.
+pub trait FdFlags {
+    /// Returns `true` for a CAN FD frame (FDF bit set), including FD frames of up to 8 bytes.
+    fn is_fd(&self) -> bool;
+
+    /// Returns `true` if the data phase is sent at the data bitrate (BRS bit).
+    fn bitrate_switch(&self) -> bool;
+
+    /// Returns `true` if the transmitter was error passive (ESI bit).
+    fn error_state_indicator(&self) -> bool;
+}
+
+/// Payload length encoded by a DLC: 0–8 as is, 9–15 the CAN FD lengths.
+///
+/// Values above 15 are assumed to be byte counts already, as some frame types report them.
+pub const fn dlc_to_len(dlc: usize) -> usize {
+    match dlc {
+        0..=8 => dlc,
+        9 => 12,
+        10 => 16,
+        11 => 20,
+        12 => 24,
+        13 => 32,
+        14 => 48,
+        15 => 64,
+        len => len,
+    }
+}
+
+/// Configurable frame equality; see the [module documentation](self).
+///
+/// [`FrameEq::new`] (also the default) is strict: identifier, frame type, DLC and every data byte
+/// must match. Remote frames never compare their data bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameEq<'a> {
+    mask: &'a [u8],
+    ignore_padding: bool,
+    remote_dlc: bool,
+    fd_flags: bool,
+}
+
+impl Default for FrameEq<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FrameEq<'a> {
+    /// Strict comparison.
+    pub const fn new() -> Self {
+        Self {
+            mask: &[],
+            ignore_padding: false,
+            remote_dlc: true,
+            fd_flags: true,
+        }
+    }
+
+    /// Compare only the data bits set in `mask`.
+    ///
+    /// `mask[i]` applies to data byte `i`; bytes past the end of the mask are compared in full.
+    pub const fn mask(mut self, mask: &'a [u8]) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Ignore data bytes past the length the DLC encodes.
+    ///
+    /// For frame types whose [`data`](Frame::data) returns a fixed-size buffer rather than the
+    /// payload. The DLCs must still match.
+    pub const fn ignore_padding(mut self) -> Self {
+        self.ignore_padding = true;
+        self
+    }
+
+    /// Treat remote frames with different DLCs as equal.
+    ///
+    /// Many nodes send remote requests with DLC 0 regardless of the length of the data frame they
+    /// request.
+    pub const fn ignore_remote_dlc(mut self) -> Self {
+        self.remote_dlc = false;
+        self
+    }
+
+    /// Whether [`FrameEq::eq_fd`] compares the [`FdFlags`] (the default) or only the contents.
+    pub const fn fd_flags(mut self, compare: bool) -> Self {
+        self.fd_flags = compare;
+        self
+    }
+
+    /// The bytes of `frame` that take part in comparisons.
+    pub fn payload<'f, F: Frame>(&self, frame: &'f F) -> &'f [u8] {
+        let data = frame.data();
+        if frame.is_remote_frame() {
+            return &[];
+        }
+        if self.ignore_padding {
+            return &data[..data.len().min(dlc_to_len(frame.dlc()))];
+        }
+        data
+    }
+
+    /// Compare identifier and contents.
+    pub fn eq<F: Frame>(&self, a: &F, b: &F) -> bool {
+        a.id() == b.id() && self.content_eq(a, b)
+    }
+
+    /// Compare everything but the identifier, e.g. two frames already known to share one.
+    pub fn content_eq<F: Frame>(&self, a: &F, b: &F) -> bool {
+        if a.is_remote_frame() != b.is_remote_frame() {
+            return false;
+        }
+        if a.is_remote_frame() {
+            return !self.remote_dlc || a.dlc() == b.dlc();
+        }
+        let (x, y) = (self.payload(a), self.payload(b));
+        a.dlc() == b.dlc()
+            && x.len() == y.len()
+            && x.iter().zip(y).enumerate().all(|(i, (x, y))| {
+                let mask = self.mask.get(i).copied().unwrap_or(0xFF);
+                (x ^ y) & mask == 0
+            })
+    }
+
+    /// Like [`FrameEq::eq`], also comparing the FD flags unless disabled with
+    /// [`FrameEq::fd_flags`].
+    pub fn eq_fd<F: Frame + FdFlags>(&self, a: &F, b: &F) -> bool {
+        let flags = |f: &F| (f.is_fd(), f.bitrate_switch(), f.error_state_indicator());
+        self.eq(a, b) && (!self.fd_flags || flags(a) == flags(b))
+    }
+}
@@ -0,0 +1,401 @@
+//! Structured fuzzing support (requires the `alloc` feature).
+//!
+//! A fuzzer hands its target a byte slice. [`Ops`] decodes that slice into a sequence of
+//! well-formed operations ([`Op`]) with valid frames, so the fuzzer spends its time exploring
+//! protocol states instead of failing frame construction. [`FuzzBus`] is an in-memory interface
+//! the operations are played against, and [`FuzzClock`] a clock that only moves when told to (or
+//! by a small step per reading, so spin-waits terminate). Together they drive the state machines
+//! in this crate, such as [`IsoTp`](crate::isotp::IsoTp), from a `cargo fuzz` target:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::fuzz::{FuzzBus, FuzzClock, Op, Ops};
+//! use embedded_can_interface::isotp::{IsoTp, IsoTpConfig, MessageIo};
+//!
+//! fuzz_target!(|data: &[u8]| {
+//!     let clock = FuzzClock::new();
+//!     let mut isotp = IsoTp::new(FuzzBus::<MyFrame>::new(), &clock, IsoTpConfig::new(tx, rx));
+//!     let mut buf = [0; 4095];
+//!     for op in Ops::<MyFrame>::new(data) {
+//!         match op {
+//!             Op::Advance(duration) => clock.advance(duration),
+//!             Op::Message(payload) => drop(isotp.send_message(payload)),
+//!             Op::Poll => drop(isotp.recv_message(&mut buf, Duration::ZERO)),
+//!             op => isotp.inner_mut().apply(op),
+//!         }
+//!     }
+//! });
+//! ```
+//!
+//! Decoding is deterministic, so a crashing input replays the same operations every time.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::error::Error;
+use core::fmt;
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+use crate::testdata::LENGTHS;
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, Clock, RxFrameIo, TxFrameIo};
+
+/// Reader handing out values from the fuzzer's bytes; reads past the end yield zeros.
+#[derive(Debug, Clone)]
+pub struct FuzzInput<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FuzzInput<'a> {
+    /// Read from `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Returns `true` once every byte has been read.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Next byte.
+    pub fn u8(&mut self) -> u8 {
+        let (&byte, rest) = self.data.split_first().unwrap_or((&0, &[]));
+        self.data = rest;
+        byte
+    }
+
+    /// Next two bytes, little-endian.
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes([self.u8(), self.u8()])
+    }
+
+    /// Next four bytes, little-endian.
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes([self.u8(), self.u8(), self.u8(), self.u8()])
+    }
+
+    /// Up to `len` bytes; fewer if the input runs out.
+    pub fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let (bytes, rest) = self.data.split_at(len.min(self.data.len()));
+        self.data = rest;
+        bytes
+    }
+
+    /// An identifier of either width.
+    pub fn id(&mut self) -> Id {
+        let raw = self.u32();
+        if raw & 1 == 0 {
+            Id::Standard(StandardId::new((raw >> 1) as u16 & StandardId::MAX.as_raw()).unwrap())
+        } else {
+            Id::Extended(ExtendedId::new((raw >> 1) & ExtendedId::MAX.as_raw()).unwrap())
+        }
+    }
+
+    /// A data or remote frame with up to `max_len` data bytes, or `None` if `F` rejects it.
+    pub fn frame<F: Frame>(&mut self, max_len: usize) -> Option<F> {
+        let shape = self.u8();
+        let id = self.id();
+        if shape & 0x80 != 0 {
+            return F::new_remote(id, usize::from(shape & 0x0F).min(8));
+        }
+        let lengths = LENGTHS.partition_point(|&len| len <= max_len).max(1);
+        let len = LENGTHS[usize::from(shape) % lengths];
+        let mut data = [0; 64];
+        let bytes = self.bytes(len);
+        data[..bytes.len()].copy_from_slice(bytes);
+        F::new(id, &data[..len])
+    }
+}
+
+/// One operation decoded by [`Ops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<'a, F> {
+    /// The bus delivers a frame to the system under test ([`FuzzBus::deliver`]).
+    Receive(F),
+    /// The application sends a frame.
+    Send(F),
+    /// The application sends a message through a message-level API (e.g. ISO-TP).
+    Message(&'a [u8]),
+    /// The application polls: receives, flushes queues, runs timers.
+    Poll,
+    /// Time passes ([`FuzzClock::advance`]).
+    Advance(Duration),
+    /// The next I/O call on the bus fails ([`FuzzBus::fail_next`]).
+    FailNext,
+    /// Transmission is blocked or unblocked ([`FuzzBus::set_tx_blocked`]).
+    BlockTx(bool),
+}
+
+/// Iterator decoding [`Op`]s from fuzzer input until it is exhausted.
+#[derive(Debug, Clone)]
+pub struct Ops<'a, F> {
+    input: FuzzInput<'a>,
+    max_len: usize,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<'a, F> Ops<'a, F> {
+    /// Decode `data`, with frames of up to 8 data bytes.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            input: FuzzInput::new(data),
+            max_len: 8,
+            _frame: PhantomData,
+        }
+    }
+
+    /// Allow frames with up to `max_len` data bytes (64 for CAN FD).
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+/// Operations whose frame `F` rejects are skipped.
+impl<'a, F: Frame> Iterator for Ops<'a, F> {
+    type Item = Op<'a, F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.input.is_empty() {
+            let op = match self.input.u8() % 8 {
+                0 | 1 => self.input.frame(self.max_len).map(Op::Receive),
+                2 => self.input.frame(self.max_len).map(Op::Send),
+                3 => {
+                    let len = usize::from(self.input.u16() % 4096);
+                    Some(Op::Message(self.input.bytes(len)))
+                }
+                4 => Some(Op::Poll),
+                5 => Some(Op::Advance(Duration::from_micros(u64::from(
+                    self.input.u32(),
+                )))),
+                6 => Some(Op::FailNext),
+                _ => Some(Op::BlockTx(self.input.u8() & 1 != 0)),
+            };
+            if op.is_some() {
+                return op;
+            }
+        }
+        None
+    }
+}
+
+/// Error returned by [`FuzzBus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzBusError {
+    /// No frame was delivered.
+    Empty,
+    /// Transmission is blocked.
+    Full,
+    /// Failure requested with [`FuzzBus::fail_next`].
+    Injected,
+}
+
+impl fmt::Display for FuzzBusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no frame delivered"),
+            Self::Full => f.write_str("transmission blocked"),
+            Self::Injected => f.write_str("injected failure"),
+        }
+    }
+}
+
+impl Error for FuzzBusError {}
+
+/// In-memory interface for fuzz targets.
+///
+/// Frames passed to [`FuzzBus::deliver`] are received in order, and sent frames are recorded.
+/// Nothing ever blocks: receiving with no frame delivered fails with [`FuzzBusError::Empty`] and
+/// sending while transmission is blocked with [`FuzzBusError::Full`], whatever the timeout. The
+/// async traits behave the same way, so their futures complete on the first poll.
+#[derive(Debug)]
+pub struct FuzzBus<F> {
+    rx: VecDeque<F>,
+    sent: Vec<F>,
+    tx_blocked: bool,
+    fail_next: bool,
+}
+
+impl<F> FuzzBus<F> {
+    /// An empty bus.
+    pub fn new() -> Self {
+        Self {
+            rx: VecDeque::new(),
+            sent: Vec::new(),
+            tx_blocked: false,
+            fail_next: false,
+        }
+    }
+
+    /// Queue `frame` for reception.
+    pub fn deliver(&mut self, frame: F) {
+        self.rx.push_back(frame);
+    }
+
+    /// Make the next send or receive fail with [`FuzzBusError::Injected`].
+    pub fn fail_next(&mut self) {
+        self.fail_next = true;
+    }
+
+    /// Block or unblock transmission.
+    pub fn set_tx_blocked(&mut self, blocked: bool) {
+        self.tx_blocked = blocked;
+    }
+
+    /// Frames sent so far.
+    pub fn sent(&self) -> &[F] {
+        &self.sent
+    }
+
+    /// Remove and return the frames sent so far.
+    pub fn take_sent(&mut self) -> Vec<F> {
+        core::mem::take(&mut self.sent)
+    }
+
+    /// Apply the bus-level operations ([`Op::Receive`], [`Op::FailNext`], [`Op::BlockTx`]);
+    /// others are ignored.
+    pub fn apply(&mut self, op: Op<'_, F>) {
+        match op {
+            Op::Receive(frame) => self.deliver(frame),
+            Op::FailNext => self.fail_next(),
+            Op::BlockTx(blocked) => self.set_tx_blocked(blocked),
+            Op::Send(_) | Op::Message(_) | Op::Poll | Op::Advance(_) => {}
+        }
+    }
+
+    fn check_injected(&mut self) -> Result<(), FuzzBusError> {
+        if core::mem::take(&mut self.fail_next) {
+            return Err(FuzzBusError::Injected);
+        }
+        Ok(())
+    }
+}
+
+impl<F> Default for FuzzBus<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Clone> TxFrameIo for FuzzBus<F> {
+    type Frame = F;
+    type Error = FuzzBusError;
+
+    fn send(&mut self, frame: &F) -> Result<(), FuzzBusError> {
+        self.try_send(frame)
+    }
+
+    fn try_send(&mut self, frame: &F) -> Result<(), FuzzBusError> {
+        self.check_injected()?;
+        if self.tx_blocked {
+            return Err(FuzzBusError::Full);
+        }
+        self.sent.push(frame.clone());
+        Ok(())
+    }
+
+    fn send_timeout(&mut self, frame: &F, _timeout: Duration) -> Result<(), FuzzBusError> {
+        self.try_send(frame)
+    }
+}
+
+impl<F> RxFrameIo for FuzzBus<F> {
+    type Frame = F;
+    type Error = FuzzBusError;
+
+    fn recv(&mut self) -> Result<F, FuzzBusError> {
+        self.try_recv()
+    }
+
+    fn try_recv(&mut self) -> Result<F, FuzzBusError> {
+        self.check_injected()?;
+        self.rx.pop_front().ok_or(FuzzBusError::Empty)
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<F, FuzzBusError> {
+        self.try_recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), FuzzBusError> {
+        self.check_injected()?;
+        if self.rx.is_empty() {
+            return Err(FuzzBusError::Empty);
+        }
+        Ok(())
+    }
+}
+
+impl<F: Clone> AsyncTxFrameIo for FuzzBus<F> {
+    type Frame = F;
+    type Error = FuzzBusError;
+
+    async fn send(&mut self, frame: &F) -> Result<(), FuzzBusError> {
+        TxFrameIo::try_send(self, frame)
+    }
+
+    async fn send_timeout(&mut self, frame: &F, _timeout: Duration) -> Result<(), FuzzBusError> {
+        TxFrameIo::try_send(self, frame)
+    }
+}
+
+impl<F> AsyncRxFrameIo for FuzzBus<F> {
+    type Frame = F;
+    type Error = FuzzBusError;
+
+    async fn recv(&mut self) -> Result<F, FuzzBusError> {
+        RxFrameIo::try_recv(self)
+    }
+
+    async fn recv_timeout(&mut self, _timeout: Duration) -> Result<F, FuzzBusError> {
+        RxFrameIo::try_recv(self)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), FuzzBusError> {
+        RxFrameIo::wait_not_empty(self)
+    }
+}
+
+/// Clock for fuzz targets, starting at zero.
+///
+/// Every reading advances the clock by a small step (1 µs by default), so code that spins until
+/// a deadline always finishes; [`FuzzClock::advance`] moves it further.
+#[derive(Debug)]
+pub struct FuzzClock {
+    now: Cell<Duration>,
+    step: Duration,
+}
+
+impl FuzzClock {
+    /// A clock at zero that advances 1 µs per reading.
+    pub fn new() -> Self {
+        Self::with_step(Duration::from_micros(1))
+    }
+
+    /// A clock at zero that advances `step` per reading.
+    pub fn with_step(step: Duration) -> Self {
+        Self {
+            now: Cell::new(Duration::ZERO),
+            step,
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get().saturating_add(duration));
+    }
+}
+
+impl Default for FuzzClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for FuzzClock {
+    fn now(&self) -> Duration {
+        let now = self.now.get();
+        self.now.set(now.saturating_add(self.step));
+        now
+    }
+}
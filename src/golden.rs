@@ -0,0 +1,615 @@
+//! Golden-trace regression tests (requires the `alloc` feature).
+//!
+//! Instead of asserting on captured frames one by one, a test runs a protocol stack through a
+//! [`Recorder`] and compares everything that crossed the interface with an expected [`Trace`],
+//! typically kept next to the test as text:
+//!
+//! ```text
+//! # ISO-TP request with flow control
+//! (0.000000) tx 7E0#100A2201020304
+//! (0.000150) rx 7E8#300000
+//! (0.000300) tx 7E0#2105060708090A
+//! ```
+//!
+//! Each line is a timestamp in seconds, the direction, and the frame in `candump` notation:
+//! hexadecimal identifier (eight digits for extended identifiers), `#`, then the data bytes, or `R`
+//! and an optional DLC digit for remote frames. Blank lines and lines starting with `#` are
+//! ignored. Timestamps are relative to the first frame and match within a tolerance, so traces
+//! recorded with a real clock stay comparable:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::golden::{Recorder, Trace};
+//!
+//! let mut can = Recorder::new(sim, &clock);
+//! isotp_send(&mut can, &request)?;
+//! let expected = Trace::parse(include_str!("traces/request.trace"))?;
+//! can.trace().assert_matches(&expected, Duration::from_micros(50));
+//! ```
+//!
+//! On a mismatch, [`Trace::assert_matches`] panics with a [`Mismatch`] report showing the first
+//! differing frame, the frames before it, and both traces' lengths. [`Trace`]'s `Display` prints
+//! the same format [`Trace::parse`] reads, which is how a golden file is first written.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+pub use crate::blackbox::Direction;
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+};
+
+/// Number of matching frames shown before the first difference in a [`Mismatch`] report.
+const CONTEXT: usize = 3;
+
+/// One frame of a trace, independent of the frame type it was recorded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Direction of the frame.
+    pub direction: Direction,
+    /// Time since the start of the trace.
+    pub at: Duration,
+    /// Identifier.
+    pub id: Id,
+    /// `true` for a remote frame.
+    pub remote: bool,
+    /// Data length code (the requested length, for remote frames).
+    pub dlc: usize,
+    /// Data bytes; empty for remote frames.
+    pub data: Vec<u8>,
+}
+
+impl Event {
+    /// Normalize `frame`.
+    pub fn new<F: Frame>(direction: Direction, at: Duration, frame: &F) -> Self {
+        Self {
+            direction,
+            at,
+            id: frame.id(),
+            remote: frame.is_remote_frame(),
+            dlc: frame.dlc(),
+            data: if frame.is_remote_frame() {
+                Vec::new()
+            } else {
+                frame.data().to_vec()
+            },
+        }
+    }
+
+    /// `true` if both events carry the same frame in the same direction, whatever their times.
+    pub fn same_frame(&self, other: &Self) -> bool {
+        self.direction == other.direction
+            && self.id == other.id
+            && self.remote == other.remote
+            && self.dlc == other.dlc
+            && self.data == other.data
+    }
+
+    /// Parse one trace line (without a trailing comment).
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let at = parse_time(fields.next()?.strip_prefix('(')?.strip_suffix(')')?)?;
+        let direction = match fields.next()? {
+            "tx" => Direction::Tx,
+            "rx" => Direction::Rx,
+            _ => return None,
+        };
+        let (id, payload) = fields.next()?.split_once('#')?;
+        if fields.next().is_some() {
+            return None;
+        }
+        let raw = u32::from_str_radix(id, 16).ok()?;
+        let id = match id.len() {
+            3 => Id::Standard(StandardId::new(u16::try_from(raw).ok()?)?),
+            8 => Id::Extended(ExtendedId::new(raw)?),
+            _ => return None,
+        };
+        if let Some(dlc) = payload.strip_prefix('R') {
+            let dlc = match dlc {
+                "" => 0,
+                dlc => dlc.parse().ok().filter(|dlc| *dlc <= 8)?,
+            };
+            return Some(Self {
+                direction,
+                at,
+                id,
+                remote: true,
+                dlc,
+                data: Vec::new(),
+            });
+        }
+        if payload.len() % 2 != 0 {
+            return None;
+        }
+        let data = (0..payload.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(payload.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(Self {
+            direction,
+            at,
+            id,
+            remote: false,
+            dlc: data.len(),
+            data,
+        })
+    }
+}
+
+/// Parse `seconds[.fraction]`, with up to nine fractional digits.
+fn parse_time(text: &str) -> Option<Duration> {
+    let (secs, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if secs.is_empty() || !all_digits(secs) || !all_digits(fraction) || fraction.len() > 9 {
+        return None;
+    }
+    let nanos = fraction
+        .bytes()
+        .chain(core::iter::repeat(b'0'))
+        .take(9)
+        .fold(0, |nanos, digit| nanos * 10 + u32::from(digit - b'0'));
+    Some(Duration::new(secs.parse().ok()?, nanos))
+}
+
+/// Same format as [`Event::parse`] reads, with microsecond timestamps.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Tx => "tx",
+            Direction::Rx => "rx",
+        };
+        write!(
+            f,
+            "({}.{:06}) {direction} ",
+            self.at.as_secs(),
+            self.at.subsec_micros()
+        )?;
+        match self.id {
+            Id::Standard(id) => write!(f, "{:03X}#", id.as_raw())?,
+            Id::Extended(id) => write!(f, "{:08X}#", id.as_raw())?,
+        }
+        if self.remote {
+            return write!(f, "R{}", self.dlc);
+        }
+        self.data
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
+/// Error returned by [`Trace::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParseError {
+    /// Line number, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for TraceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid trace line {}", self.line)
+    }
+}
+
+impl Error for TraceParseError {}
+
+/// How two traces differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// Different frames (or directions) at the same position.
+    Frame,
+    /// The same frame, at a time outside the tolerance.
+    Time,
+    /// The actual trace has frames past the end of the expected one.
+    Extra,
+    /// The actual trace ends before the expected one.
+    Missing,
+}
+
+/// First difference between an actual and an expected trace, returned by [`Trace::compare`].
+///
+/// `Display` renders a report of the difference with the frames leading up to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// How the traces differ.
+    pub kind: MismatchKind,
+    /// Position of the first differing frame.
+    pub index: usize,
+    /// Expected frame at `index`, if the expected trace is that long.
+    pub expected: Option<Event>,
+    /// Actual frame at `index`, if the actual trace is that long.
+    pub actual: Option<Event>,
+    /// Frames both traces agree on just before `index`.
+    pub context: Vec<Event>,
+    /// Length of the expected trace.
+    pub expected_len: usize,
+    /// Length of the actual trace.
+    pub actual_len: usize,
+    /// Timestamp tolerance the traces were compared with.
+    pub tolerance: Duration,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            MismatchKind::Frame => "different frame",
+            MismatchKind::Time => "timestamp out of tolerance",
+            MismatchKind::Extra => "unexpected frame",
+            MismatchKind::Missing => "missing frame",
+        };
+        writeln!(
+            f,
+            "trace mismatch at frame {}: {reason} (expected {} frames, got {})",
+            self.index, self.expected_len, self.actual_len
+        )?;
+        let first = self.index - self.context.len();
+        for (i, event) in self.context.iter().enumerate() {
+            writeln!(f, "  {:>5}   {event}", first + i)?;
+        }
+        match &self.expected {
+            Some(event) => writeln!(f, "- {:>5}   {event}", self.index)?,
+            None => writeln!(f, "- {:>5}   <end of trace>", self.index)?,
+        }
+        match &self.actual {
+            Some(event) => write!(f, "+ {:>5}   {event}", self.index)?,
+            None => write!(f, "+ {:>5}   <end of trace>", self.index)?,
+        }
+        if let (MismatchKind::Time, Some(expected), Some(actual)) =
+            (self.kind, &self.expected, &self.actual)
+        {
+            let (offset, sign) = match actual.at.checked_sub(expected.at) {
+                Some(late) => (late, '+'),
+                None => (expected.at - actual.at, '-'),
+            };
+            write!(
+                f,
+                "\n  off by {sign}{offset:?}, tolerance {:?}",
+                self.tolerance
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Mismatch {}
+
+/// A recorded or expected sequence of frames; see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<Event>,
+}
+
+impl Trace {
+    /// An empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a trace in the text format of the [module documentation](self).
+    pub fn parse(text: &str) -> Result<Self, TraceParseError> {
+        let mut events = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            events.push(Event::parse(line).ok_or(TraceParseError { line: i + 1 })?);
+        }
+        Ok(Self { events })
+    }
+
+    /// Append an event.
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// The events, in order.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Number of events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if the trace has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Compare with `expected`, frame by frame.
+    ///
+    /// Frames must match exactly and in order. Timestamps are taken relative to each trace's
+    /// first frame and may differ by up to `tolerance`.
+    pub fn compare(&self, expected: &Trace, tolerance: Duration) -> Result<(), Box<Mismatch>> {
+        let start = |trace: &Trace| trace.events.first().map_or(Duration::ZERO, |e| e.at);
+        let (actual_start, expected_start) = (start(self), start(expected));
+        let len = self.len().max(expected.len());
+        for index in 0..len {
+            let actual = self.events.get(index);
+            let wanted = expected.events.get(index);
+            let kind = match (wanted, actual) {
+                (Some(wanted), Some(actual)) if !actual.same_frame(wanted) => MismatchKind::Frame,
+                (Some(wanted), Some(actual)) => {
+                    let a = actual.at.saturating_sub(actual_start);
+                    let e = wanted.at.saturating_sub(expected_start);
+                    if a.abs_diff(e) <= tolerance {
+                        continue;
+                    }
+                    MismatchKind::Time
+                }
+                (None, _) => MismatchKind::Extra,
+                (_, None) => MismatchKind::Missing,
+            };
+            let normalize = |event: &Event, start: Duration| Event {
+                at: event.at.saturating_sub(start),
+                ..event.clone()
+            };
+            return Err(Box::new(Mismatch {
+                kind,
+                index,
+                expected: wanted.map(|e| normalize(e, expected_start)),
+                actual: actual.map(|e| normalize(e, actual_start)),
+                context: self.events[index.saturating_sub(CONTEXT)..index]
+                    .iter()
+                    .map(|e| normalize(e, actual_start))
+                    .collect(),
+                expected_len: expected.len(),
+                actual_len: self.len(),
+                tolerance,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Panic with a [`Mismatch`] report unless the trace matches `expected`; see
+    /// [`Trace::compare`].
+    #[track_caller]
+    pub fn assert_matches(&self, expected: &Trace, tolerance: Duration) {
+        if let Err(mismatch) = self.compare(expected, tolerance) {
+            panic!("{mismatch}");
+        }
+    }
+}
+
+/// One event per line, in the format [`Trace::parse`] reads.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.events
+            .iter()
+            .try_for_each(|event| writeln!(f, "{event}"))
+    }
+}
+
+impl FromIterator<Event> for Trace {
+    fn from_iter<I: IntoIterator<Item = Event>>(iter: I) -> Self {
+        Self {
+            events: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Wrapper that records every frame passing through it into a [`Trace`].
+///
+/// Timestamps are taken on `clock`, relative to the first recorded frame. Transmitted frames are
+/// recorded once the driver accepted them; failed sends are not recorded.
+#[derive(Debug)]
+pub struct Recorder<T, C> {
+    inner: T,
+    clock: C,
+    start: Option<Duration>,
+    trace: Trace,
+}
+
+impl<T, C> Recorder<T, C> {
+    /// Wrap `inner`, timestamping frames with `clock`.
+    pub fn new(inner: T, clock: C) -> Self {
+        Self {
+            inner,
+            clock,
+            start: None,
+            trace: Trace::new(),
+        }
+    }
+
+    /// The frames recorded so far.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Remove and return the frames recorded so far; the next frame starts a new trace at zero.
+    pub fn take_trace(&mut self) -> Trace {
+        self.start = None;
+        core::mem::take(&mut self.trace)
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, C: Clock> Recorder<T, C> {
+    fn record<F: Frame>(&mut self, direction: Direction, frame: &F) {
+        let now = self.clock.now();
+        let start = *self.start.get_or_insert(now);
+        self.trace
+            .push(Event::new(direction, now.saturating_sub(start), frame));
+    }
+
+    fn record_result<F: Frame, R>(&mut self, frame: &F, result: &Result<(), R>) {
+        if result.is_ok() {
+            self.record(Direction::Tx, frame);
+        }
+    }
+
+    fn record_batch<F: Frame, R>(
+        &mut self,
+        frames: &[(F, SendOptions)],
+        result: &Result<usize, PartialSend<R>>,
+    ) {
+        let sent = match result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        for (frame, _) in frames.iter().take(sent) {
+            self.record(Direction::Tx, frame);
+        }
+    }
+}
+
+impl<T, C> TxFrameIo for Recorder<T, C>
+where
+    T: TxFrameIo<Frame: Frame>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame);
+        self.record_result(frame, &result);
+        result
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.try_send(frame);
+        self.record_result(frame, &result);
+        result
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout);
+        self.record_result(frame, &result);
+        result
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options);
+        self.record_result(frame, &result);
+        result
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C> RxFrameIo for Recorder<T, C>
+where
+    T: RxFrameIo<Frame: Frame>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv()?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.try_recv()?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout)?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, C> AsyncTxFrameIo for Recorder<T, C>
+where
+    T: AsyncTxFrameIo<Frame: Frame>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame).await;
+        self.record_result(frame, &result);
+        result
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.record_result(frame, &result);
+        result
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options).await;
+        self.record_result(frame, &result);
+        result
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(frames, &result);
+        result
+    }
+}
+
+impl<T, C> AsyncRxFrameIo for Recorder<T, C>
+where
+    T: AsyncRxFrameIo<Frame: Frame>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv().await?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let frame = self.inner.recv_timeout(timeout).await?;
+        self.record(Direction::Rx, &frame);
+        Ok(frame)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
@@ -0,0 +1,239 @@
+//! Bit-field layouts for proprietary identifier schemes.
+//!
+//! Protocols on CAN commonly carve the identifier into fields: a priority in the top bits (so it
+//! decides arbitration), then a message type and a node address. [`IdScheme`] describes such a
+//! layout once and then encodes identifiers, decodes received ones, and builds acceptance filters
+//! that match on some fields and ignore the rest:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::id_scheme::{Field, IdScheme};
+//!
+//! // 3-bit priority above an 8-bit node address.
+//! const SCHEME: IdScheme<2> = IdScheme::standard([Field::new(8, 3), Field::new(0, 8)]);
+//!
+//! let id = SCHEME.encode([2, node])?;
+//! let [priority, source] = SCHEME.decode(frame.id()).unwrap();
+//! can.set_filters(&[SCHEME.filter([None, Some(u32::from(my_node))])?])?;
+//! ```
+//!
+//! Bits outside every field are fixed by [`IdScheme::with_base`] (zero by default); decoding
+//! ignores them and filters compare them.
+
+use core::error::Error;
+use core::fmt;
+
+use embedded_can::{ExtendedId, StandardId};
+
+use crate::{Id, IdMask, IdMaskFilter};
+
+/// `len` bits of an identifier starting at bit `offset` (bit 0 is the least significant).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Field {
+    /// Position of the field's least significant bit.
+    pub offset: u8,
+    /// Width of the field in bits.
+    pub len: u8,
+}
+
+impl Field {
+    /// The `len`-bit field at bit `offset`.
+    pub const fn new(offset: u8, len: u8) -> Self {
+        Self { offset, len }
+    }
+
+    /// Largest value the field holds.
+    pub const fn max(&self) -> u32 {
+        if self.len >= 32 {
+            u32::MAX
+        } else {
+            (1 << self.len) - 1
+        }
+    }
+
+    /// The field's bits within the identifier.
+    pub const fn mask(&self) -> u32 {
+        self.max() << self.offset
+    }
+}
+
+/// Error returned when a value does not fit its field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldOverflow {
+    /// Index of the field.
+    pub field: usize,
+}
+
+impl fmt::Display for FieldOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value too large for identifier field {}", self.field)
+    }
+}
+
+impl Error for FieldOverflow {}
+
+/// Layout of `N` fields within a standard or extended identifier; see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdScheme<const N: usize> {
+    extended: bool,
+    fields: [Field; N],
+    base: u32,
+}
+
+impl<const N: usize> IdScheme<N> {
+    /// Layout within an 11-bit identifier.
+    ///
+    /// # Panics
+    ///
+    /// If fields overlap or extend past bit 10; in a `const` this is a compile-time error.
+    pub const fn standard(fields: [Field; N]) -> Self {
+        Self::build(false, fields)
+    }
+
+    /// Layout within a 29-bit identifier.
+    ///
+    /// # Panics
+    ///
+    /// If fields overlap or extend past bit 28; in a `const` this is a compile-time error.
+    pub const fn extended(fields: [Field; N]) -> Self {
+        Self::build(true, fields)
+    }
+
+    const fn build(extended: bool, fields: [Field; N]) -> Self {
+        let width = if extended { 29 } else { 11 };
+        let mut used = 0;
+        let mut i = 0;
+        while i < N {
+            let field = fields[i];
+            assert!(
+                field.len > 0 && field.offset as u32 + field.len as u32 <= width,
+                "IdScheme field outside the identifier"
+            );
+            assert!(used & field.mask() == 0, "IdScheme fields overlap");
+            used |= field.mask();
+            i += 1;
+        }
+        Self {
+            extended,
+            fields,
+            base: 0,
+        }
+    }
+
+    /// Set the bits outside the fields to those of `base`; field bits of `base` are ignored.
+    pub const fn with_base(mut self, base: u32) -> Self {
+        self.base = base & self.width_mask() & !self.field_bits();
+        self
+    }
+
+    /// The fields, in the order values are given.
+    pub const fn fields(&self) -> &[Field; N] {
+        &self.fields
+    }
+
+    /// Returns `true` for a layout within extended identifiers.
+    pub const fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    const fn width_mask(&self) -> u32 {
+        if self.extended { 0x1FFF_FFFF } else { 0x7FF }
+    }
+
+    /// Bits covered by a field.
+    const fn field_bits(&self) -> u32 {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < N {
+            bits |= self.fields[i].mask();
+            i += 1;
+        }
+        bits
+    }
+
+    /// Raw identifier with `values` in their fields, or the first field a value does not fit.
+    pub const fn encode_raw(&self, values: [u32; N]) -> Result<u32, FieldOverflow> {
+        let mut raw = self.base;
+        let mut i = 0;
+        while i < N {
+            let field = self.fields[i];
+            if values[i] > field.max() {
+                return Err(FieldOverflow { field: i });
+            }
+            raw |= values[i] << field.offset;
+            i += 1;
+        }
+        Ok(raw)
+    }
+
+    /// Identifier with `values` in their fields.
+    pub fn encode(&self, values: [u32; N]) -> Result<Id, FieldOverflow> {
+        let raw = self.encode_raw(values)?;
+        // `build` keeps every field within the identifier width.
+        Ok(if self.extended {
+            Id::Extended(ExtendedId::new(raw).unwrap())
+        } else {
+            Id::Standard(StandardId::new(raw as u16).unwrap())
+        })
+    }
+
+    /// Field values of `id`, or `None` if it has the wrong width.
+    ///
+    /// Bits outside the fields are not checked; use [`IdScheme::matches`] for that.
+    pub fn decode(&self, id: impl Into<Id>) -> Option<[u32; N]> {
+        let raw = self.raw(id.into())?;
+        Some(core::array::from_fn(|i| {
+            let field = self.fields[i];
+            (raw >> field.offset) & field.max()
+        }))
+    }
+
+    /// Value of field `index` of `id`, or `None` if `id` has the wrong width.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not less than `N`.
+    pub fn field(&self, id: impl Into<Id>, index: usize) -> Option<u32> {
+        let field = self.fields[index];
+        Some((self.raw(id.into())? >> field.offset) & field.max())
+    }
+
+    /// Returns `true` if `id` has the scheme's width and its bits outside the fields equal the
+    /// base.
+    pub fn matches(&self, id: impl Into<Id>) -> bool {
+        self.raw(id.into())
+            .is_some_and(|raw| raw & !self.field_bits() == self.base)
+    }
+
+    /// Acceptance filter for identifiers of this scheme with the given field values; `None`
+    /// fields are don't-care.
+    ///
+    /// The bits outside the fields are compared with the base.
+    pub fn filter(&self, values: [Option<u32>; N]) -> Result<IdMaskFilter, FieldOverflow> {
+        let mut mask = self.width_mask() & !self.field_bits();
+        let mut exact = [0; N];
+        for (i, value) in values.iter().enumerate() {
+            if let Some(value) = *value {
+                mask |= self.fields[i].mask();
+                exact[i] = value;
+            }
+        }
+        let id = self.encode(exact)?;
+        Ok(IdMaskFilter::new(
+            id,
+            if self.extended {
+                IdMask::Extended(mask)
+            } else {
+                IdMask::Standard(mask as u16)
+            },
+        ))
+    }
+
+    fn raw(&self, id: Id) -> Option<u32> {
+        match (id, self.extended) {
+            (Id::Standard(id), false) => Some(u32::from(id.as_raw())),
+            (Id::Extended(id), true) => Some(id.as_raw()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! [`tracing`] instrumentation (feature `tracing`).
+//!
+//! [`Instrumented`] wraps an interface and reports its I/O to the application's `tracing`
+//! subscriber, so host-side services see CAN traffic in the same pipeline as the rest of their
+//! telemetry:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::instrument::Instrumented;
+//!
+//! let mut can = Instrumented::new(CanSocket::open("can0")?, "can0");
+//! can.send(&frame)?; // span `can_send` with id, extended, dlc; event with latency_us
+//! ```
+//!
+//! Every call runs in a span, entered around the wrapped call so that events the driver emits are
+//! nested inside it:
+//!
+//! | Span | Level | Fields |
+//! |------|-------|--------|
+//! | `can_send` | DEBUG | `interface`, `op`, `id`, `extended`, `dlc` |
+//! | `can_send_batch` | DEBUG | `interface`, `count` |
+//! | `can_recv` | DEBUG | `interface`, `op`; `id`, `extended`, `dlc` once a frame is received |
+//!
+//! `id` is the raw identifier. When the call returns, an event records its `latency_us`: at TRACE
+//! on success, at WARN for a failed send, and at DEBUG for a failed receive, since `try_recv`
+//! and `recv_timeout` fail routinely when no frame arrives. Filter changes are logged at INFO.
+
+use core::fmt::Debug;
+use core::time::Duration;
+use std::string::String;
+use std::time::Instant;
+
+use embedded_can::{Frame, Id};
+use tracing::field::Empty;
+use tracing::{Instrument, Span, debug, debug_span, info, trace, warn};
+
+use crate::filter::FrameFilter;
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, FilterConfig, FrameFilterConfig, IdMaskFilter, PartialSend,
+    RxFrameIo, SendOptions, TxFrameIo,
+};
+
+/// Wrapper that reports I/O to `tracing`; see the [module documentation](self).
+#[derive(Debug)]
+pub struct Instrumented<T> {
+    inner: T,
+    name: String,
+}
+
+impl<T> Instrumented<T> {
+    /// Wrap `inner`, reporting it as `name` in the `interface` field.
+    pub fn new(inner: T, name: impl Into<String>) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+        }
+    }
+
+    /// The interface name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn send_span<F: Frame>(&self, op: &'static str, frame: &F) -> Span {
+        let (id, extended) = raw_id(frame.id());
+        debug_span!("can_send", interface = %self.name, op, id, extended, dlc = frame.dlc())
+    }
+
+    fn batch_span(&self, count: usize) -> Span {
+        debug_span!("can_send_batch", interface = %self.name, count)
+    }
+
+    fn recv_span(&self, op: &'static str) -> Span {
+        debug_span!(
+            "can_recv",
+            interface = %self.name,
+            op,
+            id = Empty,
+            extended = Empty,
+            dlc = Empty
+        )
+    }
+}
+
+fn raw_id(id: Id) -> (u32, bool) {
+    match id {
+        Id::Standard(id) => (u32::from(id.as_raw()), false),
+        Id::Extended(id) => (id.as_raw(), true),
+    }
+}
+
+fn latency_us(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX)
+}
+
+fn sent<E: Debug>(start: Instant, result: &Result<(), E>) {
+    let latency_us = latency_us(start);
+    match result {
+        Ok(()) => trace!(latency_us, "sent"),
+        Err(error) => warn!(latency_us, ?error, "send failed"),
+    }
+}
+
+fn sent_batch<E: Debug>(start: Instant, result: &Result<usize, PartialSend<E>>) {
+    let latency_us = latency_us(start);
+    match result {
+        Ok(sent) => trace!(latency_us, sent, "batch sent"),
+        Err(partial) => warn!(
+            latency_us,
+            sent = partial.sent,
+            error = ?partial.error,
+            "batch send failed"
+        ),
+    }
+}
+
+fn received<F: Frame, E: Debug>(span: &Span, start: Instant, result: &Result<F, E>) {
+    let latency_us = latency_us(start);
+    match result {
+        Ok(frame) => {
+            let (id, extended) = raw_id(frame.id());
+            span.record("id", id);
+            span.record("extended", extended);
+            span.record("dlc", frame.dlc());
+            trace!(latency_us, "received");
+        }
+        Err(error) => debug!(latency_us, ?error, "receive failed"),
+    }
+}
+
+impl<T> TxFrameIo for Instrumented<T>
+where
+    T: TxFrameIo<Frame: Frame, Error: Debug>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let _span = self.send_span("send", frame).entered();
+        let start = Instant::now();
+        let result = self.inner.send(frame);
+        sent(start, &result);
+        result
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let _span = self.send_span("try_send", frame).entered();
+        let start = Instant::now();
+        let result = self.inner.try_send(frame);
+        sent(start, &result);
+        result
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let _span = self.send_span("send_timeout", frame).entered();
+        let start = Instant::now();
+        let result = self.inner.send_timeout(frame, timeout);
+        sent(start, &result);
+        result
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let _span = self.send_span("send_with", frame).entered();
+        let start = Instant::now();
+        let result = self.inner.send_with(frame, options);
+        sent(start, &result);
+        result
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let _span = self.batch_span(frames.len()).entered();
+        let start = Instant::now();
+        let result = self.inner.send_batch(frames);
+        sent_batch(start, &result);
+        result
+    }
+}
+
+impl<T> RxFrameIo for Instrumented<T>
+where
+    T: RxFrameIo<Frame: Frame, Error: Debug>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let span = self.recv_span("recv").entered();
+        let start = Instant::now();
+        let result = self.inner.recv();
+        received(&span, start, &result);
+        result
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let span = self.recv_span("try_recv").entered();
+        let start = Instant::now();
+        let result = self.inner.try_recv();
+        received(&span, start, &result);
+        result
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let span = self.recv_span("recv_timeout").entered();
+        let start = Instant::now();
+        let result = self.inner.recv_timeout(timeout);
+        received(&span, start, &result);
+        result
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T> AsyncTxFrameIo for Instrumented<T>
+where
+    T: AsyncTxFrameIo<Frame: Frame, Error: Debug>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let span = self.send_span("send", frame);
+        let start = Instant::now();
+        let result = self.inner.send(frame).instrument(span.clone()).await;
+        span.in_scope(|| sent(start, &result));
+        result
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let span = self.send_span("send_timeout", frame);
+        let start = Instant::now();
+        let result = self
+            .inner
+            .send_timeout(frame, timeout)
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| sent(start, &result));
+        result
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let span = self.send_span("send_with", frame);
+        let start = Instant::now();
+        let result = self
+            .inner
+            .send_with(frame, options)
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| sent(start, &result));
+        result
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let span = self.batch_span(frames.len());
+        let start = Instant::now();
+        let result = self.inner.send_batch(frames).instrument(span.clone()).await;
+        span.in_scope(|| sent_batch(start, &result));
+        result
+    }
+}
+
+impl<T> AsyncRxFrameIo for Instrumented<T>
+where
+    T: AsyncRxFrameIo<Frame: Frame, Error: Debug>,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let span = self.recv_span("recv");
+        let start = Instant::now();
+        let result = self.inner.recv().instrument(span.clone()).await;
+        span.in_scope(|| received(&span, start, &result));
+        result
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let span = self.recv_span("recv_timeout");
+        let start = Instant::now();
+        let result = self
+            .inner
+            .recv_timeout(timeout)
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| received(&span, start, &result));
+        result
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
+
+impl<T> FilterConfig for Instrumented<T>
+where
+    T: FilterConfig<Error: Debug>,
+{
+    type Error = T::Error;
+    type FiltersHandle<'a>
+        = T::FiltersHandle<'a>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        let result = self.inner.set_filters(filters);
+        match &result {
+            Ok(()) => info!(interface = %self.name, count = filters.len(), ?filters, "filters set"),
+            Err(error) => warn!(interface = %self.name, ?filters, ?error, "setting filters failed"),
+        }
+        result
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        info!(interface = %self.name, "modifying filters");
+        self.inner.modify_filters()
+    }
+}
+
+impl<T> FrameFilterConfig for Instrumented<T>
+where
+    T: FrameFilterConfig<Error: Debug>,
+{
+    type Error = T::Error;
+
+    fn set_frame_filters(&mut self, filters: &[FrameFilter]) -> Result<(), Self::Error> {
+        let result = self.inner.set_frame_filters(filters);
+        match &result {
+            Ok(()) => info!(interface = %self.name, count = filters.len(), ?filters, "filters set"),
+            Err(error) => warn!(interface = %self.name, ?filters, ?error, "setting filters failed"),
+        }
+        result
+    }
+}
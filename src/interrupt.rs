@@ -0,0 +1,180 @@
+//! Interrupt-driven async receive for polled controllers.
+//!
+//! Many SPI CAN controllers (MCP2515, MCP251xFD, SJA1000 on a parallel bus, …) have blocking,
+//! register-polling drivers plus an interrupt output. [`InterruptRx`] turns such a driver into an
+//! [`AsyncRxFrameIo`] by awaiting the interrupt pin through
+//! `embedded_hal_async::digital::Wait` instead of busy-polling.
+//!
+//! Requires the `embedded-hal-async` feature.
+
+use core::error::Error;
+use core::fmt;
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+use core::time::Duration;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+
+use crate::{AsyncRxFrameIo, RxFrameIo, TxRxState};
+
+/// Active level of the controller's interrupt output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqLevel {
+    /// The pin is driven low while an interrupt is pending (e.g. MCP2515 `INT`).
+    Low,
+    /// The pin is driven high while an interrupt is pending.
+    High,
+}
+
+/// Error returned by [`InterruptRx`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptRxError<E, P> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// Waiting on the interrupt pin failed.
+    Pin(P),
+    /// No frame arrived before the timeout elapsed.
+    Timeout,
+}
+
+impl<E, P> fmt::Display for InterruptRxError<E, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Pin(_) => f.write_str("interrupt pin error"),
+            Self::Timeout => f.write_str("no frame within the timeout"),
+        }
+    }
+}
+
+impl<E: Error + 'static, P: Error + 'static> Error for InterruptRxError<E, P> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Pin(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Async receive adapter driven by an interrupt pin.
+///
+/// The adapter checks [`TxRxState::rx_pending`] and, while the receive queue is empty, awaits the
+/// interrupt pin reaching its active level. Frames are then read with [`RxFrameIo::try_recv`]. If
+/// the driver does not count pending frames, the pin level alone is taken to mean a frame is
+/// waiting.
+///
+/// The pin is treated as level-triggered. If it also signals non-receive conditions (TX complete,
+/// errors) that the driver does not clear, the adapter will spin while those are pending;
+/// configure the controller to assert the pin only for receive events, or clear other flags in
+/// the driver.
+#[derive(Debug)]
+pub struct InterruptRx<T, P, D> {
+    io: T,
+    irq: P,
+    delay: D,
+    level: IrqLevel,
+}
+
+impl<T, P, D> InterruptRx<T, P, D> {
+    /// Create an adapter over `io`, waiting on `irq` (active at `level`).
+    ///
+    /// `delay` is used to implement [`AsyncRxFrameIo::recv_timeout`].
+    pub fn new(io: T, irq: P, delay: D, level: IrqLevel) -> Self {
+        Self {
+            io,
+            irq,
+            delay,
+            level,
+        }
+    }
+
+    /// Borrow the wrapped driver.
+    pub fn inner(&self) -> &T {
+        &self.io
+    }
+
+    /// Mutably borrow the wrapped driver (e.g. to transmit).
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Unwrap into the driver, interrupt pin, and delay provider.
+    pub fn into_parts(self) -> (T, P, D) {
+        (self.io, self.irq, self.delay)
+    }
+}
+
+async fn wait_pending<T, P>(
+    io: &mut T,
+    irq: &mut P,
+    level: IrqLevel,
+) -> Result<(), InterruptRxError<T::Error, P::Error>>
+where
+    T: TxRxState,
+    P: Wait,
+{
+    loop {
+        let pending = io.rx_pending().map_err(InterruptRxError::Io)?;
+        if pending.is_some_and(|pending| pending > 0) {
+            return Ok(());
+        }
+        match level {
+            IrqLevel::Low => irq.wait_for_low().await,
+            IrqLevel::High => irq.wait_for_high().await,
+        }
+        .map_err(InterruptRxError::Pin)?;
+        if pending.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+async fn with_timeout<F, D>(fut: F, delay: &mut D, timeout: Duration) -> Option<F::Output>
+where
+    F: Future,
+    D: DelayNs,
+{
+    let micros = u32::try_from(timeout.as_micros()).unwrap_or(u32::MAX);
+    let mut fut = pin!(fut);
+    let mut timer = pin!(delay.delay_us(micros));
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        if timer.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+impl<T, P, D> AsyncRxFrameIo for InterruptRx<T, P, D>
+where
+    T: RxFrameIo + TxRxState<Error = <T as RxFrameIo>::Error>,
+    P: Wait,
+    D: DelayNs,
+{
+    type Frame = T::Frame;
+    type Error = InterruptRxError<<T as RxFrameIo>::Error, P::Error>;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        wait_pending(&mut self.io, &mut self.irq, self.level).await?;
+        self.io.try_recv().map_err(InterruptRxError::Io)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let wait = wait_pending(&mut self.io, &mut self.irq, self.level);
+        with_timeout(wait, &mut self.delay, timeout)
+            .await
+            .ok_or(InterruptRxError::Timeout)??;
+        self.io.try_recv().map_err(InterruptRxError::Io)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        wait_pending(&mut self.io, &mut self.irq, self.level).await
+    }
+}
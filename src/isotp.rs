@@ -0,0 +1,554 @@
+//! ISO 15765-2 (ISO-TP) transport over classic CAN frames.
+//!
+//! ISO-TP carries messages of up to 4095 bytes by segmenting them into a first frame and numbered
+//! consecutive frames, with the receiver pacing the sender through flow-control frames. This module
+//! implements the protocol in blocking form on top of [`TxFrameIo`] and [`RxFrameIo`], using
+//! caller-provided buffers:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::isotp::{IsoTp, IsoTpConfig, MessageIo};
+//!
+//! let config = IsoTpConfig::new(StandardId::new(0x7E0).unwrap(), StandardId::new(0x7E8).unwrap());
+//! let mut tp = IsoTp::new(can, clock, config);
+//! tp.send_message(&[0x22, 0xF1, 0x90])?;
+//! let mut buf = [0u8; 64];
+//! let len = tp.recv_message(&mut buf, Duration::from_millis(1000))?;
+//! ```
+//!
+//! Protocol layers such as [`crate::obd2`] are written against the [`MessageIo`] trait so they can
+//! run over other ISO-TP implementations as well.
+//!
+//! Only normal addressing with 8-byte classic frames is supported. Frames on other identifiers are
+//! discarded while a message is being received, so the interface should be filtered to `rx_id`.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{Clock, RxFrameIo, TxFrameIo};
+
+/// Largest message size that fits the 12-bit ISO-TP length field.
+pub const MAX_MESSAGE_LEN: usize = 4095;
+
+const SINGLE: u8 = 0x0;
+const FIRST: u8 = 0x1;
+const CONSECUTIVE: u8 = 0x2;
+const FLOW_CONTROL: u8 = 0x3;
+
+const FC_CONTINUE: u8 = 0x0;
+const FC_WAIT: u8 = 0x1;
+const FC_OVERFLOW: u8 = 0x2;
+
+/// Request/response transport for byte messages.
+///
+/// Implemented by [`IsoTp`]; other transports (e.g. a kernel ISO-TP socket) can implement it too so
+/// that diagnostic protocols do not depend on a particular implementation.
+pub trait MessageIo {
+    /// Error returned by the transport.
+    type Error;
+
+    /// Send one complete message.
+    fn send_message(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Receive one complete message into `buf`, waiting up to `timeout` for it to start.
+    ///
+    /// Returns the message length.
+    fn recv_message(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Self::Error>;
+}
+
+/// Error returned by [`IsoTp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsoTpError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// The peer did not respond within the configured timeout.
+    Timeout,
+    /// The message is longer than [`MAX_MESSAGE_LEN`].
+    MessageTooLong,
+    /// The receive buffer is too small for the incoming message.
+    BufferTooSmall,
+    /// The peer rejected the message with a flow-control overflow.
+    Overflow,
+    /// A consecutive frame arrived out of sequence.
+    WrongSequence,
+    /// The peer sent more flow-control WAIT frames than allowed.
+    TooManyWaits,
+    /// A frame could not be constructed, or a malformed frame was received.
+    InvalidFrame,
+}
+
+impl<E> fmt::Display for IsoTpError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Timeout => f.write_str("ISO-TP peer did not respond in time"),
+            Self::MessageTooLong => f.write_str("message too long for ISO-TP"),
+            Self::BufferTooSmall => f.write_str("receive buffer too small"),
+            Self::Overflow => f.write_str("peer reported a flow-control overflow"),
+            Self::WrongSequence => f.write_str("consecutive frame out of sequence"),
+            Self::TooManyWaits => f.write_str("too many flow-control WAIT frames"),
+            Self::InvalidFrame => f.write_str("invalid ISO-TP frame"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for IsoTpError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration of an [`IsoTp`] channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsoTpConfig {
+    /// Identifier used for outgoing frames, including flow control.
+    pub tx_id: Id,
+    /// Identifier of the peer's frames.
+    pub rx_id: Id,
+    /// Pad outgoing frames to 8 bytes with this value; `None` sends minimal-length frames.
+    pub padding: Option<u8>,
+    /// Block size advertised to the peer (0 = no further flow control).
+    pub block_size: u8,
+    /// Raw STmin value advertised to the peer.
+    pub st_min: u8,
+    /// How long to wait for the peer's next frame within a message (N_Bs / N_Cr).
+    pub timeout: Duration,
+    /// Maximum number of consecutive flow-control WAIT frames accepted.
+    pub max_waits: u8,
+}
+
+impl IsoTpConfig {
+    /// Configuration with the given identifiers and common defaults: `0xCC` padding, no block
+    /// limit, no separation time and a 1 s frame timeout.
+    pub fn new(tx_id: impl Into<Id>, rx_id: impl Into<Id>) -> Self {
+        Self {
+            tx_id: tx_id.into(),
+            rx_id: rx_id.into(),
+            padding: Some(0xCC),
+            block_size: 0,
+            st_min: 0,
+            timeout: Duration::from_secs(1),
+            max_waits: 10,
+        }
+    }
+}
+
+/// Decode a raw STmin byte into a separation time.
+///
+/// Reserved values are treated as the maximum of 127 ms, as the standard requires.
+pub const fn decode_st_min(raw: u8) -> Duration {
+    match raw {
+        0x00..=0x7F => Duration::from_millis(raw as u64),
+        0xF1..=0xF9 => Duration::from_micros((raw - 0xF0) as u64 * 100),
+        _ => Duration::from_millis(127),
+    }
+}
+
+/// Build a padded frame on `id` carrying `bytes`.
+fn build_frame<F: Frame>(id: Id, bytes: &[u8], padding: Option<u8>) -> Option<F> {
+    match padding {
+        Some(pad) if bytes.len() < 8 => {
+            let mut padded = [pad; 8];
+            padded[..bytes.len()].copy_from_slice(bytes);
+            F::new(id, &padded)
+        }
+        _ => F::new(id, bytes),
+    }
+}
+
+/// Build an ISO-TP single frame on `id` carrying `payload` (at most 7 bytes).
+pub fn single_frame<F: Frame>(id: impl Into<Id>, payload: &[u8], padding: Option<u8>) -> Option<F> {
+    if payload.len() > 7 {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    bytes[0] = (SINGLE << 4) | payload.len() as u8;
+    bytes[1..=payload.len()].copy_from_slice(payload);
+    build_frame(id.into(), &bytes[..=payload.len()], padding)
+}
+
+/// ISO-TP channel between two fixed identifiers.
+#[derive(Debug)]
+pub struct IsoTp<T, C> {
+    io: T,
+    clock: C,
+    config: IsoTpConfig,
+}
+
+impl<T, C> IsoTp<T, C> {
+    /// Create a channel over `io`.
+    pub fn new(io: T, clock: C, config: IsoTpConfig) -> Self {
+        Self { io, clock, config }
+    }
+
+    /// The channel configuration.
+    pub fn config(&self) -> &IsoTpConfig {
+        &self.config
+    }
+
+    /// Mutable access to the channel configuration.
+    pub fn config_mut(&mut self) -> &mut IsoTpConfig {
+        &mut self.config
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.io
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    pub(crate) fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+impl<T, C, F, E> IsoTp<T, C>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    F: Frame,
+{
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), IsoTpError<E>> {
+        let frame: F = build_frame(self.config.tx_id, bytes, self.config.padding)
+            .ok_or(IsoTpError::InvalidFrame)?;
+        self.io
+            .send_timeout(&frame, self.config.timeout)
+            .map_err(IsoTpError::Io)
+    }
+
+    /// Receive the next frame from the peer before `deadline`.
+    fn recv_from_peer(&mut self, deadline: Duration) -> Result<F, IsoTpError<E>> {
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Err(IsoTpError::Timeout);
+            }
+            match self.io.recv_timeout(remaining) {
+                Ok(frame) if frame.id() == self.config.rx_id && !frame.is_remote_frame() => {
+                    return Ok(frame);
+                }
+                Ok(_) => {}
+                Err(_) if self.clock.now() >= deadline => return Err(IsoTpError::Timeout),
+                Err(e) => return Err(IsoTpError::Io(e)),
+            }
+        }
+    }
+
+    fn send_flow_control(&mut self, status: u8) -> Result<(), IsoTpError<E>> {
+        let bytes = [
+            (FLOW_CONTROL << 4) | status,
+            self.config.block_size,
+            self.config.st_min,
+        ];
+        self.send_raw(&bytes)
+    }
+
+    /// Wait for a clear-to-send, returning the peer's block size and separation time.
+    fn wait_clear_to_send(&mut self) -> Result<(u8, Duration), IsoTpError<E>> {
+        let mut waits = 0;
+        loop {
+            let deadline = self.clock.now() + self.config.timeout;
+            let frame = self.recv_from_peer(deadline)?;
+            let data = frame.data();
+            if data.len() < 3 || data[0] >> 4 != FLOW_CONTROL {
+                // Not flow control: ignore, as the standard requires.
+                continue;
+            }
+            match data[0] & 0x0F {
+                FC_CONTINUE => return Ok((data[1], decode_st_min(data[2]))),
+                FC_WAIT => {
+                    waits += 1;
+                    if waits > self.config.max_waits {
+                        return Err(IsoTpError::TooManyWaits);
+                    }
+                }
+                FC_OVERFLOW => return Err(IsoTpError::Overflow),
+                _ => return Err(IsoTpError::InvalidFrame),
+            }
+        }
+    }
+}
+
+impl<T, C, F, E> MessageIo for IsoTp<T, C>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    F: Frame,
+{
+    type Error = IsoTpError<E>;
+
+    /// Send `data`, segmenting it if it does not fit a single frame.
+    ///
+    /// The separation time requested by the peer is honoured by spinning on the clock.
+    fn send_message(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        if data.len() > MAX_MESSAGE_LEN {
+            return Err(IsoTpError::MessageTooLong);
+        }
+        if data.len() <= 7 {
+            let frame: F = single_frame(self.config.tx_id, data, self.config.padding)
+                .ok_or(IsoTpError::InvalidFrame)?;
+            return self
+                .io
+                .send_timeout(&frame, self.config.timeout)
+                .map_err(IsoTpError::Io);
+        }
+
+        let mut bytes = [0u8; 8];
+        bytes[0] = (FIRST << 4) | (data.len() >> 8) as u8;
+        bytes[1] = data.len() as u8;
+        bytes[2..].copy_from_slice(&data[..6]);
+        self.send_raw(&bytes)?;
+
+        let mut sequence = 1u8;
+        let mut chunks = data[6..].chunks(7).peekable();
+        while chunks.peek().is_some() {
+            let (block_size, st_min) = self.wait_clear_to_send()?;
+            let mut sent_in_block = 0u8;
+            while let Some(chunk) = chunks.next() {
+                bytes[0] = (CONSECUTIVE << 4) | sequence;
+                bytes[1..=chunk.len()].copy_from_slice(chunk);
+                self.send_raw(&bytes[..=chunk.len()])?;
+                sequence = (sequence + 1) & 0x0F;
+                sent_in_block = sent_in_block.wrapping_add(1);
+                if block_size != 0 && sent_in_block == block_size {
+                    break;
+                }
+                if chunks.peek().is_some() {
+                    self.clock.spin_until(self.clock.now() + st_min);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn recv_message(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Self::Error> {
+        let deadline = self.clock.now() + timeout;
+        let (len, mut received) = loop {
+            let frame = self.recv_from_peer(deadline)?;
+            let data = frame.data();
+            match data.first().map(|pci| pci >> 4) {
+                Some(SINGLE) => {
+                    let len = usize::from(data[0] & 0x0F);
+                    if len == 0 || len + 1 > data.len() {
+                        return Err(IsoTpError::InvalidFrame);
+                    }
+                    let target = buf.get_mut(..len).ok_or(IsoTpError::BufferTooSmall)?;
+                    target.copy_from_slice(&data[1..=len]);
+                    return Ok(len);
+                }
+                Some(FIRST) if data.len() == 8 => {
+                    let len = usize::from(data[0] & 0x0F) << 8 | usize::from(data[1]);
+                    if len <= 7 {
+                        return Err(IsoTpError::InvalidFrame);
+                    }
+                    if len > buf.len() {
+                        self.send_flow_control(FC_OVERFLOW)?;
+                        return Err(IsoTpError::BufferTooSmall);
+                    }
+                    buf[..6].copy_from_slice(&data[2..]);
+                    break (len, 6);
+                }
+                // Stray consecutive or flow-control frames are ignored while idle.
+                _ => {}
+            }
+        };
+
+        let mut sequence = 1u8;
+        let mut in_block = 0u8;
+        self.send_flow_control(FC_CONTINUE)?;
+        while received < len {
+            let deadline = self.clock.now() + self.config.timeout;
+            let frame = self.recv_from_peer(deadline)?;
+            let data = frame.data();
+            match data.first().map(|pci| pci >> 4) {
+                Some(CONSECUTIVE) => {}
+                // Ignore flow control addressed to us; anything else aborts the transfer.
+                Some(FLOW_CONTROL) => continue,
+                _ => return Err(IsoTpError::InvalidFrame),
+            }
+            if data[0] & 0x0F != sequence {
+                return Err(IsoTpError::WrongSequence);
+            }
+            let take = (len - received).min(data.len() - 1);
+            buf[received..received + take].copy_from_slice(&data[1..=take]);
+            received += take;
+            sequence = (sequence + 1) & 0x0F;
+            in_block = in_block.wrapping_add(1);
+            if self.config.block_size != 0 && in_block == self.config.block_size && received < len {
+                in_block = 0;
+                self.send_flow_control(FC_CONTINUE)?;
+            }
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use embedded_can::StandardId;
+
+    use super::*;
+    use crate::fuzz::{FuzzBus, FuzzBusError, FuzzClock};
+    use crate::testdata::TestFrame;
+
+    const TESTER: u16 = 0x7E0;
+    const ECU: u16 = 0x7E8;
+
+    fn channel(clock: &FuzzClock, tx: u16, rx: u16) -> IsoTp<FuzzBus<TestFrame>, &FuzzClock> {
+        let config = IsoTpConfig::new(StandardId::new(tx).unwrap(), StandardId::new(rx).unwrap());
+        IsoTp::new(FuzzBus::new(), clock, config)
+    }
+
+    fn flow_control(status: u8, block_size: u8) -> TestFrame {
+        TestFrame::standard(
+            ECU,
+            &[0x30 | status, block_size, 0, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC],
+        )
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    /// Send `data` from the tester to the ECU, returning what the ECU received and the frames it
+    /// sent back. `replies` are delivered to the tester before it sends.
+    fn transfer(
+        data: &[u8],
+        replies: &[TestFrame],
+        block_size: u8,
+    ) -> (Result<Vec<u8>, IsoTpError<FuzzBusError>>, Vec<TestFrame>) {
+        let clock = FuzzClock::new();
+        let mut tester = channel(&clock, TESTER, ECU);
+        let mut ecu = channel(&clock, ECU, TESTER);
+        ecu.config_mut().block_size = block_size;
+        for reply in replies {
+            tester.inner_mut().deliver(*reply);
+        }
+        tester.send_message(data).unwrap();
+        for frame in tester.inner_mut().take_sent() {
+            ecu.inner_mut().deliver(frame);
+        }
+        let mut buf = [0; MAX_MESSAGE_LEN];
+        let received = ecu
+            .recv_message(&mut buf, Duration::from_secs(1))
+            .map(|len| buf[..len].to_vec());
+        (received, ecu.inner_mut().take_sent())
+    }
+
+    #[test]
+    fn single_frame_loopback() {
+        let clock = FuzzClock::new();
+        let mut tester = channel(&clock, TESTER, ECU);
+        tester.send_message(&[0x01, 0x0C]).unwrap();
+        assert_eq!(
+            tester.inner().sent(),
+            [TestFrame::standard(
+                TESTER,
+                &[0x02, 0x01, 0x0C, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]
+            )]
+        );
+        let (received, replies) = transfer(&[0x01, 0x0C], &[], 0);
+        assert_eq!(received, Ok([0x01, 0x0C].to_vec()));
+        assert!(replies.is_empty());
+    }
+
+    #[test]
+    fn multi_frame_loopback() {
+        let data = message(100);
+        let (received, replies) = transfer(&data, &[flow_control(FC_CONTINUE, 0)], 0);
+        assert_eq!(received, Ok(data));
+        assert_eq!(replies, [flow_control(FC_CONTINUE, 0)]);
+    }
+
+    #[test]
+    fn block_size_paces_sender() {
+        // 100 bytes: a first frame and 14 consecutive frames, in blocks of 4.
+        let data = message(100);
+        let replies = [flow_control(FC_CONTINUE, 4); 4];
+        let (received, flow) = transfer(&data, &replies, 4);
+        assert_eq!(received, Ok(data));
+        assert_eq!(flow.len(), 4);
+
+        // The sender stops after each block until the next clear-to-send.
+        let clock = FuzzClock::new();
+        let mut tester = channel(&clock, TESTER, ECU);
+        tester.inner_mut().deliver(flow_control(FC_CONTINUE, 4));
+        assert_eq!(
+            tester.send_message(&message(100)),
+            Err(IsoTpError::Io(FuzzBusError::Empty))
+        );
+        assert_eq!(tester.inner().sent().len(), 5);
+    }
+
+    #[test]
+    fn wait_then_continue() {
+        let data = message(20);
+        let replies = [flow_control(FC_WAIT, 0), flow_control(FC_CONTINUE, 0)];
+        let (received, _) = transfer(&data, &replies, 0);
+        assert_eq!(received, Ok(data));
+    }
+
+    #[test]
+    fn too_many_waits() {
+        let clock = FuzzClock::new();
+        let mut tester = channel(&clock, TESTER, ECU);
+        for _ in 0..=tester.config().max_waits {
+            tester.inner_mut().deliver(flow_control(FC_WAIT, 0));
+        }
+        assert_eq!(
+            tester.send_message(&message(20)),
+            Err(IsoTpError::TooManyWaits)
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let clock = FuzzClock::new();
+        let mut tester = channel(&clock, TESTER, ECU);
+        tester.inner_mut().deliver(flow_control(FC_OVERFLOW, 0));
+        assert_eq!(tester.send_message(&message(20)), Err(IsoTpError::Overflow));
+        assert_eq!(tester.inner().sent().len(), 1);
+
+        // A receiver without room answers a first frame with OVERFLOW.
+        let mut ecu = channel(&clock, ECU, TESTER);
+        ecu.inner_mut()
+            .deliver(TestFrame::standard(TESTER, &[0x10, 20, 0, 1, 2, 3, 4, 5]));
+        let mut buf = [0; 8];
+        assert_eq!(
+            ecu.recv_message(&mut buf, Duration::from_secs(1)),
+            Err(IsoTpError::BufferTooSmall)
+        );
+        assert_eq!(ecu.inner().sent(), [flow_control(FC_OVERFLOW, 0)]);
+    }
+
+    #[test]
+    fn wrong_sequence() {
+        let clock = FuzzClock::new();
+        let mut ecu = channel(&clock, ECU, TESTER);
+        ecu.inner_mut()
+            .deliver(TestFrame::standard(TESTER, &[0x10, 20, 0, 1, 2, 3, 4, 5]));
+        ecu.inner_mut()
+            .deliver(TestFrame::standard(TESTER, &[0x22, 6, 7, 8, 9, 10, 11, 12]));
+        let mut buf = [0; 64];
+        assert_eq!(
+            ecu.recv_message(&mut buf, Duration::from_secs(1)),
+            Err(IsoTpError::WrongSequence)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Receive callbacks run in interrupt context.
+//!
+//! Some reactions cannot wait for a frame to travel through the receive queue to a task: an
+//! emergency-stop frame should cut the drive output from within the receive interrupt. Drivers
+//! implementing [`IsrHooks`] call a user [`RxHook`] for every received frame, straight from their
+//! interrupt handler:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::isr::{InIsr, IsrHooks, RxHook, RxHookAction};
+//!
+//! static DRIVE: DriveEnable = DriveEnable::new();
+//!
+//! fn on_frame(_isr: &InIsr, drive: &DriveEnable, frame: &MyFrame) -> RxHookAction {
+//!     if frame.id() == ESTOP_ID {
+//!         drive.cut();
+//!         return RxHookAction::Consume;
+//!     }
+//!     RxHookAction::Queue
+//! }
+//!
+//! can.set_rx_hook(RxHook::new(on_frame, &DRIVE))?;
+//! ```
+//!
+//! The contract between the hook, the driver and the rest of the program is carried by types:
+//!
+//! - The callback is a plain `fn`, so it captures nothing and cannot allocate a closure.
+//! - Its context is a `&'static` reference to a `Sync` type, so it outlives the driver and is
+//!   safe to share between the interrupt and the tasks that also use it.
+//! - It receives an [`InIsr`] token, which only a driver can create (`unsafe`), so a hook runs
+//!   only where the driver promised: in its receive interrupt, never reentered.
+//!
+//! What types cannot express remains the callback's responsibility: it must return quickly and
+//! must not block, wait on a lock held by a task, or use the interface it is installed on.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+/// Proof that code is running in a driver's receive interrupt.
+///
+/// Passed to [`RxHook`] callbacks. Only drivers create it, with [`InIsr::new`].
+#[derive(Debug)]
+pub struct InIsr {
+    // Not `Send`: the token must not leave the interrupt handler.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl InIsr {
+    /// Create the token for calling an [`RxHook`].
+    ///
+    /// # Safety
+    /// The caller must be the receive interrupt handler of the driver the hook is installed on,
+    /// and the token must be dropped before the handler returns. The handler must not be
+    /// reentered while a hook runs.
+    pub unsafe fn new() -> Self {
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// What the driver does with a frame after its [`RxHook`] ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxHookAction {
+    /// Queue the frame for reception as usual.
+    Queue,
+    /// Drop the frame; the hook has handled it.
+    Consume,
+}
+
+/// A receive callback: a function and the context it is called with.
+///
+/// The context's type is erased, so drivers can store a hook of any context type without
+/// generics or allocation.
+pub struct RxHook<F> {
+    callback: *const (),
+    context: *const (),
+    trampoline: unsafe fn(*const (), *const (), &InIsr, &F) -> RxHookAction,
+}
+
+/// Call `callback` as a `fn(&InIsr, &C, &F)` with `context` as a `&C`.
+///
+/// # Safety
+/// `callback` and `context` must come from [`RxHook::new`] with the same `C` and `F`.
+unsafe fn trampoline<C, F>(
+    callback: *const (),
+    context: *const (),
+    isr: &InIsr,
+    frame: &F,
+) -> RxHookAction {
+    // SAFETY: `callback` was cast from a `fn(&InIsr, &C, &F) -> RxHookAction` in `RxHook::new`.
+    let callback =
+        unsafe { core::mem::transmute::<*const (), fn(&InIsr, &C, &F) -> RxHookAction>(callback) };
+    // SAFETY: `context` was cast from a `&'static C` in `RxHook::new`.
+    let context = unsafe { &*context.cast::<C>() };
+    callback(isr, context, frame)
+}
+
+impl<F> RxHook<F> {
+    /// Call `callback` with `context` for every received frame.
+    pub fn new<C: Sync + 'static>(
+        callback: fn(&InIsr, &C, &F) -> RxHookAction,
+        context: &'static C,
+    ) -> Self {
+        Self {
+            callback: callback as *const (),
+            context: (context as *const C).cast(),
+            trampoline: trampoline::<C, F>,
+        }
+    }
+
+    /// Run the hook for `frame`.
+    pub fn call(&self, isr: &InIsr, frame: &F) -> RxHookAction {
+        // SAFETY: the fields were set together by `RxHook::new`.
+        unsafe { (self.trampoline)(self.callback, self.context, isr, frame) }
+    }
+}
+
+impl<F> Clone for RxHook<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F> Copy for RxHook<F> {}
+
+impl<F> fmt::Debug for RxHook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RxHook")
+            .field("callback", &self.callback)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+// SAFETY: the context is a `&'static C` with `C: Sync`, which may be used from any context, and
+// the callback is a plain function.
+unsafe impl<F> Send for RxHook<F> {}
+// SAFETY: as for `Send`; calling the hook only reads the fields.
+unsafe impl<F> Sync for RxHook<F> {}
+
+/// Register a callback run in interrupt context for each received frame.
+///
+/// Opt-in: drivers implement this only when their receive path runs in an interrupt handler. The
+/// hook sees frames after acceptance filtering and before they are queued, and its
+/// [`RxHookAction`] decides whether they are queued at all.
+pub trait IsrHooks {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Install `hook`, replacing any hook installed before.
+    ///
+    /// Implementations must make the switch atomic with respect to the interrupt handler, so a
+    /// frame is never handed to a half-installed hook.
+    fn set_rx_hook(&mut self, hook: RxHook<Self::Frame>) -> Result<(), Self::Error>;
+
+    /// Remove the installed hook, if any.
+    fn clear_rx_hook(&mut self) -> Result<(), Self::Error>;
+}
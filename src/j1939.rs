@@ -0,0 +1,730 @@
+//! SAE J1939 building blocks.
+//!
+//! J1939 runs on 29-bit identifiers split into priority, parameter group number (PGN) and source
+//! address ([`J1939Id`]). Every node owns a 64-bit [`Name`] and must claim a source address before
+//! it may send anything else (SAE J1939-81); [`NameAddressManager`] implements that procedure.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{ExtendedId, Frame, Id};
+
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, Clock};
+
+/// PGN of the Address Claimed / Cannot Claim message.
+pub const PGN_ADDRESS_CLAIMED: u32 = 0xEE00;
+/// PGN of the Request message.
+pub const PGN_REQUEST: u32 = 0xEA00;
+/// Source address used by nodes that have not claimed, or cannot claim, an address.
+pub const NULL_ADDRESS: u8 = 254;
+/// Destination address meaning “all nodes”.
+pub const GLOBAL_ADDRESS: u8 = 255;
+
+/// Time a claimed address must go uncontested before it may be used.
+pub const CLAIM_TIMEOUT: Duration = Duration::from_millis(250);
+
+const ADDRESS_CLAIM_PRIORITY: u8 = 6;
+
+/// A J1939 source or destination address.
+///
+/// Nodes use addresses 0–253; 254 ([`NodeAddr::NULL`]) is the source address of nodes without a
+/// claimed address and 255 ([`NodeAddr::GLOBAL`]) the destination “all nodes”. [`NodeAddr::new`]
+/// accepts only node addresses, so a reserved value cannot slip in where a node is meant;
+/// [`NodeAddr::from_raw`] takes any value seen on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct NodeAddr(u8);
+
+impl NodeAddr {
+    /// Source address of a node without a claimed address.
+    pub const NULL: Self = Self(NULL_ADDRESS);
+    /// Destination address of all nodes.
+    pub const GLOBAL: Self = Self(GLOBAL_ADDRESS);
+
+    /// The node address `addr`, or `None` for the reserved values 254 and 255.
+    pub const fn new(addr: u8) -> Option<Self> {
+        if addr < NULL_ADDRESS {
+            Some(Self(addr))
+        } else {
+            None
+        }
+    }
+
+    /// Any address, including the reserved ones.
+    pub const fn from_raw(addr: u8) -> Self {
+        Self(addr)
+    }
+
+    /// The address as transmitted.
+    pub const fn as_raw(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` for a node address (0–253).
+    pub const fn is_node(&self) -> bool {
+        self.0 < NULL_ADDRESS
+    }
+
+    /// Returns `true` for [`NodeAddr::NULL`].
+    pub const fn is_null(&self) -> bool {
+        self.0 == NULL_ADDRESS
+    }
+
+    /// Returns `true` for [`NodeAddr::GLOBAL`].
+    pub const fn is_global(&self) -> bool {
+        self.0 == GLOBAL_ADDRESS
+    }
+}
+
+impl From<NodeAddr> for u8 {
+    fn from(addr: NodeAddr) -> Self {
+        addr.0
+    }
+}
+
+/// A node's 64-bit J1939 NAME.
+///
+/// During address arbitration the numerically *lower* NAME wins, which the derived ordering
+/// reflects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name(pub u64);
+
+impl Name {
+    /// Returns `true` if the node can pick another address when it loses arbitration.
+    pub const fn arbitrary_address_capable(&self) -> bool {
+        self.0 >> 63 != 0
+    }
+
+    /// The 21-bit identity number.
+    pub const fn identity_number(&self) -> u32 {
+        (self.0 & 0x1F_FFFF) as u32
+    }
+
+    /// The NAME in transmission (little-endian) byte order.
+    pub const fn to_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Read a NAME from the data of an Address Claimed message.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: [u8; 8] = bytes.get(..8)?.try_into().ok()?;
+        Some(Self(u64::from_le_bytes(bytes)))
+    }
+}
+
+/// A 29-bit identifier viewed as its J1939 fields.
+///
+/// ```text
+///  28..26     25    24    23..16   15..8   7..0
+/// priority   EDP    DP      PF      PS      SA
+/// ```
+///
+/// The PGN is EDP, DP, PF and PS. For PDU1 formats (PF < 240) PS is the destination address
+/// instead and the PGN's low byte is zero; PDU2 formats (PF ≥ 240) are broadcast and PS is the
+/// group extension. Any extended identifier is a valid `J1939Id`, and conversion is free in both
+/// directions:
+///
+/// ```rust,ignore
+/// use embedded_can_interface::j1939::J1939Id;
+///
+/// let id = J1939Id::new(3, 0xF004, NodeAddr::GLOBAL, engine); // EEC1
+/// let frame = MyFrame::new(id, &data).unwrap();
+/// if let Some(id) = J1939Id::from_id(received.id()) {
+///     dispatch(id.pgn(), id.source_address(), received.data());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct J1939Id(u32);
+
+impl J1939Id {
+    /// Build an identifier sending parameter group `pgn` from `source` to `destination`.
+    ///
+    /// For PDU1 PGNs the low byte of `pgn` is replaced by `destination`; for PDU2 PGNs
+    /// `destination` is ignored. `priority` is truncated to 3 bits and `pgn` to 18.
+    pub const fn new(priority: u8, pgn: u32, destination: NodeAddr, source: NodeAddr) -> Self {
+        let pf = (pgn >> 8) as u8;
+        let ps = if pf < 240 { destination.0 } else { pgn as u8 };
+        Self::from_fields(
+            priority,
+            pgn & 0x2_0000 != 0,
+            pgn & 0x1_0000 != 0,
+            pf,
+            ps,
+            source.0,
+        )
+    }
+
+    /// Build an identifier from its individual fields. `priority` is truncated to 3 bits.
+    pub const fn from_fields(priority: u8, edp: bool, dp: bool, pf: u8, ps: u8, sa: u8) -> Self {
+        Self(
+            ((priority as u32 & 0x7) << 26)
+                | ((edp as u32) << 25)
+                | ((dp as u32) << 24)
+                | ((pf as u32) << 16)
+                | ((ps as u32) << 8)
+                | sa as u32,
+        )
+    }
+
+    /// View a raw 29-bit identifier, or `None` if `raw` has more than 29 bits.
+    pub const fn from_raw(raw: u32) -> Option<Self> {
+        if raw > 0x1FFF_FFFF {
+            None
+        } else {
+            Some(Self(raw))
+        }
+    }
+
+    /// View `id`, or `None` for a standard identifier.
+    pub fn from_id(id: impl Into<Id>) -> Option<Self> {
+        match id.into() {
+            Id::Extended(id) => Some(id.into()),
+            Id::Standard(_) => None,
+        }
+    }
+
+    /// The raw 29-bit identifier.
+    pub const fn as_raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Priority, 0 (highest) to 7.
+    pub const fn priority(&self) -> u8 {
+        (self.0 >> 26) as u8 & 0x7
+    }
+
+    /// Extended data page bit.
+    pub const fn edp(&self) -> bool {
+        self.0 & (1 << 25) != 0
+    }
+
+    /// Data page bit.
+    pub const fn dp(&self) -> bool {
+        self.0 & (1 << 24) != 0
+    }
+
+    /// PDU format (PF).
+    pub const fn pf(&self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    /// PDU specific (PS): the destination address or group extension.
+    pub const fn ps(&self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    /// Source address (SA).
+    pub const fn source_address(&self) -> NodeAddr {
+        NodeAddr(self.0 as u8)
+    }
+
+    /// Returns `true` for a PDU1 (destination-specific) format, PF < 240.
+    pub const fn is_pdu1(&self) -> bool {
+        self.pf() < 240
+    }
+
+    /// The 18-bit parameter group number, with a zero low byte for PDU1 formats.
+    pub const fn pgn(&self) -> u32 {
+        let pgn = (self.0 >> 8) & 0x3_FFFF;
+        if self.is_pdu1() { pgn & !0xFF } else { pgn }
+    }
+
+    /// Destination address of a PDU1 format; `None` for PDU2 (broadcast).
+    pub const fn destination(&self) -> Option<NodeAddr> {
+        if self.is_pdu1() {
+            Some(NodeAddr(self.ps()))
+        } else {
+            None
+        }
+    }
+
+    /// Replace the priority, e.g. to answer a request at a different priority.
+    pub const fn with_priority(self, priority: u8) -> Self {
+        Self(self.0 & !(0x7 << 26) | ((priority as u32 & 0x7) << 26))
+    }
+
+    /// Replace the source address.
+    pub const fn with_source_address(self, source: NodeAddr) -> Self {
+        Self(self.0 & !0xFF | source.0 as u32)
+    }
+}
+
+impl From<ExtendedId> for J1939Id {
+    fn from(id: ExtendedId) -> Self {
+        Self(id.as_raw())
+    }
+}
+
+impl From<J1939Id> for ExtendedId {
+    fn from(id: J1939Id) -> Self {
+        // SAFETY: every constructor keeps the value within 29 bits.
+        unsafe { ExtendedId::new_unchecked(id.0) }
+    }
+}
+
+impl From<J1939Id> for Id {
+    fn from(id: J1939Id) -> Self {
+        Id::Extended(id.into())
+    }
+}
+
+impl From<J1939Id> for crate::Id {
+    fn from(id: J1939Id) -> Self {
+        crate::Id::Extended(id.into())
+    }
+}
+
+/// State of a [`NameAddressManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimState {
+    /// No claim in progress.
+    Idle,
+    /// `address` has been claimed and the claim timeout has not yet expired.
+    Claiming {
+        /// The address being claimed.
+        address: NodeAddr,
+    },
+    /// `address` is ours.
+    Claimed {
+        /// The claimed address.
+        address: NodeAddr,
+    },
+    /// Our address was taken and no candidate is left; [`NameAddressManager::claim`] will
+    /// announce Cannot Claim.
+    Lost,
+    /// No address could be claimed; Cannot Claim has been announced.
+    CannotClaim,
+}
+
+/// Outcome of [`NameAddressManager::handle_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimEvent {
+    /// Another node claimed our address but lost arbitration; our claim was repeated.
+    Defended,
+    /// Another node with a higher-priority NAME took our address.
+    ///
+    /// If a candidate address is left, Address Claimed for it has already been sent and the state
+    /// is [`ClaimState::Claiming`]; otherwise the state is [`ClaimState::Lost`]. Either way the
+    /// node has no usable address until [`NameAddressManager::claim`] is called, which the caller
+    /// must do promptly: it confirms the new claim once uncontested for [`CLAIM_TIMEOUT`], or
+    /// announces Cannot Claim after the required back-off.
+    Lost,
+    /// A request for Address Claimed was answered.
+    Answered,
+}
+
+/// Error returned by [`NameAddressManager::claim`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// Every candidate address was taken by a higher-priority NAME; Cannot Claim was sent.
+    CannotClaim,
+    /// An identifier or frame could not be constructed.
+    InvalidFrame,
+}
+
+impl<E> fmt::Display for ClaimError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::CannotClaim => f.write_str("no address could be claimed"),
+            Self::InvalidFrame => f.write_str("cannot build an address claim frame"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ClaimError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What a received frame means for our claim.
+enum Contention {
+    None,
+    Won,
+    Lost,
+    Request,
+}
+
+/// J1939-81 address claim state machine.
+///
+/// Arbitrary-address-capable NAMEs move through `candidates` (by default the self-configurable
+/// range 128–247) after losing their preferred address; other NAMEs announce Cannot Claim after the
+/// random back-off required by the standard.
+#[derive(Debug)]
+pub struct NameAddressManager<C> {
+    name: Name,
+    preferred: NodeAddr,
+    candidates: (u8, u8),
+    state: ClaimState,
+    clock: C,
+    rng: u32,
+}
+
+impl<C: Clock> NameAddressManager<C> {
+    /// Create a manager for `name` that will first try `preferred`.
+    pub fn new(name: Name, preferred: NodeAddr, clock: C) -> Self {
+        // Seed the back-off generator from the NAME so that contending nodes diverge.
+        let rng = (name.0 ^ (name.0 >> 32)) as u32 | 1;
+        Self {
+            name,
+            preferred,
+            candidates: (128, 247),
+            state: ClaimState::Idle,
+            clock,
+            rng,
+        }
+    }
+
+    /// Set the inclusive range of addresses tried after losing the preferred one.
+    ///
+    /// The bounds may be given in either order. Addresses above 253 cannot be claimed and are left
+    /// out of the range.
+    pub fn set_candidates(&mut self, first: NodeAddr, last: NodeAddr) {
+        let (first, last) = (first.0.min(last.0), first.0.max(last.0));
+        self.candidates = (first, last.min(253));
+    }
+
+    /// Our NAME.
+    pub fn name(&self) -> Name {
+        self.name
+    }
+
+    /// Current state.
+    pub fn state(&self) -> ClaimState {
+        self.state
+    }
+
+    /// The claimed address, once [`ClaimState::Claimed`].
+    pub fn address(&self) -> Option<NodeAddr> {
+        match self.state {
+            ClaimState::Claimed { address } => Some(address),
+            _ => None,
+        }
+    }
+
+    /// Pseudo-random back-off of 0–153 ms (0.6 ms steps) before Cannot Claim.
+    fn backoff(&mut self) -> Duration {
+        // xorshift32
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        Duration::from_micros(600 * u64::from(self.rng & 0xFF))
+    }
+
+    fn next_candidate(&self, lost: NodeAddr) -> Option<NodeAddr> {
+        let (first, last) = self.candidates;
+        if !self.name.arbitrary_address_capable() || first > last {
+            return None;
+        }
+        Some(NodeAddr(if lost.0 < first || lost.0 >= last {
+            first
+        } else {
+            lost.0 + 1
+        }))
+    }
+
+    fn claim_frame<F: Frame>(&self, source: NodeAddr) -> Option<F> {
+        let id = J1939Id::new(
+            ADDRESS_CLAIM_PRIORITY,
+            PGN_ADDRESS_CLAIMED,
+            NodeAddr::GLOBAL,
+            source,
+        );
+        F::new(id, &self.name.to_bytes())
+    }
+
+    async fn send_claim<T>(&self, io: &mut T, source: NodeAddr) -> Result<(), ClaimError<T::Error>>
+    where
+        T: AsyncTxFrameIo,
+        T::Frame: Frame,
+    {
+        let frame = self.claim_frame(source).ok_or(ClaimError::InvalidFrame)?;
+        io.send(&frame).await.map_err(ClaimError::Io)
+    }
+
+    /// Classify `frame` with respect to our claim on `address`.
+    fn contention<F: Frame>(&self, frame: &F, address: Option<NodeAddr>) -> Contention {
+        let Some(id) = J1939Id::from_id(frame.id()) else {
+            return Contention::None;
+        };
+        let (pf, ps, sa) = (id.pf(), NodeAddr(id.ps()), id.source_address());
+        let data = frame.data();
+        match pf {
+            0xEE if Some(sa) == address => match Name::from_bytes(data) {
+                Some(theirs) if theirs == self.name => Contention::None,
+                Some(theirs) if self.name < theirs => Contention::Won,
+                Some(_) => Contention::Lost,
+                None => Contention::None,
+            },
+            0xEA if (ps.is_global() || Some(ps) == address)
+                && data.get(..3) == Some(&[0x00, 0xEE, 0x00]) =>
+            {
+                Contention::Request
+            }
+            _ => Contention::None,
+        }
+    }
+
+    /// Claim an address, returning it once the claim has gone uncontested for
+    /// [`CLAIM_TIMEOUT`].
+    ///
+    /// Frames received while claiming are consumed. Starts from the preferred address, or from
+    /// the next candidate if the previous claim was lost.
+    pub async fn claim<T, F, E>(&mut self, io: &mut T) -> Result<NodeAddr, ClaimError<E>>
+    where
+        T: AsyncTxFrameIo<Frame = F, Error = E> + AsyncRxFrameIo<Frame = F, Error = E>,
+        F: Frame,
+    {
+        let mut address = match self.state {
+            ClaimState::Claimed { address } => return Ok(address),
+            ClaimState::Claiming { address } => address,
+            ClaimState::Idle | ClaimState::CannotClaim => self.preferred,
+            ClaimState::Lost => return self.cannot_claim(io).await,
+        };
+        // Every candidate plus the preferred address may be lost once before giving up.
+        let mut losses_left = usize::from(self.candidates.1.saturating_sub(self.candidates.0)) + 2;
+        'claim: loop {
+            self.state = ClaimState::Claiming { address };
+            self.send_claim(io, address).await?;
+            let deadline = self.clock.now() + CLAIM_TIMEOUT;
+            loop {
+                let remaining = deadline.saturating_sub(self.clock.now());
+                if remaining.is_zero() {
+                    break;
+                }
+                let frame = match io.recv_timeout(remaining).await {
+                    Ok(frame) => frame,
+                    Err(_) if self.clock.now() >= deadline => break,
+                    Err(e) => return Err(ClaimError::Io(e)),
+                };
+                match self.contention(&frame, Some(address)) {
+                    Contention::None => {}
+                    Contention::Won | Contention::Request => self.send_claim(io, address).await?,
+                    Contention::Lost => match self.next_candidate(address) {
+                        Some(next) if losses_left > 1 => {
+                            losses_left -= 1;
+                            address = next;
+                            continue 'claim;
+                        }
+                        _ => return self.cannot_claim(io).await,
+                    },
+                }
+            }
+            self.state = ClaimState::Claimed { address };
+            return Ok(address);
+        }
+    }
+
+    async fn cannot_claim<T, F, E>(&mut self, io: &mut T) -> Result<NodeAddr, ClaimError<E>>
+    where
+        T: AsyncTxFrameIo<Frame = F, Error = E> + AsyncRxFrameIo<Frame = F, Error = E>,
+        F: Frame,
+    {
+        self.state = ClaimState::CannotClaim;
+        let deadline = self.clock.now() + self.backoff();
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                break;
+            }
+            match io.recv_timeout(remaining).await {
+                Ok(_) => {}
+                Err(_) if self.clock.now() >= deadline => break,
+                Err(e) => return Err(ClaimError::Io(e)),
+            }
+        }
+        self.send_claim(io, NodeAddr::NULL).await?;
+        Err(ClaimError::CannotClaim)
+    }
+
+    /// Process a frame received after [`NameAddressManager::claim`] succeeded.
+    ///
+    /// Defends our address against lower-priority claimants and answers requests for Address
+    /// Claimed. Call it for every received frame (or at least every PGN 0xEE00/0xEA00 frame), and
+    /// call [`NameAddressManager::claim`] after [`ClaimEvent::Lost`].
+    pub async fn handle_frame<T, F>(
+        &mut self,
+        io: &mut T,
+        frame: &F,
+    ) -> Result<Option<ClaimEvent>, ClaimError<T::Error>>
+    where
+        T: AsyncTxFrameIo<Frame = F>,
+        F: Frame,
+    {
+        let (address, source) = match self.state {
+            ClaimState::Claimed { address } | ClaimState::Claiming { address } => {
+                (Some(address), address)
+            }
+            ClaimState::CannotClaim => (None, NodeAddr::NULL),
+            ClaimState::Idle | ClaimState::Lost => return Ok(None),
+        };
+        match self.contention(frame, address) {
+            Contention::None => Ok(None),
+            Contention::Won => {
+                self.send_claim(io, source).await?;
+                Ok(Some(ClaimEvent::Defended))
+            }
+            Contention::Request => {
+                self.send_claim(io, source).await?;
+                Ok(Some(ClaimEvent::Answered))
+            }
+            Contention::Lost => {
+                match self.next_candidate(source) {
+                    Some(next) => {
+                        self.state = ClaimState::Claiming { address: next };
+                        self.send_claim(io, next).await?;
+                    }
+                    // Cannot Claim needs a random back-off first, which `claim` waits out.
+                    None => self.state = ClaimState::Lost,
+                }
+                Ok(Some(ClaimEvent::Lost))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::fuzz::{FuzzBus, FuzzClock};
+    use crate::testdata::TestFrame;
+
+    const ARBITRARY: u64 = 1 << 63;
+    const OURS: Name = Name(ARBITRARY | 0x500);
+    const STRONGER: Name = Name(0x100);
+    const WEAKER: Name = Name(ARBITRARY | 0x900);
+    const PREFERRED: NodeAddr = NodeAddr(0x20);
+    const FIRST_CANDIDATE: NodeAddr = NodeAddr(128);
+
+    /// Poll `future` once; nothing in these tests waits.
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("FuzzBus never blocks"),
+        }
+    }
+
+    /// A clock read twice per claim window, so each window receives at most one frame.
+    fn clock() -> FuzzClock {
+        FuzzClock::with_step(CLAIM_TIMEOUT / 2)
+    }
+
+    fn claim(name: Name, source: NodeAddr) -> TestFrame {
+        let id = J1939Id::new(6, PGN_ADDRESS_CLAIMED, NodeAddr::GLOBAL, source);
+        TestFrame::new(id, &name.to_bytes()).unwrap()
+    }
+
+    fn request(destination: NodeAddr) -> TestFrame {
+        let id = J1939Id::new(6, PGN_REQUEST, destination, NodeAddr(0x10));
+        TestFrame::new(id, &[0x00, 0xEE, 0x00]).unwrap()
+    }
+
+    #[test]
+    fn claim_uncontested() {
+        let mut manager = NameAddressManager::new(OURS, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        assert_eq!(ready(manager.claim(&mut bus)), Ok(PREFERRED));
+        assert_eq!(manager.address(), Some(PREFERRED));
+        assert_eq!(bus.sent(), [claim(OURS, PREFERRED)]);
+    }
+
+    #[test]
+    fn claim_contended_and_won() {
+        let mut manager = NameAddressManager::new(OURS, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        bus.deliver(claim(WEAKER, PREFERRED));
+        assert_eq!(ready(manager.claim(&mut bus)), Ok(PREFERRED));
+        assert_eq!(bus.sent(), [claim(OURS, PREFERRED); 2]);
+    }
+
+    #[test]
+    fn claim_lost_moves_to_candidate() {
+        let mut manager = NameAddressManager::new(OURS, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        bus.deliver(claim(STRONGER, PREFERRED));
+        assert_eq!(ready(manager.claim(&mut bus)), Ok(FIRST_CANDIDATE));
+        assert_eq!(
+            bus.sent(),
+            [claim(OURS, PREFERRED), claim(OURS, FIRST_CANDIDATE)]
+        );
+    }
+
+    #[test]
+    fn claim_lost_without_candidates() {
+        let fixed = Name(0x500);
+        let mut manager = NameAddressManager::new(fixed, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        bus.deliver(claim(STRONGER, PREFERRED));
+        assert_eq!(ready(manager.claim(&mut bus)), Err(ClaimError::CannotClaim));
+        assert_eq!(manager.state(), ClaimState::CannotClaim);
+        assert_eq!(
+            bus.sent(),
+            [claim(fixed, PREFERRED), claim(fixed, NodeAddr::NULL)]
+        );
+    }
+
+    #[test]
+    fn claimed_address_defended_and_announced() {
+        let mut manager = NameAddressManager::new(OURS, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        ready(manager.claim(&mut bus)).unwrap();
+        bus.take_sent();
+        let event = ready(manager.handle_frame(&mut bus, &claim(WEAKER, PREFERRED)));
+        assert_eq!(event, Ok(Some(ClaimEvent::Defended)));
+        let event = ready(manager.handle_frame(&mut bus, &request(NodeAddr::GLOBAL)));
+        assert_eq!(event, Ok(Some(ClaimEvent::Answered)));
+        let event = ready(manager.handle_frame(&mut bus, &claim(WEAKER, NodeAddr(0x21))));
+        assert_eq!(event, Ok(None));
+        assert_eq!(bus.sent(), [claim(OURS, PREFERRED); 2]);
+        assert_eq!(manager.address(), Some(PREFERRED));
+    }
+
+    #[test]
+    fn claimed_address_lost_to_candidate() {
+        let mut manager = NameAddressManager::new(OURS, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        ready(manager.claim(&mut bus)).unwrap();
+        bus.take_sent();
+        let event = ready(manager.handle_frame(&mut bus, &claim(STRONGER, PREFERRED)));
+        assert_eq!(event, Ok(Some(ClaimEvent::Lost)));
+        assert_eq!(
+            manager.state(),
+            ClaimState::Claiming {
+                address: FIRST_CANDIDATE
+            }
+        );
+        assert_eq!(bus.take_sent(), [claim(OURS, FIRST_CANDIDATE)]);
+        assert_eq!(ready(manager.claim(&mut bus)), Ok(FIRST_CANDIDATE));
+    }
+
+    #[test]
+    fn claimed_address_lost_then_cannot_claim() {
+        let fixed = Name(0x500);
+        let mut manager = NameAddressManager::new(fixed, PREFERRED, clock());
+        let mut bus = FuzzBus::<TestFrame>::new();
+        ready(manager.claim(&mut bus)).unwrap();
+        bus.take_sent();
+        let event = ready(manager.handle_frame(&mut bus, &claim(STRONGER, PREFERRED)));
+        assert_eq!(event, Ok(Some(ClaimEvent::Lost)));
+        assert_eq!(manager.state(), ClaimState::Lost);
+        assert!(bus.sent().is_empty());
+        assert_eq!(ready(manager.claim(&mut bus)), Err(ClaimError::CannotClaim));
+        assert_eq!(bus.sent(), [claim(fixed, NodeAddr::NULL)]);
+        // Requests are answered from the null address.
+        bus.take_sent();
+        let event = ready(manager.handle_frame(&mut bus, &request(NodeAddr::GLOBAL)));
+        assert_eq!(event, Ok(Some(ClaimEvent::Answered)));
+        assert_eq!(bus.sent(), [claim(fixed, NodeAddr::NULL)]);
+    }
+}
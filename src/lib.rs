@@ -17,6 +17,8 @@
 //! - ✅ Defines traits for sending/receiving frames, configuring acceptance filters, and optional
 //!   driver controls (nonblocking toggle, TX-idle query, buffering wrapper, builder/binding).
 //! - ✅ Provides small helper types for common ID/mask filter patterns.
+//! - ✅ Provides optional wrappers built purely on the traits (e.g. [`supervised::Supervised`] for
+//!   bus-off recovery).
 //! - ❌ Does not define an error model (e.g. “would block” vs “bus off”); that remains driver-
 //!   specific.
 //! - ❌ Does not define a frame type; you use a type implementing [`embedded_can::Frame`].
@@ -68,9 +70,92 @@
 #![no_std]
 #![allow(async_fn_in_trait)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 use core::time::Duration;
 use embedded_can::{ExtendedId, StandardId};
 
+pub mod address;
+pub mod autobaud;
+pub mod bitlen;
+pub mod blackbox;
+pub mod buffered;
+pub mod burst;
+pub mod busload;
+#[cfg(feature = "bxcan")]
+pub mod bxcan_io;
+pub mod canopen;
+pub mod change;
+pub mod cobs;
+pub mod codec;
+pub mod confirmed;
+#[cfg(feature = "critical-section")]
+pub mod cs_can;
+pub mod cyclic;
+pub mod debug_ctx;
+pub mod dedup;
+#[cfg(feature = "defmt")]
+pub mod defmt_log;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "std")]
+pub mod enumerate;
+#[cfg(feature = "alloc")]
+pub mod erased;
+pub mod error;
+#[cfg(feature = "esp")]
+pub mod esp;
+#[cfg(feature = "ffi-backend")]
+pub mod ffi;
+pub mod filter;
+pub mod frame_eq;
+#[cfg(feature = "alloc")]
+pub mod fuzz;
+#[cfg(feature = "alloc")]
+pub mod golden;
+pub mod id_scheme;
+#[cfg(feature = "tracing")]
+pub mod instrument;
+#[cfg(feature = "embedded-hal-async")]
+pub mod interrupt;
+pub mod isotp;
+pub mod isr;
+pub mod j1939;
+pub mod matching;
+#[cfg(feature = "mcp2515")]
+pub mod mcp2515_io;
+#[cfg(feature = "nb")]
+pub mod nb_io;
+pub mod obd2;
+pub mod ping;
+pub mod replay;
+#[cfg(all(feature = "socketcan", target_os = "linux"))]
+pub mod socketcan;
+pub mod spsc;
+#[cfg(target_has_atomic = "8")]
+pub mod static_can;
+pub mod stats;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod stream;
+pub mod supervised;
+pub mod testdata;
+pub mod timesync;
+pub mod timing;
+pub mod transceiver;
+pub mod typestate;
+#[cfg(feature = "udp-multicast")]
+pub mod udp_multicast;
+pub mod uds;
+pub mod validate;
+pub mod watchdog;
+pub mod xcp;
+
+pub use timing::{BitTiming, Bitrate};
+
 /// A CAN identifier (standard 11-bit or extended 29-bit).
 ///
 /// Many embedded CAN HALs want to stay `no_std` and avoid allocating or storing extra metadata.
@@ -83,6 +168,52 @@ pub enum Id {
     Extended(ExtendedId),
 }
 
+/// Orders identifiers by CAN arbitration priority: the “smallest” ID wins arbitration.
+///
+/// This matches the ordering of [`embedded_can::Id`], so a standard ID sorts before an extended ID
+/// sharing the same 11 base bits.
+impl Ord for Id {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        embedded_can::Id::from(*self).cmp(&embedded_can::Id::from(*other))
+    }
+}
+
+impl PartialOrd for Id {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<embedded_can::Id> for Id {
+    fn from(id: embedded_can::Id) -> Self {
+        match id {
+            embedded_can::Id::Standard(id) => Id::Standard(id),
+            embedded_can::Id::Extended(id) => Id::Extended(id),
+        }
+    }
+}
+
+impl From<Id> for embedded_can::Id {
+    fn from(id: Id) -> Self {
+        match id {
+            Id::Standard(id) => embedded_can::Id::Standard(id),
+            Id::Extended(id) => embedded_can::Id::Extended(id),
+        }
+    }
+}
+
+impl From<StandardId> for Id {
+    fn from(id: StandardId) -> Self {
+        Id::Standard(id)
+    }
+}
+
+impl From<ExtendedId> for Id {
+    fn from(id: ExtendedId) -> Self {
+        Id::Extended(id)
+    }
+}
+
 /// Bitmask corresponding to a CAN identifier (standard or extended width).
 ///
 /// This is typically used for acceptance filtering: a frame is accepted when the masked bits match.
@@ -100,13 +231,347 @@ pub enum IdMask {
 /// - “mask bit = 1” means “compare this bit”
 /// - “mask bit = 0” means “don’t care”
 ///
+/// By default a filter accepts data and remote frames whose identifier has the same type
+/// (standard or extended) as `id`; [`FrameTypes`] and [`IdTypes`] narrow or widen that.
+///
 /// Exact matching rules and hardware limits are driver-specific.
+///
+/// The struct is `#[non_exhaustive]` so that constraints can be added without breaking callers;
+/// build it with [`IdMaskFilter::new`], [`IdMaskFilter::standard`] or [`IdMaskFilter::extended`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct IdMaskFilter {
     /// Identifier to match (standard or extended).
     pub id: Id,
     /// Mask to apply; ones are compared, zeros are don't-care.
     pub mask: IdMask,
+    /// Frame types (data/remote) the filter accepts.
+    pub frame_types: FrameTypes,
+    /// Identifier types (standard/extended) the filter accepts.
+    pub id_types: IdTypes,
+}
+
+impl IdMaskFilter {
+    /// Filter accepting data and remote frames whose identifier matches `id` under `mask` and has
+    /// the same type as `id`.
+    ///
+    /// `id` may be an [`Id`], an [`embedded_can::Id`], or a [`StandardId`] or [`ExtendedId`]. In
+    /// constant expressions, use [`IdMaskFilter::standard`] or [`IdMaskFilter::extended`].
+    pub fn new(id: impl Into<Id>, mask: IdMask) -> Self {
+        Self::from_parts(id.into(), mask)
+    }
+
+    /// Filter accepting data and remote frames with a standard identifier matching `id` under
+    /// `mask`.
+    pub const fn standard(id: StandardId, mask: u16) -> Self {
+        Self::from_parts(Id::Standard(id), IdMask::Standard(mask))
+    }
+
+    /// Filter accepting data and remote frames with an extended identifier matching `id` under
+    /// `mask`.
+    pub const fn extended(id: ExtendedId, mask: u32) -> Self {
+        Self::from_parts(Id::Extended(id), IdMask::Extended(mask))
+    }
+
+    const fn from_parts(id: Id, mask: IdMask) -> Self {
+        Self {
+            id,
+            mask,
+            frame_types: FrameTypes::Both,
+            id_types: IdTypes::SameAsFilter,
+        }
+    }
+
+    /// Restrict the filter to the given frame types.
+    pub const fn with_frame_types(mut self, frame_types: FrameTypes) -> Self {
+        self.frame_types = frame_types;
+        self
+    }
+
+    /// Set which identifier types the filter accepts.
+    pub const fn with_id_types(mut self, id_types: IdTypes) -> Self {
+        self.id_types = id_types;
+        self
+    }
+}
+
+/// Frame types accepted by an [`IdMaskFilter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FrameTypes {
+    /// Data and remote frames.
+    #[default]
+    Both,
+    /// Data frames only.
+    Data,
+    /// Remote (RTR) frames only.
+    Remote,
+}
+
+/// Identifier types accepted by an [`IdMaskFilter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IdTypes {
+    /// Only identifiers of the same type as the filter's `id`: a standard filter never matches an
+    /// extended identifier, even if the masked bits agree.
+    #[default]
+    SameAsFilter,
+    /// Standard and extended identifiers, compared by raw value under the mask (the behaviour of
+    /// a SocketCAN filter without `CAN_EFF_FLAG` in its mask).
+    Both,
+}
+
+/// Growable list of acceptance filters (requires the `alloc` feature).
+///
+/// Host-side tools often build their filter configuration incrementally at runtime. The list
+/// ignores duplicates and derefs to `[IdMaskFilter]`, so it can be passed straight to
+/// [`FilterConfig::set_filters`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterList {
+    filters: alloc::vec::Vec<IdMaskFilter>,
+}
+
+#[cfg(feature = "alloc")]
+impl FilterList {
+    /// Create an empty list.
+    pub const fn new() -> Self {
+        Self {
+            filters: alloc::vec::Vec::new(),
+        }
+    }
+
+    /// Add a filter. Returns `false` if an identical filter was already present.
+    pub fn add(&mut self, filter: IdMaskFilter) -> bool {
+        if self.filters.contains(&filter) {
+            return false;
+        }
+        self.filters.push(filter);
+        true
+    }
+
+    /// Remove a filter. Returns `false` if it was not present.
+    pub fn remove(&mut self, filter: &IdMaskFilter) -> bool {
+        let before = self.filters.len();
+        self.filters.retain(|f| f != filter);
+        self.filters.len() != before
+    }
+
+    /// Remove all filters.
+    pub fn clear(&mut self) {
+        self.filters.clear();
+    }
+
+    /// Replace the list with a smaller one accepting the same identifiers; see
+    /// [`filter::minimize`].
+    pub fn minimize(&mut self) {
+        let len = filter::minimize(&mut self.filters);
+        self.filters.truncate(len);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::ops::Deref for FilterList {
+    type Target = [IdMaskFilter];
+
+    fn deref(&self) -> &Self::Target {
+        &self.filters
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl FromIterator<IdMaskFilter> for FilterList {
+    fn from_iter<I: IntoIterator<Item = IdMaskFilter>>(iter: I) -> Self {
+        let mut list = Self::new();
+        for filter in iter {
+            list.add(filter);
+        }
+        list
+    }
+}
+
+/// Fixed-capacity set of acceptance filters.
+///
+/// The `no_std` counterpart of [`FilterList`]: filters can be added and removed incrementally
+/// without allocation, duplicates are ignored, and the set derefs to `[IdMaskFilter]` so it can be
+/// passed straight to [`FilterConfig::set_filters`]. `N` is typically the number of hardware
+/// filter banks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterSet<const N: usize> {
+    filters: [IdMaskFilter; N],
+    len: usize,
+}
+
+/// Error returned when adding to a full [`FilterSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterSetFull;
+
+impl<const N: usize> FilterSet<N> {
+    /// Placeholder for unused slots; never visible through the public API.
+    const UNUSED: IdMaskFilter = IdMaskFilter::standard(StandardId::ZERO, 0);
+
+    /// Create an empty set.
+    pub const fn new() -> Self {
+        Self {
+            filters: [Self::UNUSED; N],
+            len: 0,
+        }
+    }
+
+    /// Add a filter. Returns `Ok(false)` if an identical filter was already present.
+    pub fn add(&mut self, filter: IdMaskFilter) -> Result<bool, FilterSetFull> {
+        if self.contains(&filter) {
+            return Ok(false);
+        }
+        let slot = self.filters.get_mut(self.len).ok_or(FilterSetFull)?;
+        *slot = filter;
+        self.len += 1;
+        Ok(true)
+    }
+
+    /// Remove a filter. Returns `false` if it was not present.
+    ///
+    /// The remaining filters keep their relative order.
+    pub fn remove(&mut self, filter: &IdMaskFilter) -> bool {
+        let Some(index) = self.iter().position(|f| f == filter) else {
+            return false;
+        };
+        self.filters[index..self.len].rotate_left(1);
+        self.len -= 1;
+        self.filters[self.len] = Self::UNUSED;
+        true
+    }
+
+    /// Remove all filters.
+    pub fn clear(&mut self) {
+        self.filters = [Self::UNUSED; N];
+        self.len = 0;
+    }
+
+    /// Maximum number of filters.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns `true` if no more filters can be added.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Replace the set with a smaller one accepting the same identifiers; see
+    /// [`filter::minimize`].
+    ///
+    /// Useful to make room before [`FilterSet::add`] when the set is full.
+    pub fn minimize(&mut self) {
+        let len = filter::minimize(&mut self.filters[..self.len]);
+        self.filters[len..].fill(Self::UNUSED);
+        self.len = len;
+    }
+}
+
+impl<const N: usize> Default for FilterSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> core::ops::Deref for FilterSet<N> {
+    type Target = [IdMaskFilter];
+
+    fn deref(&self) -> &Self::Target {
+        &self.filters[..self.len]
+    }
+}
+
+impl<const N: usize> TryFrom<&[IdMaskFilter]> for FilterSet<N> {
+    type Error = FilterSetFull;
+
+    fn try_from(filters: &[IdMaskFilter]) -> Result<Self, Self::Error> {
+        let mut set = Self::new();
+        for filter in filters {
+            set.add(*filter)?;
+        }
+        Ok(set)
+    }
+}
+
+/// Coarse traffic class of an outgoing frame.
+///
+/// Classes are ordered from least to most urgent. They are a hint for schedulers and gateways
+/// sharing one bus between several producers; the frame's ID still decides bus arbitration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PriorityClass {
+    /// Bulk or diagnostic traffic that may be delayed.
+    Low,
+    /// Regular application traffic.
+    #[default]
+    Normal,
+    /// Time-critical traffic that should bypass queued lower classes.
+    High,
+}
+
+/// Per-frame transmission policy for [`TxFrameIo::send_with`] and [`AsyncTxFrameIo::send_with`].
+///
+/// `SendOptions::default()` describes a plain [`TxFrameIo::send`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendOptions {
+    /// Traffic class used by software schedulers.
+    pub class: PriorityClass,
+    /// Transmit at most once: disable automatic retransmission after lost arbitration or errors.
+    pub one_shot: bool,
+    /// Latest time, on the caller's [`Clock`], at which the frame is still worth sending.
+    ///
+    /// Implementations may drop the frame once the deadline has passed instead of sending it late.
+    pub deadline: Option<Duration>,
+}
+
+/// Error returned by the multi-frame sends ([`TxFrameIo::send_all`], [`TxFrameIo::send_batch`]
+/// and their async counterparts) when a send fails part-way through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSend<E> {
+    /// Number of frames accepted by the driver before the failure.
+    ///
+    /// These are the first `sent` frames submitted; the failed frame is not included.
+    pub sent: usize,
+    /// Error returned for frame number `sent`.
+    pub error: E,
+}
+
+impl<E> core::fmt::Display for PartialSend<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "send failed after {} frames", self.sent)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for PartialSend<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Error returned by [`TxFrameIo::send_gather`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GatherError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// The segments do not form a payload the frame type can carry.
+    InvalidFrame,
+}
+
+impl<E> core::fmt::Display for GatherError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::InvalidFrame => f.write_str("payload does not fit the frame type"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for GatherError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::InvalidFrame => None,
+        }
+    }
 }
 
 /// Transmit-side (blocking) CAN frame I/O.
@@ -135,6 +600,80 @@ pub trait TxFrameIo {
     ///
     /// Implementations that cannot support timeouts may treat this as [`TxFrameIo::send`].
     fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error>;
+
+    /// Send a frame with per-frame [`SendOptions`], blocking until it is accepted by the driver.
+    ///
+    /// The default implementation ignores the options and calls [`TxFrameIo::send`]. Drivers and
+    /// wrappers that can honour some of the options (e.g. a hardware one-shot mode) override it.
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let _ = options;
+        self.send(frame)
+    }
+
+    /// Send a batch of frames with per-frame [`SendOptions`], in order, and return how many were
+    /// sent.
+    ///
+    /// Drivers with several TX mailboxes or a TX FIFO override this to load as many frames as fit
+    /// at once, e.g. within a single critical section. The default implementation calls
+    /// [`TxFrameIo::send_with`] for each frame; either way, sending stops at the first error,
+    /// which is returned with the number of frames accepted before it.
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        for (sent, (frame, options)) in frames.iter().enumerate() {
+            self.send_with(frame, options)
+                .map_err(|error| PartialSend { sent, error })?;
+        }
+        Ok(frames.len())
+    }
+
+    /// Send a data frame with identifier `id` whose payload is the concatenation of `segments`,
+    /// blocking until it is accepted by the driver.
+    ///
+    /// Lets multi-segment encoders (e.g. an ISO-TP header followed by a slice of the message) send
+    /// without concatenating into a temporary buffer first. The default implementation copies the
+    /// segments into a 64-byte stack buffer, builds the frame and calls [`TxFrameIo::send`];
+    /// drivers that can assemble the payload in place (see [`ChunkedTx`]) override it.
+    fn send_gather(
+        &mut self,
+        id: embedded_can::Id,
+        segments: &[&[u8]],
+    ) -> Result<(), GatherError<Self::Error>>
+    where
+        Self::Frame: embedded_can::Frame,
+    {
+        let mut buf = [0; 64];
+        let mut len = 0;
+        for segment in segments {
+            let end = len + segment.len();
+            buf.get_mut(len..end)
+                .ok_or(GatherError::InvalidFrame)?
+                .copy_from_slice(segment);
+            len = end;
+        }
+        let frame = <Self::Frame as embedded_can::Frame>::new(id, &buf[..len])
+            .ok_or(GatherError::InvalidFrame)?;
+        self.send(&frame).map_err(GatherError::Io)
+    }
+
+    /// Send every frame of `frames` in order, blocking on each, and return how many were sent.
+    ///
+    /// Sending stops at the first error, which is returned together with the number of frames
+    /// the driver had already accepted; the remaining frames are not consumed from the iterator.
+    fn send_all<I>(&mut self, frames: I) -> Result<usize, PartialSend<Self::Error>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Self::Frame>,
+    {
+        let mut sent = 0;
+        for frame in frames {
+            self.send(&frame)
+                .map_err(|error| PartialSend { sent, error })?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
 }
 
 /// Receive-side (blocking) CAN frame I/O.
@@ -165,6 +704,39 @@ pub trait RxFrameIo {
     ///
     /// This can be used by polling-style protocols to avoid busy loops.
     fn wait_not_empty(&mut self) -> Result<(), Self::Error>;
+
+    /// Receive up to `max` frames, appending them to `frames`, and return how many were received.
+    ///
+    /// Waits up to `timeout` for the first frame and then takes only frames that are already
+    /// queued. Drivers that can drain their queue in bulk (e.g. with one system call) override
+    /// this. The default implementation calls [`RxFrameIo::recv_timeout`] once and then
+    /// [`RxFrameIo::try_recv`] until it fails; since it cannot tell “would block” from other
+    /// errors, an error after the first frame just ends the batch, and is reported by the next
+    /// receive if it persists.
+    fn recv_batch<E>(
+        &mut self,
+        frames: &mut E,
+        max: usize,
+        timeout: Duration,
+    ) -> Result<usize, Self::Error>
+    where
+        Self: Sized,
+        E: Extend<Self::Frame>,
+    {
+        if max == 0 {
+            return Ok(0);
+        }
+        frames.extend(Some(self.recv_timeout(timeout)?));
+        let mut received = 1;
+        while received < max {
+            let Ok(frame) = self.try_recv() else {
+                break;
+            };
+            frames.extend(Some(frame));
+            received += 1;
+        }
+        Ok(received)
+    }
 }
 
 /// Transmit-side (async) CAN frame I/O.
@@ -188,6 +760,51 @@ pub trait AsyncTxFrameIo {
         frame: &Self::Frame,
         timeout: Duration,
     ) -> Result<(), Self::Error>;
+
+    /// Send a frame asynchronously with per-frame [`SendOptions`].
+    ///
+    /// The default implementation ignores the options and calls [`AsyncTxFrameIo::send`].
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let _ = options;
+        self.send(frame).await
+    }
+
+    /// Send a batch of frames with per-frame [`SendOptions`], in order, and return how many were
+    /// sent.
+    ///
+    /// See [`TxFrameIo::send_batch`].
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        for (sent, (frame, options)) in frames.iter().enumerate() {
+            self.send_with(frame, options)
+                .await
+                .map_err(|error| PartialSend { sent, error })?;
+        }
+        Ok(frames.len())
+    }
+
+    /// Send every frame of `frames` in order and return how many were sent.
+    ///
+    /// See [`TxFrameIo::send_all`] for the partial-failure semantics.
+    async fn send_all<I>(&mut self, frames: I) -> Result<usize, PartialSend<Self::Error>>
+    where
+        I: IntoIterator<Item = Self::Frame>,
+    {
+        let mut sent = 0;
+        for frame in frames {
+            self.send(&frame)
+                .await
+                .map_err(|error| PartialSend { sent, error })?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
 }
 
 /// Receive-side (async) CAN frame I/O.
@@ -211,6 +828,39 @@ pub trait AsyncRxFrameIo {
     async fn wait_not_empty(&mut self) -> Result<(), Self::Error>;
 }
 
+/// Metadata the hardware attaches to a received frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RxMeta {
+    /// Index of the acceptance filter that matched, if the hardware reports it.
+    ///
+    /// Indices refer to the order passed to [`FilterConfig::set_filters`].
+    pub filter_index: Option<u16>,
+    /// `true` if this is one of our own transmitted frames (loopback / self-reception).
+    pub self_reception: bool,
+    /// Channel the frame was received on, for multi-channel devices.
+    pub channel: Option<u8>,
+    /// Receive timestamp, if available, on the same timebase as the driver's [`Clock`].
+    pub timestamp: Option<Duration>,
+}
+
+/// Blocking receive that also returns [`RxMeta`].
+///
+/// ISO-TP and J1939 stacks can use the matched filter index to dispatch frames without
+/// re-classifying their identifiers.
+pub trait RxMetaFrameIo: RxFrameIo {
+    /// Receive a frame and its metadata, blocking until one is available.
+    fn recv_with_meta(&mut self) -> Result<(Self::Frame, RxMeta), Self::Error>;
+
+    /// Attempt to receive a frame and its metadata without blocking.
+    fn try_recv_with_meta(&mut self) -> Result<(Self::Frame, RxMeta), Self::Error>;
+}
+
+/// Async receive that also returns [`RxMeta`].
+pub trait AsyncRxMetaFrameIo: AsyncRxFrameIo {
+    /// Receive a frame and its metadata asynchronously.
+    async fn recv_with_meta(&mut self) -> Result<(Self::Frame, RxMeta), Self::Error>;
+}
+
 /// Convenience marker for types that implement both [`TxFrameIo`] and [`RxFrameIo`] using the same
 /// frame and error types.
 ///
@@ -241,6 +891,37 @@ impl<T> AsyncFrameIo for T where
 {
 }
 
+/// Transmit a data frame whose payload is written in pieces.
+///
+/// CAN FD payloads reach 64 bytes and CAN XL payloads 2048. A protocol encoder that produces a
+/// payload incrementally would otherwise need a staging buffer of the full size just to build the
+/// frame for [`TxFrameIo::send`]; with this trait, the driver writes each chunk straight into its
+/// own frame buffer (a mailbox, DMA descriptor or kernel frame struct):
+///
+/// ```rust,ignore
+/// use embedded_can_interface::ChunkedTx;
+///
+/// can.begin_frame(id, header.len() + body.len())?;
+/// can.write_chunk(&header)?;
+/// for block in encoder.blocks() {
+///     can.write_chunk(block)?;
+/// }
+/// ```
+pub trait ChunkedTx: TxFrameIo {
+    /// Start a data frame with identifier `id` and a payload of `len` bytes.
+    ///
+    /// Fails if the interface cannot send a payload of that length. An unfinished frame started
+    /// earlier is discarded.
+    fn begin_frame(&mut self, id: embedded_can::Id, len: usize) -> Result<(), Self::Error>;
+
+    /// Append `data` to the payload of the frame started with [`ChunkedTx::begin_frame`].
+    ///
+    /// The chunk that completes the payload also sends the frame, blocking like
+    /// [`TxFrameIo::send`]; an empty payload is sent by writing an empty chunk. Writing without a
+    /// started frame, or past its length, fails.
+    fn write_chunk(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
 /// Split a CAN interface into transmit and receive halves.
 ///
 /// This trait is usually implemented for a concrete CAN driver type that internally owns shared
@@ -259,6 +940,62 @@ pub trait SplitTxRx {
     fn split(self) -> (Self::Tx, Self::Rx);
 }
 
+/// A device exposing several CAN channels (dual-channel USB adapters, MCAN pairs, …).
+///
+/// Each channel is borrowed from the device and behaves like an independent interface.
+pub trait MultiChannel {
+    /// Error returned by the driver implementation (e.g. invalid channel index).
+    type Error;
+
+    /// Handle for a single channel.
+    type Channel<'a>: FrameIo
+    where
+        Self: 'a;
+
+    /// Number of channels the device provides.
+    fn channel_count(&self) -> usize;
+
+    /// Borrow channel `index` (zero-based).
+    fn channel(&mut self, index: usize) -> Result<Self::Channel<'_>, Self::Error>;
+}
+
+/// Open further independent handles on one physical interface.
+///
+/// Some backends multiplex a device into several handles, each with its own acceptance filters
+/// and receive queue, that all see the whole bus: every SocketCAN socket on a network interface,
+/// or every handle a host driver hands out for a gs_usb channel. Components such as a logger, a
+/// diagnostic stack and the application can then each own a handle instead of sharing one.
+pub trait CloneChannel: Sized {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Open another handle on the same interface.
+    ///
+    /// The new handle starts with the backend's default configuration rather than a copy of this
+    /// handle's filters and modes. Whether frames sent through one handle are received by the
+    /// others is backend-specific.
+    fn clone_channel(&self) -> Result<Self, Self::Error>;
+}
+
+/// Open owned handles on the channels of a multi-channel device.
+///
+/// The owned counterpart of [`MultiChannel`]: channels do not borrow the device, so they can be
+/// moved to different tasks, and a backend may allow several handles on the same channel (see
+/// [`CloneChannel`]).
+pub trait OpenChannel {
+    /// Error returned by the driver implementation (e.g. invalid channel index).
+    type Error;
+
+    /// Handle for a single channel.
+    type Channel: FrameIo;
+
+    /// Number of channels the device provides.
+    fn channel_count(&self) -> usize;
+
+    /// Open a handle on channel `index` (zero-based).
+    fn open_channel(&self, index: usize) -> Result<Self::Channel, Self::Error>;
+}
+
 /// Configure acceptance filters (aka “hardware filtering”).
 ///
 /// CAN controllers often provide a fixed number of acceptance filter “banks”. Protocol layers may
@@ -288,6 +1025,111 @@ pub trait FilterConfig {
     fn modify_filters(&mut self) -> Self::FiltersHandle<'_>;
 }
 
+/// Acceptance filters with a capacity known at compile time.
+///
+/// When the filter list is fixed, as it usually is on an ECU, [`set_filters_const`] turns
+/// exceeding the hardware's filter banks into a build error instead of an error at boot:
+///
+/// ```rust,ignore
+/// use embedded_can_interface::ConstFilterConfig;
+///
+/// can.set_filters_const(&[heartbeat_filter, command_filter])?;
+/// ```
+///
+/// Other causes of [`FilterConfig::set_filters`] errors, such as filters the hardware cannot
+/// represent, are still reported at runtime.
+///
+/// [`set_filters_const`]: ConstFilterConfig::set_filters_const
+pub trait ConstFilterConfig: FilterConfig {
+    /// Largest number of filters the hardware can hold at once.
+    const MAX_FILTERS: usize;
+
+    /// Replace the current filter configuration with a fixed-size list.
+    ///
+    /// Fails to compile if `N` exceeds [`ConstFilterConfig::MAX_FILTERS`].
+    fn set_filters_const<const N: usize>(
+        &mut self,
+        filters: &[IdMaskFilter; N],
+    ) -> Result<(), Self::Error>
+    where
+        Self: Sized,
+    {
+        const {
+            assert!(
+                N <= Self::MAX_FILTERS,
+                "more filters than the interface can hold"
+            )
+        };
+        self.set_filters(filters)
+    }
+}
+
+/// Configure acceptance filters that can also match on payload bytes.
+///
+/// Implemented by controllers whose filters can compare data bytes, and by the software
+/// [`filter::SoftwareFilter`] wrapper for everything else. See [`filter::FrameFilter`].
+pub trait FrameFilterConfig {
+    /// Error returned by the implementation.
+    type Error;
+
+    /// Replace the current filter configuration. A frame is accepted if any filter accepts it; an
+    /// empty list accepts every frame.
+    fn set_frame_filters(&mut self, filters: &[filter::FrameFilter]) -> Result<(), Self::Error>;
+}
+
+/// Dyn-compatible subset of [`FilterConfig`].
+///
+/// [`FilterConfig`] has a generic associated handle type and cannot be used as a trait object.
+/// This trait exposes just the list-based configuration and is implemented for every
+/// [`FilterConfig`]; see [`AsCapabilities::as_filter_config`].
+pub trait DynFilterConfig {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Replace the current filter configuration; see [`FilterConfig::set_filters`].
+    fn apply_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error>;
+}
+
+impl<T: FilterConfig> DynFilterConfig for T {
+    type Error = T::Error;
+
+    fn apply_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.set_filters(filters)
+    }
+}
+
+/// Set of occupied transmit mailboxes or queue slots, as returned by [`TxRxState::pending_tx`].
+///
+/// Bit `n` is set when slot `n` holds a frame awaiting transmission. Slot numbering is defined by
+/// the driver, typically the hardware mailbox index; up to 32 slots can be represented.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PendingMask(pub u32);
+
+impl PendingMask {
+    /// No slot is pending.
+    pub const EMPTY: Self = Self(0);
+
+    /// Returns `true` if `slot` holds a pending frame.
+    pub const fn is_pending(self, slot: u8) -> bool {
+        slot < 32 && self.0 & (1 << slot) != 0
+    }
+
+    /// Number of pending slots.
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns `true` if no slot is pending.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Pending slot numbers, in ascending order.
+    pub fn slots(self) -> impl Iterator<Item = u8> {
+        (0..32).filter(move |&slot| self.is_pending(slot))
+    }
+}
+
 /// Inspect driver state related to transmit/receive operation.
 pub trait TxRxState {
     /// Error returned by the driver implementation.
@@ -297,6 +1139,540 @@ pub trait TxRxState {
     ///
     /// The exact definition of “idle” depends on the hardware/driver (e.g. all mailboxes empty).
     fn is_transmitter_idle(&self) -> Result<bool, Self::Error>;
+
+    /// Returns the number of frames the transmitter can currently accept without blocking, or
+    /// `None` if the driver cannot tell.
+    ///
+    /// Depending on the driver this counts free hardware mailboxes, free software queue slots, or
+    /// both. Schedulers can use it to batch up to the available capacity instead of probing with
+    /// [`TxFrameIo::try_send`] until it fails.
+    fn tx_free(&self) -> Result<Option<usize>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Returns which transmit mailboxes or queue slots currently hold a pending frame, or `None` if
+    /// the driver cannot tell.
+    ///
+    /// Schedulers replacing a stale cyclic frame can use this to target its slot (e.g. with a
+    /// driver-specific abort) instead of aborting all pending transmissions.
+    fn pending_tx(&self) -> Result<Option<PendingMask>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Returns the number of received frames waiting to be read, or `None` if the driver cannot
+    /// tell.
+    ///
+    /// This lets consumers drain exactly the available frames in a bounded loop (e.g. in an ISR or
+    /// cooperative scheduler) without calling [`RxFrameIo::try_recv`] until it reports “would
+    /// block”.
+    fn rx_pending(&self) -> Result<Option<usize>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Returns the number of receive overruns (frames lost to a full receive queue) since the
+    /// interface was opened, or `None` if the driver does not count them.
+    fn rx_overruns(&self) -> Result<Option<u32>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Frames `tx` can accept without blocking: [`TxRxState::tx_free`], or one slot if the driver
+/// does not count them and the transmitter is idle.
+pub(crate) fn tx_capacity<T: TxRxState + ?Sized>(tx: &T) -> Result<usize, T::Error> {
+    match tx.tx_free()? {
+        Some(free) => Ok(free),
+        None => Ok(usize::from(tx.is_transmitter_idle()?)),
+    }
+}
+
+/// Await driver state related to transmit operation.
+///
+/// This is the async counterpart of [`TxRxState`], for drivers that can wake a task when the state
+/// changes (e.g. from a “transmission complete” interrupt) instead of being polled.
+pub trait AsyncTxRxState {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Wait until the transmitter is idle (no frames pending).
+    ///
+    /// Use this to make sure the last frame has left the controller before powering down or
+    /// changing the bitrate. “Idle” has the same meaning as in
+    /// [`TxRxState::is_transmitter_idle`].
+    async fn wait_transmitter_idle(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Fault-confinement state of a CAN controller.
+///
+/// These follow the states defined by ISO 11898-1, plus the commonly reported “warning” level
+/// (an error counter has reached 96).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorState {
+    /// Normal operation; both error counters are below the warning level.
+    ErrorActive,
+    /// At least one error counter has reached the warning level (96).
+    ErrorWarning,
+    /// At least one error counter has exceeded 127; the node only sends passive error flags.
+    ErrorPassive,
+    /// The transmit error counter exceeded 255; the node has disconnected from the bus.
+    BusOff,
+}
+
+/// Transmit and receive error counters (TEC/REC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ErrorCounters {
+    /// Transmit error counter.
+    pub tx: u8,
+    /// Receive error counter.
+    pub rx: u8,
+}
+
+/// Inspect and recover the controller's bus (fault-confinement) state.
+pub trait BusState {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Returns the current fault-confinement state.
+    fn bus_state(&self) -> Result<ErrorState, Self::Error>;
+
+    /// Returns the current transmit/receive error counters.
+    fn error_counters(&self) -> Result<ErrorCounters, Self::Error>;
+
+    /// Request recovery from [`ErrorState::BusOff`].
+    ///
+    /// The controller still has to observe the bus-off recovery sequence (128 occurrences of 11
+    /// recessive bits) before it rejoins the bus, so recovery is not instantaneous. Drivers for
+    /// hardware with automatic recovery may treat this as a no-op.
+    fn recover_bus_off(&mut self) -> Result<(), Self::Error>;
+
+    /// Returns the most recent bus error seen by the controller (the “last error code”), or `None`
+    /// if there was none or the driver does not track it.
+    fn last_error(&self) -> Result<Option<embedded_can::ErrorKind>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Compact bus health summary returned by [`HealthCheck::health`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusHealth {
+    /// Fault-confinement state.
+    pub state: ErrorState,
+    /// Transmit/receive error counters.
+    pub counters: ErrorCounters,
+    /// Most recent bus error, if known.
+    pub last_error: Option<embedded_can::ErrorKind>,
+    /// Receive overruns since the interface was opened, if counted.
+    pub rx_overruns: Option<u32>,
+    /// `true` when no frames are waiting to be transmitted.
+    pub tx_idle: bool,
+    /// Free transmit slots, if known.
+    pub tx_free: Option<usize>,
+    /// Received frames waiting to be read, if known.
+    pub rx_pending: Option<usize>,
+}
+
+/// One-call health summary for supervisory tasks.
+///
+/// The provided implementation combines [`BusState`] and [`TxRxState`]; drivers only need an empty
+/// `impl HealthCheck for MyCan {}`, or can override [`HealthCheck::health`] when the hardware can
+/// report everything with fewer register reads.
+pub trait HealthCheck: BusState + TxRxState<Error = <Self as BusState>::Error> {
+    /// Collect a [`BusHealth`] snapshot.
+    fn health(&self) -> Result<BusHealth, <Self as BusState>::Error> {
+        Ok(BusHealth {
+            state: self.bus_state()?,
+            counters: self.error_counters()?,
+            last_error: self.last_error()?,
+            rx_overruns: self.rx_overruns()?,
+            tx_idle: self.is_transmitter_idle()?,
+            tx_free: self.tx_free()?,
+            rx_pending: self.rx_pending()?,
+        })
+    }
+}
+
+/// Operating state of a CAN interface, as seen by [`Lifecycle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleState {
+    /// The controller is disabled and does not participate in bus traffic.
+    Stopped,
+    /// The controller is in its configuration (init) mode; filters and bit timing may be changed.
+    Configuring,
+    /// The controller is participating in bus traffic.
+    Running,
+}
+
+/// Start, stop, and reset a CAN interface.
+///
+/// Many controllers (bxCAN, FDCAN/MCAN, MCP2515, …) only accept filter or bit-timing changes in a
+/// dedicated configuration mode. This trait gives middleware a portable way to sequence
+/// reconfiguration:
+///
+/// 1. [`Lifecycle::disable`] (→ [`LifecycleState::Configuring`] or [`LifecycleState::Stopped`])
+/// 2. apply changes (e.g. [`FilterConfig::set_filters`])
+/// 3. [`Lifecycle::enable`] (→ [`LifecycleState::Running`])
+///
+/// Implementations whose hardware can be reconfigured while running may accept configuration
+/// changes in any state; callers should not rely on that.
+pub trait Lifecycle {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Start participating in bus traffic.
+    fn enable(&mut self) -> Result<(), Self::Error>;
+
+    /// Stop participating in bus traffic.
+    ///
+    /// On hardware with a distinct configuration mode, the interface should enter
+    /// [`LifecycleState::Configuring`] so that configuration changes are accepted.
+    fn disable(&mut self) -> Result<(), Self::Error>;
+
+    /// Reset the controller to its power-on configuration.
+    ///
+    /// Pending frames are discarded. The resulting state is driver-specific; query it with
+    /// [`Lifecycle::state`].
+    fn reset(&mut self) -> Result<(), Self::Error>;
+
+    /// Returns the current operating state.
+    fn state(&self) -> Result<LifecycleState, Self::Error>;
+}
+
+/// Error types with a “device disconnected” case.
+///
+/// USB adapters, serial links and network bridges can disappear while open, e.g. when a cable is
+/// unplugged. Applications that should survive that match on this error and call
+/// [`Reconnectable::reopen`] until the device is back, instead of restarting.
+pub trait Disconnected {
+    /// Returns `true` if the operation failed because the device or link has gone away.
+    fn is_disconnected(&self) -> bool;
+}
+
+/// The connection-lost error kinds, and on Unix `ENODEV` and `ENXIO` (the device has gone).
+#[cfg(feature = "std")]
+impl Disconnected for std::io::Error {
+    fn is_disconnected(&self) -> bool {
+        use std::io::ErrorKind;
+
+        // `ENXIO` and `ENODEV`; the values are the same on every Unix.
+        if cfg!(unix) && matches!(self.raw_os_error(), Some(6 | 19)) {
+            return true;
+        }
+        matches!(
+            self.kind(),
+            ErrorKind::NotConnected
+                | ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NetworkDown
+        )
+    }
+}
+
+/// Reopen an interface whose device was disconnected.
+///
+/// [`Reconnectable::reopen`] releases the old device handle and opens the same device again with
+/// the parameters it was originally opened with, keeping the value (and any wrappers around it)
+/// in place. Whether configuration applied after opening, such as filters, survives is
+/// backend-specific.
+///
+/// ```rust,ignore
+/// use embedded_can_interface::{Disconnected, Reconnectable, RxFrameIo};
+///
+/// loop {
+///     match can.recv() {
+///         Ok(frame) => log(&frame),
+///         Err(e) if e.is_disconnected() => {
+///             while can.reopen().is_err() {
+///                 std::thread::sleep(Duration::from_secs(1));
+///             }
+///         }
+///         Err(e) => return Err(e),
+///     }
+/// }
+/// ```
+pub trait Reconnectable {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Open the device again; on error the interface stays unusable and may be retried.
+    fn reopen(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Program register-level bit timing.
+///
+/// This is the low-level counterpart of [`BitrateConfig`]: callers specify the prescaler and
+/// segment lengths directly (see [`BitTiming`]). Some controllers only accept timing changes in
+/// [`LifecycleState::Configuring`].
+pub trait BitTimingConfig {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Frequency of the clock feeding the bit-timing logic, in Hz.
+    ///
+    /// Use this with [`BitTiming::calculate`] to derive timings for a desired [`Bitrate`].
+    fn clock_hz(&self) -> u32;
+
+    /// Set the nominal (arbitration phase) bit timing.
+    fn set_bit_timing(&mut self, timing: &BitTiming) -> Result<(), Self::Error>;
+
+    /// Set the CAN FD data phase bit timing.
+    ///
+    /// Classic-only controllers should return an error.
+    fn set_data_bit_timing(&mut self, timing: &BitTiming) -> Result<(), Self::Error>;
+}
+
+/// Configure the bitrate by name.
+///
+/// This is the portable, high-level way to set up an interface at boot. Drivers that implement
+/// [`BitTimingConfig`] usually implement this trait by running [`BitTiming::calculate`] against
+/// their clock and limits and then programming the result.
+pub trait BitrateConfig {
+    /// Error returned by the driver implementation.
+    ///
+    /// Drivers should report an error if the requested rate cannot be produced from their clock.
+    type Error;
+
+    /// Set the nominal (arbitration phase) bitrate.
+    fn set_bitrate(&mut self, bitrate: Bitrate) -> Result<(), Self::Error>;
+
+    /// Set the CAN FD data phase bitrate.
+    ///
+    /// Classic-only controllers should return an error.
+    fn set_data_bitrate(&mut self, bitrate: Bitrate) -> Result<(), Self::Error>;
+}
+
+/// Describe an interface's identity and configuration.
+///
+/// Logging tools can stamp captures with this metadata, and health monitors can check it at
+/// startup to catch misconfiguration (e.g. wrong bitrate, FD expected but not available).
+pub trait InterfaceInfo {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Interface name (e.g. `can0`, or a board-specific label such as `CAN1`).
+    fn name(&self) -> &str;
+
+    /// Returns `true` if the interface supports CAN FD frames.
+    fn is_fd_capable(&self) -> bool;
+
+    /// Currently configured nominal bitrate, or `None` if not configured yet.
+    fn bitrate(&self) -> Result<Option<Bitrate>, Self::Error>;
+
+    /// Currently configured CAN FD data phase bitrate, or `None` if FD is not in use.
+    fn data_bitrate(&self) -> Result<Option<Bitrate>, Self::Error>;
+
+    /// Current operating state.
+    fn operating_state(&self) -> Result<LifecycleState, Self::Error>;
+}
+
+/// Report bus utilization.
+///
+/// Controllers or adapters that measure load in hardware implement this directly; otherwise
+/// [`busload::LoadMonitor`] estimates it in software from the frames passing through it.
+pub trait BusLoad {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Bus utilization over the implementation's measurement window, in percent (0.0–100.0).
+    fn bus_load(&self) -> Result<f32, Self::Error>;
+}
+
+/// A bus condition reported by [`BusEvents`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusEvent {
+    /// The fault-confinement state changed to the contained state.
+    StateChanged(ErrorState),
+    /// One or more received frames were lost because the receive queue was full.
+    RxOverrun,
+    /// A frame finished transmission successfully.
+    TxComplete,
+    /// The controller woke up from sleep due to bus activity.
+    WakeUp,
+    /// An error frame was detected or signalled, with the reason if known
+    /// ([`embedded_can::ErrorKind::Other`] otherwise).
+    ErrorFrame(embedded_can::ErrorKind),
+}
+
+/// Stream of bus events, alongside frame reception.
+///
+/// This lets applications react to bus conditions (state changes, overruns, wake-ups, …) without
+/// polling several status methods.
+pub trait BusEvents {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Wait for the next bus event.
+    ///
+    /// Drivers with limited event storage may coalesce repeated events of the same kind.
+    async fn next_event(&mut self) -> Result<BusEvent, Self::Error>;
+}
+
+/// Control listen-only (bus monitoring) mode.
+///
+/// In listen-only mode the controller receives frames but never drives the bus: it sends no ACKs,
+/// error frames, or data frames. This makes it safe to attach to a bus whose bitrate is unknown.
+pub trait ListenOnlyControl {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Enable or disable listen-only mode.
+    ///
+    /// Some controllers only accept this change in [`LifecycleState::Configuring`].
+    fn set_listen_only(&mut self, on: bool) -> Result<(), Self::Error>;
+}
+
+/// Control whether transmitted frames are echoed to the receive path.
+///
+/// Bridges and loggers usually want their own frames to appear on RX (so the receive stream is a
+/// complete picture of the bus); control applications usually do not. Echoed frames can be
+/// recognized via [`RxMeta::self_reception`] where the driver supports it.
+pub trait SelfReceptionControl {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Enable (`true`) or disable (`false`) self-reception.
+    fn set_self_reception(&mut self, on: bool) -> Result<(), Self::Error>;
+}
+
+/// Put the CAN controller to sleep and wake it up.
+///
+/// Sleep is a low-power mode in which the controller stops participating in bus traffic. Many
+/// controllers can be configured to wake automatically on bus activity.
+pub trait SleepControl {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Enter the controller's low-power sleep mode.
+    fn sleep(&mut self) -> Result<(), Self::Error>;
+
+    /// Leave sleep mode and resume normal operation.
+    fn wake_up(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Control a CAN transceiver's standby (STB) or enable (EN) input.
+///
+/// Transceivers draw significant current when active even if the controller is asleep, so putting
+/// a node to sleep usually means sleeping the controller *and* putting the transceiver into
+/// standby. See [`transceiver::Transceiver`] for a wrapper that coordinates both.
+pub trait TransceiverControl {
+    /// Error returned by the implementation (e.g. a GPIO error).
+    type Error;
+
+    /// Put the transceiver into standby (`true`) or normal mode (`false`).
+    fn set_standby(&mut self, standby: bool) -> Result<(), Self::Error>;
+}
+
+/// Switch a built-in 120 Ω bus termination resistor.
+///
+/// Common on USB adapters and some ECUs. Test tooling can use this to terminate the bus only when
+/// the interface sits at one of its ends.
+pub trait TerminationControl {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Enable (`true`) or disable (`false`) the termination resistor.
+    fn set_termination(&mut self, on: bool) -> Result<(), Self::Error>;
+}
+
+/// Automatic bitrate detection.
+///
+/// Backends with hardware support can implement this directly. Any backend exposing the necessary
+/// primitives can implement it with [`autobaud::detect_by_listening`].
+pub trait AutoBaud {
+    /// Error returned by the driver implementation.
+    ///
+    /// Implementations should return an error if none of the candidates could be confirmed.
+    type Error;
+
+    /// Detect which of `candidates` the bus is running at.
+    async fn detect_bitrate(&mut self, candidates: &[Bitrate]) -> Result<Bitrate, Self::Error>;
+}
+
+/// Runtime discovery of optional capabilities.
+///
+/// Generic code that holds an interface behind `dyn` (or that does not want to require every
+/// capability in its bounds) can probe for optional features at runtime. Each accessor returns
+/// `None` by default; drivers override the ones they support by returning `Some(self)`:
+///
+/// ```rust,ignore
+/// impl AsCapabilities for MyCan {
+///     type Error = MyError;
+///
+///     fn as_bus_state(&mut self) -> Option<&mut dyn BusState<Error = MyError>> {
+///         Some(self)
+///     }
+/// }
+/// ```
+///
+/// All capabilities share the interface's error type.
+pub trait AsCapabilities {
+    /// Error type shared by the exposed capabilities.
+    type Error;
+
+    /// Acceptance filter configuration.
+    fn as_filter_config(&mut self) -> Option<&mut dyn DynFilterConfig<Error = Self::Error>> {
+        None
+    }
+
+    /// Bus (fault-confinement) state and recovery.
+    fn as_bus_state(&mut self) -> Option<&mut dyn BusState<Error = Self::Error>> {
+        None
+    }
+
+    /// Transmit/receive queue state.
+    fn as_tx_rx_state(&mut self) -> Option<&mut dyn TxRxState<Error = Self::Error>> {
+        None
+    }
+
+    /// Enable/disable/reset sequencing.
+    fn as_lifecycle(&mut self) -> Option<&mut dyn Lifecycle<Error = Self::Error>> {
+        None
+    }
+
+    /// Bitrate configuration.
+    fn as_bitrate_config(&mut self) -> Option<&mut dyn BitrateConfig<Error = Self::Error>> {
+        None
+    }
+
+    /// Bus load reporting.
+    fn as_bus_load(&mut self) -> Option<&mut dyn BusLoad<Error = Self::Error>> {
+        None
+    }
+}
+
+/// Monotonic time source.
+///
+/// Wrappers and helpers in this crate that need to measure elapsed time take a `Clock` rather than
+/// assuming a platform timer. The returned value is the time elapsed since an arbitrary, fixed
+/// epoch (e.g. boot); only differences between readings are meaningful.
+pub trait Clock {
+    /// Returns the current time since the clock's epoch.
+    fn now(&self) -> Duration;
+
+    /// Busy-wait until the clock reaches `until`.
+    fn spin_until(&self, until: Duration) {
+        while self.now() < until {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// Manipulate driver-side frame queues.
+pub trait QueueControl {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Discard all pending received frames, returning how many were dropped.
+    ///
+    /// Protocols that start a new session (e.g. after an ECU reset request) can use this to flush
+    /// stale traffic in one operation. Where the hardware allows it, implementations should clear
+    /// the queue atomically rather than looping over [`RxFrameIo::try_recv`].
+    fn clear_rx(&mut self) -> Result<usize, Self::Error>;
 }
 
 /// Control blocking vs nonblocking behavior.
@@ -341,6 +1717,41 @@ pub trait BufferedIo {
     ) -> Self::Buffered<'a, TX, RX>;
 }
 
+/// Portable interface configuration, applied when opening with [`ConfigBinding::open_with`].
+///
+/// Fields left at their defaults (`None`, no filters) keep the backend's own defaults,
+/// so a configuration only needs to name what it cares about:
+///
+/// ```rust,ignore
+/// use embedded_can_interface::{Bitrate, CanConfig, ConfigBinding};
+///
+/// let config = CanConfig {
+///     bitrate: Some(Bitrate::Kbps500),
+///     filters: &[heartbeat_filter],
+///     ..CanConfig::default()
+/// };
+/// let can = MyCan::open_with("can0", &config)?;
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CanConfig<'a> {
+    /// Nominal (arbitration phase) bitrate; see [`BitrateConfig::set_bitrate`].
+    pub bitrate: Option<Bitrate>,
+    /// CAN FD data phase bitrate; see [`BitrateConfig::set_data_bitrate`]. Setting it asks for an
+    /// FD-capable interface.
+    pub data_bitrate: Option<Bitrate>,
+    /// Open in listen-only mode; see [`ListenOnlyControl`].
+    pub listen_only: Option<bool>,
+    /// Echo transmitted frames to the receive path; see [`SelfReceptionControl`].
+    pub self_reception: Option<bool>,
+    /// Acceptance filters to install before the interface starts; an empty list accepts every
+    /// frame.
+    pub filters: &'a [IdMaskFilter],
+    /// Transmit queue length, in frames, where the backend's queue is configurable.
+    pub tx_queue_len: Option<usize>,
+    /// Receive queue length, in frames, where the backend's queue is configurable.
+    pub rx_queue_len: Option<usize>,
+}
+
 /// Constructors/binding helpers.
 ///
 /// This is an optional trait for backends that can be opened by name (e.g. `can0`) or configured via
@@ -356,4 +1767,54 @@ pub trait BuilderBinding: Sized {
 
     /// Create a builder that can configure before constructing the driver.
     fn builder() -> Self::Builder;
+
+    /// Open by interface name and enter configuration mode, tracked in the type system; see
+    /// [`typestate`].
+    fn open_configured(
+        name: &str,
+    ) -> Result<typestate::Configured<Self>, <Self as BuilderBinding>::Error>
+    where
+        Self: Lifecycle<Error = <Self as BuilderBinding>::Error>,
+    {
+        typestate::Configured::new(Self::open(name)?).map_err(|e| e.error)
+    }
+}
+
+/// Open with a [`CanConfig`].
+///
+/// This extends [`BuilderBinding`] for backends that can apply a configuration while opening.
+/// Implementing it is a promise to honour every field that is set, or to fail: a backend must
+/// report an error rather than open with settings it cannot apply.
+pub trait ConfigBinding: BuilderBinding {
+    /// Open/bind by interface name and apply `config` before the interface starts.
+    fn open_with(name: &str, config: &CanConfig<'_>) -> Result<Self, Self::Error>;
+
+    /// Open/bind by connection string, e.g. `socketcan:can0?bitrate=500000`.
+    ///
+    /// The default implementation calls [`ConfigBinding::open_with`] with the address's path and
+    /// standard parameters; backends with parameters of their own override it. Checking that the
+    /// scheme names this backend is left to the caller.
+    fn open_address(address: &address::InterfaceAddress<'_>) -> Result<Self, Self::Error> {
+        Self::open_with(address.path(), &address.config())
+    }
+}
+
+/// Async counterpart of [`BuilderBinding`], for backends whose initialization awaits the
+/// hardware.
+///
+/// Opening a USB adapter may wait for enumeration, a remote bridge for its handshake, and a
+/// controller for bus-off recovery before it starts. Doing that in a blocking
+/// [`BuilderBinding::open`] stalls the executor; these methods await it instead.
+pub trait AsyncBuilderBinding: Sized {
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Open/bind by interface name (SocketCAN-style).
+    async fn open(name: &str) -> Result<Self, Self::Error>;
+
+    /// Open/bind by interface name and apply `config` before the interface starts.
+    ///
+    /// As with [`ConfigBinding::open_with`], every field that is set must be honoured, or the call
+    /// must fail.
+    async fn open_with(name: &str, config: &CanConfig<'_>) -> Result<Self, Self::Error>;
 }
@@ -0,0 +1,223 @@
+//! Waiting for a particular frame.
+//!
+//! Request/response code repeatedly needs “receive until a frame matching this filter arrives, or
+//! time out”, and the details — whether the timeout covers the whole wait, and what happens to
+//! unrelated frames received meanwhile — tend to differ from one hand-written loop to the next.
+//! [`RecvMatching`] and [`AsyncRecvMatching`] provide that loop once, for every receive interface:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::matching::{Discard, RecvMatching};
+//!
+//! let response = can.recv_matching(&filter, Duration::from_millis(50), &clock, &mut Discard)?;
+//! ```
+//!
+//! [`recv_from`](RecvMatching::recv_from) covers the most common case, waiting for one exact
+//! identifier.
+//!
+//! The timeout bounds the whole call. Frames that do not match are handed to an [`OnMismatch`]
+//! policy: [`Discard`] drops them, and [`Keep`] stores them in a [`FrameQueue`] for later
+//! processing.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+use crate::buffered::FrameQueue;
+use crate::{AsyncRxFrameIo, Clock, Id, IdMask, IdMaskFilter, RxFrameIo};
+
+/// Error returned while waiting for a matching frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// No matching frame arrived within the timeout.
+    Timeout,
+}
+
+impl<E> fmt::Display for RecvError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Timeout => f.write_str("no matching frame within the timeout"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for RecvError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with frames that arrive while waiting for a matching one.
+pub trait OnMismatch<F> {
+    /// Dispose of a frame that did not match.
+    fn mismatched(&mut self, frame: F);
+}
+
+/// Drop frames that do not match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Discard;
+
+impl<F> OnMismatch<F> for Discard {
+    fn mismatched(&mut self, _frame: F) {}
+}
+
+/// Keep frames that do not match in a [`FrameQueue`], in arrival order.
+///
+/// When the queue is full, further frames are dropped.
+#[derive(Debug)]
+pub struct Keep<'q, Q>(pub &'q mut Q);
+
+impl<F, Q: FrameQueue<F>> OnMismatch<F> for Keep<'_, Q> {
+    fn mismatched(&mut self, frame: F) {
+        let _ = self.0.push(frame);
+    }
+}
+
+/// Filter accepting data and remote frames with exactly `id`.
+fn exact(id: Id) -> IdMaskFilter {
+    let mask = match id {
+        Id::Standard(_) => IdMask::Standard(0x7FF),
+        Id::Extended(_) => IdMask::Extended(0x1FFF_FFFF),
+    };
+    IdMaskFilter::new(id, mask)
+}
+
+/// Blocking receive of the next frame accepted by a filter.
+///
+/// Implemented for every [`RxFrameIo`].
+pub trait RecvMatching: RxFrameIo {
+    /// Receive the next frame `filter` accepts within `timeout`, passing other frames to
+    /// `mismatched`.
+    fn recv_matching<C, P>(
+        &mut self,
+        filter: &IdMaskFilter,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>;
+
+    /// Receive the next frame with identifier `id` (data or remote) within `timeout`, passing
+    /// other frames to `mismatched`.
+    fn recv_from<C, P>(
+        &mut self,
+        id: impl Into<Id>,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>,
+    {
+        self.recv_matching(&exact(id.into()), timeout, clock, mismatched)
+    }
+}
+
+impl<T> RecvMatching for T
+where
+    T: RxFrameIo,
+    T::Frame: Frame,
+{
+    fn recv_matching<C, P>(
+        &mut self,
+        filter: &IdMaskFilter,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>,
+    {
+        let deadline = clock.now() + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(clock.now());
+            if remaining.is_zero() {
+                return Err(RecvError::Timeout);
+            }
+            match self.recv_timeout(remaining) {
+                Ok(frame) if filter.accepts(&frame) => return Ok(frame),
+                Ok(frame) => mismatched.mismatched(frame),
+                Err(_) if clock.now() >= deadline => return Err(RecvError::Timeout),
+                Err(e) => return Err(RecvError::Io(e)),
+            }
+        }
+    }
+}
+
+/// Async receive of the next frame accepted by a filter.
+///
+/// Implemented for every [`AsyncRxFrameIo`].
+pub trait AsyncRecvMatching: AsyncRxFrameIo {
+    /// Receive the next frame `filter` accepts within `timeout`, passing other frames to
+    /// `mismatched`.
+    async fn recv_matching<C, P>(
+        &mut self,
+        filter: &IdMaskFilter,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>;
+
+    /// Receive the next frame with identifier `id` (data or remote) within `timeout`, passing
+    /// other frames to `mismatched`.
+    async fn recv_from<C, P>(
+        &mut self,
+        id: impl Into<Id>,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>,
+    {
+        self.recv_matching(&exact(id.into()), timeout, clock, mismatched)
+            .await
+    }
+}
+
+impl<T> AsyncRecvMatching for T
+where
+    T: AsyncRxFrameIo,
+    T::Frame: Frame,
+{
+    async fn recv_matching<C, P>(
+        &mut self,
+        filter: &IdMaskFilter,
+        timeout: Duration,
+        clock: &C,
+        mismatched: &mut P,
+    ) -> Result<Self::Frame, RecvError<Self::Error>>
+    where
+        C: Clock,
+        P: OnMismatch<Self::Frame>,
+    {
+        let deadline = clock.now() + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(clock.now());
+            if remaining.is_zero() {
+                return Err(RecvError::Timeout);
+            }
+            match self.recv_timeout(remaining).await {
+                Ok(frame) if filter.accepts(&frame) => return Ok(frame),
+                Ok(frame) => mismatched.mismatched(frame),
+                Err(_) if clock.now() >= deadline => return Err(RecvError::Timeout),
+                Err(e) => return Err(RecvError::Io(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Adapter for the MCP2515 SPI CAN controller driver of the [`mcp2515`] crate (requires the
+//! `mcp2515` feature).
+//!
+//! [`Mcp2515`] wraps an initialised [`MCP2515`] driver and implements:
+//!
+//! - [`TxFrameIo`] and [`RxFrameIo`] (and so [`FrameIo`](crate::FrameIo));
+//! - [`TxRxState`], from the controller's status register;
+//! - [`FilterConfig`], mapping filters onto the two masks and six acceptance filters;
+//! - [`BitTimingConfig`] and [`BitrateConfig`], programming CNF1–CNF3.
+//!
+//! With the `embedded-hal-async` feature, [`Mcp2515::into_interrupt_rx`] turns it into an
+//! [`InterruptRx`](crate::interrupt::InterruptRx) that awaits the `INT` pin instead of polling over
+//! SPI.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::{BitrateConfig, Bitrate, FilterConfig};
+//! use embedded_can_interface::mcp2515_io::Mcp2515;
+//!
+//! let mut driver = mcp2515::MCP2515::new(spi);
+//! driver.init(&mut delay, settings)?;
+//! let mut can = Mcp2515::new(driver, 16_000_000);
+//! can.set_bitrate(Bitrate::Kbps500)?;
+//! can.set_filters(&filters)?;
+//! ```
+//!
+//! The driver has no timer, so `send_timeout`/`recv_timeout` block like `send`/`recv`, and the
+//! blocking operations poll the controller over SPI.
+
+use core::cell::RefCell;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_hal::spi::SpiDevice;
+use mcp2515::MCP2515;
+use mcp2515::error::Error;
+use mcp2515::filter::{RxFilter, RxMask};
+use mcp2515::frame::CanFrame;
+use mcp2515::regs::{CanInte, CanStat, Cnf1, Cnf2, Cnf3, OpMode};
+
+use crate::timing::{BitTiming, BitTimingLimits, Bitrate};
+use crate::{
+    BitTimingConfig, BitrateConfig, ConstFilterConfig, FilterConfig, Id, IdMask, IdMaskFilter,
+    IdTypes, PendingMask, RxFrameIo, TxFrameIo, TxRxState,
+};
+
+/// Bit-timing limits of the MCP2515, in units of the bit-timing clock (half the oscillator).
+///
+/// Phase segment 2 must also be at least two time quanta, no longer than `seg1`, and longer than
+/// SJW; [`Mcp2515`] checks these separately.
+pub const LIMITS: BitTimingLimits = BitTimingLimits {
+    max_prescaler: 64,
+    max_seg1: 16,
+    max_seg2: 8,
+    max_sjw: 4,
+};
+
+/// Error returned when configuring filters or bit timing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mcp2515ConfigError<E> {
+    /// The driver reported an error.
+    Io(E),
+    /// More than six filters were given, or more than two filters for the buffer with the
+    /// two-filter mask.
+    TooManyFilters,
+    /// The filters need more than the two masks the controller has.
+    TooManyMasks,
+    /// A filter's identifier and mask have different widths.
+    MixedIdTypes,
+    /// A filter uses [`IdTypes::Both`]; each MCP2515 filter matches one identifier type.
+    UnsupportedIdTypes,
+    /// The bit timing or bitrate cannot be produced by the controller.
+    UnsupportedTiming,
+}
+
+impl<E> fmt::Display for Mcp2515ConfigError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::TooManyFilters => f.write_str("filters do not fit the acceptance filters"),
+            Self::TooManyMasks => f.write_str("filters need more than two masks"),
+            Self::MixedIdTypes => f.write_str("filter identifier and mask have different widths"),
+            Self::UnsupportedIdTypes => f.write_str("filters cannot match both identifier types"),
+            Self::UnsupportedTiming => f.write_str("bit timing not supported by the controller"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Mcp2515ConfigError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<E> From<E> for Mcp2515ConfigError<E> {
+    fn from(error: E) -> Self {
+        Mcp2515ConfigError::Io(error)
+    }
+}
+
+/// MCP2515 driver with the traits of this crate.
+///
+/// The driver needs `&mut` access for every SPI transaction, including status reads, so it is
+/// kept in a [`RefCell`] to implement the `&self` methods of [`TxRxState`].
+///
+/// Frames are sent one at a time: the controller sends loaded TX buffers of equal priority
+/// highest-numbered first, so loading several at once would reorder frames queued back to back.
+/// [`TxRxState::tx_free`] is accordingly 1 while the transmitter is idle and 0 otherwise.
+pub struct Mcp2515<SPI> {
+    dev: RefCell<MCP2515<SPI>>,
+    osc_hz: u32,
+}
+
+impl<SPI: SpiDevice> Mcp2515<SPI> {
+    /// Wrap a driver on which [`MCP2515::init`] has been called; `osc_hz` is the frequency of the
+    /// controller's oscillator.
+    pub fn new(dev: MCP2515<SPI>, osc_hz: u32) -> Self {
+        Self {
+            dev: RefCell::new(dev),
+            osc_hz,
+        }
+    }
+
+    /// Mutably borrow the wrapped driver.
+    pub fn inner_mut(&mut self) -> &mut MCP2515<SPI> {
+        self.dev.get_mut()
+    }
+
+    /// Unwrap, returning the wrapped driver.
+    pub fn into_inner(self) -> MCP2515<SPI> {
+        self.dev.into_inner()
+    }
+
+    /// Let the `INT` pin signal received frames only.
+    ///
+    /// [`MCP2515::init`] also enables the error interrupts, whose flags nothing in this crate
+    /// clears; a level-triggered waiter such as [`InterruptRx`](crate::interrupt::InterruptRx)
+    /// would then see the pin asserted forever.
+    pub fn rx_interrupts_only(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.dev
+            .get_mut()
+            .write_register(CanInte::new().with_rx0ie(true).with_rx1ie(true))
+    }
+
+    /// Enable only the receive interrupts and wait for frames on the `INT` pin (active low).
+    ///
+    /// `delay` implements [`AsyncRxFrameIo::recv_timeout`](crate::AsyncRxFrameIo::recv_timeout).
+    #[cfg(feature = "embedded-hal-async")]
+    pub fn into_interrupt_rx<P, D>(
+        mut self,
+        int: P,
+        delay: D,
+    ) -> Result<crate::interrupt::InterruptRx<Self, P, D>, Error<SPI::Error>> {
+        self.rx_interrupts_only()?;
+        Ok(crate::interrupt::InterruptRx::new(
+            self,
+            int,
+            delay,
+            crate::interrupt::IrqLevel::Low,
+        ))
+    }
+
+    /// Run `configure` in configuration mode, then return to the previous mode.
+    fn configure<T>(
+        &mut self,
+        configure: impl FnOnce(&mut MCP2515<SPI>) -> Result<T, Mcp2515ConfigError<Error<SPI::Error>>>,
+    ) -> Result<T, Mcp2515ConfigError<Error<SPI::Error>>> {
+        let dev = self.dev.get_mut();
+        let status: CanStat = dev.read_register()?;
+        let mode = status.opmod_or_err().unwrap_or(OpMode::Normal);
+        dev.set_mode(OpMode::Configuration)?;
+        let result = configure(dev);
+        dev.set_mode(mode)?;
+        result
+    }
+}
+
+impl<SPI: SpiDevice> TxFrameIo for Mcp2515<SPI> {
+    type Frame = CanFrame;
+    type Error = Error<SPI::Error>;
+
+    fn send(&mut self, frame: &CanFrame) -> Result<(), Self::Error> {
+        loop {
+            match self.try_send(frame) {
+                Err(Error::TxBusy) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns [`Error::TxBusy`] while a frame is pending.
+    fn try_send(&mut self, frame: &CanFrame) -> Result<(), Self::Error> {
+        if !self.is_transmitter_idle()? {
+            return Err(Error::TxBusy);
+        }
+        self.dev.get_mut().send_message(*frame)
+    }
+
+    fn send_timeout(&mut self, frame: &CanFrame, _timeout: Duration) -> Result<(), Self::Error> {
+        self.send(frame)
+    }
+}
+
+impl<SPI: SpiDevice> RxFrameIo for Mcp2515<SPI> {
+    type Frame = CanFrame;
+    type Error = Error<SPI::Error>;
+
+    fn recv(&mut self) -> Result<CanFrame, Self::Error> {
+        loop {
+            match self.try_recv() {
+                Err(Error::NoMessage) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns [`Error::NoMessage`] if both receive buffers are empty.
+    fn try_recv(&mut self) -> Result<CanFrame, Self::Error> {
+        self.dev.get_mut().read_message()
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<CanFrame, Self::Error> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        while self.rx_pending()? == Some(0) {}
+        Ok(())
+    }
+}
+
+/// TX buffer `n` is slot `n` of [`TxRxState::pending_tx`]. `rx_pending` counts the full receive
+/// buffers, so it is at most 2.
+///
+/// # Panics
+///
+/// The methods borrow the driver, so they panic if called re-entrantly, e.g. from an interrupt
+/// handler while another method is running.
+impl<SPI: SpiDevice> TxRxState for Mcp2515<SPI> {
+    type Error = Error<SPI::Error>;
+
+    fn is_transmitter_idle(&self) -> Result<bool, Self::Error> {
+        let status = self.dev.borrow_mut().read_status()?;
+        Ok(!(status.tx0req() || status.tx1req() || status.tx2req()))
+    }
+
+    fn tx_free(&self) -> Result<Option<usize>, Self::Error> {
+        Ok(Some(usize::from(self.is_transmitter_idle()?)))
+    }
+
+    fn pending_tx(&self) -> Result<Option<PendingMask>, Self::Error> {
+        let status = self.dev.borrow_mut().read_status()?;
+        let pending = [status.tx0req(), status.tx1req(), status.tx2req()];
+        Ok(Some(PendingMask(
+            pending
+                .iter()
+                .enumerate()
+                .filter(|(_, pending)| **pending)
+                .fold(0, |mask, (slot, _)| mask | 1 << slot),
+        )))
+    }
+
+    fn rx_pending(&self) -> Result<Option<usize>, Self::Error> {
+        let status = self.dev.borrow_mut().read_status()?;
+        Ok(Some(
+            usize::from(status.rx0if()) + usize::from(status.rx1if()),
+        ))
+    }
+}
+
+/// Mask register value of `filter`, and the identifier for its filter register.
+///
+/// The mask is in the layout of an extended identifier (standard bits above the 18 extended
+/// bits), so a standard filter and an extended filter comparing the same standard bits share it.
+fn mask_and_id<E>(filter: &IdMaskFilter) -> Result<(u32, embedded_can::Id), Mcp2515ConfigError<E>> {
+    if filter.id_types == IdTypes::Both {
+        return Err(Mcp2515ConfigError::UnsupportedIdTypes);
+    }
+    match (filter.id, filter.mask) {
+        (Id::Standard(id), IdMask::Standard(mask)) => Ok((
+            u32::from(mask & 0x7FF) << 18,
+            embedded_can::Id::Standard(id),
+        )),
+        (Id::Extended(id), IdMask::Extended(mask)) => {
+            Ok((mask & 0x1FFF_FFFF, embedded_can::Id::Extended(id)))
+        }
+        _ => Err(Mcp2515ConfigError::MixedIdTypes),
+    }
+}
+
+const ANY_STANDARD: embedded_can::Id = embedded_can::Id::Standard(embedded_can::StandardId::ZERO);
+const ANY_EXTENDED: embedded_can::Id = embedded_can::Id::Extended(embedded_can::ExtendedId::ZERO);
+
+/// Identifier whose bits are the mask register value `mask`.
+fn mask_id(mask: u32) -> embedded_can::Id {
+    // `mask_and_id` keeps masks within 29 bits.
+    embedded_can::Id::Extended(
+        embedded_can::ExtendedId::new(mask).unwrap_or(embedded_can::ExtendedId::MAX),
+    )
+}
+
+/// Maps filters onto the controller's two receive buffers: RXB0 has mask 0 and filters 0–1,
+/// RXB1 has mask 1 and filters 2–5.
+///
+/// Filters with the same mask share a buffer, so a list may use at most two distinct masks, with
+/// at most two filters on one of them and four on the other. Unused filter slots repeat a filter of
+/// the same buffer. An empty list restores the accept-all configuration of [`MCP2515::init`].
+///
+/// The filters cannot tell data and remote frames apart, so [`FrameTypes`](crate::FrameTypes) is
+/// ignored and the controller may deliver frames the list rejects; wrap the interface in
+/// [`SoftwareFilter`](crate::filter::SoftwareFilter) when that matters. [`IdTypes::Both`] is
+/// rejected. The list is checked before any register is written; the registers are written in
+/// configuration mode, after which the previous mode is restored.
+impl<SPI: SpiDevice> FilterConfig for Mcp2515<SPI> {
+    type Error = Mcp2515ConfigError<Error<SPI::Error>>;
+    type FiltersHandle<'a>
+        = &'a mut MCP2515<SPI>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        if filters.len() > 6 {
+            return Err(Mcp2515ConfigError::TooManyFilters);
+        }
+        let mut entries = [(0, ANY_STANDARD); 6];
+        let mut masks = [0u32; 2];
+        let mut mask_count = 0;
+        for (entry, filter) in entries.iter_mut().zip(filters) {
+            *entry = mask_and_id(filter)?;
+            if !masks[..mask_count].contains(&entry.0) {
+                if mask_count == 2 {
+                    return Err(Mcp2515ConfigError::TooManyMasks);
+                }
+                masks[mask_count] = entry.0;
+                mask_count += 1;
+            }
+        }
+        let entries = &entries[..filters.len()];
+
+        // Filter identifiers of RXB0 and RXB1.
+        let mut buffers = [([ANY_STANDARD; 4], 0usize); 2];
+        let mut add = |buffer: usize, id| {
+            let (ids, len) = &mut buffers[buffer];
+            ids[*len] = id;
+            *len += 1;
+        };
+        if mask_count == 1 {
+            masks[1] = masks[0];
+            for (index, (_, id)) in entries.iter().enumerate() {
+                add(usize::from(index >= 2), *id);
+            }
+        } else if mask_count == 2 {
+            let count = |mask| entries.iter().filter(|(m, _)| *m == mask).count();
+            if count(masks[0]) > 2 || count(masks[1]) > 4 {
+                masks.swap(0, 1);
+            }
+            if count(masks[0]) > 2 || count(masks[1]) > 4 {
+                return Err(Mcp2515ConfigError::TooManyFilters);
+            }
+            for (mask, id) in entries {
+                add(usize::from(*mask != masks[0]), *id);
+            }
+        }
+        if buffers[1].1 == 0 {
+            buffers[1] = buffers[0];
+        }
+
+        self.configure(|dev| {
+            if mask_count == 0 {
+                for filter in RxFilter::ALL {
+                    let id = if filter == RxFilter::F1 {
+                        ANY_EXTENDED
+                    } else {
+                        ANY_STANDARD
+                    };
+                    dev.set_filter(filter, id)?;
+                }
+                for mask in RxMask::ALL {
+                    dev.set_mask(mask, ANY_EXTENDED)?;
+                }
+                return Ok(());
+            }
+            dev.set_mask(RxMask::Mask0, mask_id(masks[0]))?;
+            dev.set_mask(RxMask::Mask1, mask_id(masks[1]))?;
+            let slots = [
+                (RxFilter::F0, 0, 0),
+                (RxFilter::F1, 0, 1),
+                (RxFilter::F2, 1, 0),
+                (RxFilter::F3, 1, 1),
+                (RxFilter::F4, 1, 2),
+                (RxFilter::F5, 1, 3),
+            ];
+            for (filter, buffer, slot) in slots {
+                let (ids, len) = &buffers[buffer];
+                dev.set_filter(filter, ids[slot % len])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The driver itself; note that filter registers can only be written in configuration mode.
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        self.dev.get_mut()
+    }
+}
+
+/// Six acceptance filters; how many of them a list may use also depends on its masks.
+impl<SPI: SpiDevice> ConstFilterConfig for Mcp2515<SPI> {
+    const MAX_FILTERS: usize = 6;
+}
+
+/// The bit-timing clock is half the oscillator frequency. Timings must respect [`LIMITS`] and the
+/// controller's extra rules (phase segment 2 of at least two quanta, no longer than `seg1`, and
+/// longer than SJW); `seg1` is split evenly between the propagation and phase 1 segments.
+impl<SPI: SpiDevice> BitTimingConfig for Mcp2515<SPI> {
+    type Error = Mcp2515ConfigError<Error<SPI::Error>>;
+
+    fn clock_hz(&self) -> u32 {
+        self.osc_hz / 2
+    }
+
+    fn set_bit_timing(&mut self, timing: &BitTiming) -> Result<(), Self::Error> {
+        let BitTiming {
+            prescaler,
+            seg1,
+            seg2,
+            sjw,
+        } = *timing;
+        if prescaler == 0
+            || prescaler > LIMITS.max_prescaler
+            || !(2..=LIMITS.max_seg1).contains(&seg1)
+            || !(2..=LIMITS.max_seg2).contains(&seg2)
+            || seg1 < seg2
+            || sjw == 0
+            || sjw >= seg2
+            || sjw > LIMITS.max_sjw
+        {
+            return Err(Mcp2515ConfigError::UnsupportedTiming);
+        }
+        let phase1 = seg1 / 2;
+        let propagation = seg1 - phase1;
+        let cnf1 = Cnf1::from_bytes([(sjw - 1) << 6 | (prescaler - 1) as u8]);
+        // BTLMODE: phase segment 2 is taken from CNF3.
+        let cnf2 = Cnf2::from_bytes([0x80 | (phase1 - 1) << 3 | (propagation - 1)]);
+        self.configure(|dev| {
+            let cnf3: Cnf3 = dev.read_register()?;
+            dev.write_register(cnf1)?;
+            dev.write_register(cnf2)?;
+            dev.write_register(cnf3.with_phseg2(seg2 - 1))?;
+            Ok(())
+        })
+    }
+
+    /// Returns [`Mcp2515ConfigError::UnsupportedTiming`]; the MCP2515 is a classic CAN controller.
+    fn set_data_bit_timing(&mut self, _timing: &BitTiming) -> Result<(), Self::Error> {
+        Err(Mcp2515ConfigError::UnsupportedTiming)
+    }
+}
+
+/// Uses [`BitTiming::calculate`] with [`LIMITS`]. When that gives a phase segment 2 of a single
+/// quantum, one quantum moves over from `seg1`, so at high bitrates the sample point can be earlier
+/// than 87.5 %.
+impl<SPI: SpiDevice> BitrateConfig for Mcp2515<SPI> {
+    type Error = Mcp2515ConfigError<Error<SPI::Error>>;
+
+    fn set_bitrate(&mut self, bitrate: Bitrate) -> Result<(), Self::Error> {
+        let mut timing = BitTiming::calculate(self.clock_hz(), bitrate, &LIMITS)
+            .ok_or(Mcp2515ConfigError::UnsupportedTiming)?;
+        if timing.seg2 < 2 {
+            timing.seg1 -= 1;
+            timing.seg2 += 1;
+        }
+        timing.sjw = timing.sjw.min(timing.seg2 - 1);
+        self.set_bit_timing(&timing)
+    }
+
+    /// Returns [`Mcp2515ConfigError::UnsupportedTiming`]; the MCP2515 is a classic CAN controller.
+    fn set_data_bitrate(&mut self, _bitrate: Bitrate) -> Result<(), Self::Error> {
+        Err(Mcp2515ConfigError::UnsupportedTiming)
+    }
+}
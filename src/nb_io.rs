@@ -0,0 +1,275 @@
+//! `nb`-style frame I/O (requires the `nb` feature).
+//!
+//! Much existing HAL code is written against the [`nb`] convention: a single non-blocking
+//! operation whose [`nb::Error::WouldBlock`] variant asks the caller to retry. The main traits of
+//! this crate instead offer blocking, non-blocking and timeout variants side by side, and leave it
+//! to the driver's error type to express “would block”. This module connects the two worlds:
+//!
+//! - [`NbTxFrameIo`] and [`NbRxFrameIo`] are the `nb`-style traits.
+//! - [`FromNb`] turns an `nb`-style driver into a [`TxFrameIo`]/[`RxFrameIo`] whose error type is
+//!   [`nb::Error`].
+//! - [`IntoNb`] turns a [`TxFrameIo`]/[`RxFrameIo`] into an `nb`-style interface, using
+//!   [`WouldBlock`] to recognise the driver's “would block” errors.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::nb_io::FromNb;
+//!
+//! let mut can = FromNb::new(nb_driver);
+//! can.send(&frame)?; // retries while the driver reports `WouldBlock`
+//! ```
+
+use core::time::Duration;
+
+use crate::{RxFrameIo, TxFrameIo};
+
+/// Non-blocking transmit in the [`nb`] style.
+pub trait NbTxFrameIo {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Submit a frame, or return [`nb::Error::WouldBlock`] if the driver cannot accept it yet.
+    fn transmit(&mut self, frame: &Self::Frame) -> nb::Result<(), Self::Error>;
+}
+
+/// Non-blocking receive in the [`nb`] style.
+pub trait NbRxFrameIo {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Return the next received frame, or [`nb::Error::WouldBlock`] if none is available.
+    fn receive(&mut self) -> nb::Result<Self::Frame, Self::Error>;
+}
+
+/// Error types with a “would block” case.
+///
+/// Implemented for the error types in this crate that have one; implement it for a driver error
+/// type to use the driver with [`IntoNb`].
+pub trait WouldBlock {
+    /// Returns `true` if the operation failed only because it could not complete immediately.
+    fn is_would_block(&self) -> bool;
+}
+
+impl<E> WouldBlock for nb::Error<E> {
+    fn is_would_block(&self) -> bool {
+        matches!(self, nb::Error::WouldBlock)
+    }
+}
+
+impl WouldBlock for crate::replay::ReplayError {
+    fn is_would_block(&self) -> bool {
+        matches!(self, crate::replay::ReplayError::WouldBlock)
+    }
+}
+
+#[cfg(feature = "ffi-backend")]
+impl<E> WouldBlock for crate::ffi::FfiError<E> {
+    fn is_would_block(&self) -> bool {
+        matches!(self, crate::ffi::FfiError::WouldBlock)
+    }
+}
+
+#[cfg(feature = "bxcan")]
+impl WouldBlock for crate::bxcan_io::BxcanError {
+    fn is_would_block(&self) -> bool {
+        matches!(self, crate::bxcan_io::BxcanError::WouldBlock)
+    }
+}
+
+#[cfg(feature = "mcp2515")]
+impl<E: core::fmt::Debug> WouldBlock for mcp2515::error::Error<E> {
+    fn is_would_block(&self) -> bool {
+        matches!(
+            self,
+            mcp2515::error::Error::TxBusy | mcp2515::error::Error::NoMessage
+        )
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E> WouldBlock for crate::cobs::CobsCanError<E> {
+    fn is_would_block(&self) -> bool {
+        matches!(self, crate::cobs::CobsCanError::WouldBlock)
+    }
+}
+
+#[cfg(feature = "std")]
+impl WouldBlock for std::io::Error {
+    fn is_would_block(&self) -> bool {
+        self.kind() == std::io::ErrorKind::WouldBlock
+    }
+}
+
+#[cfg(feature = "std")]
+impl WouldBlock for crate::error::Error {
+    fn is_would_block(&self) -> bool {
+        self.kind() == crate::error::ErrorKind::WouldBlock
+    }
+}
+
+/// Adapter from an `nb`-style driver to [`TxFrameIo`] and [`RxFrameIo`].
+///
+/// Blocking operations retry while the driver reports [`nb::Error::WouldBlock`]; the
+/// non-blocking ones return it. The driver has no notion of time, so timeouts are not supported
+/// and `send_timeout`/`recv_timeout` block like `send`/`recv`.
+#[derive(Debug)]
+pub struct FromNb<T, F> {
+    inner: T,
+    /// Frame read by `wait_not_empty` and not yet returned.
+    peeked: Option<F>,
+}
+
+impl<T: NbRxFrameIo> FromNb<T, T::Frame> {
+    /// Wrap an `nb`-style driver.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            peeked: None,
+        }
+    }
+}
+
+impl<T: NbTxFrameIo> FromNb<T, ()> {
+    /// Wrap an `nb`-style driver that can only transmit.
+    pub fn tx_only(inner: T) -> Self {
+        Self {
+            inner,
+            peeked: None,
+        }
+    }
+}
+
+impl<T, F> FromNb<T, F> {
+    /// Borrow the wrapped driver.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped driver.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped driver.
+    ///
+    /// A frame already read by [`RxFrameIo::wait_not_empty`] is lost.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: NbTxFrameIo, F> TxFrameIo for FromNb<T, F> {
+    type Frame = T::Frame;
+    type Error = nb::Error<T::Error>;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        nb::block!(self.inner.transmit(frame)).map_err(nb::Error::Other)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.transmit(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, _timeout: Duration) -> Result<(), Self::Error> {
+        self.send(frame)
+    }
+}
+
+impl<T: NbRxFrameIo<Frame = F>, F> RxFrameIo for FromNb<T, F> {
+    type Frame = F;
+    type Error = nb::Error<T::Error>;
+
+    fn recv(&mut self) -> Result<F, Self::Error> {
+        if let Some(frame) = self.peeked.take() {
+            return Ok(frame);
+        }
+        nb::block!(self.inner.receive()).map_err(nb::Error::Other)
+    }
+
+    fn try_recv(&mut self) -> Result<F, Self::Error> {
+        match self.peeked.take() {
+            Some(frame) => Ok(frame),
+            None => self.inner.receive(),
+        }
+    }
+
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<F, Self::Error> {
+        self.recv()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        if self.peeked.is_none() {
+            let frame = nb::block!(self.inner.receive()).map_err(nb::Error::Other)?;
+            self.peeked = Some(frame);
+        }
+        Ok(())
+    }
+}
+
+/// Adapter from [`TxFrameIo`] and [`RxFrameIo`] to the `nb`-style traits.
+///
+/// Each `nb` operation is a single [`TxFrameIo::try_send`] or [`RxFrameIo::try_recv`]; driver
+/// errors for which [`WouldBlock::is_would_block`] holds become [`nb::Error::WouldBlock`].
+#[derive(Debug)]
+pub struct IntoNb<T> {
+    inner: T,
+}
+
+impl<T> IntoNb<T> {
+    /// Wrap an interface.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+fn classify<E: WouldBlock>(error: E) -> nb::Error<E> {
+    if error.is_would_block() {
+        nb::Error::WouldBlock
+    } else {
+        nb::Error::Other(error)
+    }
+}
+
+impl<T> NbTxFrameIo for IntoNb<T>
+where
+    T: TxFrameIo,
+    T::Error: WouldBlock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn transmit(&mut self, frame: &Self::Frame) -> nb::Result<(), Self::Error> {
+        self.inner.try_send(frame).map_err(classify)
+    }
+}
+
+impl<T> NbRxFrameIo for IntoNb<T>
+where
+    T: RxFrameIo,
+    T::Error: WouldBlock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn receive(&mut self) -> nb::Result<Self::Frame, Self::Error> {
+        self.inner.try_recv().map_err(classify)
+    }
+}
@@ -0,0 +1,395 @@
+//! OBD-II (SAE J1979) request/response helper.
+//!
+//! [`Obd2`] sends requests using the standard 11-bit addressing conventions and returns the ECU's
+//! response payload. Responses travel over ISO-TP ([`crate::isotp`]), so multi-frame replies such
+//! as the VIN or a long DTC list are reassembled transparently; single-frame PIDs simply arrive as
+//! ISO-TP single frames.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::obd2::{pid, Obd2, Request};
+//!
+//! let mut obd = Obd2::new(can, clock);
+//! let mut buf = [0u8; 64];
+//! let data = obd.query(&Request::current_data(pid::ENGINE_RPM), &mut buf)?;
+//! let rpm = pid::engine_rpm(data);
+//! ```
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, StandardId};
+
+use crate::isotp::{IsoTp, IsoTpConfig, IsoTpError, MessageIo, single_frame};
+use crate::uds::UdsTiming;
+use crate::{Clock, RxFrameIo, TxFrameIo};
+
+/// Functional (broadcast) request identifier.
+pub const FUNCTIONAL_REQUEST_ID: u16 = 0x7DF;
+
+/// Physical request identifier of ECU `ecu` (0–7).
+pub const fn physical_request_id(ecu: u8) -> u16 {
+    0x7E0 + (ecu & 0x07) as u16
+}
+
+/// Response identifier of ECU `ecu` (0–7).
+pub const fn response_id(ecu: u8) -> u16 {
+    0x7E8 + (ecu & 0x07) as u16
+}
+
+/// OBD-II service (mode) numbers supported by the request builders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Service {
+    /// Mode 01: current powertrain data.
+    CurrentData = 0x01,
+    /// Mode 03: stored diagnostic trouble codes.
+    StoredDtcs = 0x03,
+    /// Mode 09: vehicle information (VIN, calibration IDs, …).
+    VehicleInfo = 0x09,
+}
+
+/// Positive responses echo the service number with this bit set.
+const POSITIVE_RESPONSE: u8 = 0x40;
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+const RESPONSE_PENDING: u8 = 0x78;
+
+/// An OBD-II request message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Request {
+    bytes: [u8; 2],
+    len: usize,
+}
+
+impl Request {
+    /// Mode 01 request for `pid`.
+    pub const fn current_data(pid: u8) -> Self {
+        Self {
+            bytes: [Service::CurrentData as u8, pid],
+            len: 2,
+        }
+    }
+
+    /// Mode 03 request for stored trouble codes.
+    pub const fn stored_dtcs() -> Self {
+        Self {
+            bytes: [Service::StoredDtcs as u8, 0],
+            len: 1,
+        }
+    }
+
+    /// Mode 09 request for `pid` (e.g. [`pid::VIN`]).
+    pub const fn vehicle_info(pid: u8) -> Self {
+        Self {
+            bytes: [Service::VehicleInfo as u8, pid],
+            len: 2,
+        }
+    }
+
+    /// The service number.
+    pub const fn service(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    /// The PID, for services that take one.
+    pub const fn pid(&self) -> Option<u8> {
+        if self.len == 2 {
+            Some(self.bytes[1])
+        } else {
+            None
+        }
+    }
+
+    /// The encoded request.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Check a raw response against this request and return its data bytes.
+    ///
+    /// The response header (service, PID and, for mode 09, the item count) is stripped.
+    pub fn parse_response<'a>(&self, response: &'a [u8]) -> Result<&'a [u8], ResponseError> {
+        match response {
+            [NEGATIVE_RESPONSE, service, code, ..] if *service == self.service() => {
+                Err(ResponseError::Negative { code: *code })
+            }
+            [service, rest @ ..] if *service == self.service() | POSITIVE_RESPONSE => {
+                match (self.pid(), rest) {
+                    (None, data) => Ok(data),
+                    (Some(pid), [got, data @ ..]) if *got == pid => {
+                        if self.service() == Service::VehicleInfo as u8 {
+                            // Mode 09 responses carry a data item count before the data.
+                            data.split_first()
+                                .map(|(_, data)| data)
+                                .ok_or(ResponseError::Malformed)
+                        } else {
+                            Ok(data)
+                        }
+                    }
+                    _ => Err(ResponseError::Malformed),
+                }
+            }
+            _ => Err(ResponseError::Malformed),
+        }
+    }
+}
+
+/// Problem with a response to a [`Request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseError {
+    /// The ECU answered with a negative response code.
+    Negative {
+        /// The negative response code (e.g. `0x12`, sub-function not supported).
+        code: u8,
+    },
+    /// The response does not match the request.
+    Malformed,
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Negative { code } => write!(f, "negative response, code {code:#04x}"),
+            Self::Malformed => f.write_str("response does not match the request"),
+        }
+    }
+}
+
+impl Error for ResponseError {}
+
+/// Error returned by [`Obd2`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Obd2Error<E> {
+    /// Transport failure.
+    Transport(IsoTpError<E>),
+    /// The ECU's response was negative or did not match the request.
+    Response(ResponseError),
+}
+
+impl<E> fmt::Display for Obd2Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(_) => f.write_str("transport error"),
+            Self::Response(_) => f.write_str("invalid response"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for Obd2Error<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            Self::Response(e) => Some(e),
+        }
+    }
+}
+
+impl<E> From<IsoTpError<E>> for Obd2Error<E> {
+    fn from(error: IsoTpError<E>) -> Self {
+        Obd2Error::Transport(error)
+    }
+}
+
+/// A diagnostic trouble code as reported by mode 03.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dtc(pub u16);
+
+impl Dtc {
+    /// The system letter: `P`owertrain, `C`hassis, `B`ody or `U` (network).
+    pub const fn system(&self) -> char {
+        match self.0 >> 14 {
+            0 => 'P',
+            1 => 'C',
+            2 => 'B',
+            _ => 'U',
+        }
+    }
+}
+
+/// Formats as the conventional five-character code, e.g. `P0301`.
+impl fmt::Display for Dtc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:04X}", self.system(), self.0 & 0x3FFF)
+    }
+}
+
+/// Iterate over the trouble codes in mode 03 response data.
+///
+/// Accepts data with or without the leading DTC count byte used on CAN; padding codes of `0000`
+/// are skipped.
+pub fn parse_dtcs(data: &[u8]) -> impl Iterator<Item = Dtc> + '_ {
+    let codes = if data.len() % 2 == 1 {
+        &data[1..]
+    } else {
+        data
+    };
+    codes
+        .chunks_exact(2)
+        .map(|pair| Dtc(u16::from_be_bytes([pair[0], pair[1]])))
+        .filter(|dtc| dtc.0 != 0)
+}
+
+/// Common PIDs and decoders for their mode 01 data.
+pub mod pid {
+    /// Bitmap of supported PIDs 0x01–0x20.
+    pub const SUPPORTED_01_20: u8 = 0x00;
+    /// Engine coolant temperature.
+    pub const COOLANT_TEMP: u8 = 0x05;
+    /// Engine speed.
+    pub const ENGINE_RPM: u8 = 0x0C;
+    /// Vehicle speed.
+    pub const VEHICLE_SPEED: u8 = 0x0D;
+    /// Mode 09: vehicle identification number.
+    pub const VIN: u8 = 0x02;
+
+    /// Decode [`COOLANT_TEMP`] in °C.
+    pub fn coolant_temp(data: &[u8]) -> Option<i16> {
+        data.first().map(|&a| i16::from(a) - 40)
+    }
+
+    /// Decode [`ENGINE_RPM`] in revolutions per minute.
+    pub fn engine_rpm(data: &[u8]) -> Option<f32> {
+        match data {
+            [a, b, ..] => Some(f32::from(u16::from_be_bytes([*a, *b])) / 4.0),
+            _ => None,
+        }
+    }
+
+    /// Decode [`VEHICLE_SPEED`] in km/h.
+    pub fn vehicle_speed(data: &[u8]) -> Option<u8> {
+        data.first().copied()
+    }
+
+    /// Returns `true` if the bitmap from a “supported PIDs” request lists `pid`.
+    ///
+    /// `base` is the PID the bitmap was requested with (0x00, 0x20, …).
+    pub fn is_supported(bitmap: &[u8], base: u8, pid: u8) -> bool {
+        let Some(offset) = pid
+            .checked_sub(base)
+            .and_then(|offset| offset.checked_sub(1))
+        else {
+            return false;
+        };
+        let offset = usize::from(offset);
+        bitmap
+            .get(offset / 8)
+            .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+    }
+}
+
+/// How requests are addressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Addressing {
+    /// Broadcast requests on 0x7DF and listen to the ECU with the given index (usually 0, the
+    /// engine controller).
+    Functional(u8),
+    /// Address ECU `n` directly on 0x7E0 + `n`.
+    Physical(u8),
+}
+
+/// OBD-II client over an ISO-TP channel.
+#[derive(Debug)]
+pub struct Obd2<T, C> {
+    tp: IsoTp<T, C>,
+    addressing: Addressing,
+    timing: UdsTiming,
+    max_wait: Duration,
+}
+
+impl<T, C> Obd2<T, C> {
+    /// Create a client using functional addressing, listening to ECU 0.
+    pub fn new(io: T, clock: C) -> Self {
+        Self::with_addressing(io, clock, Addressing::Functional(0))
+    }
+
+    /// Create a client with explicit addressing.
+    pub fn with_addressing(io: T, clock: C, addressing: Addressing) -> Self {
+        let ecu = match addressing {
+            Addressing::Functional(ecu) | Addressing::Physical(ecu) => ecu,
+        };
+        // Flow control always goes to the responding ECU's physical address.
+        let config = IsoTpConfig::new(
+            StandardId::new(physical_request_id(ecu)).unwrap_or(StandardId::ZERO),
+            StandardId::new(response_id(ecu)).unwrap_or(StandardId::ZERO),
+        );
+        Self {
+            tp: IsoTp::new(io, clock, config),
+            addressing,
+            timing: UdsTiming::default(),
+            max_wait: Duration::from_secs(30),
+        }
+    }
+
+    /// Set how long to wait for a response (50 ms by default, as J1979 specifies).
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.timing.p2 = timeout;
+    }
+
+    /// Set how long to wait after a “response pending” reply (5 s by default).
+    pub fn set_pending_timeout(&mut self, timeout: Duration) {
+        self.timing.p2_star = timeout;
+    }
+
+    /// Set the longest a query waits for its final response, however often “response pending”
+    /// replies extend the wait (30 s by default).
+    pub fn set_max_wait(&mut self, max_wait: Duration) {
+        self.max_wait = max_wait;
+    }
+
+    /// Borrow the underlying ISO-TP channel.
+    pub fn transport(&mut self) -> &mut IsoTp<T, C> {
+        &mut self.tp
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.tp.into_inner()
+    }
+}
+
+impl<T, C, F, E> Obd2<T, C>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    F: Frame,
+{
+    /// Send `request` and return the response data (header stripped) in `buf`.
+    ///
+    /// “Response pending” negative responses (NRC 0x78) are waited out: each extends the wait to
+    /// the pending timeout, up to the maximum wait, after which [`IsoTpError::Timeout`] is
+    /// returned.
+    pub fn query<'b>(
+        &mut self,
+        request: &Request,
+        buf: &'b mut [u8],
+    ) -> Result<&'b [u8], Obd2Error<E>> {
+        match self.addressing {
+            Addressing::Physical(_) => self.tp.send_message(request.as_bytes())?,
+            Addressing::Functional(_) => {
+                let id = StandardId::new(FUNCTIONAL_REQUEST_ID).unwrap_or(StandardId::ZERO);
+                let frame: F = single_frame(id, request.as_bytes(), self.tp.config().padding)
+                    .ok_or(IsoTpError::InvalidFrame)?;
+                self.tp.inner_mut().send(&frame).map_err(IsoTpError::Io)?;
+            }
+        }
+        let limit = self.tp.clock().now() + self.max_wait;
+        let mut deadline = self.tp.clock().now() + self.timing.p2;
+        loop {
+            let remaining = deadline.min(limit).saturating_sub(self.tp.clock().now());
+            if remaining.is_zero() {
+                return Err(IsoTpError::Timeout.into());
+            }
+            let len = self.tp.recv_message(buf, remaining)?;
+            match request.parse_response(&buf[..len]) {
+                Err(ResponseError::Negative {
+                    code: RESPONSE_PENDING,
+                }) => deadline = self.tp.clock().now() + self.timing.p2_star,
+                Err(error) => return Err(Obd2Error::Response(error)),
+                Ok(data) => {
+                    let start = len - data.len();
+                    return Ok(&buf[start..len]);
+                }
+            }
+        }
+    }
+}
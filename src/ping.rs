@@ -0,0 +1,280 @@
+//! Round-trip latency measurement.
+//!
+//! [`Pinger`] sends probe frames and times how long each takes to come back, accumulating the
+//! round-trip times in [`PingStats`]. A probe comes back either as an echo of itself (from an
+//! interface with self-reception enabled, or a gateway looping frames back) or as the reply of a
+//! node running a [`PingResponder`], which answers on another identifier with the same payload.
+//! Both ends only need the async I/O traits, so the same tool works on any driver when
+//! commissioning a bus or hunting for a bad cable.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::ping::{PingError, Pinger};
+//!
+//! let mut pinger = Pinger::new(request_id, reply_id, clock);
+//! for _ in 0..100 {
+//!     match pinger.ping(&mut can, Duration::from_millis(100)).await {
+//!         Ok(_) | Err(PingError::Timeout) => {}
+//!         Err(e) => return Err(e),
+//!     }
+//!     delay.delay_ms(10).await;
+//! }
+//! let stats = pinger.stats();
+//! log!("{} of {} lost, mean {:?}", stats.lost(), stats.sent(), stats.mean());
+//! ```
+//!
+//! A probe's payload is its sequence number followed by its send time in microseconds on the
+//! pinger's clock, both little-endian `u32`s, so a bus trace shows which probes went unanswered
+//! and when they were sent.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{AsyncFrameIo, AsyncRxFrameIo, AsyncTxFrameIo, Clock};
+
+/// Length of a probe payload.
+pub const PROBE_LEN: usize = 8;
+
+/// Error returned by [`Pinger::ping`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// No reply arrived within the timeout.
+    Timeout,
+    /// The frame type cannot carry a probe with the configured identifier.
+    InvalidFrame,
+}
+
+impl<E> fmt::Display for PingError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Timeout => f.write_str("no reply within the timeout"),
+            Self::InvalidFrame => f.write_str("cannot build a probe frame"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for PingError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Round-trip statistics collected by a [`Pinger`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PingStats {
+    sent: u32,
+    received: u32,
+    min: Option<Duration>,
+    max: Duration,
+    total: Duration,
+}
+
+impl PingStats {
+    /// Record one probe: its round-trip time, or `None` if it went unanswered.
+    pub fn record(&mut self, rtt: Option<Duration>) {
+        self.sent = self.sent.saturating_add(1);
+        let Some(rtt) = rtt else {
+            return;
+        };
+        self.received = self.received.saturating_add(1);
+        self.min = Some(self.min.map_or(rtt, |min| min.min(rtt)));
+        self.max = self.max.max(rtt);
+        self.total = self.total.saturating_add(rtt);
+    }
+
+    /// Number of probes sent.
+    pub fn sent(&self) -> u32 {
+        self.sent
+    }
+
+    /// Number of probes answered.
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    /// Number of probes that went unanswered.
+    pub fn lost(&self) -> u32 {
+        self.sent - self.received
+    }
+
+    /// Shortest round-trip time, if any probe was answered.
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    /// Longest round-trip time, if any probe was answered.
+    pub fn max(&self) -> Option<Duration> {
+        self.min.map(|_| self.max)
+    }
+
+    /// Mean round-trip time, if any probe was answered.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.received > 0).then(|| self.total / self.received)
+    }
+}
+
+/// Whether `data` is the payload of probe `sequence`.
+fn is_probe(data: &[u8], sequence: u32) -> bool {
+    data.len() == PROBE_LEN && data[..4] == sequence.to_le_bytes()
+}
+
+/// Sends probes and measures the time until each one is answered.
+#[derive(Debug)]
+pub struct Pinger<C> {
+    request_id: Id,
+    reply_id: Id,
+    clock: C,
+    sequence: u32,
+    stats: PingStats,
+}
+
+impl<C: Clock> Pinger<C> {
+    /// Send probes on `request_id` and wait for replies on `reply_id`.
+    ///
+    /// Use the same identifier for both to measure against an echo of the probe itself.
+    pub fn new(request_id: impl Into<Id>, reply_id: impl Into<Id>, clock: C) -> Self {
+        Self {
+            request_id: request_id.into(),
+            reply_id: reply_id.into(),
+            clock,
+            sequence: 0,
+            stats: PingStats::default(),
+        }
+    }
+
+    /// Send one probe and wait up to `timeout` for its reply, returning the round-trip time.
+    ///
+    /// Frames other than the reply, including late replies to earlier probes, are dropped.
+    /// Answered and timed-out probes are recorded in [`Pinger::stats`]; probes that fail with an
+    /// I/O error are not.
+    pub async fn ping<T>(
+        &mut self,
+        io: &mut T,
+        timeout: Duration,
+    ) -> Result<Duration, PingError<<T as AsyncRxFrameIo>::Error>>
+    where
+        T: AsyncFrameIo,
+        <T as AsyncRxFrameIo>::Frame: Frame,
+    {
+        let sequence = self.sequence;
+        let sent = self.clock.now();
+        let mut payload = [0u8; PROBE_LEN];
+        payload[..4].copy_from_slice(&sequence.to_le_bytes());
+        payload[4..].copy_from_slice(&(sent.as_micros() as u32).to_le_bytes());
+        let probe = <T as AsyncRxFrameIo>::Frame::new(self.request_id, &payload)
+            .ok_or(PingError::InvalidFrame)?;
+        io.send(&probe).await.map_err(PingError::Io)?;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let deadline = sent + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                self.stats.record(None);
+                return Err(PingError::Timeout);
+            }
+            match io.recv_timeout(remaining).await {
+                Ok(frame) if frame.id() == self.reply_id && is_probe(frame.data(), sequence) => {
+                    let rtt = self.clock.now().saturating_sub(sent);
+                    self.stats.record(Some(rtt));
+                    return Ok(rtt);
+                }
+                Ok(_) => {}
+                Err(_) if self.clock.now() >= deadline => {
+                    self.stats.record(None);
+                    return Err(PingError::Timeout);
+                }
+                Err(e) => return Err(PingError::Io(e)),
+            }
+        }
+    }
+
+    /// Send `count` probes back to back, each waiting up to `timeout`, and return the statistics
+    /// of the whole run.
+    ///
+    /// Unanswered probes count as lost; an I/O error ends the run.
+    pub async fn run<T>(
+        &mut self,
+        io: &mut T,
+        count: u32,
+        timeout: Duration,
+    ) -> Result<PingStats, PingError<<T as AsyncRxFrameIo>::Error>>
+    where
+        T: AsyncFrameIo,
+        <T as AsyncRxFrameIo>::Frame: Frame,
+    {
+        let mut run = PingStats::default();
+        for _ in 0..count {
+            match self.ping(io, timeout).await {
+                Ok(rtt) => run.record(Some(rtt)),
+                Err(PingError::Timeout) => run.record(None),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(run)
+    }
+
+    /// Statistics of every probe sent so far.
+    pub fn stats(&self) -> PingStats {
+        self.stats
+    }
+
+    /// Clear the statistics; the sequence numbers carry on.
+    pub fn reset_stats(&mut self) {
+        self.stats = PingStats::default();
+    }
+}
+
+/// Answers probes from a [`Pinger`] on another node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingResponder {
+    request_id: Id,
+    reply_id: Id,
+}
+
+impl PingResponder {
+    /// Answer probes received on `request_id` with a reply on `reply_id`.
+    pub fn new(request_id: impl Into<Id>, reply_id: impl Into<Id>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            reply_id: reply_id.into(),
+        }
+    }
+
+    /// The reply to `frame`, or `None` if it is not a probe.
+    pub fn reply<F: Frame>(&self, frame: &F) -> Option<F> {
+        if frame.id() != self.request_id || frame.is_remote_frame() {
+            return None;
+        }
+        if frame.data().len() != PROBE_LEN {
+            return None;
+        }
+        F::new(self.reply_id, frame.data())
+    }
+
+    /// Send the reply to `frame` if it is a probe; returns `true` if a reply was sent.
+    ///
+    /// Call this with every frame the node's receive loop sees.
+    pub async fn respond<T>(
+        &self,
+        io: &mut T,
+        frame: &<T as AsyncTxFrameIo>::Frame,
+    ) -> Result<bool, <T as AsyncTxFrameIo>::Error>
+    where
+        T: AsyncTxFrameIo,
+        <T as AsyncTxFrameIo>::Frame: Frame,
+    {
+        match self.reply(frame) {
+            Some(reply) => io.send(&reply).await.map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Replay of recorded traffic as a receive interface.
+//!
+//! [`Replay`] turns any iterator of timestamped frames (for example, records parsed from a
+//! candump or ASC log) into an [`RxFrameIo`], so protocol stacks and applications can be exercised
+//! against captured traffic. By default frames are delivered as fast as they are read. A paced
+//! replay instead reproduces the capture's inter-frame timing on a [`Clock`], optionally sped up
+//! or slowed down, which hardware-in-the-loop setups need:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::replay::Replay;
+//!
+//! let records = log.lines().filter_map(parse_candump_line); // (Duration, MyFrame)
+//! let mut rx = Replay::paced(records, clock);
+//! while let Ok(frame) = rx.recv() {
+//!     stack.handle(&frame);
+//! }
+//! ```
+//!
+//! Paced waits spin on the clock, as there is no portable way to sleep in `no_std`; use
+//! [`Replay::next_due`] with [`RxFrameIo::try_recv`] to sleep between frames instead.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use crate::{Clock, RxFrameIo};
+
+/// Error returned by [`Replay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The next frame is not due yet (non-blocking receive only).
+    WouldBlock,
+    /// The next frame did not become due within the timeout.
+    Timeout,
+    /// The recording has been fully replayed.
+    End,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WouldBlock => f.write_str("next frame not due yet"),
+            Self::Timeout => f.write_str("next frame not due within the timeout"),
+            Self::End => f.write_str("end of recording"),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Clock of an unpaced [`Replay`]; always reads zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Unpaced;
+
+impl Clock for Unpaced {
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Receive interface that replays `(capture timestamp, frame)` records.
+#[derive(Debug)]
+pub struct Replay<I: Iterator, C = Unpaced> {
+    records: I,
+    next: Option<I::Item>,
+    clock: C,
+    /// Playback speed relative to the capture, or `None` to deliver as fast as possible.
+    speed: Option<f32>,
+    /// `false` for replays built without a real clock, which cannot be paced.
+    pacing_allowed: bool,
+    /// Capture timestamp and clock reading at which playback started.
+    origin: Option<(Duration, Duration)>,
+}
+
+impl<I, F> Replay<I, Unpaced>
+where
+    I: Iterator<Item = (Duration, F)>,
+{
+    /// Replay `records` as fast as they are read.
+    pub fn new(records: impl IntoIterator<IntoIter = I>) -> Self {
+        Self::build(records.into_iter(), Unpaced, None, false)
+    }
+}
+
+impl<I, F, C> Replay<I, C>
+where
+    I: Iterator<Item = (Duration, F)>,
+    C: Clock,
+{
+    /// Replay `records` in real time, reproducing the gaps between their capture timestamps on
+    /// `clock`.
+    ///
+    /// The first frame is due as soon as it is requested; later frames keep their offset from it.
+    pub fn paced(records: impl IntoIterator<IntoIter = I>, clock: C) -> Self {
+        Self::build(records.into_iter(), clock, Some(1.0), true)
+    }
+
+    fn build(records: I, clock: C, speed: Option<f32>, pacing_allowed: bool) -> Self {
+        Self {
+            records,
+            next: None,
+            clock,
+            speed,
+            pacing_allowed,
+            origin: None,
+        }
+    }
+
+    /// Change the playback speed (2.0 replays twice as fast), or switch pacing off with `None`.
+    ///
+    /// Changing the speed re-anchors playback at the next frame. Non-positive speeds switch pacing
+    /// off, and replays created with [`Replay::new`] have no clock to pace on and stay unpaced.
+    pub fn set_speed(&mut self, speed: Option<f32>) {
+        self.speed = speed.filter(|speed| self.pacing_allowed && *speed > 0.0);
+        self.origin = None;
+    }
+
+    /// Time on the clock at which the next frame is due, or `None` once the recording has ended.
+    ///
+    /// Unpaced replays report the current time while frames remain. Delays too long to represent,
+    /// after dividing by a tiny speed, saturate at [`Duration::MAX`].
+    pub fn next_due(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+        let (captured, _) = self.peek()?;
+        let captured = *captured;
+        let Some(speed) = self.speed else {
+            return Some(now);
+        };
+        let (origin_capture, origin_clock) = *self.origin.get_or_insert((captured, now));
+        let offset = captured.saturating_sub(origin_capture).as_secs_f64() / f64::from(speed);
+        let offset = Duration::try_from_secs_f64(offset).unwrap_or(Duration::MAX);
+        Some(origin_clock.saturating_add(offset))
+    }
+
+    /// Restart pacing from the next frame, e.g. after the consumer was paused.
+    pub fn reanchor(&mut self) {
+        self.origin = None;
+    }
+
+    /// Borrow the clock.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Unwrap, returning the remaining records (including a frame already read ahead).
+    pub fn into_inner(self) -> core::iter::Chain<core::option::IntoIter<(Duration, F)>, I> {
+        self.next.into_iter().chain(self.records)
+    }
+
+    fn peek(&mut self) -> Option<&(Duration, F)> {
+        if self.next.is_none() {
+            self.next = self.records.next();
+        }
+        self.next.as_ref()
+    }
+
+    /// Wait for the next frame until `deadline` (on the clock), or indefinitely.
+    fn wait_due(&mut self, deadline: Option<Duration>) -> Result<(), ReplayError> {
+        let due = self.next_due().ok_or(ReplayError::End)?;
+        self.clock
+            .spin_until(deadline.map_or(due, |deadline| deadline.min(due)));
+        if self.clock.now() < due {
+            return Err(ReplayError::Timeout);
+        }
+        Ok(())
+    }
+
+    fn take(&mut self) -> Result<F, ReplayError> {
+        self.next
+            .take()
+            .map(|(_, frame)| frame)
+            .ok_or(ReplayError::End)
+    }
+}
+
+impl<I, F, C> RxFrameIo for Replay<I, C>
+where
+    I: Iterator<Item = (Duration, F)>,
+    C: Clock,
+{
+    type Frame = F;
+    type Error = ReplayError;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.wait_due(None)?;
+        self.take()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let due = self.next_due().ok_or(ReplayError::End)?;
+        if self.clock.now() < due {
+            return Err(ReplayError::WouldBlock);
+        }
+        self.take()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let deadline = self.clock.now() + timeout;
+        self.wait_due(Some(deadline))?;
+        self.take()
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.wait_due(None)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::fuzz::FuzzClock;
+    use crate::testdata::TestFrame;
+
+    #[test]
+    fn tiny_speed_saturates() {
+        let clock = FuzzClock::new();
+        let frame = TestFrame::standard(0x123, &[1]);
+        let records = [(Duration::ZERO, frame), (Duration::from_secs(1), frame)];
+        let mut replay = Replay::paced(records, &clock);
+        replay.set_speed(Some(f32::MIN_POSITIVE));
+        assert_eq!(replay.try_recv(), Ok(frame));
+        assert_eq!(replay.next_due(), Some(Duration::MAX));
+        assert_eq!(replay.try_recv(), Err(ReplayError::WouldBlock));
+        assert_eq!(
+            replay.recv_timeout(Duration::from_millis(1)),
+            Err(ReplayError::Timeout)
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Broadcast manager (`CAN_BCM`) sockets.
+//!
+//! The structures mirror `<linux/can/bcm.h>`.
+
+use core::time::Duration;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::time::Instant;
+use std::vec::Vec;
+
+use embedded_can::{Frame, Id};
+
+use super::sys::{self, RawFrame, SockaddrCan, Wait};
+use crate::cyclic::{CyclicError, EntryHandle, PeriodicTx, ScheduleError};
+use crate::error::Error;
+use crate::watchdog::{Monitored, MonitoredRx, WatchError, WatchdogError, WatchdogEvent};
+
+const TX_SETUP: u32 = 1;
+const TX_DELETE: u32 = 2;
+const RX_SETUP: u32 = 5;
+const RX_DELETE: u32 = 6;
+const RX_TIMEOUT: u32 = 11;
+const RX_CHANGED: u32 = 12;
+
+const SETTIMER: u32 = 0x0001;
+const STARTTIMER: u32 = 0x0002;
+const TX_ANNOUNCE: u32 = 0x0008;
+const RX_FILTER_ID: u32 = 0x0020;
+
+/// `struct bcm_timeval`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct BcmTimeval {
+    tv_sec: libc::c_long,
+    tv_usec: libc::c_long,
+}
+
+impl BcmTimeval {
+    /// Rounded up to whole microseconds, so short nonzero intervals stay nonzero.
+    fn new(interval: Duration) -> Self {
+        let micros = interval.as_nanos().div_ceil(1_000);
+        Self {
+            tv_sec: libc::c_long::try_from(micros / 1_000_000).unwrap_or(libc::c_long::MAX),
+            tv_usec: (micros % 1_000_000) as libc::c_long,
+        }
+    }
+}
+
+/// `struct bcm_msg_head`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct BcmHead {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    ival1: BcmTimeval,
+    ival2: BcmTimeval,
+    can_id: u32,
+    nframes: u32,
+}
+
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(size_of::<BcmHead>() == 56);
+
+/// A message head followed by one frame.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct BcmMsg {
+    head: BcmHead,
+    frame: RawFrame,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BcmWatch {
+    id: Id,
+    last_seen: Duration,
+    reported_missing: bool,
+}
+
+/// A broadcast manager socket on one interface, running schedules in the kernel.
+///
+/// - [`PeriodicTx`]: each entry becomes a kernel transmission job, so cyclic frames keep their
+///   period without a userspace wakeup per frame. No bandwidth budget is enforced; use a
+///   [`CyclicScheduler`](crate::cyclic::CyclicScheduler) to check a schedule first.
+/// - [`MonitoredRx`]: each watched identifier becomes a kernel receive job with a timeout. Only
+///   watched identifiers are received on this socket; other traffic needs a separate
+///   [`CanSocket`](super::CanSocket).
+///
+/// Times in [`WatchdogEvent::Missing`] are measured from when the socket was opened.
+pub struct BcmSocket<F> {
+    fd: OwnedFd,
+    opened: Instant,
+    /// Generation and frame of the transmission jobs, indexed by the slot of an [`EntryHandle`].
+    cyclic: Vec<(u32, Option<F>)>,
+    watches: Vec<BcmWatch>,
+    /// Recovery to report on the next receive.
+    recovered: Option<Id>,
+}
+
+impl<F> core::fmt::Debug for BcmSocket<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BcmSocket")
+            .field("fd", &self.fd)
+            .field("watches", &self.watches)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> BcmSocket<F> {
+    /// Open a broadcast manager socket on the interface `name`.
+    pub fn open(name: &str) -> io::Result<Self> {
+        let ifindex = sys::interface_index(name)?;
+        let fd = sys::socket(libc::SOCK_DGRAM, sys::CAN_BCM)?;
+        sys::connect(&fd, &SockaddrCan::new(ifindex))?;
+        Ok(Self {
+            fd,
+            opened: Instant::now(),
+            cyclic: Vec::new(),
+            watches: Vec::new(),
+            recovered: None,
+        })
+    }
+
+    fn write_head(&self, head: BcmHead) -> io::Result<()> {
+        sys::write_struct(self.fd.as_raw_fd(), &head)
+    }
+
+    fn write_msg(&self, head: BcmHead, frame: RawFrame) -> io::Result<()> {
+        let head = BcmHead { nframes: 1, ..head };
+        sys::write_struct(self.fd.as_raw_fd(), &BcmMsg { head, frame })
+    }
+
+    fn read_msg(&self) -> io::Result<(BcmMsg, usize)> {
+        loop {
+            match sys::read_partial(self.fd.as_raw_fd()) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    sys::poll(self.fd.as_raw_fd(), libc::POLLIN, Wait::Forever)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn since_opened(&self) -> Duration {
+        self.opened.elapsed()
+    }
+
+    /// The slot of a live transmission job.
+    fn job(&self, handle: EntryHandle) -> Result<usize, ScheduleError> {
+        match self.cyclic.get(handle.slot) {
+            Some((generation, Some(_))) if *generation == handle.generation => Ok(handle.slot),
+            _ => Err(ScheduleError::UnknownEntry),
+        }
+    }
+}
+
+impl<F> AsFd for BcmSocket<F> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl<F> AsRawFd for BcmSocket<F> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// The kernel sends each entry's first frame as soon as it is added, so `now` is not used.
+impl<F: Frame> PeriodicTx for BcmSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn add(
+        &mut self,
+        frame: F,
+        period: Duration,
+        _now: Duration,
+    ) -> Result<EntryHandle, CyclicError<Error>> {
+        if period.is_zero() {
+            return Err(ScheduleError::ZeroPeriod.into());
+        }
+        let slot = match self.cyclic.iter().position(|(_, job)| job.is_none()) {
+            Some(slot) => slot,
+            None => {
+                self.cyclic.push((0, None));
+                self.cyclic.len() - 1
+            }
+        };
+        // The job is keyed by the slot rather than the frame's identifier, so several entries
+        // may send the same identifier.
+        let head = BcmHead {
+            opcode: TX_SETUP,
+            flags: SETTIMER | STARTTIMER | TX_ANNOUNCE,
+            ival2: BcmTimeval::new(period),
+            can_id: slot as u32,
+            ..BcmHead::default()
+        };
+        self.write_msg(head, sys::to_raw(&frame))
+            .map_err(|e| CyclicError::Io(e.into()))?;
+        let (generation, job) = &mut self.cyclic[slot];
+        *job = Some(frame);
+        Ok(EntryHandle {
+            slot,
+            generation: *generation,
+        })
+    }
+
+    fn set_frame(&mut self, handle: EntryHandle, frame: F) -> Result<F, CyclicError<Error>> {
+        let slot = self.job(handle)?;
+        let head = BcmHead {
+            opcode: TX_SETUP,
+            can_id: slot as u32,
+            ..BcmHead::default()
+        };
+        self.write_msg(head, sys::to_raw(&frame))
+            .map_err(|e| CyclicError::Io(e.into()))?;
+        self.cyclic[slot]
+            .1
+            .replace(frame)
+            .ok_or(ScheduleError::UnknownEntry.into())
+    }
+
+    fn remove(&mut self, handle: EntryHandle) -> Result<F, CyclicError<Error>> {
+        let slot = self.job(handle)?;
+        let head = BcmHead {
+            opcode: TX_DELETE,
+            can_id: slot as u32,
+            ..BcmHead::default()
+        };
+        self.write_head(head)
+            .map_err(|e| CyclicError::Io(e.into()))?;
+        let (generation, job) = &mut self.cyclic[slot];
+        *generation = generation.wrapping_add(1);
+        job.take().ok_or(ScheduleError::UnknownEntry.into())
+    }
+
+    fn poll(&mut self, _now: Duration) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn next_due(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<F: Frame> MonitoredRx for BcmSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn watch(&mut self, id: Id, max_interval: Duration) -> Result<(), WatchError<Error>> {
+        if self.watches.iter().any(|watch| watch.id == id) {
+            return Err(WatchError::Watchdog(WatchdogError::AlreadyWatched));
+        }
+        let head = BcmHead {
+            opcode: RX_SETUP,
+            flags: SETTIMER | STARTTIMER | RX_FILTER_ID,
+            ival1: BcmTimeval::new(max_interval),
+            can_id: sys::raw_id(id),
+            ..BcmHead::default()
+        };
+        self.write_head(head)
+            .map_err(|e| WatchError::Io(e.into()))?;
+        self.watches.push(BcmWatch {
+            id,
+            last_seen: self.since_opened(),
+            reported_missing: false,
+        });
+        Ok(())
+    }
+
+    fn unwatch(&mut self, id: Id) -> Result<bool, Error> {
+        let Some(index) = self.watches.iter().position(|watch| watch.id == id) else {
+            return Ok(false);
+        };
+        let head = BcmHead {
+            opcode: RX_DELETE,
+            can_id: sys::raw_id(id),
+            ..BcmHead::default()
+        };
+        self.write_head(head)?;
+        self.watches.swap_remove(index);
+        if self.recovered == Some(id) {
+            self.recovered = None;
+        }
+        Ok(true)
+    }
+
+    fn recv_monitored(&mut self) -> Result<Monitored<F>, Error> {
+        if let Some(id) = self.recovered.take() {
+            return Ok(Monitored::Event(WatchdogEvent::Recovered { id }));
+        }
+        loop {
+            let (msg, len) = self.read_msg()?;
+            let Some(id) = sys::id_from_raw(msg.head.can_id) else {
+                continue;
+            };
+            let now = self.since_opened();
+            let Some(watch) = self.watches.iter_mut().find(|watch| watch.id == id) else {
+                continue;
+            };
+            match msg.head.opcode {
+                RX_TIMEOUT if !watch.reported_missing => {
+                    watch.reported_missing = true;
+                    return Ok(Monitored::Event(WatchdogEvent::Missing {
+                        id,
+                        last_seen: watch.last_seen,
+                    }));
+                }
+                RX_CHANGED if msg.head.nframes >= 1 && len == size_of::<BcmMsg>() => {
+                    watch.last_seen = now;
+                    if core::mem::take(&mut watch.reported_missing) {
+                        self.recovered = Some(id);
+                    }
+                    if let Some(frame) = sys::from_raw(&msg.frame) {
+                        return Ok(Monitored::Frame(frame));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
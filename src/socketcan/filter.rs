@@ -0,0 +1,177 @@
+//! Kernel-side acceptance filters (`CAN_RAW_FILTER`).
+
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::vec::Vec;
+
+use super::CanSocket;
+use super::sys::{self, CanFilter};
+use crate::error::Error;
+use crate::{ConstFilterConfig, FilterConfig, FrameTypes, Id, IdMask, IdMaskFilter, IdTypes};
+
+/// `struct can_filter` equivalent of an [`IdMaskFilter`].
+fn to_kernel(filter: &IdMaskFilter) -> CanFilter {
+    let mut can_id = match filter.id {
+        Id::Standard(id) => u32::from(id.as_raw()),
+        Id::Extended(id) => id.as_raw() | sys::CAN_EFF_FLAG,
+    };
+    let mut can_mask = match (filter.id, filter.mask) {
+        (Id::Standard(_), IdMask::Standard(mask)) => u32::from(mask) & sys::CAN_SFF_MASK,
+        (_, IdMask::Standard(mask)) => u32::from(mask),
+        (_, IdMask::Extended(mask)) => mask & sys::CAN_EFF_MASK,
+    };
+    if filter.id_types == IdTypes::SameAsFilter {
+        can_mask |= sys::CAN_EFF_FLAG;
+    }
+    match filter.frame_types {
+        FrameTypes::Both => {}
+        FrameTypes::Data => can_mask |= sys::CAN_RTR_FLAG,
+        FrameTypes::Remote => {
+            can_mask |= sys::CAN_RTR_FLAG;
+            can_id |= sys::CAN_RTR_FLAG;
+        }
+    }
+    CanFilter { can_id, can_mask }
+}
+
+/// Filter matching every frame.
+const ACCEPT_ALL: CanFilter = CanFilter {
+    can_id: 0,
+    can_mask: 0,
+};
+
+/// Install `filters` on `fd`, combined with AND if `join` is set and with OR otherwise.
+fn install(fd: RawFd, filters: &[CanFilter], join: bool) -> io::Result<()> {
+    match sys::set_option(
+        fd,
+        sys::SOL_CAN_RAW,
+        sys::CAN_RAW_JOIN_FILTERS,
+        &libc::c_int::from(join),
+    ) {
+        // Kernels before 4.1 always combine with OR; that only matters for several filters.
+        Err(e) if e.raw_os_error() == Some(libc::ENOPROTOOPT) && (!join || filters.len() < 2) => {}
+        result => result?,
+    }
+    sys::set_option(fd, sys::SOL_CAN_RAW, sys::CAN_RAW_FILTER, filters)
+}
+
+/// Handle for the kernel filters of a [`CanSocket`], returned by
+/// [`FilterConfig::modify_filters`].
+///
+/// The kernel drops frames the filters reject before they reach the socket, so uninteresting
+/// traffic on a busy bus costs no system calls. Besides the plain accept list of
+/// [`FilterConfig::set_filters`], the handle can install a reject list built from inverted
+/// filters.
+#[derive(Debug)]
+pub struct KernelFilters<'a> {
+    fd: BorrowedFd<'a>,
+}
+
+impl KernelFilters<'_> {
+    /// Receive frames accepted by any of `filters`; an empty list accepts every frame.
+    pub fn accept(&mut self, filters: &[IdMaskFilter]) -> io::Result<()> {
+        let filters: Vec<CanFilter> = filters.iter().map(to_kernel).collect();
+        if filters.is_empty() {
+            return install(self.fd.as_raw_fd(), &[ACCEPT_ALL], false);
+        }
+        install(self.fd.as_raw_fd(), &filters, false)
+    }
+
+    /// Receive every frame except those accepted by one of `filters`.
+    ///
+    /// Uses inverted filters joined with `CAN_RAW_JOIN_FILTERS`, so several filters need Linux
+    /// 4.1 or later. An empty list accepts every frame.
+    pub fn reject(&mut self, filters: &[IdMaskFilter]) -> io::Result<()> {
+        let filters: Vec<CanFilter> = filters
+            .iter()
+            .map(|filter| {
+                let filter = to_kernel(filter);
+                CanFilter {
+                    can_id: filter.can_id | sys::CAN_INV_FILTER,
+                    ..filter
+                }
+            })
+            .collect();
+        if filters.is_empty() {
+            return install(self.fd.as_raw_fd(), &[ACCEPT_ALL], false);
+        }
+        install(self.fd.as_raw_fd(), &filters, true)
+    }
+
+    /// Receive no frames at all, e.g. on a socket used only for transmission.
+    pub fn reject_all(&mut self) -> io::Result<()> {
+        install(self.fd.as_raw_fd(), &[], false)
+    }
+}
+
+/// Filters are installed in the kernel with `CAN_RAW_FILTER`; see [`KernelFilters`].
+impl<F> FilterConfig for CanSocket<F> {
+    type Error = Error;
+    type FiltersHandle<'a>
+        = KernelFilters<'a>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.modify_filters().accept(filters).map_err(Error::from)
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        KernelFilters {
+            fd: self.fd.as_fd(),
+        }
+    }
+}
+
+/// The kernel accepts up to 512 filters per socket (`CAN_RAW_FILTER_MAX`).
+impl<F> ConstFilterConfig for CanSocket<F> {
+    const MAX_FILTERS: usize = sys::CAN_RAW_FILTER_MAX;
+}
+
+#[cfg(feature = "socketcan-tokio")]
+impl<F> FilterConfig for super::AsyncCanSocket<F> {
+    type Error = Error;
+    type FiltersHandle<'a>
+        = KernelFilters<'a>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.modify_filters().accept(filters).map_err(Error::from)
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        KernelFilters {
+            fd: self.get_ref().as_fd(),
+        }
+    }
+}
+
+#[cfg(feature = "socketcan-tokio")]
+impl<F> ConstFilterConfig for super::AsyncCanSocket<F> {
+    const MAX_FILTERS: usize = sys::CAN_RAW_FILTER_MAX;
+}
+
+#[cfg(feature = "socketcan-uring")]
+impl<F> FilterConfig for super::UringCanSocket<F> {
+    type Error = Error;
+    type FiltersHandle<'a>
+        = KernelFilters<'a>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.modify_filters().accept(filters).map_err(Error::from)
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        KernelFilters {
+            fd: self.get_ref().as_fd(),
+        }
+    }
+}
+
+#[cfg(feature = "socketcan-uring")]
+impl<F> ConstFilterConfig for super::UringCanSocket<F> {
+    const MAX_FILTERS: usize = sys::CAN_RAW_FILTER_MAX;
+}
@@ -0,0 +1,169 @@
+//! Kernel ISO-TP (`CAN_ISOTP`) sockets.
+//!
+//! The structures mirror `<linux/can/isotp.h>`.
+
+use core::time::Duration;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::time::Instant;
+
+use super::sys::{self, SockaddrCan, Wait};
+use crate::error::Error;
+use crate::isotp::{IsoTpConfig, IsoTpError, MAX_MESSAGE_LEN, MessageIo};
+
+const CAN_ISOTP: libc::c_int = 6;
+const SOL_CAN_ISOTP: libc::c_int = 100 + CAN_ISOTP;
+const CAN_ISOTP_OPTS: libc::c_int = 1;
+const CAN_ISOTP_RECV_FC: libc::c_int = 2;
+
+const CAN_ISOTP_TX_PADDING: u32 = 0x0004;
+
+/// `struct can_isotp_options`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct IsoTpOptions {
+    flags: u32,
+    frame_txtime: u32,
+    ext_address: u8,
+    txpad_content: u8,
+    rxpad_content: u8,
+    rx_ext_address: u8,
+}
+
+/// `struct can_isotp_fc_options`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct IsoTpFcOptions {
+    bs: u8,
+    stmin: u8,
+    wftmax: u8,
+}
+
+/// An ISO-TP channel on one interface, using the kernel's ISO-TP implementation.
+///
+/// Implements [`MessageIo`] with the same error type as the in-crate
+/// [`IsoTp`](crate::isotp::IsoTp) over a [`CanSocket`](super::CanSocket), so the two can be
+/// swapped, also at runtime behind `dyn MessageIo<Error = IsoTpError<Error>>`.
+///
+/// [`IsoTpConfig`] is applied when the socket is opened. The kernel uses its own frame timeouts,
+/// so [`IsoTpConfig::timeout`] has no effect, and it reports most protocol failures as plain I/O
+/// errors; those it distinguishes map to the matching [`IsoTpError`] variants.
+#[derive(Debug)]
+pub struct IsoTpSocket {
+    fd: OwnedFd,
+}
+
+impl IsoTpSocket {
+    /// Open a channel on the interface `interface`.
+    pub fn open(interface: &str, config: &IsoTpConfig) -> io::Result<Self> {
+        let ifindex = sys::interface_index(interface)?;
+        let fd = sys::socket(libc::SOCK_DGRAM, CAN_ISOTP)?;
+        let options = IsoTpOptions {
+            flags: if config.padding.is_some() {
+                CAN_ISOTP_TX_PADDING
+            } else {
+                0
+            },
+            txpad_content: config.padding.unwrap_or(0),
+            ..IsoTpOptions::default()
+        };
+        sys::set_option(fd.as_raw_fd(), SOL_CAN_ISOTP, CAN_ISOTP_OPTS, &options)?;
+        let flow_control = IsoTpFcOptions {
+            bs: config.block_size,
+            stmin: config.st_min,
+            wftmax: config.max_waits,
+        };
+        sys::set_option(
+            fd.as_raw_fd(),
+            SOL_CAN_ISOTP,
+            CAN_ISOTP_RECV_FC,
+            &flow_control,
+        )?;
+        let rx_id = sys::raw_id(config.rx_id).to_ne_bytes();
+        let tx_id = sys::raw_id(config.tx_id).to_ne_bytes();
+        let mut addr = SockaddrCan::new(ifindex);
+        addr.addr[0] = u64::from_ne_bytes([
+            rx_id[0], rx_id[1], rx_id[2], rx_id[3], tx_id[0], tx_id[1], tx_id[2], tx_id[3],
+        ]);
+        sys::bind(&fd, &addr)?;
+        Ok(Self { fd })
+    }
+
+    fn wait(&self, events: libc::c_short, wait: Wait) -> Result<(), IsoTpError<Error>> {
+        match sys::poll(self.fd.as_raw_fd(), events, wait) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(IsoTpError::Timeout),
+            Err(e) => Err(IsoTpError::Io(e.into())),
+        }
+    }
+}
+
+/// Map the kernel's ISO-TP errors to [`IsoTpError`].
+fn isotp_error(error: io::Error) -> IsoTpError<Error> {
+    match error.raw_os_error() {
+        Some(libc::ETIMEDOUT) | Some(libc::ECOMM) => IsoTpError::Timeout,
+        Some(libc::EILSEQ) => IsoTpError::WrongSequence,
+        // Raised for a flow-control overflow; oversized messages are rejected before sending.
+        Some(libc::EMSGSIZE) => IsoTpError::Overflow,
+        Some(libc::EBADMSG) => IsoTpError::InvalidFrame,
+        _ => IsoTpError::Io(error.into()),
+    }
+}
+
+impl MessageIo for IsoTpSocket {
+    type Error = IsoTpError<Error>;
+
+    fn send_message(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        if data.len() > MAX_MESSAGE_LEN {
+            return Err(IsoTpError::MessageTooLong);
+        }
+        loop {
+            // SAFETY: `data` is valid for reads of its length.
+            let result = sys::cvt(unsafe {
+                libc::write(self.fd.as_raw_fd(), data.as_ptr().cast(), data.len())
+            });
+            match result {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.wait(libc::POLLOUT, Wait::Forever)?;
+                }
+                Err(e) => return Err(isotp_error(e)),
+                Ok(_) => return Ok(()),
+            }
+        }
+    }
+
+    fn recv_message(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Self::Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // SAFETY: `buf` is valid for writes of its length.
+            let result = sys::cvt(unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    libc::MSG_TRUNC,
+                )
+            });
+            match result {
+                Ok(len) if len as usize > buf.len() => return Err(IsoTpError::BufferTooSmall),
+                Ok(len) => return Ok(len as usize),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.wait(libc::POLLIN, Wait::Until(deadline))?;
+                }
+                Err(e) => return Err(isotp_error(e)),
+            }
+        }
+    }
+}
+
+impl AsFd for IsoTpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for IsoTpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,228 @@
+//! Kernel J1939 (`CAN_J1939`) sockets.
+//!
+//! The constants mirror `<linux/can/j1939.h>`.
+
+use core::time::Duration;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::time::Instant;
+
+use super::sys::{self, SockaddrCan, Wait};
+use crate::j1939::Name;
+
+const CAN_J1939: libc::c_int = 7;
+const SOL_CAN_J1939: libc::c_int = 100 + CAN_J1939;
+const SO_J1939_PROMISC: libc::c_int = 2;
+const SO_J1939_SEND_PRIO: libc::c_int = 3;
+
+const J1939_NO_NAME: u64 = 0;
+const J1939_NO_PGN: u32 = 0x4_0000;
+
+/// `struct sockaddr_can` with its J1939 address (`name`, `pgn`, `addr`).
+fn j1939_addr(ifindex: libc::c_int, name: Option<Name>, pgn: Option<u32>, addr: u8) -> SockaddrCan {
+    let pgn = pgn.unwrap_or(J1939_NO_PGN).to_ne_bytes();
+    let mut addr_can = SockaddrCan::new(ifindex);
+    addr_can.addr = [
+        name.map_or(J1939_NO_NAME, |name| name.0),
+        u64::from_ne_bytes([pgn[0], pgn[1], pgn[2], pgn[3], addr, 0, 0, 0]),
+    ];
+    addr_can
+}
+
+/// Split the J1939 address of a `struct sockaddr_can` into NAME, PGN and address.
+fn split_addr(addr: &SockaddrCan) -> (Option<Name>, u32, u8) {
+    let name = (addr.addr[0] != J1939_NO_NAME).then_some(Name(addr.addr[0]));
+    let bytes = addr.addr[1].to_ne_bytes();
+    let pgn = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (name, pgn, bytes[4])
+}
+
+/// Where a message received by [`J1939Socket`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct J1939Received {
+    /// Parameter group number of the message.
+    pub pgn: u32,
+    /// Source address of the sender.
+    pub source: u8,
+    /// NAME of the sender, if the kernel has seen it claim `source`.
+    pub source_name: Option<Name>,
+    /// Length of the message data.
+    pub len: usize,
+}
+
+/// A J1939 socket on one interface, using the kernel's J1939 stack.
+///
+/// The kernel segments and reassembles messages longer than 8 bytes with the transport
+/// protocols (TP and ETP) and, for sockets bound to a [`Name`], tracks address claims on the bus
+/// so destinations can be given by NAME. It does not claim an address itself: a NAME-bound
+/// socket sends its Address Claimed message like any other (PGN
+/// [`PGN_ADDRESS_CLAIMED`](crate::j1939::PGN_ADDRESS_CLAIMED) to
+/// [`GLOBAL_ADDRESS`](crate::j1939::GLOBAL_ADDRESS)), or the claim is left to a daemon such as
+/// `j1939acd`.
+///
+/// Broadcasts are enabled on the socket. Timeouts are reported as [`io::ErrorKind::TimedOut`].
+#[derive(Debug)]
+pub struct J1939Socket {
+    fd: OwnedFd,
+    ifindex: libc::c_int,
+}
+
+impl J1939Socket {
+    /// Open a socket on the interface `interface` with the source address `address`.
+    ///
+    /// With a `name`, the socket uses whatever address the kernel has seen `name` claim, and
+    /// `address` may be [`NULL_ADDRESS`](crate::j1939::NULL_ADDRESS) until a claim has been made.
+    /// `pgn` restricts reception to one parameter group.
+    pub fn open(
+        interface: &str,
+        name: Option<Name>,
+        address: u8,
+        pgn: Option<u32>,
+    ) -> io::Result<Self> {
+        let ifindex = sys::interface_index(interface)?;
+        let fd = sys::socket(libc::SOCK_DGRAM, CAN_J1939)?;
+        let broadcast: libc::c_int = 1;
+        sys::set_option(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BROADCAST,
+            &broadcast,
+        )?;
+        sys::bind(&fd, &j1939_addr(ifindex, name, pgn, address))?;
+        Ok(Self { fd, ifindex })
+    }
+
+    /// Set the priority (0–7, 0 highest) of sent messages; the kernel default is 6.
+    pub fn set_priority(&self, priority: u8) -> io::Result<()> {
+        let priority = libc::c_int::from(priority);
+        sys::set_option(
+            self.fd.as_raw_fd(),
+            SOL_CAN_J1939,
+            SO_J1939_SEND_PRIO,
+            &priority,
+        )
+    }
+
+    /// Receive messages for all destinations, not only this socket's address and broadcasts.
+    pub fn set_promiscuous(&self, promiscuous: bool) -> io::Result<()> {
+        let promiscuous = libc::c_int::from(promiscuous);
+        sys::set_option(
+            self.fd.as_raw_fd(),
+            SOL_CAN_J1939,
+            SO_J1939_PROMISC,
+            &promiscuous,
+        )
+    }
+
+    /// Send `data` as parameter group `pgn` to the node at `destination`, or to everyone with
+    /// [`GLOBAL_ADDRESS`](crate::j1939::GLOBAL_ADDRESS).
+    pub fn send_to(&self, pgn: u32, destination: u8, data: &[u8]) -> io::Result<()> {
+        self.send(
+            &j1939_addr(self.ifindex, None, Some(pgn), destination),
+            data,
+        )
+    }
+
+    /// Send `data` as parameter group `pgn` to the node that claimed an address with `name`.
+    pub fn send_to_name(&self, pgn: u32, name: Name, data: &[u8]) -> io::Result<()> {
+        let destination = crate::j1939::NULL_ADDRESS;
+        self.send(
+            &j1939_addr(self.ifindex, Some(name), Some(pgn), destination),
+            data,
+        )
+    }
+
+    fn send(&self, addr: &SockaddrCan, data: &[u8]) -> io::Result<()> {
+        loop {
+            // SAFETY: `data` and `addr` are valid for the given lengths.
+            let result = sys::cvt(unsafe {
+                libc::sendto(
+                    self.fd.as_raw_fd(),
+                    data.as_ptr().cast(),
+                    data.len(),
+                    0,
+                    (addr as *const SockaddrCan).cast(),
+                    size_of::<SockaddrCan>() as libc::socklen_t,
+                )
+            });
+            match result {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    sys::poll(self.fd.as_raw_fd(), libc::POLLOUT, Wait::Forever)?;
+                }
+                Err(e) => return Err(e),
+                Ok(_) => return Ok(()),
+            }
+        }
+    }
+
+    /// Receive the next message into `buf`, waiting as long as it takes.
+    ///
+    /// Data beyond `buf.len()` is discarded; [`J1939Received::len`] reports the full length.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<J1939Received> {
+        self.recv(buf, Wait::Forever)
+    }
+
+    /// Receive the next message into `buf`, waiting at most `timeout`.
+    pub fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> io::Result<J1939Received> {
+        self.recv(buf, Wait::Until(Instant::now() + timeout))
+    }
+
+    /// Receive a message into `buf` if one is queued.
+    pub fn try_recv_from(&self, buf: &mut [u8]) -> io::Result<J1939Received> {
+        self.recv(buf, Wait::Never)
+    }
+
+    fn recv(&self, buf: &mut [u8], wait: Wait) -> io::Result<J1939Received> {
+        loop {
+            let mut addr = SockaddrCan::new(0);
+            let mut addr_len = size_of::<SockaddrCan>() as libc::socklen_t;
+            // SAFETY: `buf` and `addr` are valid for writes of the given lengths.
+            let result = sys::cvt(unsafe {
+                libc::recvfrom(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    libc::MSG_TRUNC,
+                    (&mut addr as *mut SockaddrCan).cast(),
+                    &mut addr_len,
+                )
+            });
+            match result {
+                Ok(len) => {
+                    let (source_name, pgn, source) = split_addr(&addr);
+                    return Ok(J1939Received {
+                        pgn,
+                        source,
+                        source_name,
+                        len: len as usize,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if matches!(wait, Wait::Never) {
+                        return Err(e);
+                    }
+                    if !sys::poll(self.fd.as_raw_fd(), libc::POLLIN, wait)? {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl AsFd for J1939Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for J1939Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
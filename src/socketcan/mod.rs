@@ -0,0 +1,390 @@
+//! SocketCAN backend (requires the `socketcan` feature; Linux only).
+//!
+//! [`CanSocket`] is a raw `CAN_RAW` socket bound to one interface. Like
+//! [`UdpMulticastBus`](crate::udp_multicast::UdpMulticastBus) it is generic over the frame type,
+//! converting to and from the kernel's `struct can_frame`:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::socketcan::CanSocket;
+//!
+//! let mut can: CanSocket<MyFrame> = CanSocket::open("can0")?;
+//! can.send(&frame)?;
+//! let reply = can.recv_timeout(Duration::from_millis(100))?;
+//! ```
+//!
+//! The socket is non-blocking underneath; blocking calls wait with `poll(2)`. Errors are
+//! [`crate::error::Error`]s: timeouts have kind
+//! [`ErrorKind::Timeout`](crate::error::ErrorKind::Timeout), and empty or full queues in `try_*`
+//! calls [`ErrorKind::WouldBlock`](crate::error::ErrorKind::WouldBlock). Error frames are
+//! discarded, as are frames the frame type cannot represent.
+//!
+//! [`FilterConfig`](crate::FilterConfig) installs acceptance filters in the kernel
+//! (`CAN_RAW_FILTER`), so rejected frames never reach userspace; [`KernelFilters`] adds reject
+//! lists built from inverted filters.
+//!
+//! [`CloneChannel`] opens further sockets on the same interface, each with
+//! its own filters and receive queue, so independent components need not share one socket.
+//!
+//! [`J1939Socket`] uses the kernel's J1939 stack, including its transport protocols, with the
+//! NAMEs and addresses of [`crate::j1939`]. [`IsoTpSocket`] uses the kernel's ISO-TP
+//! implementation behind the same [`MessageIo`](crate::isotp::MessageIo) interface as
+//! [`crate::isotp::IsoTp`].
+//!
+//! [`BcmSocket`] hands periodic transmission and receive timeouts to the kernel's broadcast
+//! manager, implementing [`PeriodicTx`](crate::cyclic::PeriodicTx) and
+//! [`MonitoredRx`](crate::watchdog::MonitoredRx) so gateways avoid a userspace wakeup per cyclic
+//! frame.
+//!
+//! With the `socketcan-tokio` feature, [`AsyncCanSocket`] provides the async traits on a tokio
+//! runtime, waiting on socket readiness instead of blocking a thread. With the `socketcan-uring`
+//! feature, [`UringCanSocket`] implements [`TxFrameIo::send_batch`] and
+//! [`RxFrameIo::recv_batch`] with io_uring, moving many frames per system call.
+
+mod bcm;
+mod filter;
+mod isotp;
+mod j1939;
+mod sys;
+mod timestamp;
+#[cfg(feature = "socketcan-tokio")]
+mod tokio_io;
+#[cfg(feature = "socketcan-uring")]
+mod uring;
+
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::string::String;
+use std::time::Instant;
+
+use embedded_can::Frame;
+
+use crate::error::Error;
+use crate::{ChunkedTx, CloneChannel, GatherError, Reconnectable, RxFrameIo, TxFrameIo};
+use sys::{RawFdFrame, RawFrame, SockaddrCan, Wait};
+
+pub use bcm::BcmSocket;
+pub use filter::KernelFilters;
+pub use isotp::IsoTpSocket;
+pub use j1939::{J1939Received, J1939Socket};
+pub use timestamp::TxTicket;
+
+#[cfg(feature = "socketcan-tokio")]
+pub use tokio_io::AsyncCanSocket;
+#[cfg(feature = "socketcan-uring")]
+pub use uring::UringCanSocket;
+
+/// Pause before retrying a write that failed because the interface queue was full.
+///
+/// The kernel reports a full queue as `ENOBUFS` without making the socket unwritable, so there
+/// is no readiness event to wait for.
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(1);
+
+/// A raw SocketCAN socket bound to one interface.
+#[derive(Debug)]
+pub struct CanSocket<F> {
+    fd: OwnedFd,
+    /// Interface name, for reopening.
+    interface: String,
+    /// Frames written so far, which is the kernel's `SOF_TIMESTAMPING_OPT_ID` key of the next one.
+    tx_count: AtomicU32,
+    /// Frame started with [`ChunkedTx::begin_frame`] and the number of payload bytes written.
+    chunked: Option<(RawFdFrame, usize)>,
+    /// Send-only socket with `CAN_RAW_FD_FRAMES`, opened for the first chunked CAN FD frame.
+    fd_tx: Option<OwnedFd>,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<F> CanSocket<F> {
+    /// Open a raw socket on the interface `name` (e.g. `can0` or `vcan0`).
+    pub fn open(name: &str) -> io::Result<Self> {
+        Self::open_index(name, sys::interface_index(name)?)
+    }
+
+    fn open_index(name: &str, ifindex: libc::c_int) -> io::Result<Self> {
+        let fd = sys::socket(libc::SOCK_RAW, sys::CAN_RAW)?;
+        sys::bind(&fd, &SockaddrCan::new(ifindex))?;
+        Ok(Self {
+            fd,
+            interface: name.into(),
+            tx_count: AtomicU32::new(0),
+            chunked: None,
+            fd_tx: None,
+            _frame: PhantomData,
+        })
+    }
+
+    /// Name of the interface the socket was opened on.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    #[cfg(feature = "socketcan-tokio")]
+    fn write_raw(&self, raw: &RawFrame) -> io::Result<()> {
+        sys::write_struct(self.fd.as_raw_fd(), raw)?;
+        self.tx_count.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn read_raw(&self) -> io::Result<RawFrame> {
+        sys::read_struct(self.fd.as_raw_fd())
+    }
+
+    fn send_raw(&self, raw: &RawFrame, wait: Wait) -> io::Result<()> {
+        write_waiting(self.fd.as_raw_fd(), raw, wait)?;
+        self.tx_count.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Send a frame assembled by [`ChunkedTx`]: as a classic frame if the payload fits, as a
+    /// CAN FD frame through [`CanSocket::fd_tx`] otherwise.
+    fn send_chunked(&mut self, raw: &RawFdFrame) -> io::Result<()> {
+        if raw.len <= 8 {
+            let mut classic = RawFrame {
+                can_id: raw.can_id,
+                len: raw.len,
+                ..RawFrame::default()
+            };
+            classic.data.copy_from_slice(&raw.data[..8]);
+            return self.send_raw(&classic, Wait::Forever);
+        }
+        let fd = match &self.fd_tx {
+            Some(fd) => fd.as_raw_fd(),
+            None => {
+                let fd = self.open_fd_tx()?;
+                self.fd_tx.insert(fd).as_raw_fd()
+            }
+        };
+        let raw = RawFdFrame {
+            flags: sys::CANFD_FDF,
+            ..*raw
+        };
+        write_waiting(fd, &raw, Wait::Forever)
+    }
+
+    /// Open a socket on the same interface that sends CAN FD frames and receives nothing.
+    fn open_fd_tx(&self) -> io::Result<OwnedFd> {
+        let fd = sys::socket(libc::SOCK_RAW, sys::CAN_RAW)?;
+        let no_filters: &[sys::CanFilter] = &[];
+        sys::set_option(
+            fd.as_raw_fd(),
+            sys::SOL_CAN_RAW,
+            sys::CAN_RAW_FILTER,
+            no_filters,
+        )?;
+        let enable: libc::c_int = 1;
+        sys::set_option(
+            fd.as_raw_fd(),
+            sys::SOL_CAN_RAW,
+            sys::CAN_RAW_FD_FRAMES,
+            &enable,
+        )?;
+        let ifindex = sys::bound_interface(self.fd.as_raw_fd())?;
+        sys::bind(&fd, &SockaddrCan::new(ifindex))?;
+        Ok(fd)
+    }
+
+    fn recv_frame(&self, wait: Wait) -> io::Result<F>
+    where
+        F: Frame,
+    {
+        loop {
+            match self.read_raw() {
+                Ok(raw) => {
+                    if let Some(frame) = sys::from_raw(&raw) {
+                        return Ok(frame);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if matches!(wait, Wait::Never) {
+                        return Err(e);
+                    }
+                    if !sys::poll(self.fd.as_raw_fd(), libc::POLLIN, wait)? {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Write `value` to the non-blocking socket `fd`, waiting for queue space as `wait` allows.
+fn write_waiting<T: Copy>(fd: RawFd, value: &T, wait: Wait) -> io::Result<()> {
+    loop {
+        match sys::write_struct(fd, value) {
+            Err(e) if is_queue_full(&e) => match wait {
+                Wait::Never => return Err(io::ErrorKind::WouldBlock.into()),
+                Wait::Until(deadline) if Instant::now() >= deadline => {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                _ => std::thread::sleep(QUEUE_FULL_BACKOFF),
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if matches!(wait, Wait::Never) {
+                    return Err(e);
+                }
+                if !sys::poll(fd, libc::POLLOUT, wait)? {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `ENOBUFS`: the interface transmit queue is full.
+fn is_queue_full(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ENOBUFS)
+}
+
+impl<F> AsFd for CanSocket<F> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl<F> AsRawFd for CanSocket<F> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Opens another raw socket bound to the same interface.
+///
+/// The new socket has the default options: no filters (every frame is received) and no
+/// timestamping. With the default `CAN_RAW_LOOPBACK`, frames sent through one socket are
+/// received by the others on the same host.
+impl<F> CloneChannel for CanSocket<F> {
+    type Error = Error;
+
+    fn clone_channel(&self) -> Result<Self, Self::Error> {
+        let ifindex = sys::bound_interface(self.fd.as_raw_fd())?;
+        Ok(Self::open_index(&self.interface, ifindex)?)
+    }
+}
+
+/// Opens a new socket on the interface with the same name, which is how a USB adapter shows up
+/// again after being replugged.
+///
+/// Socket options, including filters, are not carried over.
+impl<F> Reconnectable for CanSocket<F> {
+    type Error = Error;
+
+    fn reopen(&mut self) -> Result<(), Self::Error> {
+        *self = Self::open(&self.interface)?;
+        Ok(())
+    }
+}
+
+impl<F: Frame> TxFrameIo for CanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.send_raw(&sys::to_raw(frame), Wait::Forever)
+            .map_err(Error::from)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.send_raw(&sys::to_raw(frame), Wait::Never)
+            .map_err(Error::from)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.send_raw(&sys::to_raw(frame), Wait::Until(Instant::now() + timeout))
+            .map_err(Error::from)
+    }
+
+    /// Copies the segments straight into the kernel frame struct.
+    fn send_gather(
+        &mut self,
+        id: embedded_can::Id,
+        segments: &[&[u8]],
+    ) -> Result<(), GatherError<Self::Error>> {
+        let len: usize = segments.iter().map(|segment| segment.len()).sum();
+        if len > 8 {
+            return Err(GatherError::InvalidFrame);
+        }
+        let mut raw = RawFrame {
+            can_id: sys::raw_id(id),
+            len: len as u8,
+            ..RawFrame::default()
+        };
+        let mut written = 0;
+        for segment in segments {
+            raw.data[written..written + segment.len()].copy_from_slice(segment);
+            written += segment.len();
+        }
+        self.send_raw(&raw, Wait::Forever)
+            .map_err(|e| GatherError::Io(e.into()))
+    }
+}
+
+/// Chunks are written into the kernel frame struct. Payloads of up to 8 bytes are sent as
+/// classic frames; longer ones must have a CAN FD length (12, 16, 20, 24, 32, 48 or 64 bytes) and
+/// are sent as CAN FD frames without bitrate switching, which needs an interface in FD mode.
+/// CAN XL payloads are not supported.
+///
+/// FD frames go through a second, send-only socket with `CAN_RAW_FD_FRAMES`, opened on first use,
+/// so this socket keeps receiving classic frames only. They are not counted for
+/// [`CanSocket::send_tracked`] tickets.
+impl<F: Frame> ChunkedTx for CanSocket<F> {
+    fn begin_frame(&mut self, id: embedded_can::Id, len: usize) -> Result<(), Self::Error> {
+        self.chunked = None;
+        if !matches!(len, 0..=8 | 12 | 16 | 20 | 24 | 32 | 48 | 64) {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        let raw = RawFdFrame {
+            can_id: sys::raw_id(id),
+            len: len as u8,
+            ..RawFdFrame::default()
+        };
+        self.chunked = Some((raw, 0));
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let Some((raw, written)) = &mut self.chunked else {
+            return Err(io::ErrorKind::InvalidInput.into());
+        };
+        let end = *written + data.len();
+        if end > usize::from(raw.len) {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        raw.data[*written..end].copy_from_slice(data);
+        *written = end;
+        if end < usize::from(raw.len) {
+            return Ok(());
+        }
+        let raw = *raw;
+        self.chunked = None;
+        self.send_chunked(&raw).map_err(Error::from)
+    }
+}
+
+impl<F: Frame> RxFrameIo for CanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Forever).map_err(Error::from)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Never).map_err(Error::from)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Until(Instant::now() + timeout))
+            .map_err(Error::from)
+    }
+
+    /// Wait until a frame is queued; it may still be an error frame that `recv` discards.
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        sys::poll(self.fd.as_raw_fd(), libc::POLLIN, Wait::Forever)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+//! Kernel interface shared by the SocketCAN sockets.
+//!
+//! The structures mirror `<linux/can.h>` directly rather than going through `libc`'s definitions,
+//! whose field layout has changed between `libc` releases.
+
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::Instant;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+pub(super) const AF_CAN: libc::c_int = 29;
+pub(super) const CAN_RAW: libc::c_int = 1;
+pub(super) const CAN_BCM: libc::c_int = 2;
+
+pub(super) const CAN_EFF_FLAG: u32 = 0x8000_0000;
+pub(super) const CAN_RTR_FLAG: u32 = 0x4000_0000;
+pub(super) const CAN_ERR_FLAG: u32 = 0x2000_0000;
+pub(super) const CAN_INV_FILTER: u32 = 0x2000_0000;
+pub(super) const CAN_SFF_MASK: u32 = 0x0000_07FF;
+pub(super) const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+
+/// `struct can_frame`, which the kernel aligns to 8 bytes.
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RawFrame {
+    pub can_id: u32,
+    pub len: u8,
+    pub pad: u8,
+    pub res0: u8,
+    pub len8_dlc: u8,
+    pub data: [u8; 8],
+}
+
+const _: () = assert!(size_of::<RawFrame>() == 16, "CAN_MTU");
+
+/// `struct canfd_frame`, which the kernel aligns to 8 bytes.
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RawFdFrame {
+    pub can_id: u32,
+    pub len: u8,
+    pub flags: u8,
+    pub res0: u8,
+    pub res1: u8,
+    pub data: [u8; 64],
+}
+
+impl Default for RawFdFrame {
+    fn default() -> Self {
+        Self {
+            can_id: 0,
+            len: 0,
+            flags: 0,
+            res0: 0,
+            res1: 0,
+            data: [0; 64],
+        }
+    }
+}
+
+const _: () = assert!(size_of::<RawFdFrame>() == 72, "CANFD_MTU");
+
+/// `canfd_frame::flags` bit marking a CAN FD frame.
+pub(super) const CANFD_FDF: u8 = 0x04;
+
+pub(super) const SOL_CAN_RAW: libc::c_int = 100 + CAN_RAW;
+pub(super) const CAN_RAW_FILTER: libc::c_int = 1;
+pub(super) const CAN_RAW_FD_FRAMES: libc::c_int = 5;
+pub(super) const CAN_RAW_JOIN_FILTERS: libc::c_int = 6;
+/// Most filters the kernel accepts in one `CAN_RAW_FILTER` option.
+pub(super) const CAN_RAW_FILTER_MAX: usize = 512;
+
+/// `struct can_filter`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct CanFilter {
+    pub can_id: u32,
+    pub can_mask: u32,
+}
+
+/// `struct sockaddr_can`, with the protocol-specific address as raw words.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SockaddrCan {
+    pub family: libc::sa_family_t,
+    pub ifindex: libc::c_int,
+    pub addr: [u64; 2],
+}
+
+const _: () = assert!(size_of::<SockaddrCan>() == 24);
+
+impl SockaddrCan {
+    pub fn new(ifindex: libc::c_int) -> Self {
+        Self {
+            family: AF_CAN as libc::sa_family_t,
+            ifindex,
+            addr: [0; 2],
+        }
+    }
+}
+
+/// How a blocking operation waits for the socket.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Wait {
+    Never,
+    Forever,
+    Until(Instant),
+}
+
+/// Turn a negative syscall result into the current OS error.
+pub(super) fn cvt<T: Default + PartialOrd>(result: T) -> io::Result<T> {
+    if result < T::default() {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// Index of the network interface `name`.
+pub(super) fn interface_index(name: &str) -> io::Result<libc::c_int> {
+    let name = CString::new(name).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: `name` is a valid NUL-terminated string.
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    libc::c_int::try_from(index).map_err(|_| io::ErrorKind::InvalidInput.into())
+}
+
+/// Open a non-blocking CAN socket of the given type and protocol.
+pub(super) fn socket(ty: libc::c_int, protocol: libc::c_int) -> io::Result<OwnedFd> {
+    // SAFETY: plain syscall without pointer arguments.
+    let fd = cvt(unsafe {
+        libc::socket(
+            AF_CAN,
+            ty | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            protocol,
+        )
+    })?;
+    // SAFETY: `fd` was just returned by `socket` and is owned by nobody else.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Bind `fd` to `addr`.
+pub(super) fn bind(fd: &OwnedFd, addr: &SockaddrCan) -> io::Result<()> {
+    // SAFETY: `addr` points to a live `sockaddr_can` of the given size.
+    cvt(unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            (addr as *const SockaddrCan).cast(),
+            size_of::<SockaddrCan>() as libc::socklen_t,
+        )
+    })
+    .map(drop)
+}
+
+/// Index of the interface `fd` is bound to.
+pub(super) fn bound_interface(fd: RawFd) -> io::Result<libc::c_int> {
+    let mut addr = SockaddrCan::new(0);
+    let mut len = size_of::<SockaddrCan>() as libc::socklen_t;
+    // SAFETY: `addr` and `len` are valid for writes and `len` holds the size of `addr`.
+    cvt(unsafe { libc::getsockname(fd, (&mut addr as *mut SockaddrCan).cast(), &mut len) })?;
+    Ok(addr.ifindex)
+}
+
+/// Connect `fd` to `addr`.
+pub(super) fn connect(fd: &OwnedFd, addr: &SockaddrCan) -> io::Result<()> {
+    // SAFETY: `addr` points to a live `sockaddr_can` of the given size.
+    cvt(unsafe {
+        libc::connect(
+            fd.as_raw_fd(),
+            (addr as *const SockaddrCan).cast(),
+            size_of::<SockaddrCan>() as libc::socklen_t,
+        )
+    })
+    .map(drop)
+}
+
+/// Set a socket option to the bytes of `value`.
+pub(super) fn set_option<T: ?Sized>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: &T,
+) -> io::Result<()> {
+    // SAFETY: `value` points to `size_of_val(value)` readable bytes.
+    cvt(unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            (value as *const T).cast(),
+            size_of_val(value) as libc::socklen_t,
+        )
+    })
+    .map(drop)
+}
+
+/// Wait until `fd` reports `events`; returns `false` if `wait` expired first.
+pub(super) fn poll(fd: RawFd, events: libc::c_short, wait: Wait) -> io::Result<bool> {
+    loop {
+        let timeout = match wait {
+            Wait::Never => 0,
+            Wait::Forever => -1,
+            Wait::Until(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                // Round up so the deadline has passed when poll returns.
+                let millis = remaining.as_nanos().div_ceil(1_000_000);
+                libc::c_int::try_from(millis).unwrap_or(libc::c_int::MAX)
+            }
+        };
+        let mut pollfd = libc::pollfd {
+            fd,
+            events,
+            revents: 0,
+        };
+        // SAFETY: `pollfd` is a single valid entry.
+        match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout) }) {
+            Ok(0) => return Ok(false),
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read one fixed-size structure from `fd`.
+pub(super) fn read_struct<T: Copy + Default>(fd: RawFd) -> io::Result<T> {
+    match read_partial(fd)? {
+        (value, len) if len == size_of::<T>() => Ok(value),
+        _ => Err(io::ErrorKind::InvalidData.into()),
+    }
+}
+
+/// Read a message of at most `size_of::<T>()` bytes from `fd`, returning its length.
+///
+/// Bytes the message does not cover keep their default value.
+pub(super) fn read_partial<T: Copy + Default>(fd: RawFd) -> io::Result<(T, usize)> {
+    let mut value = T::default();
+    // SAFETY: `value` provides `size_of::<T>()` writable bytes, and `T` is a plain kernel struct.
+    let len = cvt(unsafe { libc::read(fd, (&mut value as *mut T).cast(), size_of::<T>()) })?;
+    Ok((value, len as usize))
+}
+
+/// Receive one message of at most `size_of::<T>()` bytes with `recvmsg(2)`, passing each
+/// control message (level, type, data) to `on_control`.
+///
+/// Returns the message, its length and the message flags.
+pub(super) fn recv_msg<T: Copy + Default>(
+    fd: RawFd,
+    flags: libc::c_int,
+    mut on_control: impl FnMut(libc::c_int, libc::c_int, &[u8]),
+) -> io::Result<(T, usize, libc::c_int)> {
+    let mut value = T::default();
+    let mut control = [0u64; 32];
+    let mut iov = libc::iovec {
+        iov_base: (&mut value as *mut T).cast(),
+        iov_len: size_of::<T>(),
+    };
+    // SAFETY: an all-zero `msghdr` is a valid empty header.
+    let mut msg: libc::msghdr = unsafe { core::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = size_of_val(&control) as _;
+    // SAFETY: `msg` points to the live buffers set up above.
+    let len = cvt(unsafe { libc::recvmsg(fd, &mut msg, flags) })?;
+    // SAFETY: the kernel filled `msg_controllen` bytes of `control` with well-formed control
+    // messages, which the `CMSG_*` helpers walk without leaving that range.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let header = &*cmsg;
+            let data_len = (header.cmsg_len as usize).saturating_sub(libc::CMSG_LEN(0) as usize);
+            let data = core::slice::from_raw_parts(libc::CMSG_DATA(cmsg), data_len);
+            on_control(header.cmsg_level, header.cmsg_type, data);
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Ok((value, len as usize, msg.msg_flags))
+}
+
+/// Write one fixed-size structure to `fd`.
+pub(super) fn write_struct<T: Copy>(fd: RawFd, value: &T) -> io::Result<()> {
+    // SAFETY: `value` provides `size_of::<T>()` readable bytes.
+    let len = cvt(unsafe { libc::write(fd, (value as *const T).cast(), size_of::<T>()) })?;
+    if len as usize != size_of::<T>() {
+        return Err(io::ErrorKind::WriteZero.into());
+    }
+    Ok(())
+}
+
+/// `can_id` of an identifier, with `CAN_EFF_FLAG` for extended identifiers.
+pub(super) fn raw_id(id: Id) -> u32 {
+    match id {
+        Id::Standard(id) => u32::from(id.as_raw()),
+        Id::Extended(id) => id.as_raw() | CAN_EFF_FLAG,
+    }
+}
+
+/// Identifier of a `can_id`, ignoring the RTR and error flags.
+pub(super) fn id_from_raw(can_id: u32) -> Option<Id> {
+    if can_id & CAN_EFF_FLAG != 0 {
+        ExtendedId::new(can_id & CAN_EFF_MASK).map(Id::Extended)
+    } else {
+        StandardId::new((can_id & CAN_SFF_MASK) as u16).map(Id::Standard)
+    }
+}
+
+pub(super) fn to_raw<F: Frame>(frame: &F) -> RawFrame {
+    let mut raw = RawFrame {
+        can_id: raw_id(frame.id()),
+        ..RawFrame::default()
+    };
+    if frame.is_remote_frame() {
+        raw.can_id |= CAN_RTR_FLAG;
+        raw.len = frame.dlc().min(8) as u8;
+    } else {
+        let data = frame.data();
+        let len = data.len().min(8);
+        raw.len = len as u8;
+        raw.data[..len].copy_from_slice(&data[..len]);
+    }
+    raw
+}
+
+/// Convert a received frame; error frames and frames `F` cannot represent yield `None`.
+pub(super) fn from_raw<F: Frame>(raw: &RawFrame) -> Option<F> {
+    if raw.can_id & CAN_ERR_FLAG != 0 {
+        return None;
+    }
+    let id = id_from_raw(raw.can_id)?;
+    let len = usize::from(raw.len).min(8);
+    if raw.can_id & CAN_RTR_FLAG != 0 {
+        F::new_remote(id, len)
+    } else {
+        F::new(id, &raw.data[..len])
+    }
+}
@@ -0,0 +1,194 @@
+//! Kernel and hardware timestamps (`SO_TIMESTAMPING`).
+
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::time::Instant;
+
+use embedded_can::Frame;
+
+use super::CanSocket;
+use super::sys::{self, RawFrame, Wait};
+use crate::error::Error;
+use crate::{RxMeta, RxMetaFrameIo, TxFrameIo};
+
+const SOF_TIMESTAMPING_TX_HARDWARE: u32 = 1 << 0;
+const SOF_TIMESTAMPING_TX_SOFTWARE: u32 = 1 << 1;
+const SOF_TIMESTAMPING_RX_HARDWARE: u32 = 1 << 2;
+const SOF_TIMESTAMPING_RX_SOFTWARE: u32 = 1 << 3;
+const SOF_TIMESTAMPING_SOFTWARE: u32 = 1 << 4;
+const SOF_TIMESTAMPING_RAW_HARDWARE: u32 = 1 << 6;
+const SOF_TIMESTAMPING_OPT_ID: u32 = 1 << 7;
+const SOF_TIMESTAMPING_OPT_TSONLY: u32 = 1 << 11;
+
+const SCM_CAN_RAW_ERRQUEUE: libc::c_int = 1;
+const SO_EE_ORIGIN_TIMESTAMPING: u8 = 4;
+
+/// `struct sock_extended_err`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ExtendedErr {
+    ee_errno: u32,
+    ee_origin: u8,
+    ee_type: u8,
+    ee_code: u8,
+    ee_pad: u8,
+    ee_info: u32,
+    ee_data: u32,
+}
+
+/// Identifies a frame sent with [`CanSocket::send_tracked`], to match it with its transmit
+/// timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TxTicket(u32);
+
+/// Timestamp from an `SCM_TIMESTAMPING` control message: the raw hardware one if the adapter
+/// provided it, the software one otherwise.
+fn parse_timestamping(data: &[u8]) -> Option<Duration> {
+    if data.len() < size_of::<[libc::timespec; 3]>() {
+        return None;
+    }
+    // SAFETY: `data` holds at least three `timespec`s; the read does not assume alignment.
+    let stamps: [libc::timespec; 3] =
+        unsafe { data.as_ptr().cast::<[libc::timespec; 3]>().read_unaligned() };
+    [stamps[2], stamps[0]]
+        .into_iter()
+        .find(|ts| ts.tv_sec != 0 || ts.tv_nsec != 0)
+        .map(|ts| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+impl<F> CanSocket<F> {
+    /// Ask the kernel to timestamp received and transmitted frames.
+    ///
+    /// Frames are stamped by the adapter where it supports hardware timestamping (which usually
+    /// has to be switched on for the interface as well, e.g. with `hwstamp_ctl`), and by the
+    /// kernel otherwise. Received timestamps are reported in [`RxMeta::timestamp`] by
+    /// [`RxMetaFrameIo`]; transmit timestamps by [`CanSocket::recv_tx_timestamp`].
+    ///
+    /// Software timestamps are `CLOCK_REALTIME`, hardware timestamps use the adapter's own
+    /// clock; neither is the timebase of a [`Clock`](crate::Clock).
+    pub fn enable_timestamping(&mut self) -> io::Result<()> {
+        let flags = SOF_TIMESTAMPING_RX_HARDWARE
+            | SOF_TIMESTAMPING_RX_SOFTWARE
+            | SOF_TIMESTAMPING_TX_HARDWARE
+            | SOF_TIMESTAMPING_TX_SOFTWARE
+            | SOF_TIMESTAMPING_SOFTWARE
+            | SOF_TIMESTAMPING_RAW_HARDWARE
+            | SOF_TIMESTAMPING_OPT_ID
+            | SOF_TIMESTAMPING_OPT_TSONLY;
+        sys::set_option(
+            self.fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_TIMESTAMPING,
+            &flags,
+        )?;
+        // The kernel numbers transmitted frames from zero once `OPT_ID` is set.
+        self.tx_count.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Send a frame like [`TxFrameIo::send`] and return a ticket for its transmit timestamp.
+    pub fn send_tracked(&mut self, frame: &F) -> io::Result<TxTicket>
+    where
+        F: Frame,
+    {
+        let ticket = TxTicket(self.tx_count.load(Ordering::Relaxed));
+        self.send(frame)?;
+        Ok(ticket)
+    }
+
+    /// Wait up to `timeout` for the next transmit timestamp.
+    ///
+    /// Timestamps arrive in transmission order once the frames have left the adapter; tickets
+    /// tell which frame each one belongs to. Requires [`CanSocket::enable_timestamping`].
+    pub fn recv_tx_timestamp(&mut self, timeout: Duration) -> io::Result<(TxTicket, Duration)> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut ticket = None;
+            let mut timestamp = None;
+            let result = sys::recv_msg::<RawFrame>(
+                self.fd.as_raw_fd(),
+                libc::MSG_ERRQUEUE,
+                |level, ty, data| match (level, ty) {
+                    (libc::SOL_SOCKET, libc::SO_TIMESTAMPING) => {
+                        timestamp = parse_timestamping(data);
+                    }
+                    (sys::SOL_CAN_RAW, SCM_CAN_RAW_ERRQUEUE)
+                        if data.len() >= size_of::<ExtendedErr>() =>
+                    {
+                        // SAFETY: `data` holds a `sock_extended_err`; the read does not assume
+                        // alignment.
+                        let err = unsafe { data.as_ptr().cast::<ExtendedErr>().read_unaligned() };
+                        if err.ee_origin == SO_EE_ORIGIN_TIMESTAMPING {
+                            ticket = Some(TxTicket(err.ee_data));
+                        }
+                    }
+                    _ => {}
+                },
+            );
+            match result {
+                Ok(_) => {
+                    if let (Some(ticket), Some(timestamp)) = (ticket, timestamp) {
+                        return Ok((ticket, timestamp));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // The error queue is signalled as `POLLERR`, which needs no request.
+                    if !sys::poll(self.fd.as_raw_fd(), 0, Wait::Until(deadline))? {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn recv_frame_meta(&self, wait: Wait) -> io::Result<(F, RxMeta)>
+    where
+        F: Frame,
+    {
+        loop {
+            let mut timestamp = None;
+            let result = sys::recv_msg::<RawFrame>(self.fd.as_raw_fd(), 0, |level, ty, data| {
+                if (level, ty) == (libc::SOL_SOCKET, libc::SO_TIMESTAMPING) {
+                    timestamp = parse_timestamping(data);
+                }
+            });
+            match result {
+                Ok((raw, len, flags)) => {
+                    if len != size_of::<RawFrame>() {
+                        return Err(io::ErrorKind::InvalidData.into());
+                    }
+                    if let Some(frame) = sys::from_raw(&raw) {
+                        let meta = RxMeta {
+                            // Set for frames this socket sent itself (`CAN_RAW_RECV_OWN_MSGS`).
+                            self_reception: flags & libc::MSG_CONFIRM != 0,
+                            timestamp,
+                            ..RxMeta::default()
+                        };
+                        return Ok((frame, meta));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if matches!(wait, Wait::Never) {
+                        return Err(e);
+                    }
+                    sys::poll(self.fd.as_raw_fd(), libc::POLLIN, wait)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Timestamps are only present after [`CanSocket::enable_timestamping`].
+impl<F: Frame> RxMetaFrameIo for CanSocket<F> {
+    fn recv_with_meta(&mut self) -> Result<(F, RxMeta), Error> {
+        self.recv_frame_meta(Wait::Forever).map_err(Error::from)
+    }
+
+    fn try_recv_with_meta(&mut self) -> Result<(F, RxMeta), Error> {
+        self.recv_frame_meta(Wait::Never).map_err(Error::from)
+    }
+}
@@ -0,0 +1,135 @@
+//! Async SocketCAN on tokio (requires the `socketcan-tokio` feature).
+
+use core::time::Duration;
+use std::io;
+use std::os::fd::AsRawFd;
+
+use embedded_can::Frame;
+use tokio::io::unix::AsyncFd;
+
+use super::sys::{self, Wait};
+use super::{CanSocket, QUEUE_FULL_BACKOFF, is_queue_full};
+use crate::error::Error;
+use crate::{AsyncRxFrameIo, AsyncTxFrameIo, CloneChannel, Reconnectable};
+
+/// A raw SocketCAN socket driven by tokio's reactor.
+///
+/// Operations wait for socket readiness through [`AsyncFd`], so many buses can be served from a
+/// few runtime threads. Timeouts use tokio's timer and are reported with
+/// [`ErrorKind::Timeout`](crate::error::ErrorKind::Timeout).
+#[derive(Debug)]
+pub struct AsyncCanSocket<F> {
+    inner: AsyncFd<CanSocket<F>>,
+}
+
+impl<F> AsyncCanSocket<F> {
+    /// Open a raw socket on the interface `name` and register it with the current tokio runtime.
+    pub fn open(name: &str) -> io::Result<Self> {
+        Self::new(CanSocket::open(name)?)
+    }
+
+    /// Register an open socket with the current tokio runtime.
+    ///
+    /// Must be called from within a runtime with I/O enabled.
+    pub fn new(socket: CanSocket<F>) -> io::Result<Self> {
+        Ok(Self {
+            inner: AsyncFd::new(socket)?,
+        })
+    }
+
+    /// Borrow the underlying socket.
+    pub fn get_ref(&self) -> &CanSocket<F> {
+        self.inner.get_ref()
+    }
+
+    /// Deregister from the runtime, returning the underlying socket.
+    pub fn into_inner(self) -> CanSocket<F> {
+        self.inner.into_inner()
+    }
+}
+
+/// Opens another socket on the same interface (see [`CanSocket`]'s implementation) and registers
+/// it with the current tokio runtime.
+impl<F> CloneChannel for AsyncCanSocket<F> {
+    type Error = Error;
+
+    fn clone_channel(&self) -> Result<Self, Self::Error> {
+        Ok(Self::new(self.get_ref().clone_channel()?)?)
+    }
+}
+
+/// Reopens the socket by name (see [`CanSocket`]'s implementation) and registers it with the
+/// current tokio runtime.
+impl<F> Reconnectable for AsyncCanSocket<F> {
+    type Error = Error;
+
+    fn reopen(&mut self) -> Result<(), Self::Error> {
+        *self = Self::open(self.get_ref().interface())?;
+        Ok(())
+    }
+}
+
+impl<F: Frame> AsyncTxFrameIo for AsyncCanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let raw = sys::to_raw(frame);
+        loop {
+            let mut guard = self.inner.writable().await?;
+            match guard.try_io(|socket| socket.get_ref().write_raw(&raw)) {
+                Ok(Err(e)) if is_queue_full(&e) => tokio::time::sleep(QUEUE_FULL_BACKOFF).await,
+                Ok(result) => return result.map_err(Error::from),
+                Err(_would_block) => {}
+            }
+        }
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        tokio::time::timeout(timeout, self.send(frame))
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+    }
+}
+
+impl<F: Frame> AsyncRxFrameIo for AsyncCanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            match guard.try_io(|socket| socket.get_ref().read_raw()) {
+                Ok(Ok(raw)) => {
+                    if let Some(frame) = sys::from_raw(&raw) {
+                        return Ok(frame);
+                    }
+                }
+                Ok(Err(e)) => return Err(e.into()),
+                Err(_would_block) => {}
+            }
+        }
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        tokio::time::timeout(timeout, self.recv())
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+    }
+
+    /// Wait until a frame is queued; it may still be an error frame that `recv` discards.
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            // Readiness can be stale; check without consuming a frame.
+            if sys::poll(self.inner.as_raw_fd(), libc::POLLIN, Wait::Never)? {
+                return Ok(());
+            }
+            guard.clear_ready();
+        }
+    }
+}
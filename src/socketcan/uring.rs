@@ -0,0 +1,233 @@
+//! Bulk SocketCAN I/O through io_uring (requires the `socketcan-uring` feature).
+
+use core::time::Duration;
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::time::Instant;
+use std::vec::Vec;
+
+use embedded_can::Frame;
+use io_uring::{IoUring, opcode, squeue, types};
+
+use super::sys::{self, RawFrame, Wait};
+use super::{CanSocket, QUEUE_FULL_BACKOFF, is_queue_full};
+use crate::error::Error;
+use crate::{PartialSend, RxFrameIo, SendOptions, TxFrameIo};
+
+/// A raw SocketCAN socket that moves whole batches of frames per system call.
+///
+/// [`TxFrameIo::send_batch`] and [`RxFrameIo::recv_batch`] submit up to `depth` writes or reads
+/// to an io_uring at once, which keeps logging rigs and gateways on busy buses from spending
+/// their time in per-frame syscalls. Single-frame operations behave exactly like [`CanSocket`].
+///
+/// SocketCAN has no per-frame transmit options, so the [`SendOptions`] of a batch are ignored.
+pub struct UringCanSocket<F> {
+    socket: CanSocket<F>,
+    ring: IoUring,
+    buffers: Vec<RawFrame>,
+    results: Vec<i32>,
+}
+
+impl<F> core::fmt::Debug for UringCanSocket<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UringCanSocket")
+            .field("fd", &self.raw_fd())
+            .field("depth", &self.buffers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> UringCanSocket<F> {
+    /// Open a raw socket on the interface `name` with room for `depth` frames per batch.
+    pub fn open(name: &str, depth: u32) -> io::Result<Self> {
+        Self::new(CanSocket::open(name)?, depth)
+    }
+
+    /// Use an open socket with room for `depth` frames per batch.
+    pub fn new(socket: CanSocket<F>, depth: u32) -> io::Result<Self> {
+        let ring = IoUring::new(depth.max(1))?;
+        let depth = ring.params().sq_entries() as usize;
+        Ok(Self {
+            socket,
+            ring,
+            buffers: std::vec![RawFrame::default(); depth],
+            results: std::vec![0; depth],
+        })
+    }
+
+    /// Borrow the underlying socket.
+    pub fn get_ref(&self) -> &CanSocket<F> {
+        &self.socket
+    }
+
+    /// Unwrap, returning the underlying socket.
+    pub fn into_inner(self) -> CanSocket<F> {
+        self.socket
+    }
+
+    /// Submit `count` linked reads or writes of `buffers[..count]` and wait for all of them.
+    ///
+    /// Returns the number of leading operations that transferred a whole frame, and the error of
+    /// the first one that did not. Linking makes the kernel cancel everything after a failure,
+    /// so the frames stay in order.
+    fn run(&mut self, count: usize, write: bool) -> io::Result<(usize, Option<io::Error>)> {
+        let fd = types::Fd(self.socket.as_raw_fd());
+        let len = size_of::<RawFrame>() as u32;
+        {
+            let mut submission = self.ring.submission();
+            for (index, buffer) in self.buffers[..count].iter_mut().enumerate() {
+                let entry = if write {
+                    opcode::Write::new(fd, (buffer as *const RawFrame).cast(), len).build()
+                } else {
+                    opcode::Read::new(fd, (buffer as *mut RawFrame).cast(), len).build()
+                };
+                let flags = if index + 1 < count {
+                    squeue::Flags::IO_LINK
+                } else {
+                    squeue::Flags::empty()
+                };
+                // SAFETY: the buffer lives in `self.buffers`, which is neither moved nor touched
+                // until all `count` completions have been reaped below.
+                unsafe { submission.push(&entry.flags(flags).user_data(index as u64)) }
+                    .map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+            }
+        }
+        self.ring.submit_and_wait(count)?;
+        for completion in self.ring.completion() {
+            if let Some(result) = self.results.get_mut(completion.user_data() as usize) {
+                *result = completion.result();
+            }
+        }
+        let mut done = 0;
+        for &result in &self.results[..count] {
+            match result {
+                result if result == len as i32 => done += 1,
+                result if result < 0 => {
+                    return Ok((done, Some(io::Error::from_raw_os_error(-result))));
+                }
+                _ => return Ok((done, Some(io::ErrorKind::InvalidData.into()))),
+            }
+        }
+        Ok((done, None))
+    }
+
+    fn raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl<F: Frame> TxFrameIo for UringCanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.socket.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.socket.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.socket.send_timeout(frame, timeout)
+    }
+
+    /// Send the frames in batches of up to `depth` writes per system call, waiting while the
+    /// interface queue is full.
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let mut sent = 0;
+        while sent < frames.len() {
+            let count = (frames.len() - sent).min(self.buffers.len());
+            for (buffer, (frame, _)) in self.buffers.iter_mut().zip(&frames[sent..sent + count]) {
+                *buffer = sys::to_raw(frame);
+            }
+            let (done, error) = self.run(count, true).map_err(|error| PartialSend {
+                sent,
+                error: error.into(),
+            })?;
+            sent += done;
+            self.socket
+                .tx_count
+                .fetch_add(done as u32, core::sync::atomic::Ordering::Relaxed);
+            match error {
+                None => {}
+                Some(error) if is_queue_full(&error) => std::thread::sleep(QUEUE_FULL_BACKOFF),
+                Some(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    sys::poll(self.raw_fd(), libc::POLLOUT, Wait::Forever).map_err(|error| {
+                        PartialSend {
+                            sent,
+                            error: error.into(),
+                        }
+                    })?;
+                }
+                Some(error) => {
+                    return Err(PartialSend {
+                        sent,
+                        error: error.into(),
+                    });
+                }
+            }
+        }
+        Ok(sent)
+    }
+}
+
+impl<F: Frame> RxFrameIo for UringCanSocket<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.socket.recv()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.socket.try_recv()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.socket.recv_timeout(timeout)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.socket.wait_not_empty()
+    }
+
+    /// Drain queued frames with up to `depth` reads per system call.
+    fn recv_batch<E>(
+        &mut self,
+        frames: &mut E,
+        max: usize,
+        timeout: Duration,
+    ) -> Result<usize, Self::Error>
+    where
+        E: Extend<Self::Frame>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut received = 0;
+        while received < max {
+            if received == 0 && !sys::poll(self.raw_fd(), libc::POLLIN, Wait::Until(deadline))? {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let count = (max - received).min(self.buffers.len());
+            let (done, error) = self.run(count, false)?;
+            for raw in &self.buffers[..done] {
+                if let Some(frame) = sys::from_raw(raw) {
+                    frames.extend(Some(frame));
+                    received += 1;
+                }
+            }
+            match error {
+                None => {}
+                // Queue drained, or a later error the next receive will report.
+                Some(_) if received > 0 => break,
+                // The queue held only error frames; wait again.
+                Some(error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                Some(error) => return Err(error.into()),
+            }
+        }
+        Ok(received)
+    }
+}
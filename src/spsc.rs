@@ -0,0 +1,298 @@
+//! Lock-free single-producer single-consumer TX queue.
+//!
+//! A common design has a task produce frames and the TX-complete interrupt (or a task on another
+//! core) move them into the controller's mailboxes. [`SpscQueue`] connects the two without a
+//! critical section: its halves share the queue through atomic loads and stores only, so
+//! neither side ever masks interrupts or waits for the other.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::spsc::SpscQueue;
+//! use embedded_can_interface::static_can::StaticCan;
+//!
+//! static QUEUE: StaticCan<SpscQueue<MyFrame, 32>> = StaticCan::new();
+//!
+//! let (mut producer, consumer) = QUEUE.init(SpscQueue::new()).split();
+//! // Move `consumer` into the TX interrupt handler, which calls
+//! // `consumer.drain_into(&mut can_tx)` whenever mailboxes free up.
+//! producer.try_send(&frame)?;
+//! ```
+//!
+//! [`TxProducer`] implements [`TxFrameIo`], so protocol code can send into the queue as if it
+//! were the interface.
+
+use core::cell::UnsafeCell;
+use core::error::Error;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::{TxFrameIo, TxRxState};
+
+/// Error returned by [`TxProducer::try_send`] when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxQueueFull;
+
+impl fmt::Display for TxQueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TX queue full")
+    }
+}
+
+impl Error for TxQueueFull {}
+
+/// Fixed-capacity queue of `N` frames, split into a [`TxProducer`] and a [`TxConsumer`].
+///
+/// The indices count up freely and wrap; a slot is read or written by only one side at a time.
+/// `N` must be a power of two, so that slots stay in step with the indices when they wrap.
+pub struct SpscQueue<F, const N: usize> {
+    slots: [UnsafeCell<MaybeUninit<F>>; N],
+    /// Number of frames ever dequeued; only the consumer stores it.
+    head: AtomicUsize,
+    /// Number of frames ever enqueued; only the producer stores it.
+    tail: AtomicUsize,
+}
+
+// SAFETY: the producer only writes slots outside `head..tail` and the consumer only reads slots
+// inside it; the index stores (`Release`) and loads (`Acquire`) order those accesses. Frames
+// move between contexts, hence `F: Send`.
+unsafe impl<F: Send, const N: usize> Sync for SpscQueue<F, N> {}
+
+impl<F, const N: usize> core::fmt::Debug for SpscQueue<F, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SpscQueue")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, const N: usize> SpscQueue<F, N> {
+    /// Create an empty queue.
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N.is_power_of_two(),
+                "SpscQueue needs a power-of-two number of slots"
+            )
+        };
+        Self {
+            slots: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split into the producing and consuming halves.
+    pub fn split(&mut self) -> (TxProducer<'_, F, N>, TxConsumer<'_, F, N>) {
+        (TxProducer { queue: self }, TxConsumer { queue: self })
+    }
+
+    /// Number of queued frames.
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    /// Returns `true` if no frames are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Slot for the frame with index `index`.
+    fn slot(&self, index: usize) -> *mut MaybeUninit<F> {
+        self.slots[index % N].get()
+    }
+}
+
+impl<F, const N: usize> Default for SpscQueue<F, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, const N: usize> Drop for SpscQueue<F, N> {
+    fn drop(&mut self) {
+        let (_, mut consumer) = self.split();
+        while consumer.dequeue().is_some() {}
+    }
+}
+
+/// Producing half of an [`SpscQueue`].
+pub struct TxProducer<'a, F, const N: usize> {
+    queue: &'a SpscQueue<F, N>,
+}
+
+impl<F, const N: usize> core::fmt::Debug for TxProducer<'_, F, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TxProducer")
+            .field("queue", self.queue)
+            .finish()
+    }
+}
+
+impl<F, const N: usize> TxProducer<'_, F, N> {
+    /// Append a frame, handing it back if the queue is full.
+    pub fn enqueue(&mut self, frame: F) -> Result<(), F> {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.queue.head.load(Ordering::Acquire)) == N {
+            return Err(frame);
+        }
+        // SAFETY: the slot is outside `head..tail`, so the consumer does not access it, and this
+        // is the only producer.
+        unsafe { (*self.queue.slot(tail)).write(frame) };
+        self.queue
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Number of queued frames.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` if no frames are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns `true` if another [`TxProducer::enqueue`] would fail.
+    pub fn is_full(&self) -> bool {
+        self.queue.len() == N
+    }
+}
+
+/// Sending queues the frame; the blocking methods spin until the consumer makes room.
+///
+/// Only [`TxFrameIo::try_send`] fails, with [`TxQueueFull`]. The queue cannot time out on its own,
+/// so [`TxFrameIo::send_timeout`] behaves like [`TxFrameIo::send`].
+impl<F: Clone, const N: usize> TxFrameIo for TxProducer<'_, F, N> {
+    type Frame = F;
+    type Error = TxQueueFull;
+
+    fn send(&mut self, frame: &F) -> Result<(), TxQueueFull> {
+        let mut frame = frame.clone();
+        while let Err(returned) = self.enqueue(frame) {
+            frame = returned;
+            core::hint::spin_loop();
+        }
+        Ok(())
+    }
+
+    fn try_send(&mut self, frame: &F) -> Result<(), TxQueueFull> {
+        self.enqueue(frame.clone()).map_err(|_| TxQueueFull)
+    }
+
+    fn send_timeout(&mut self, frame: &F, _timeout: Duration) -> Result<(), TxQueueFull> {
+        self.send(frame)
+    }
+}
+
+/// Consuming half of an [`SpscQueue`].
+pub struct TxConsumer<'a, F, const N: usize> {
+    queue: &'a SpscQueue<F, N>,
+}
+
+impl<F, const N: usize> core::fmt::Debug for TxConsumer<'_, F, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TxConsumer")
+            .field("queue", self.queue)
+            .finish()
+    }
+}
+
+impl<F, const N: usize> TxConsumer<'_, F, N> {
+    /// Remove and return the frame at the front of the queue.
+    pub fn dequeue(&mut self) -> Option<F> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        if self.queue.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+        // SAFETY: the slot is inside `head..tail`, so the producer has initialized it and does
+        // not access it until `head` moves past it. This is the only consumer.
+        let frame = unsafe { (*self.queue.slot(head)).assume_init_read() };
+        self.queue
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(frame)
+    }
+
+    /// Borrow the frame at the front of the queue.
+    pub fn peek(&self) -> Option<&F> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        if self.queue.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+        // SAFETY: as in `dequeue`; the frame stays in place until this consumer dequeues it,
+        // which needs `&mut self`.
+        Some(unsafe { (*self.queue.slot(head)).assume_init_ref() })
+    }
+
+    /// Number of queued frames.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` if no frames are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Move queued frames into the free transmit slots of `tx`, returning how many were moved.
+    ///
+    /// Uses [`TxRxState::tx_free`] to decide how many frames fit, so driver errors are never
+    /// mistaken for a full mailbox. If the driver does not count free slots, one frame is moved
+    /// when the transmitter is idle. A frame whose send fails stays at the front of the queue.
+    pub fn drain_into<T>(&mut self, tx: &mut T) -> Result<usize, <T as TxFrameIo>::Error>
+    where
+        T: TxFrameIo<Frame = F> + TxRxState<Error = <T as TxFrameIo>::Error>,
+    {
+        let free = crate::tx_capacity(tx)?;
+        let mut moved = 0;
+        while moved < free {
+            let Some(frame) = self.peek() else {
+                break;
+            };
+            tx.try_send(frame)?;
+            self.dequeue();
+            moved += 1;
+        }
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A queue whose indices start at `index`.
+    fn starting_at<const N: usize>(index: usize) -> SpscQueue<u32, N> {
+        let queue = SpscQueue::new();
+        queue.head.store(index, Ordering::Relaxed);
+        queue.tail.store(index, Ordering::Relaxed);
+        queue
+    }
+
+    #[test]
+    fn indices_wrap() {
+        let mut queue = starting_at::<4>(usize::MAX - 5);
+        let (mut producer, mut consumer) = queue.split();
+        let mut next = 0;
+        for _ in 0..4 {
+            for frame in next..next + 3 {
+                assert_eq!(producer.enqueue(frame), Ok(()));
+            }
+            assert_eq!(producer.enqueue(99), Ok(()));
+            assert!(producer.is_full());
+            assert_eq!(producer.enqueue(100), Err(100));
+            for frame in next..next + 3 {
+                assert_eq!(consumer.dequeue(), Some(frame));
+            }
+            assert_eq!(consumer.dequeue(), Some(99));
+            assert_eq!(consumer.dequeue(), None);
+            assert!(consumer.is_empty());
+            next += 3;
+        }
+        assert!(queue.head.load(Ordering::Relaxed) < 16);
+    }
+}
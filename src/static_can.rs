@@ -0,0 +1,89 @@
+//! Static storage for interfaces shared with interrupt handlers and tasks.
+//!
+//! RTIC resources, Embassy tasks, and bare-metal interrupt handlers generally need `'static`
+//! references. [`StaticCan`] is a one-shot cell that can live in a `static` and hands out a single
+//! `&'static mut` to its contents, so user code does not need `MaybeUninit` and `unsafe`:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::static_can::{StaticCan, init_split};
+//!
+//! static TX: StaticCan<MyTx> = StaticCan::new();
+//! static RX: StaticCan<MyRx> = StaticCan::new();
+//!
+//! let (tx, rx) = init_split(can, &TX, &RX);
+//! // Move `tx` into a task and `rx` into the RX interrupt handler.
+//! ```
+//!
+//! Only available on targets with atomic compare-and-swap.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::SplitTxRx;
+
+/// A one-shot cell for placing a CAN interface (or one half of it) in a `static`.
+///
+/// The cell starts empty. [`StaticCan::init`] moves a value in and returns the only mutable
+/// reference to it; the value lives for the rest of the program.
+pub struct StaticCan<T> {
+    taken: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: the `taken` flag guarantees at most one `&'static mut T` is ever created, so sharing the
+// cell only ever moves a `T` to whichever context calls `init`.
+unsafe impl<T: Send> Sync for StaticCan<T> {}
+
+impl<T> StaticCan<T> {
+    /// Create an empty cell.
+    pub const fn new() -> Self {
+        Self {
+            taken: AtomicBool::new(false),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Store `value` and return a `'static` mutable reference to it.
+    ///
+    /// Returns `Err(value)` if the cell was already initialized.
+    #[allow(clippy::mut_from_ref)] // uniqueness is enforced by the `taken` flag
+    pub fn try_init(&'static self, value: T) -> Result<&'static mut T, T> {
+        if self.taken.swap(true, Ordering::AcqRel) {
+            return Err(value);
+        }
+        // SAFETY: `taken` was false, so this is the only access to `value` there will ever be.
+        let slot = unsafe { &mut *self.value.get() };
+        Ok(slot.write(value))
+    }
+
+    /// Store `value` and return a `'static` mutable reference to it.
+    ///
+    /// # Panics
+    /// Panics if the cell was already initialized.
+    pub fn init(&'static self, value: T) -> &'static mut T {
+        match self.try_init(value) {
+            Ok(value) => value,
+            Err(_) => panic!("StaticCan already initialized"),
+        }
+    }
+}
+
+impl<T> Default for StaticCan<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split `can` and store both halves in static cells.
+///
+/// # Panics
+/// Panics if either cell was already initialized.
+pub fn init_split<T: SplitTxRx>(
+    can: T,
+    tx: &'static StaticCan<T::Tx>,
+    rx: &'static StaticCan<T::Rx>,
+) -> (&'static mut T::Tx, &'static mut T::Rx) {
+    let (tx_half, rx_half) = can.split();
+    (tx.init(tx_half), rx.init(rx_half))
+}
@@ -0,0 +1,501 @@
+//! Traffic counters and latency histograms.
+//!
+//! [`StatsMonitor`] wraps an interface and counts the frames it sends and receives. It also keeps
+//! two [`Histogram`]s: how long each send call takes to complete, and the gaps between received
+//! frames. They measure the same things on every backend, so they can be compared when tuning a
+//! driver or a task schedule:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::stats::StatsMonitor;
+//!
+//! let mut can = StatsMonitor::<_, _, 64>::new(can, clock, Duration::from_micros(50));
+//! // ... run the workload through `can` ...
+//! log!(
+//!     "send p50 {:?} p99 {:?}, rx gap max {:?}",
+//!     can.send_latency().percentile(50.0),
+//!     can.send_latency().percentile(99.0),
+//!     can.interarrival().max(),
+//! );
+//! ```
+//!
+//! Latencies are measured with the wrapper's [`Clock`], so their resolution is that of the clock.
+//!
+//! # `metrics` facade
+//!
+//! With the `metrics` feature, the monitor also publishes through the `metrics` facade, so an
+//! installed exporter (e.g. Prometheus) picks the traffic up without polling the wrapper:
+//!
+//! | Metric | Type | Updated by |
+//! |--------|------|------------|
+//! | `frames_tx_total` | counter | every frame sent successfully |
+//! | `frames_rx_total` | counter | every frame received |
+//! | `tx_latency_seconds` | histogram | every successful, unbatched send |
+//! | `rx_overruns_total` | counter | `StatsMonitor::publish_rx_overruns` |
+//!
+//! Metrics are registered with the recorder installed when they are first updated. Use
+//! `StatsMonitor::with_metrics_label` to tell several interfaces apart, and `describe_metrics`
+//! to register help text. [`StatsMonitor::reset`] does not affect published counters.
+
+use core::time::Duration;
+
+#[cfg(feature = "metrics")]
+use std::string::String;
+#[cfg(feature = "metrics")]
+use std::vec::Vec;
+
+#[cfg(feature = "metrics")]
+use crate::TxRxState;
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+};
+
+/// Histogram of durations in `N` buckets of equal width.
+///
+/// Bucket `i` counts samples in `[i * width, (i + 1) * width)`; longer samples are counted as
+/// overflow. Percentiles are resolved to a bucket, so choose `width` as the resolution needed and
+/// `N` to cover the longest duration of interest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram<const N: usize> {
+    width: Duration,
+    buckets: [u32; N],
+    overflow: u32,
+    count: u32,
+    min: Option<Duration>,
+    max: Duration,
+    total: Duration,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// Create an empty histogram with buckets `width` wide.
+    pub const fn new(width: Duration) -> Self {
+        Self {
+            width,
+            buckets: [0; N],
+            overflow: 0,
+            count: 0,
+            min: None,
+            max: Duration::ZERO,
+            total: Duration::ZERO,
+        }
+    }
+
+    /// Record one sample.
+    pub fn record(&mut self, sample: Duration) {
+        let bucket = if self.width.is_zero() {
+            0
+        } else {
+            usize::try_from(sample.as_nanos() / self.width.as_nanos()).unwrap_or(usize::MAX)
+        };
+        match self.buckets.get_mut(bucket) {
+            Some(count) => *count = count.saturating_add(1),
+            None => self.overflow = self.overflow.saturating_add(1),
+        }
+        self.count = self.count.saturating_add(1);
+        self.min = Some(self.min.map_or(sample, |min| min.min(sample)));
+        self.max = self.max.max(sample);
+        self.total = self.total.saturating_add(sample);
+    }
+
+    /// Number of samples recorded.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Sample counts per bucket.
+    pub fn buckets(&self) -> &[u32; N] {
+        &self.buckets
+    }
+
+    /// Number of samples beyond the last bucket.
+    pub fn overflow(&self) -> u32 {
+        self.overflow
+    }
+
+    /// Width of each bucket.
+    pub fn bucket_width(&self) -> Duration {
+        self.width
+    }
+
+    /// Shortest sample, if any were recorded.
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    /// Longest sample, if any were recorded.
+    pub fn max(&self) -> Option<Duration> {
+        self.min.map(|_| self.max)
+    }
+
+    /// Mean of the samples, if any were recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total / self.count)
+    }
+
+    /// Duration below which `percent` of the samples fall, if any were recorded.
+    ///
+    /// Reported as the upper edge of the bucket holding that sample, capped at [`Histogram::max`];
+    /// samples in the overflow report the maximum. `percent` is clamped to `0.0..=100.0`.
+    pub fn percentile(&self, percent: f32) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let fraction = percent.clamp(0.0, 100.0) / 100.0;
+        // Rank of the sample, counting from one, rounded up (`f32::ceil` needs `std`).
+        let exact = self.count as f32 * fraction;
+        let mut rank = exact as u32;
+        if (rank as f32) < exact {
+            rank += 1;
+        }
+        let rank = rank.clamp(1, self.count);
+        let mut seen = 0u32;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen = seen.saturating_add(*count);
+            if seen >= rank {
+                let upper = self.width.saturating_mul(index as u32 + 1);
+                return Some(upper.min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Discard every sample.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.width);
+    }
+}
+
+/// Wrapper counting frames and recording latencies in histograms of `N` buckets.
+///
+/// Send latency is the time from calling a send method to its successful return, so for most
+/// drivers it covers queueing the frame rather than its transmission. Batched sends are counted
+/// but not timed. Interarrival time is the gap between consecutive frames returned by the receive
+/// methods.
+#[derive(Debug)]
+pub struct StatsMonitor<T, C, const N: usize> {
+    inner: T,
+    clock: C,
+    tx_frames: u32,
+    rx_frames: u32,
+    send_latency: Histogram<N>,
+    interarrival: Histogram<N>,
+    last_rx: Option<Duration>,
+    #[cfg(feature = "metrics")]
+    published: Option<Published>,
+}
+
+/// Register help text for the metrics [`StatsMonitor`] publishes (feature `metrics`).
+#[cfg(feature = "metrics")]
+pub fn describe_metrics() {
+    metrics::describe_counter!("frames_tx_total", "CAN frames sent successfully");
+    metrics::describe_counter!("frames_rx_total", "CAN frames received");
+    metrics::describe_counter!(
+        "rx_overruns_total",
+        "CAN frames lost to a full receive queue"
+    );
+    metrics::describe_histogram!(
+        "tx_latency_seconds",
+        metrics::Unit::Seconds,
+        "Time taken by successful CAN send calls"
+    );
+}
+
+/// Handles of the published metrics.
+#[cfg(feature = "metrics")]
+#[derive(Debug)]
+struct Published {
+    tx_frames: metrics::Counter,
+    rx_frames: metrics::Counter,
+    rx_overruns: metrics::Counter,
+    tx_latency: metrics::Histogram,
+    /// Driver overrun count at the last [`StatsMonitor::publish_rx_overruns`].
+    last_overruns: Option<u32>,
+}
+
+#[cfg(feature = "metrics")]
+impl Published {
+    fn register(labels: Vec<metrics::Label>) -> Self {
+        Self {
+            tx_frames: metrics::counter!("frames_tx_total", labels.clone()),
+            rx_frames: metrics::counter!("frames_rx_total", labels.clone()),
+            rx_overruns: metrics::counter!("rx_overruns_total", labels.clone()),
+            tx_latency: metrics::histogram!("tx_latency_seconds", labels),
+            last_overruns: None,
+        }
+    }
+}
+
+impl<T, C: Clock, const N: usize> StatsMonitor<T, C, N> {
+    /// Wrap `inner`, with histogram buckets `bucket_width` wide.
+    pub fn new(inner: T, clock: C, bucket_width: Duration) -> Self {
+        Self {
+            inner,
+            clock,
+            tx_frames: 0,
+            rx_frames: 0,
+            send_latency: Histogram::new(bucket_width),
+            interarrival: Histogram::new(bucket_width),
+            last_rx: None,
+            #[cfg(feature = "metrics")]
+            published: None,
+        }
+    }
+
+    /// Publish metrics with an `interface` label of `interface` (feature `metrics`).
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics_label(mut self, interface: impl Into<String>) -> Self {
+        let label = metrics::Label::new("interface", interface.into());
+        self.published = Some(Published::register(std::vec![label]));
+        self
+    }
+
+    /// Metric handles, registered without labels on first use.
+    #[cfg(feature = "metrics")]
+    fn published(&mut self) -> &mut Published {
+        self.published
+            .get_or_insert_with(|| Published::register(Vec::new()))
+    }
+
+    /// Number of frames sent successfully.
+    pub fn tx_frames(&self) -> u32 {
+        self.tx_frames
+    }
+
+    /// Number of frames received.
+    pub fn rx_frames(&self) -> u32 {
+        self.rx_frames
+    }
+
+    /// Time taken by successful send calls.
+    pub fn send_latency(&self) -> &Histogram<N> {
+        &self.send_latency
+    }
+
+    /// Gaps between received frames.
+    pub fn interarrival(&self) -> &Histogram<N> {
+        &self.interarrival
+    }
+
+    /// Clear the counters and histograms.
+    pub fn reset(&mut self) {
+        self.tx_frames = 0;
+        self.rx_frames = 0;
+        self.send_latency.reset();
+        self.interarrival.reset();
+        self.last_rx = None;
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record_send<E>(&mut self, started: Duration, result: Result<(), E>) -> Result<(), E> {
+        result?;
+        let latency = self.clock.now().saturating_sub(started);
+        self.tx_frames = self.tx_frames.saturating_add(1);
+        self.send_latency.record(latency);
+        #[cfg(feature = "metrics")]
+        {
+            let published = self.published();
+            published.tx_frames.increment(1);
+            published.tx_latency.record(latency);
+        }
+        Ok(())
+    }
+
+    fn record_batch<E>(
+        &mut self,
+        result: Result<usize, PartialSend<E>>,
+    ) -> Result<usize, PartialSend<E>> {
+        let sent = match &result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        self.tx_frames = self
+            .tx_frames
+            .saturating_add(u32::try_from(sent).unwrap_or(u32::MAX));
+        #[cfg(feature = "metrics")]
+        self.published().tx_frames.increment(sent as u64);
+        result
+    }
+
+    fn record_recv<F, E>(&mut self, result: Result<F, E>) -> Result<F, E> {
+        let frame = result?;
+        let now = self.clock.now();
+        if let Some(last) = self.last_rx.replace(now) {
+            self.interarrival.record(now.saturating_sub(last));
+        }
+        self.rx_frames = self.rx_frames.saturating_add(1);
+        #[cfg(feature = "metrics")]
+        self.published().rx_frames.increment(1);
+        Ok(frame)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<T: TxRxState, C: Clock, const N: usize> StatsMonitor<T, C, N> {
+    /// Read the driver's receive overrun count and add the overruns since the last call to
+    /// `rx_overruns_total` (feature `metrics`).
+    ///
+    /// Call it periodically, e.g. from the task that services the exporter. Does nothing if the
+    /// driver does not count overruns. A count lower than the previous one is taken to mean the
+    /// driver restarted counting.
+    pub fn publish_rx_overruns(&mut self) -> Result<(), T::Error> {
+        let Some(count) = self.inner.rx_overruns()? else {
+            return Ok(());
+        };
+        let published = self.published();
+        let new = match published.last_overruns {
+            Some(last) if count >= last => count - last,
+            _ => count,
+        };
+        published.last_overruns = Some(count);
+        published.rx_overruns.increment(u64::from(new));
+        Ok(())
+    }
+}
+
+impl<T, C, const N: usize> TxFrameIo for StatsMonitor<T, C, N>
+where
+    T: TxFrameIo,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send(frame);
+        self.record_send(started, result)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.try_send(frame);
+        self.record_send(started, result)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send_timeout(frame, timeout);
+        self.record_send(started, result)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send_with(frame, options);
+        self.record_send(started, result)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(result)
+    }
+}
+
+impl<T, C, const N: usize> RxFrameIo for StatsMonitor<T, C, N>
+where
+    T: RxFrameIo,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv();
+        self.record_recv(result)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.try_recv();
+        self.record_recv(result)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout);
+        self.record_recv(result)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, C, const N: usize> AsyncTxFrameIo for StatsMonitor<T, C, N>
+where
+    T: AsyncTxFrameIo,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send(frame).await;
+        self.record_send(started, result)
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.record_send(started, result)
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let started = self.clock.now();
+        let result = self.inner.send_with(frame, options).await;
+        self.record_send(started, result)
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(result)
+    }
+}
+
+impl<T, C, const N: usize> AsyncRxFrameIo for StatsMonitor<T, C, N>
+where
+    T: AsyncRxFrameIo,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv().await;
+        self.record_recv(result)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout).await;
+        self.record_recv(result)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
@@ -0,0 +1,177 @@
+//! `proptest` strategies for this crate's types (requires the `proptest` feature).
+//!
+//! [`Id`], [`IdMask`], [`IdMaskFilter`], [`SendOptions`] and the enums they are built from
+//! implement [`Arbitrary`], so they can be drawn with `any::<T>()`. [`frame`] and [`frame_with`]
+//! generate frames of any [`Frame`] type:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::{IdMaskFilter, strategy};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn filter_matches_like_hardware(filter: IdMaskFilter, frame in strategy::frame::<MyFrame>()) {
+//!         prop_assert_eq!(soft_match(&filter, &frame), hw_match(&filter, &frame));
+//!     }
+//! }
+//! ```
+//!
+//! Data lengths are drawn from [`testdata::LENGTHS`](crate::testdata::LENGTHS), so payloads cover
+//! every classic and CAN FD length rather than arbitrary sizes. Frames the type cannot represent
+//! (FD lengths for a classic frame type, remote frames for an FD-only one) are rejected by the
+//! strategy, the same way [`testdata`](crate::testdata) skips them. Identifiers shrink towards
+//! zero and payloads towards shorter lengths and zero bytes.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::time::Duration;
+
+use embedded_can::{ExtendedId, Frame, StandardId};
+use proptest::prelude::*;
+
+use crate::testdata::LENGTHS;
+use crate::{FrameTypes, Id, IdMask, IdMaskFilter, IdTypes, PriorityClass, SendOptions};
+
+/// Any standard (11-bit) identifier.
+pub fn standard_id() -> impl Strategy<Value = StandardId> {
+    (0..=StandardId::MAX.as_raw()).prop_map(|raw| StandardId::new(raw).unwrap())
+}
+
+/// Any extended (29-bit) identifier.
+pub fn extended_id() -> impl Strategy<Value = ExtendedId> {
+    (0..=ExtendedId::MAX.as_raw()).prop_map(|raw| ExtendedId::new(raw).unwrap())
+}
+
+/// Frames of type `F` with any identifier; see [`frame_with`].
+pub fn frame<F: Frame + Debug>() -> impl Strategy<Value = F> {
+    frame_with(any::<Id>())
+}
+
+/// Frames of type `F` with identifiers drawn from `ids`.
+///
+/// About one frame in ten is a remote frame with a DLC of 0–8; the rest are data frames with a
+/// length from [`LENGTHS`] and arbitrary payload bytes.
+pub fn frame_with<F, S>(ids: S) -> impl Strategy<Value = F>
+where
+    F: Frame + Debug,
+    S: Strategy<Value = Id>,
+{
+    let payload = prop::sample::select(&LENGTHS[..])
+        .prop_flat_map(|len| prop::collection::vec(any::<u8>(), len));
+    (ids, prop::bool::weighted(0.1), 0..=8usize, payload).prop_filter_map(
+        "frame type cannot represent the frame",
+        |(id, remote, dlc, data): (Id, bool, usize, Vec<u8>)| {
+            let id = match id {
+                Id::Standard(id) => embedded_can::Id::Standard(id),
+                Id::Extended(id) => embedded_can::Id::Extended(id),
+            };
+            if remote {
+                F::new_remote(id, dlc)
+            } else {
+                F::new(id, &data)
+            }
+        },
+    )
+}
+
+impl Arbitrary for Id {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            standard_id().prop_map(Id::Standard),
+            extended_id().prop_map(Id::Extended),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for IdMask {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            (0..=StandardId::MAX.as_raw()).prop_map(IdMask::Standard),
+            (0..=ExtendedId::MAX.as_raw()).prop_map(IdMask::Extended),
+        ]
+        .boxed()
+    }
+}
+
+/// The mask has the same width as the identifier, as drivers expect.
+impl Arbitrary for IdMaskFilter {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let standard = (standard_id(), 0..=StandardId::MAX.as_raw())
+            .prop_map(|(id, mask)| IdMaskFilter::standard(id, mask));
+        let extended = (extended_id(), 0..=ExtendedId::MAX.as_raw())
+            .prop_map(|(id, mask)| IdMaskFilter::extended(id, mask));
+        (
+            prop_oneof![standard, extended],
+            any::<FrameTypes>(),
+            any::<IdTypes>(),
+        )
+            .prop_map(|(filter, frame_types, id_types)| {
+                filter.with_frame_types(frame_types).with_id_types(id_types)
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for FrameTypes {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(FrameTypes::Both),
+            Just(FrameTypes::Data),
+            Just(FrameTypes::Remote),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for IdTypes {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![Just(IdTypes::SameAsFilter), Just(IdTypes::Both)].boxed()
+    }
+}
+
+impl Arbitrary for PriorityClass {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(PriorityClass::Normal),
+            Just(PriorityClass::Low),
+            Just(PriorityClass::High),
+        ]
+        .boxed()
+    }
+}
+
+/// Deadlines, when present, are at most a minute away.
+impl Arbitrary for SendOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let deadline = prop::option::of((0..=60_000_000u64).prop_map(Duration::from_micros));
+        (any::<PriorityClass>(), any::<bool>(), deadline)
+            .prop_map(|(class, one_shot, deadline)| SendOptions {
+                class,
+                one_shot,
+                deadline,
+            })
+            .boxed()
+    }
+}
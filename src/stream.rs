@@ -0,0 +1,320 @@
+//! Live frame capture through a byte sink.
+//!
+//! A debug probe already has a data channel to the host: an RTT up-channel, ITM stimulus port or
+//! a spare UART. `FrameStream` (feature `embedded-io`) wraps an interface and writes every frame
+//! it sends or receives to such a channel in a compact binary form, and [`decode_records`]
+//! turns the captured bytes back into frames on the host. Bus traffic can then be captured without
+//! a CAN adapter attached to the host:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::stream::FrameStream;
+//!
+//! // Target: `rtt` implements `embedded_io::Write`.
+//! let mut can = FrameStream::new(twai, rtt, clock);
+//!
+//! // Host: decode a chunk read from the probe, keeping a partial frame for the next chunk.
+//! let mut records = decode_records::<MyFrame>(&buf);
+//! for record in &mut records {
+//!     println!("{:?} {:?} {:?}", record.at, record.direction, record.frame);
+//! }
+//! let tail = records.remaining().len();
+//! ```
+//!
+//! Frames are written in the [`codec`](crate::codec) format, with their direction and a timestamp
+//! from the wrapper's clock; a classic frame with 8 data bytes takes 22 bytes. Each frame is
+//! written with a single `write_all`, so channels that drop writes when full (RTT in non-blocking
+//! mode) drop whole frames, and the decoder resynchronises after a lost byte on a UART.
+
+use core::marker::PhantomData;
+#[cfg(feature = "embedded-io")]
+use core::time::Duration;
+
+use embedded_can::Frame;
+
+pub use crate::blackbox::{Direction, Record};
+use crate::codec::{WireFrames, decode_wire_frames};
+#[cfg(feature = "embedded-io")]
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, Clock, PartialSend, RxFrameIo, SendOptions, TxFrameIo,
+    codec::{MAX_ENCODED_LEN, WireFrame},
+};
+
+/// Decode the frames captured in `buf`; see [`Records`].
+pub fn decode_records<F: Frame>(buf: &[u8]) -> Records<'_, F> {
+    Records {
+        wire: decode_wire_frames(buf),
+        unsupported: 0,
+        _frame: PhantomData,
+    }
+}
+
+/// Iterator over the frames in a captured byte buffer.
+///
+/// Bytes that do not start a frame are skipped, as are frames the frame type cannot represent;
+/// [`Records::skipped`] counts both. Iteration stops at a frame cut off by the end of the buffer,
+/// which [`Records::remaining`] returns. Frames without a timestamp are recorded at zero.
+#[derive(Debug, Clone)]
+pub struct Records<'a, F> {
+    wire: WireFrames<'a>,
+    /// Bytes of frames the frame type cannot represent.
+    unsupported: usize,
+    _frame: PhantomData<F>,
+}
+
+impl<'a, F> Records<'a, F> {
+    /// The bytes not decoded yet: after iteration, the start of an incomplete frame, if any.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.wire.remaining()
+    }
+
+    /// Number of bytes skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.wire.skipped() + self.unsupported
+    }
+}
+
+impl<F: Frame> Iterator for Records<'_, F> {
+    type Item = Record<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let wire = self.wire.next()?;
+            match wire.to_frame() {
+                Some(frame) => {
+                    return Some(Record {
+                        direction: wire.direction,
+                        at: wire.timestamp.unwrap_or_default(),
+                        frame,
+                    });
+                }
+                None => self.unsupported += wire.encoded_len(),
+            }
+        }
+    }
+}
+
+/// Wrapper that writes every frame sent or received to an `embedded_io` sink (feature
+/// `embedded-io`); see the [module documentation](self).
+///
+/// Sent frames are written once the driver accepted them. Sink errors do not fail the bus
+/// operation; they are counted by [`FrameStream::write_errors`].
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+pub struct FrameStream<T, W, C> {
+    inner: T,
+    sink: W,
+    clock: C,
+    write_errors: u32,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, W: embedded_io::Write, C: Clock> FrameStream<T, W, C> {
+    /// Wrap `inner`, writing frames to `sink` with timestamps from `clock`.
+    pub fn new(inner: T, sink: W, clock: C) -> Self {
+        Self {
+            inner,
+            sink,
+            clock,
+            write_errors: 0,
+        }
+    }
+
+    /// Number of frames the sink failed to accept.
+    pub fn write_errors(&self) -> u32 {
+        self.write_errors
+    }
+
+    /// Borrow the sink.
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface and the sink.
+    pub fn into_parts(self) -> (T, W) {
+        (self.inner, self.sink)
+    }
+
+    fn write<F: Frame>(&mut self, direction: Direction, frame: &F) {
+        let mut buf = [0; MAX_ENCODED_LEN];
+        let len = WireFrame::from_frame(frame)
+            .with_direction(direction)
+            .with_timestamp(self.clock.now())
+            .encode(&mut buf);
+        if self.sink.write_all(&buf[..len]).is_err() {
+            self.write_errors = self.write_errors.saturating_add(1);
+        }
+    }
+
+    fn record_send<F: Frame, E>(&mut self, frame: &F, result: Result<(), E>) -> Result<(), E> {
+        result?;
+        self.write(Direction::Tx, frame);
+        Ok(())
+    }
+
+    fn record_batch<F: Frame, E>(
+        &mut self,
+        frames: &[(F, SendOptions)],
+        result: Result<usize, PartialSend<E>>,
+    ) -> Result<usize, PartialSend<E>> {
+        let sent = match &result {
+            Ok(sent) => *sent,
+            Err(partial) => partial.sent,
+        };
+        for (frame, _) in frames.iter().take(sent) {
+            self.write(Direction::Tx, frame);
+        }
+        result
+    }
+
+    fn record_recv<F: Frame, E>(&mut self, result: Result<F, E>) -> Result<F, E> {
+        let frame = result?;
+        self.write(Direction::Rx, &frame);
+        Ok(frame)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, W, C> TxFrameIo for FrameStream<T, W, C>
+where
+    T: TxFrameIo<Frame: Frame>,
+    W: embedded_io::Write,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame);
+        self.record_send(frame, result)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.try_send(frame);
+        self.record_send(frame, result)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout);
+        self.record_send(frame, result)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options);
+        self.record_send(frame, result)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames);
+        self.record_batch(frames, result)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, W, C> RxFrameIo for FrameStream<T, W, C>
+where
+    T: RxFrameIo<Frame: Frame>,
+    W: embedded_io::Write,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv();
+        self.record_recv(result)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.try_recv();
+        self.record_recv(result)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout);
+        self.record_recv(result)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, W, C> AsyncTxFrameIo for FrameStream<T, W, C>
+where
+    T: AsyncTxFrameIo<Frame: Frame>,
+    W: embedded_io::Write,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        let result = self.inner.send(frame).await;
+        self.record_send(frame, result)
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_timeout(frame, timeout).await;
+        self.record_send(frame, result)
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.send_with(frame, options).await;
+        self.record_send(frame, result)
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        let result = self.inner.send_batch(frames).await;
+        self.record_batch(frames, result)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, W, C> AsyncRxFrameIo for FrameStream<T, W, C>
+where
+    T: AsyncRxFrameIo<Frame: Frame>,
+    W: embedded_io::Write,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv().await;
+        self.record_recv(result)
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        let result = self.inner.recv_timeout(timeout).await;
+        self.record_recv(result)
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
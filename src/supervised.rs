@@ -0,0 +1,333 @@
+//! Bus-off supervision.
+//!
+//! [`Supervised`] wraps a CAN interface that implements [`BusState`] and takes care of detecting
+//! bus-off and requesting recovery with an exponential backoff, while I/O continues to go through
+//! the wrapper unchanged.
+
+use core::time::Duration;
+
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, BusState, Clock, ErrorState, PartialSend, RxFrameIo,
+    SendOptions, TxFrameIo,
+};
+
+/// Backoff policy used between bus-off recovery attempts.
+///
+/// The first attempt is made `initial` after bus-off is detected; each following attempt doubles
+/// the delay, capped at `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the first recovery attempt.
+    pub initial: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max: Duration,
+    /// Give up after this many attempts (`None` retries forever).
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(10),
+            max: Duration::from_secs(1),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Event reported by [`Supervised`] when the recovery state changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryEvent {
+    /// The controller entered bus-off.
+    BusOff,
+    /// A recovery request was issued (1-based attempt number).
+    RecoveryAttempt {
+        /// Attempt number, starting at 1.
+        attempt: u32,
+    },
+    /// The controller left bus-off.
+    Recovered {
+        /// Number of recovery requests issued before the bus came back.
+        attempts: u32,
+    },
+    /// [`Backoff::max_attempts`] was reached; no further recovery requests will be issued until
+    /// [`Supervised::retry`] is called.
+    GaveUp {
+        /// Number of recovery requests issued.
+        attempts: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Healthy,
+    Recovering {
+        attempts: u32,
+        next_attempt: Duration,
+        delay: Duration,
+    },
+    GaveUp {
+        attempts: u32,
+    },
+}
+
+/// Wrapper that supervises bus-off and performs recovery with backoff.
+///
+/// Supervision runs at the start of every I/O call and whenever [`Supervised::supervise`] is
+/// called explicitly. Applications that may go quiet during bus-off (e.g. only receiving) should
+/// call `supervise` periodically so recovery still makes progress.
+///
+/// Only the most recent [`RecoveryEvent`] is retained; retrieve it with
+/// [`Supervised::take_event`] or from the return value of [`Supervised::supervise`].
+#[derive(Debug)]
+pub struct Supervised<T, C> {
+    inner: T,
+    clock: C,
+    backoff: Backoff,
+    phase: Phase,
+    event: Option<RecoveryEvent>,
+}
+
+impl<T, C> Supervised<T, C>
+where
+    T: BusState,
+    C: Clock,
+{
+    /// Wrap `inner` using the default [`Backoff`] policy.
+    pub fn new(inner: T, clock: C) -> Self {
+        Self::with_backoff(inner, clock, Backoff::default())
+    }
+
+    /// Wrap `inner` using a custom backoff policy.
+    pub fn with_backoff(inner: T, clock: C, backoff: Backoff) -> Self {
+        Self {
+            inner,
+            clock,
+            backoff,
+            phase: Phase::Healthy,
+            event: None,
+        }
+    }
+
+    /// Check the bus state and advance recovery if needed.
+    ///
+    /// Returns the event produced by this call, if any. The event is also retained for
+    /// [`Supervised::take_event`].
+    pub fn supervise(&mut self) -> Result<Option<RecoveryEvent>, T::Error> {
+        let bus_off = self.inner.bus_state()? == ErrorState::BusOff;
+        let now = self.clock.now();
+
+        let event = match self.phase {
+            Phase::Healthy if bus_off => {
+                self.phase = Phase::Recovering {
+                    attempts: 0,
+                    next_attempt: now + self.backoff.initial,
+                    delay: self.backoff.initial,
+                };
+                Some(RecoveryEvent::BusOff)
+            }
+            Phase::Healthy => None,
+            Phase::Recovering { attempts, .. } | Phase::GaveUp { attempts } if !bus_off => {
+                self.phase = Phase::Healthy;
+                Some(RecoveryEvent::Recovered { attempts })
+            }
+            Phase::Recovering {
+                attempts,
+                next_attempt,
+                delay,
+            } if now >= next_attempt => {
+                if self.backoff.max_attempts.is_some_and(|max| attempts >= max) {
+                    self.phase = Phase::GaveUp { attempts };
+                    Some(RecoveryEvent::GaveUp { attempts })
+                } else {
+                    self.inner.recover_bus_off()?;
+                    let delay = delay.saturating_mul(2).min(self.backoff.max);
+                    self.phase = Phase::Recovering {
+                        attempts: attempts + 1,
+                        next_attempt: now + delay,
+                        delay,
+                    };
+                    Some(RecoveryEvent::RecoveryAttempt {
+                        attempt: attempts + 1,
+                    })
+                }
+            }
+            Phase::Recovering { .. } | Phase::GaveUp { .. } => None,
+        };
+
+        if event.is_some() {
+            self.event = event;
+        }
+        Ok(event)
+    }
+
+    /// Resume recovery attempts after [`RecoveryEvent::GaveUp`].
+    pub fn retry(&mut self) {
+        if let Phase::GaveUp { .. } = self.phase {
+            self.phase = Phase::Recovering {
+                attempts: 0,
+                next_attempt: self.clock.now(),
+                delay: self.backoff.initial,
+            };
+        }
+    }
+}
+
+impl<T, C> Supervised<T, C> {
+    /// Take the most recent recovery event, if one has not been taken yet.
+    pub fn take_event(&mut self) -> Option<RecoveryEvent> {
+        self.event.take()
+    }
+
+    /// Returns `true` while the supervisor considers the bus off (recovering or given up).
+    pub fn is_recovering(&self) -> bool {
+        !matches!(self.phase, Phase::Healthy)
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, C> TxFrameIo for Supervised<T, C>
+where
+    T: TxFrameIo + BusState<Error = <T as TxFrameIo>::Error>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = <T as TxFrameIo>::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.supervise()
+            .map_err(|error| PartialSend { sent: 0, error })?;
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<T, C> RxFrameIo for Supervised<T, C>
+where
+    T: RxFrameIo + BusState<Error = <T as RxFrameIo>::Error>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = <T as RxFrameIo>::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.supervise()?;
+        self.inner.recv()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.supervise()?;
+        self.inner.try_recv()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.supervise()?;
+        self.inner.recv_timeout(timeout)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T, C> AsyncTxFrameIo for Supervised<T, C>
+where
+    T: AsyncTxFrameIo + BusState<Error = <T as AsyncTxFrameIo>::Error>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = <T as AsyncTxFrameIo>::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.supervise()
+            .map_err(|error| PartialSend { sent: 0, error })?;
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<T, C> AsyncRxFrameIo for Supervised<T, C>
+where
+    T: AsyncRxFrameIo + BusState<Error = <T as AsyncRxFrameIo>::Error>,
+    C: Clock,
+{
+    type Frame = T::Frame;
+    type Error = <T as AsyncRxFrameIo>::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.supervise()?;
+        self.inner.recv().await
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.supervise()?;
+        self.inner.recv_timeout(timeout).await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.supervise()?;
+        self.inner.wait_not_empty().await
+    }
+}
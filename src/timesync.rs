@@ -0,0 +1,346 @@
+//! Shared timebase across nodes.
+//!
+//! A [`TimeSyncMaster`] periodically broadcasts its clock in a sync frame. Each
+//! [`TimeSyncSlave`] records when those frames arrive on its own clock, estimates the offset and
+//! drift between the two, and translates timestamps in either direction, so that logs taken on
+//! different nodes can be merged on one timeline.
+//!
+//! The payload format is pluggable through [`TimestampCodec`]; [`MicrosLe`] (a little-endian
+//! `u64` microsecond count) is provided. The master timestamps the frame when it hands it to the
+//! driver, so queueing and arbitration delay show up as a (roughly constant) offset error; use a
+//! high-priority identifier for the sync frame to keep it small.
+//!
+//! [`ClockCorrelator`] solves the same problem within one host: it maps a driver's or adapter's
+//! timestamps (e.g. [`RxMeta::timestamp`](crate::RxMeta::timestamp)) onto the application's
+//! clock, so captures from several interfaces share one timeline.
+
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{Clock, TxFrameIo};
+
+/// Encoding of a timestamp in a sync frame payload.
+pub trait TimestampCodec {
+    /// Encode `time` into `buf`, returning the payload length.
+    fn encode(&self, time: Duration, buf: &mut [u8; 8]) -> usize;
+
+    /// Decode a payload produced by [`TimestampCodec::encode`].
+    fn decode(&self, data: &[u8]) -> Option<Duration>;
+}
+
+/// Microseconds since the master's epoch as a little-endian `u64`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MicrosLe;
+
+impl TimestampCodec for MicrosLe {
+    fn encode(&self, time: Duration, buf: &mut [u8; 8]) -> usize {
+        let micros = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+        *buf = micros.to_le_bytes();
+        8
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<Duration> {
+        let bytes: [u8; 8] = data.get(..8)?.try_into().ok()?;
+        Some(Duration::from_micros(u64::from_le_bytes(bytes)))
+    }
+}
+
+/// Periodic sender of sync frames.
+#[derive(Debug)]
+pub struct TimeSyncMaster<C, K = MicrosLe> {
+    id: Id,
+    period: Duration,
+    clock: C,
+    codec: K,
+    next_due: Duration,
+}
+
+impl<C: Clock> TimeSyncMaster<C> {
+    /// Broadcast on `id` every `period` using the [`MicrosLe`] encoding.
+    pub fn new(id: impl Into<Id>, period: Duration, clock: C) -> Self {
+        Self::with_codec(id, period, clock, MicrosLe)
+    }
+}
+
+impl<C: Clock, K: TimestampCodec> TimeSyncMaster<C, K> {
+    /// Broadcast on `id` every `period` using `codec`.
+    pub fn with_codec(id: impl Into<Id>, period: Duration, clock: C, codec: K) -> Self {
+        let next_due = clock.now();
+        Self {
+            id: id.into(),
+            period,
+            clock,
+            codec,
+            next_due,
+        }
+    }
+
+    /// Send a sync frame if one is due; returns `true` if a frame was sent.
+    pub fn poll<T>(&mut self, io: &mut T) -> Result<bool, T::Error>
+    where
+        T: TxFrameIo,
+        T::Frame: Frame,
+    {
+        let now = self.clock.now();
+        if now < self.next_due {
+            return Ok(false);
+        }
+        self.send_now(io)?;
+        self.next_due = now + self.period;
+        Ok(true)
+    }
+
+    /// Send a sync frame immediately.
+    pub fn send_now<T>(&mut self, io: &mut T) -> Result<(), T::Error>
+    where
+        T: TxFrameIo,
+        T::Frame: Frame,
+    {
+        let mut buf = [0u8; 8];
+        let len = self.codec.encode(self.clock.now(), &mut buf);
+        // An id/length combination the frame type rejects is a configuration error; skip it.
+        match T::Frame::new(self.id, &buf[..len]) {
+            Some(frame) => io.try_send(&frame),
+            None => Ok(()),
+        }
+    }
+
+    /// Time at which the next sync frame is due.
+    pub fn next_due(&self) -> Duration {
+        self.next_due
+    }
+}
+
+/// Estimator of the master's timebase relative to the local clock.
+///
+/// Each sync frame provides one (local, bus) time pair. The most recent pair anchors the
+/// translation, and the ratio between successive pairs is smoothed into a rate estimate that
+/// accounts for oscillator drift between syncs.
+#[derive(Debug)]
+pub struct TimeSyncSlave<K = MicrosLe> {
+    id: Id,
+    codec: K,
+    smoothing: f64,
+    anchor: Option<(Duration, Duration)>,
+    /// Bus seconds per local second.
+    rate: f64,
+}
+
+impl TimeSyncSlave {
+    /// Listen for sync frames on `id` using the [`MicrosLe`] encoding.
+    pub fn new(id: impl Into<Id>) -> Self {
+        Self::with_codec(id, MicrosLe)
+    }
+}
+
+impl<K: TimestampCodec> TimeSyncSlave<K> {
+    /// Listen for sync frames on `id` using `codec`.
+    pub fn with_codec(id: impl Into<Id>, codec: K) -> Self {
+        Self {
+            id: id.into(),
+            codec,
+            smoothing: 0.1,
+            anchor: None,
+            rate: 1.0,
+        }
+    }
+
+    /// Set how strongly each new sync updates the drift estimate (0–1, default 0.1).
+    pub fn set_smoothing(&mut self, smoothing: f64) {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+    }
+
+    /// Feed a received frame together with the local time it arrived.
+    ///
+    /// Returns `true` if it was a sync frame and the estimate was updated.
+    pub fn handle_frame<F: Frame>(&mut self, frame: &F, local: Duration) -> bool {
+        if frame.id() != self.id || frame.is_remote_frame() {
+            return false;
+        }
+        match self.codec.decode(frame.data()) {
+            Some(bus) => {
+                self.observe(bus, local);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Record that the master's clock read `bus` at local time `local`.
+    pub fn observe(&mut self, bus: Duration, local: Duration) {
+        if let Some((prev_local, prev_bus)) = self.anchor
+            && local > prev_local
+            && bus > prev_bus
+        {
+            let measured = (bus - prev_bus).as_secs_f64() / (local - prev_local).as_secs_f64();
+            self.rate += self.smoothing * (measured - self.rate);
+        }
+        self.anchor = Some((local, bus));
+    }
+
+    /// Returns `true` once at least one sync frame has been received.
+    pub fn is_synchronized(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// Estimated drift of the master's clock relative to ours, in parts per million.
+    pub fn drift_ppm(&self) -> f64 {
+        (self.rate - 1.0) * 1e6
+    }
+
+    /// Translate a local timestamp to bus time.
+    pub fn to_bus(&self, local: Duration) -> Option<Duration> {
+        let (anchor_local, anchor_bus) = self.anchor?;
+        Some(shift(
+            anchor_bus,
+            signed_secs(local, anchor_local) * self.rate,
+        ))
+    }
+
+    /// Translate a bus timestamp to local time.
+    pub fn to_local(&self, bus: Duration) -> Option<Duration> {
+        let (anchor_local, anchor_bus) = self.anchor?;
+        Some(shift(
+            anchor_local,
+            signed_secs(bus, anchor_bus) / self.rate,
+        ))
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        self.anchor = None;
+        self.rate = 1.0;
+    }
+}
+
+/// Estimator of the relation between a device clock and the local clock.
+///
+/// Feed it pairs of a device timestamp and the local time the same event was observed, e.g. the
+/// hardware timestamp of a received frame and a [`Clock`] reading taken right after receiving it.
+/// It fits offset and drift by least squares over the last `N` pairs, which averages out the
+/// jitter of the local readings while following slow changes in drift.
+///
+/// If the device clock runs backwards (it was reset or wrapped), the window restarts.
+#[derive(Debug, Clone)]
+pub struct ClockCorrelator<const N: usize> {
+    /// `(device, local)` pairs, oldest first from `next` once the window is full.
+    samples: [(Duration, Duration); N],
+    len: usize,
+    next: usize,
+    /// Point the fitted line goes through, as `(device, local)`.
+    anchor: Option<(Duration, Duration)>,
+    /// Local seconds per device second.
+    rate: f64,
+}
+
+impl<const N: usize> Default for ClockCorrelator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ClockCorrelator<N> {
+    /// Create a correlator without samples.
+    pub const fn new() -> Self {
+        Self {
+            samples: [(Duration::ZERO, Duration::ZERO); N],
+            len: 0,
+            next: 0,
+            anchor: None,
+            rate: 1.0,
+        }
+    }
+
+    /// Record that the device clock read `device` at local time `local`.
+    pub fn observe(&mut self, device: Duration, local: Duration) {
+        if N == 0 {
+            return;
+        }
+        let newest = (self.next + N - 1) % N;
+        if self.len > 0 && device < self.samples[newest].0 {
+            self.reset();
+        }
+        self.samples[self.next] = (device, local);
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        self.fit();
+    }
+
+    fn fit(&mut self) {
+        let samples = &self.samples[..self.len];
+        let (device_ref, local_ref) = samples[0];
+        let count = samples.len() as f64;
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        for &(device, local) in samples {
+            sum_x += signed_secs(device, device_ref);
+            sum_y += signed_secs(local, local_ref);
+        }
+        let (mean_x, mean_y) = (sum_x / count, sum_y / count);
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for &(device, local) in samples {
+            let x = signed_secs(device, device_ref) - mean_x;
+            let y = signed_secs(local, local_ref) - mean_y;
+            covariance += x * y;
+            variance += x * x;
+        }
+        if variance > 0.0 && covariance > 0.0 {
+            self.rate = covariance / variance;
+        }
+        self.anchor = Some((shift(device_ref, mean_x), shift(local_ref, mean_y)));
+    }
+
+    /// Number of pairs in the window.
+    pub fn sample_count(&self) -> usize {
+        self.len
+    }
+
+    /// Estimated drift of the device clock relative to the local one, in parts per million.
+    pub fn drift_ppm(&self) -> f64 {
+        (1.0 / self.rate - 1.0) * 1e6
+    }
+
+    /// Translate a device timestamp to local time, once at least one pair was observed.
+    pub fn to_local(&self, device: Duration) -> Option<Duration> {
+        let (anchor_device, anchor_local) = self.anchor?;
+        Some(shift(
+            anchor_local,
+            signed_secs(device, anchor_device) * self.rate,
+        ))
+    }
+
+    /// Translate a local time to the device clock, once at least one pair was observed.
+    pub fn to_device(&self, local: Duration) -> Option<Duration> {
+        let (anchor_device, anchor_local) = self.anchor?;
+        Some(shift(
+            anchor_device,
+            signed_secs(local, anchor_local) / self.rate,
+        ))
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.anchor = None;
+        self.rate = 1.0;
+    }
+}
+
+/// `a - b` in seconds, which may be negative.
+fn signed_secs(a: Duration, b: Duration) -> f64 {
+    if a >= b {
+        (a - b).as_secs_f64()
+    } else {
+        -(b - a).as_secs_f64()
+    }
+}
+
+/// `base + secs`, saturating at zero.
+fn shift(base: Duration, secs: f64) -> Duration {
+    if secs >= 0.0 {
+        base + Duration::from_secs_f64(secs)
+    } else {
+        base.saturating_sub(Duration::from_secs_f64(-secs))
+    }
+}
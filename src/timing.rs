@@ -0,0 +1,430 @@
+//! Bitrates and bit timing.
+//!
+//! [`Bitrate`] names the rates used on real buses; [`BitTiming`] is the register-level description
+//! (prescaler and segment lengths in time quanta) that controllers are actually programmed with.
+//! [`BitTiming::calculate`] bridges the two for a given controller clock, and
+//! [`BitTiming::validate`] checks the sample point and SJW against CiA recommendations.
+
+use core::error::Error;
+use core::fmt;
+
+/// A CAN bitrate.
+///
+/// The named presets cover the rates in common use (CiA 301 nominal rates and typical CAN FD data
+/// rates). Use [`Bitrate::Raw`] for anything else. Comparison is by bits per second, so
+/// `Bitrate::Raw(500_000) == Bitrate::Kbps500`.
+#[derive(Debug, Clone, Copy)]
+pub enum Bitrate {
+    /// 125 kbit/s.
+    Kbps125,
+    /// 250 kbit/s.
+    Kbps250,
+    /// 500 kbit/s.
+    Kbps500,
+    /// 1 Mbit/s (the maximum nominal rate for classic CAN).
+    Mbps1,
+    /// 2 Mbit/s (CAN FD data phase).
+    Mbps2,
+    /// 4 Mbit/s (CAN FD data phase).
+    Mbps4,
+    /// 5 Mbit/s (CAN FD data phase).
+    Mbps5,
+    /// 8 Mbit/s (CAN FD data phase).
+    Mbps8,
+    /// Any other rate, in bits per second.
+    Raw(u32),
+}
+
+impl Bitrate {
+    /// Returns the rate in bits per second.
+    pub const fn bits_per_second(self) -> u32 {
+        match self {
+            Bitrate::Kbps125 => 125_000,
+            Bitrate::Kbps250 => 250_000,
+            Bitrate::Kbps500 => 500_000,
+            Bitrate::Mbps1 => 1_000_000,
+            Bitrate::Mbps2 => 2_000_000,
+            Bitrate::Mbps4 => 4_000_000,
+            Bitrate::Mbps5 => 5_000_000,
+            Bitrate::Mbps8 => 8_000_000,
+            Bitrate::Raw(bps) => bps,
+        }
+    }
+
+    /// Build a bitrate from bits per second, using a named preset when one matches.
+    pub const fn from_bits_per_second(bps: u32) -> Self {
+        match bps {
+            125_000 => Bitrate::Kbps125,
+            250_000 => Bitrate::Kbps250,
+            500_000 => Bitrate::Kbps500,
+            1_000_000 => Bitrate::Mbps1,
+            2_000_000 => Bitrate::Mbps2,
+            4_000_000 => Bitrate::Mbps4,
+            5_000_000 => Bitrate::Mbps5,
+            8_000_000 => Bitrate::Mbps8,
+            bps => Bitrate::Raw(bps),
+        }
+    }
+}
+
+impl PartialEq for Bitrate {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits_per_second() == other.bits_per_second()
+    }
+}
+
+impl Eq for Bitrate {}
+
+/// Register-level bit timing.
+///
+/// One bit is divided into time quanta (tq) of `prescaler / clock_hz` seconds each:
+///
+/// ```text
+/// | sync (1 tq) | seg1 (prop + phase 1) | seg2 (phase 2) |
+///                                       ^ sample point
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitTiming {
+    /// Clock prescaler (length of one time quantum in controller clock cycles).
+    pub prescaler: u16,
+    /// Propagation plus phase segment 1, in time quanta.
+    pub seg1: u8,
+    /// Phase segment 2, in time quanta.
+    pub seg2: u8,
+    /// Synchronization jump width, in time quanta.
+    pub sjw: u8,
+}
+
+/// Hardware limits used when searching for a [`BitTiming`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitTimingLimits {
+    /// Largest supported prescaler.
+    pub max_prescaler: u16,
+    /// Largest supported `seg1`.
+    pub max_seg1: u8,
+    /// Largest supported `seg2`.
+    pub max_seg2: u8,
+    /// Largest supported `sjw`.
+    pub max_sjw: u8,
+}
+
+impl BitTimingLimits {
+    /// Limits of the classic bxCAN / SJA1000-style register layout.
+    pub const CLASSIC: Self = Self {
+        max_prescaler: 1024,
+        max_seg1: 16,
+        max_seg2: 8,
+        max_sjw: 4,
+    };
+}
+
+impl Default for BitTimingLimits {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+/// Sample point position within a bit, in per mille of the bit time.
+///
+/// For example, 87.5 % is `SamplePoint::from_permille(875)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SamplePoint(u16);
+
+impl SamplePoint {
+    /// 87.5 %, the CiA recommendation for nominal rates up to 500 kbit/s.
+    pub const CIA_DEFAULT: Self = Self(875);
+
+    /// Create a sample point from per mille of the bit time (clamped to 1000).
+    pub const fn from_permille(permille: u16) -> Self {
+        if permille > 1000 {
+            Self(1000)
+        } else {
+            Self(permille)
+        }
+    }
+
+    /// Returns the position in per mille of the bit time.
+    pub const fn permille(self) -> u16 {
+        self.0
+    }
+
+    /// CiA-recommended sample point range for a nominal (arbitration phase) `bitrate`.
+    ///
+    /// Following CiA 301, rates of 800 kbit/s and above tolerate a sample point as early as 75 %; all
+    /// lower rates should sample between 85 % and 90 %.
+    pub const fn recommended_nominal(bitrate: Bitrate) -> (Self, Self) {
+        if bitrate.bits_per_second() >= 800_000 {
+            (Self(750), Self(900))
+        } else {
+            (Self(850), Self(900))
+        }
+    }
+
+    /// CiA-recommended sample point range for a CAN FD data phase (CiA 601-3: 70 % to 80 %).
+    pub const fn recommended_data() -> (Self, Self) {
+        (Self(700), Self(800))
+    }
+}
+
+/// Desired bit timing, expressed in bus terms rather than register values.
+///
+/// Pass to [`BitTiming::calculate_with`] to find matching register settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingSpec {
+    /// Target bitrate (must be produced exactly).
+    pub bitrate: Bitrate,
+    /// Target sample point; the closest achievable position is chosen.
+    pub sample_point: SamplePoint,
+    /// Synchronization jump width in time quanta, or `None` for the largest valid value.
+    pub sjw: Option<u8>,
+}
+
+impl TimingSpec {
+    /// Spec for `bitrate` with the CiA default sample point and maximum SJW.
+    pub const fn new(bitrate: Bitrate) -> Self {
+        Self {
+            bitrate,
+            sample_point: SamplePoint::CIA_DEFAULT,
+            sjw: None,
+        }
+    }
+}
+
+/// Reason a [`BitTiming`] failed [`BitTiming::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingError {
+    /// A field is zero or exceeds the hardware limits.
+    OutOfRange,
+    /// SJW is larger than one of the phase segments.
+    SjwTooLarge,
+    /// The sample point lies outside the recommended range.
+    SamplePoint {
+        /// Sample point of the checked timing.
+        actual: SamplePoint,
+        /// Earliest recommended sample point.
+        min: SamplePoint,
+        /// Latest recommended sample point.
+        max: SamplePoint,
+    },
+}
+
+impl fmt::Display for TimingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => f.write_str("bit timing field out of range"),
+            Self::SjwTooLarge => f.write_str("SJW larger than a phase segment"),
+            Self::SamplePoint { actual, min, max } => write!(
+                f,
+                "sample point {} ‰ outside the recommended {}–{} ‰",
+                actual.permille(),
+                min.permille(),
+                max.permille()
+            ),
+        }
+    }
+}
+
+impl Error for TimingError {}
+
+impl BitTiming {
+    /// Number of time quanta per bit.
+    pub const fn quanta_per_bit(&self) -> u32 {
+        1 + self.seg1 as u32 + self.seg2 as u32
+    }
+
+    /// Resulting bitrate, in bits per second, for a controller clock of `clock_hz`.
+    ///
+    /// Returns 0 for a prescaler of 0, which no controller accepts.
+    pub const fn bits_per_second(&self, clock_hz: u32) -> u32 {
+        if self.prescaler == 0 {
+            return 0;
+        }
+        clock_hz / (self.prescaler as u32 * self.quanta_per_bit())
+    }
+
+    /// Position of the sample point (end of `seg1`) within the bit.
+    pub const fn sample_point(&self) -> SamplePoint {
+        SamplePoint(((1 + self.seg1 as u32) * 1000 / self.quanta_per_bit()) as u16)
+    }
+
+    /// Find a bit timing that produces `bitrate` exactly from `clock_hz`.
+    ///
+    /// Equivalent to [`BitTiming::calculate_with`] using [`TimingSpec::new`], i.e. a sample point
+    /// of 87.5 % and the largest valid SJW.
+    pub fn calculate(clock_hz: u32, bitrate: Bitrate, limits: &BitTimingLimits) -> Option<Self> {
+        Self::calculate_with(clock_hz, &TimingSpec::new(bitrate), limits)
+    }
+
+    /// Find a bit timing matching `spec` from `clock_hz`.
+    ///
+    /// The bitrate must be produced exactly. Among exact solutions, the one whose sample point is
+    /// closest to `spec.sample_point` wins; ties go to more time quanta per bit (finer
+    /// resolution). If `spec.sjw` is `None`, SJW is set to the largest value permitted by both
+    /// `limits` and the phase segments.
+    ///
+    /// Returns `None` if no exact solution exists within `limits`, if the requested SJW does not
+    /// fit the chosen segments, or if any of the limits is 0.
+    pub fn calculate_with(
+        clock_hz: u32,
+        spec: &TimingSpec,
+        limits: &BitTimingLimits,
+    ) -> Option<Self> {
+        let bps = spec.bitrate.bits_per_second();
+        if bps == 0
+            || limits.max_prescaler == 0
+            || limits.max_seg1 == 0
+            || limits.max_seg2 == 0
+            || limits.max_sjw == 0
+        {
+            return None;
+        }
+        let target = spec.sample_point.permille() as u32;
+        let max_tq = 1 + limits.max_seg1 as u32 + limits.max_seg2 as u32;
+        let mut best: Option<(u32, Self)> = None;
+        for tq in (4..=max_tq).rev() {
+            let Some(denominator) = bps.checked_mul(tq) else {
+                continue;
+            };
+            if !clock_hz.is_multiple_of(denominator) {
+                continue;
+            }
+            let prescaler = clock_hz / denominator;
+            if prescaler == 0 || prescaler > limits.max_prescaler as u32 {
+                continue;
+            }
+            // seg2 = tq * (1 - sample point), rounded to nearest, leaving at least one quantum for
+            // seg1.
+            let seg2 = ((tq * (1000 - target) + 500) / 1000)
+                .clamp(1, limits.max_seg2 as u32)
+                .min(tq - 2);
+            let seg1 = tq - 1 - seg2;
+            if seg1 > limits.max_seg1 as u32 {
+                continue;
+            }
+            let max_sjw = seg1.min(seg2).min(limits.max_sjw as u32);
+            let sjw = match spec.sjw {
+                Some(sjw) if sjw == 0 || sjw as u32 > max_sjw => continue,
+                Some(sjw) => sjw as u32,
+                None => max_sjw,
+            };
+            let timing = Self {
+                prescaler: prescaler as u16,
+                seg1: seg1 as u8,
+                seg2: seg2 as u8,
+                sjw: sjw as u8,
+            };
+            let error = (timing.sample_point().permille() as u32).abs_diff(target);
+            if best.is_none_or(|(best_error, _)| error < best_error) {
+                best = Some((error, timing));
+            }
+        }
+        best.map(|(_, timing)| timing)
+    }
+
+    /// Check register ranges, SJW, and the sample point against a recommended range.
+    ///
+    /// `recommended` is typically [`SamplePoint::recommended_nominal`] or
+    /// [`SamplePoint::recommended_data`]. Incorrect sample points cause intermittent errors that
+    /// only show up with particular cable lengths or node mixes, so validating at configuration
+    /// time is worthwhile.
+    pub fn validate(
+        &self,
+        limits: &BitTimingLimits,
+        recommended: (SamplePoint, SamplePoint),
+    ) -> Result<(), TimingError> {
+        if self.prescaler == 0
+            || self.prescaler > limits.max_prescaler
+            || self.seg1 == 0
+            || self.seg1 > limits.max_seg1
+            || self.seg2 == 0
+            || self.seg2 > limits.max_seg2
+            || self.sjw == 0
+            || self.sjw > limits.max_sjw
+        {
+            return Err(TimingError::OutOfRange);
+        }
+        if self.sjw > self.seg1 || self.sjw > self.seg2 {
+            return Err(TimingError::SjwTooLarge);
+        }
+        let (min, max) = recommended;
+        let actual = self.sample_point();
+        if actual < min || actual > max {
+            return Err(TimingError::SamplePoint { actual, min, max });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculate_with_low_sample_point() {
+        let spec = TimingSpec {
+            bitrate: Bitrate::Kbps500,
+            sample_point: SamplePoint::from_permille(0),
+            sjw: None,
+        };
+        let timing = BitTiming::calculate_with(8_000_000, &spec, &BitTimingLimits::CLASSIC);
+        assert_eq!(
+            timing,
+            Some(BitTiming {
+                prescaler: 2,
+                seg1: 1,
+                seg2: 6,
+                sjw: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn calculate_with_high_sample_point() {
+        let spec = TimingSpec {
+            bitrate: Bitrate::Kbps500,
+            sample_point: SamplePoint::from_permille(1000),
+            sjw: None,
+        };
+        let timing =
+            BitTiming::calculate_with(8_000_000, &spec, &BitTimingLimits::CLASSIC).unwrap();
+        assert_eq!(timing.seg2, 1);
+        assert_eq!(timing.bits_per_second(8_000_000), 500_000);
+    }
+
+    #[test]
+    fn bits_per_second_zero_prescaler() {
+        let timing = BitTiming {
+            prescaler: 0,
+            seg1: 13,
+            seg2: 2,
+            sjw: 1,
+        };
+        assert_eq!(timing.bits_per_second(8_000_000), 0);
+    }
+
+    #[test]
+    fn calculate_with_degenerate_limits() {
+        let spec = TimingSpec::new(Bitrate::Kbps500);
+        let degenerate = [
+            BitTimingLimits {
+                max_prescaler: 0,
+                ..BitTimingLimits::CLASSIC
+            },
+            BitTimingLimits {
+                max_seg1: 0,
+                ..BitTimingLimits::CLASSIC
+            },
+            BitTimingLimits {
+                max_seg2: 0,
+                ..BitTimingLimits::CLASSIC
+            },
+            BitTimingLimits {
+                max_sjw: 0,
+                ..BitTimingLimits::CLASSIC
+            },
+        ];
+        for limits in degenerate {
+            assert_eq!(BitTiming::calculate_with(8_000_000, &spec, &limits), None);
+        }
+    }
+}
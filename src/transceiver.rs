@@ -0,0 +1,244 @@
+//! Transceiver power coordination.
+//!
+//! [`Transceiver`] pairs a CAN controller with a [`TransceiverControl`] so that sleeping and waking
+//! the whole CAN node is one call. With the `embedded-hal` feature, [`StandbyPin`] implements
+//! [`TransceiverControl`] for any `embedded_hal::digital::OutputPin`.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, PartialSend, RxFrameIo, SendOptions, SleepControl,
+    TransceiverControl, TxFrameIo,
+};
+
+/// Error from [`Transceiver::sleep`] or [`Transceiver::wake_up`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransceiverError<C, X> {
+    /// The controller reported an error.
+    Controller(C),
+    /// The transceiver control (e.g. the STB pin) reported an error.
+    Transceiver(X),
+}
+
+impl<C, X> fmt::Display for TransceiverError<C, X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Controller(_) => f.write_str("controller error"),
+            Self::Transceiver(_) => f.write_str("transceiver control error"),
+        }
+    }
+}
+
+impl<C: Error + 'static, X: Error + 'static> Error for TransceiverError<C, X> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Controller(e) => Some(e),
+            Self::Transceiver(e) => Some(e),
+        }
+    }
+}
+
+/// A CAN controller together with its transceiver's standby control.
+///
+/// [`Transceiver::sleep`] sleeps the controller first and then puts the transceiver into standby;
+/// [`Transceiver::wake_up`] reverses the order so the controller never sees a floating bus. Frame
+/// I/O is forwarded to the controller unchanged.
+#[derive(Debug)]
+pub struct Transceiver<T, X> {
+    can: T,
+    xcvr: X,
+}
+
+impl<T, X> Transceiver<T, X> {
+    /// Pair a controller with its transceiver control.
+    pub fn new(can: T, xcvr: X) -> Self {
+        Self { can, xcvr }
+    }
+
+    /// Borrow the controller.
+    pub fn inner(&self) -> &T {
+        &self.can
+    }
+
+    /// Mutably borrow the controller.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.can
+    }
+
+    /// Mutably borrow the transceiver control.
+    pub fn transceiver_mut(&mut self) -> &mut X {
+        &mut self.xcvr
+    }
+
+    /// Unwrap into the controller and transceiver control.
+    pub fn into_parts(self) -> (T, X) {
+        (self.can, self.xcvr)
+    }
+}
+
+impl<T, X> Transceiver<T, X>
+where
+    T: SleepControl,
+    X: TransceiverControl,
+{
+    /// Put the controller to sleep, then the transceiver into standby.
+    pub fn sleep(&mut self) -> Result<(), TransceiverError<T::Error, X::Error>> {
+        self.can.sleep().map_err(TransceiverError::Controller)?;
+        self.xcvr
+            .set_standby(true)
+            .map_err(TransceiverError::Transceiver)
+    }
+
+    /// Take the transceiver out of standby, then wake the controller.
+    pub fn wake_up(&mut self) -> Result<(), TransceiverError<T::Error, X::Error>> {
+        self.xcvr
+            .set_standby(false)
+            .map_err(TransceiverError::Transceiver)?;
+        self.can.wake_up().map_err(TransceiverError::Controller)
+    }
+}
+
+impl<T: TxFrameIo, X> TxFrameIo for Transceiver<T, X> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.can.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.can.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.can.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.can.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.can.send_batch(frames)
+    }
+}
+
+impl<T: RxFrameIo, X> RxFrameIo for Transceiver<T, X> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.can.recv()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.can.try_recv()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.can.recv_timeout(timeout)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.can.wait_not_empty()
+    }
+}
+
+impl<T: AsyncTxFrameIo, X> AsyncTxFrameIo for Transceiver<T, X> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.can.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.can.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.can.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.can.send_batch(frames).await
+    }
+}
+
+impl<T: AsyncRxFrameIo, X> AsyncRxFrameIo for Transceiver<T, X> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.can.recv().await
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.can.recv_timeout(timeout).await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.can.wait_not_empty().await
+    }
+}
+
+/// Level of a standby/enable pin that puts the transceiver into standby.
+#[cfg(feature = "embedded-hal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandbyLevel {
+    /// Driving the pin high selects standby (STB inputs, e.g. TJA1042 or MCP2562).
+    High,
+    /// Driving the pin low selects standby (active-high EN inputs).
+    Low,
+}
+
+/// [`TransceiverControl`] for a transceiver whose standby is selected by a single GPIO.
+#[cfg(feature = "embedded-hal")]
+#[derive(Debug)]
+pub struct StandbyPin<P> {
+    pin: P,
+    standby_level: StandbyLevel,
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<P: embedded_hal::digital::OutputPin> StandbyPin<P> {
+    /// Wrap `pin`, which selects standby when driven to `standby_level`.
+    pub fn new(pin: P, standby_level: StandbyLevel) -> Self {
+        Self { pin, standby_level }
+    }
+
+    /// Unwrap, returning the pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<P: embedded_hal::digital::OutputPin> TransceiverControl for StandbyPin<P> {
+    type Error = P::Error;
+
+    fn set_standby(&mut self, standby: bool) -> Result<(), Self::Error> {
+        let high = standby == (self.standby_level == StandbyLevel::High);
+        if high {
+            self.pin.set_high()
+        } else {
+            self.pin.set_low()
+        }
+    }
+}
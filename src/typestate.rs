@@ -0,0 +1,344 @@
+//! Configuration and running states in the type system.
+//!
+//! Many controllers only accept filter and bit-timing changes in their configuration (init)
+//! mode, and only move frames once started. [`Lifecycle`] sequences this at runtime; the wrappers
+//! here make the compiler enforce it:
+//!
+//! - [`Configured`] holds an interface in configuration mode and implements the configuration
+//!   traits ([`FilterConfig`], [`BitrateConfig`], [`BitTimingConfig`], …) but no I/O;
+//! - [`Running`] holds a started interface and implements the I/O and status traits but no
+//!   configuration.
+//!
+//! [`Configured::start`] and [`Running::stop`] move between the two:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::{BitrateConfig, BuilderBinding, FilterConfig, TxFrameIo};
+//!
+//! let mut can = MyCan::open_configured("can0")?;
+//! can.set_bitrate(Bitrate::Kbps500)?;
+//! can.set_filters(&filters)?;
+//! let mut can = can.start().map_err(|e| e.error)?;
+//! can.send(&frame)?;
+//! ```
+//!
+//! Drivers with a builder can return a [`Configured`] from it via [`Configured::new`], so the
+//! whole flow runs `Builder -> Configured<T> -> Running<T>`.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use crate::{
+    AsyncRxFrameIo, AsyncTxFrameIo, BitTiming, BitTimingConfig, Bitrate, BitrateConfig, BusState,
+    ConstFilterConfig, ErrorCounters, ErrorState, FilterConfig, IdMaskFilter, Lifecycle,
+    ListenOnlyControl, PartialSend, PendingMask, RxFrameIo, SelfReceptionControl, SendOptions,
+    TerminationControl, TxFrameIo, TxRxState,
+};
+
+/// A failed state change, handing back the interface in the state it was in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransitionError<S, E> {
+    /// The interface, unchanged.
+    pub interface: S,
+    /// Error returned by the driver.
+    pub error: E,
+}
+
+impl<S, E> fmt::Display for TransitionError<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("state change failed")
+    }
+}
+
+impl<S: fmt::Debug, E: Error + 'static> Error for TransitionError<S, E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// An interface in configuration mode: configurable, but without I/O.
+#[derive(Debug)]
+pub struct Configured<T> {
+    inner: T,
+}
+
+impl<T: Lifecycle> Configured<T> {
+    /// Put `inner` into configuration mode with [`Lifecycle::disable`].
+    pub fn new(mut inner: T) -> Result<Self, TransitionError<T, T::Error>> {
+        match inner.disable() {
+            Ok(()) => Ok(Self { inner }),
+            Err(error) => Err(TransitionError {
+                interface: inner,
+                error,
+            }),
+        }
+    }
+
+    /// Start the interface with [`Lifecycle::enable`].
+    pub fn start(mut self) -> Result<Running<T>, TransitionError<Self, T::Error>> {
+        match self.inner.enable() {
+            Ok(()) => Ok(Running { inner: self.inner }),
+            Err(error) => Err(TransitionError {
+                interface: self,
+                error,
+            }),
+        }
+    }
+}
+
+impl<T> Configured<T> {
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap, returning the interface in configuration mode.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+/// A started interface: I/O, but no configuration.
+#[derive(Debug)]
+pub struct Running<T> {
+    inner: T,
+}
+
+impl<T: Lifecycle> Running<T> {
+    /// Stop the interface with [`Lifecycle::disable`] to change its configuration.
+    pub fn stop(mut self) -> Result<Configured<T>, TransitionError<Self, T::Error>> {
+        match self.inner.disable() {
+            Ok(()) => Ok(Configured { inner: self.inner }),
+            Err(error) => Err(TransitionError {
+                interface: self,
+                error,
+            }),
+        }
+    }
+}
+
+impl<T> Running<T> {
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap, returning the running interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: FilterConfig> FilterConfig for Configured<T> {
+    type Error = T::Error;
+    type FiltersHandle<'a>
+        = T::FiltersHandle<'a>
+    where
+        Self: 'a;
+
+    fn set_filters(&mut self, filters: &[IdMaskFilter]) -> Result<(), Self::Error> {
+        self.inner.set_filters(filters)
+    }
+
+    fn modify_filters(&mut self) -> Self::FiltersHandle<'_> {
+        self.inner.modify_filters()
+    }
+}
+
+impl<T: ConstFilterConfig> ConstFilterConfig for Configured<T> {
+    const MAX_FILTERS: usize = T::MAX_FILTERS;
+}
+
+impl<T: BitrateConfig> BitrateConfig for Configured<T> {
+    type Error = T::Error;
+
+    fn set_bitrate(&mut self, bitrate: Bitrate) -> Result<(), Self::Error> {
+        self.inner.set_bitrate(bitrate)
+    }
+
+    fn set_data_bitrate(&mut self, bitrate: Bitrate) -> Result<(), Self::Error> {
+        self.inner.set_data_bitrate(bitrate)
+    }
+}
+
+impl<T: BitTimingConfig> BitTimingConfig for Configured<T> {
+    type Error = T::Error;
+
+    fn clock_hz(&self) -> u32 {
+        self.inner.clock_hz()
+    }
+
+    fn set_bit_timing(&mut self, timing: &BitTiming) -> Result<(), Self::Error> {
+        self.inner.set_bit_timing(timing)
+    }
+
+    fn set_data_bit_timing(&mut self, timing: &BitTiming) -> Result<(), Self::Error> {
+        self.inner.set_data_bit_timing(timing)
+    }
+}
+
+impl<T: ListenOnlyControl> ListenOnlyControl for Configured<T> {
+    type Error = T::Error;
+
+    fn set_listen_only(&mut self, on: bool) -> Result<(), Self::Error> {
+        self.inner.set_listen_only(on)
+    }
+}
+
+impl<T: SelfReceptionControl> SelfReceptionControl for Configured<T> {
+    type Error = T::Error;
+
+    fn set_self_reception(&mut self, on: bool) -> Result<(), Self::Error> {
+        self.inner.set_self_reception(on)
+    }
+}
+
+impl<T: TerminationControl> TerminationControl for Configured<T> {
+    type Error = T::Error;
+
+    fn set_termination(&mut self, on: bool) -> Result<(), Self::Error> {
+        self.inner.set_termination(on)
+    }
+}
+
+impl<T: TxFrameIo> TxFrameIo for Running<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame)
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.try_send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout)
+    }
+
+    fn send_with(&mut self, frame: &Self::Frame, options: &SendOptions) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options)
+    }
+
+    fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames)
+    }
+}
+
+impl<T: RxFrameIo> RxFrameIo for Running<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.inner.recv()
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.inner.try_recv()
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.inner.recv_timeout(timeout)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty()
+    }
+}
+
+impl<T: AsyncTxFrameIo> AsyncTxFrameIo for Running<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.inner.send(frame).await
+    }
+
+    async fn send_timeout(
+        &mut self,
+        frame: &Self::Frame,
+        timeout: Duration,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_timeout(frame, timeout).await
+    }
+
+    async fn send_with(
+        &mut self,
+        frame: &Self::Frame,
+        options: &SendOptions,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_with(frame, options).await
+    }
+
+    async fn send_batch(
+        &mut self,
+        frames: &[(Self::Frame, SendOptions)],
+    ) -> Result<usize, PartialSend<Self::Error>> {
+        self.inner.send_batch(frames).await
+    }
+}
+
+impl<T: AsyncRxFrameIo> AsyncRxFrameIo for Running<T> {
+    type Frame = T::Frame;
+    type Error = T::Error;
+
+    async fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.inner.recv().await
+    }
+
+    async fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.inner.recv_timeout(timeout).await
+    }
+
+    async fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.inner.wait_not_empty().await
+    }
+}
+
+impl<T: TxRxState> TxRxState for Running<T> {
+    type Error = T::Error;
+
+    fn is_transmitter_idle(&self) -> Result<bool, Self::Error> {
+        self.inner.is_transmitter_idle()
+    }
+
+    fn tx_free(&self) -> Result<Option<usize>, Self::Error> {
+        self.inner.tx_free()
+    }
+
+    fn pending_tx(&self) -> Result<Option<PendingMask>, Self::Error> {
+        self.inner.pending_tx()
+    }
+
+    fn rx_pending(&self) -> Result<Option<usize>, Self::Error> {
+        self.inner.rx_pending()
+    }
+
+    fn rx_overruns(&self) -> Result<Option<u32>, Self::Error> {
+        self.inner.rx_overruns()
+    }
+}
+
+impl<T: BusState> BusState for Running<T> {
+    type Error = T::Error;
+
+    fn bus_state(&self) -> Result<ErrorState, Self::Error> {
+        self.inner.bus_state()
+    }
+
+    fn error_counters(&self) -> Result<ErrorCounters, Self::Error> {
+        self.inner.error_counters()
+    }
+
+    fn recover_bus_off(&mut self) -> Result<(), Self::Error> {
+        self.inner.recover_bus_off()
+    }
+
+    fn last_error(&self) -> Result<Option<embedded_can::ErrorKind>, Self::Error> {
+        self.inner.last_error()
+    }
+}
@@ -0,0 +1,287 @@
+//! Virtual bus compatible with python-can's `udp_multicast` interface (requires the
+//! `udp-multicast` feature).
+//!
+//! python-can's UDP multicast bus sends each frame as one datagram to a multicast group, encoded
+//! as a MessagePack map of the `can.Message` fields. [`UdpMulticastBus`] speaks the same format on
+//! the same default groups and port, so Rust nodes built on this crate can join simulations made
+//! of python-can scripts on the same machine or LAN:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::udp_multicast::{UdpMulticastBus, DEFAULT_GROUP_IPV6};
+//!
+//! let mut bus: UdpMulticastBus<MyFrame> = UdpMulticastBus::open(DEFAULT_GROUP_IPV6.into())?;
+//! bus.send(&frame)?;
+//! ```
+//!
+//! Every participant receives every frame except its own. Error frames are discarded, as are
+//! frames the frame type `F` cannot represent (e.g. CAN FD frames for a classic frame type).
+//! Errors are [`crate::error::Error`]s: timeouts have
+//! [`ErrorKind::Timeout`](crate::error::ErrorKind::Timeout) and empty queues in `try_*` calls
+//! [`ErrorKind::WouldBlock`](crate::error::ErrorKind::WouldBlock).
+
+use core::marker::PhantomData;
+use core::time::Duration;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Instant, SystemTime};
+use std::vec::Vec;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+use rmpv::Value;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::error::Error;
+use crate::{Reconnectable, RxFrameIo, TxFrameIo};
+
+/// python-can's default IPv4 multicast group.
+pub const DEFAULT_GROUP_IPV4: Ipv4Addr = Ipv4Addr::new(239, 74, 163, 2);
+
+/// python-can's default IPv6 multicast group (site-local scope).
+pub const DEFAULT_GROUP_IPV6: Ipv6Addr = Ipv6Addr::new(
+    0xff15, 0x7079, 0x7468, 0x6f6e, 0x6465, 0x6d6f, 0x6d63, 0x6173,
+);
+
+/// python-can's default UDP port.
+pub const DEFAULT_PORT: u16 = 43113;
+
+/// Largest datagram accepted; python-can uses the same limit.
+const MAX_DATAGRAM: usize = 4096;
+
+/// How a receive call waits for a datagram.
+enum Wait {
+    Never,
+    Forever,
+    Until(Instant),
+}
+
+/// A node on a python-can compatible UDP multicast bus.
+#[derive(Debug)]
+pub struct UdpMulticastBus<F> {
+    rx: UdpSocket,
+    tx: UdpSocket,
+    group: SocketAddr,
+    hop_limit: u32,
+    /// Local port of `tx`, used to recognize our own datagrams.
+    tx_port: u16,
+    buf: Vec<u8>,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<F> UdpMulticastBus<F> {
+    /// Join `group` on [`DEFAULT_PORT`] with a hop limit of 1 (python-can's defaults).
+    pub fn open(group: IpAddr) -> io::Result<Self> {
+        Self::open_with(SocketAddr::new(group, DEFAULT_PORT), 1)
+    }
+
+    /// Join the multicast group `group`, sending with the given TTL / hop limit.
+    pub fn open_with(group: SocketAddr, hop_limit: u32) -> io::Result<Self> {
+        let domain = Domain::for_address(group);
+        let rx = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        // Several processes on one host listen on the same port.
+        rx.set_reuse_address(true)?;
+        let tx = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        match group.ip() {
+            IpAddr::V4(ip) => {
+                rx.bind(&SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), group.port()).into())?;
+                rx.join_multicast_v4(&ip, &Ipv4Addr::UNSPECIFIED)?;
+                tx.set_multicast_ttl_v4(hop_limit)?;
+                tx.set_multicast_loop_v4(true)?;
+                tx.bind(&SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0).into())?;
+            }
+            IpAddr::V6(ip) => {
+                rx.set_only_v6(true)?;
+                rx.bind(&SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), group.port()).into())?;
+                rx.join_multicast_v6(&ip, 0)?;
+                tx.set_multicast_hops_v6(hop_limit)?;
+                tx.set_multicast_loop_v6(true)?;
+                tx.bind(&SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0).into())?;
+            }
+        }
+        let tx: UdpSocket = tx.into();
+        let tx_port = tx.local_addr()?.port();
+        Ok(Self {
+            rx: rx.into(),
+            tx,
+            group,
+            hop_limit,
+            tx_port,
+            buf: std::vec![0; MAX_DATAGRAM],
+            _frame: PhantomData,
+        })
+    }
+
+    /// The multicast group and port this bus is joined to.
+    pub fn group(&self) -> SocketAddr {
+        self.group
+    }
+
+    /// Receive and decode datagrams until one yields a frame.
+    fn recv_frame(&mut self, wait: Wait) -> io::Result<F>
+    where
+        F: Frame,
+    {
+        loop {
+            match wait {
+                Wait::Never => self.rx.set_nonblocking(true)?,
+                Wait::Forever => {
+                    self.rx.set_nonblocking(false)?;
+                    self.rx.set_read_timeout(None)?;
+                }
+                Wait::Until(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                    self.rx.set_nonblocking(false)?;
+                    self.rx.set_read_timeout(Some(remaining))?;
+                }
+            }
+            let (len, from) = match self.rx.recv_from(&mut self.buf) {
+                // Read timeouts surface as `WouldBlock` on Unix and `TimedOut` on Windows.
+                Err(e)
+                    if matches!(wait, Wait::Until(_))
+                        && matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                result => result?,
+            };
+            // The group loops our own datagrams back. python-can peers send from the group port,
+            // so only another node's ephemeral send port could collide with ours.
+            if from.port() == self.tx_port {
+                continue;
+            }
+            if let Some(frame) = decode(&self.buf[..len]) {
+                return Ok(frame);
+            }
+        }
+    }
+}
+
+/// Creates new sockets and joins the group again, e.g. after the network interface went down.
+impl<F> Reconnectable for UdpMulticastBus<F> {
+    type Error = Error;
+
+    fn reopen(&mut self) -> Result<(), Self::Error> {
+        *self = Self::open_with(self.group, self.hop_limit)?;
+        Ok(())
+    }
+}
+
+impl<F: Frame> TxFrameIo for UdpMulticastBus<F> {
+    type Frame = F;
+    type Error = Error;
+
+    /// Datagrams are never held back, so this does not block.
+    fn send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.tx.send_to(&encode(frame), self.group)?;
+        Ok(())
+    }
+
+    fn try_send(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+        self.send(frame)
+    }
+
+    fn send_timeout(&mut self, frame: &Self::Frame, _timeout: Duration) -> Result<(), Self::Error> {
+        self.send(frame)
+    }
+}
+
+impl<F: Frame> RxFrameIo for UdpMulticastBus<F> {
+    type Frame = F;
+    type Error = Error;
+
+    fn recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Forever).map_err(Error::from)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Never).map_err(Error::from)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Frame, Self::Error> {
+        self.recv_frame(Wait::Until(Instant::now() + timeout))
+            .map_err(Error::from)
+    }
+
+    fn wait_not_empty(&mut self) -> Result<(), Self::Error> {
+        self.rx.set_nonblocking(false)?;
+        self.rx.set_read_timeout(None)?;
+        loop {
+            let (len, from) = self.rx.peek_from(&mut self.buf)?;
+            if from.port() != self.tx_port && decode::<F>(&self.buf[..len]).is_some() {
+                return Ok(());
+            }
+            // Discard datagrams `recv` would skip anyway.
+            self.rx.recv_from(&mut self.buf)?;
+        }
+    }
+}
+
+/// Encode `frame` the way python-can's `pack_message` does.
+fn encode<F: Frame>(frame: &F) -> Vec<u8> {
+    let (arbitration_id, is_extended) = match frame.id() {
+        Id::Standard(id) => (u32::from(id.as_raw()), false),
+        Id::Extended(id) => (id.as_raw(), true),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0.0, |t| t.as_secs_f64());
+    let data = if frame.is_remote_frame() {
+        &[][..]
+    } else {
+        frame.data()
+    };
+    let field = |key: &str, value: Value| (Value::from(key), value);
+    let message = Value::Map(std::vec![
+        field("timestamp", timestamp.into()),
+        field("arbitration_id", arbitration_id.into()),
+        field("is_extended_id", is_extended.into()),
+        field("is_remote_frame", frame.is_remote_frame().into()),
+        field("is_error_frame", false.into()),
+        field("channel", Value::Nil),
+        field("dlc", frame.dlc().into()),
+        field("data", data.into()),
+        field("is_fd", (data.len() > 8).into()),
+        field("bitrate_switch", false.into()),
+        field("error_state_indicator", false.into()),
+    ]);
+    let mut out = Vec::with_capacity(192);
+    // Writing to a `Vec` cannot fail.
+    let _ = rmpv::encode::write_value(&mut out, &message);
+    out
+}
+
+/// Decode a datagram produced by python-can's `pack_message`.
+fn decode<F: Frame>(mut bytes: &[u8]) -> Option<F> {
+    let message = rmpv::decode::read_value(&mut bytes).ok()?;
+    let field = |key: &str| {
+        message
+            .as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    };
+    let flag = |key: &str| field(key).and_then(Value::as_bool).unwrap_or(false);
+    if flag("is_error_frame") {
+        return None;
+    }
+    let raw = u32::try_from(field("arbitration_id")?.as_u64()?).ok()?;
+    let id = if flag("is_extended_id") {
+        Id::Extended(ExtendedId::new(raw)?)
+    } else {
+        Id::Standard(StandardId::new(u16::try_from(raw).ok()?)?)
+    };
+    let data = field("data").and_then(Value::as_slice).unwrap_or(&[]);
+    if flag("is_remote_frame") {
+        let dlc = field("dlc")
+            .and_then(Value::as_u64)
+            .map_or(data.len(), |dlc| dlc as usize);
+        F::new_remote(id, dlc)
+    } else {
+        F::new(id, data)
+    }
+}
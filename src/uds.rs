@@ -0,0 +1,195 @@
+//! ISO 14229 (UDS) transport semantics over a message transport.
+//!
+//! [`UdsTransport`] turns a [`MessageIo`] (usually [`crate::isotp::IsoTp`]) into a
+//! request/response channel with the application-layer timing of ISO 14229-2:
+//!
+//! - the server must start responding within P2 (default 50 ms);
+//! - a “response pending” negative response (NRC 0x78) extends the wait to P2* (default 5 s),
+//!   and may be repeated.
+//!
+//! It deliberately stops there: responses, positive or negative, are handed back as raw bytes so a
+//! full UDS client can sit on top and interpret them.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::uds::UdsTransport;
+//!
+//! let mut uds = UdsTransport::new(isotp, clock);
+//! let mut buf = [0u8; 256];
+//! let response = uds.request(&[0x22, 0xF1, 0x90], &mut buf)?;
+//! ```
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use crate::Clock;
+use crate::isotp::MessageIo;
+
+/// Service identifier of negative responses.
+pub const NEGATIVE_RESPONSE: u8 = 0x7F;
+/// Negative response code: request received, response pending.
+pub const RESPONSE_PENDING: u8 = 0x78;
+/// Positive responses echo the request SID with this bit set.
+pub const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+/// Sub-function bit asking the server not to send a positive response.
+pub const SUPPRESS_POSITIVE_RESPONSE: u8 = 0x80;
+
+/// Application-layer timing parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdsTiming {
+    /// Time the server has to start its response (P2 server max).
+    pub p2: Duration,
+    /// Time the server has after a “response pending” (P2* server max).
+    pub p2_star: Duration,
+}
+
+impl Default for UdsTiming {
+    fn default() -> Self {
+        Self {
+            p2: Duration::from_millis(50),
+            p2_star: Duration::from_millis(5000),
+        }
+    }
+}
+
+impl UdsTiming {
+    /// Read the timing a server reports in its DiagnosticSessionControl (0x50) response.
+    ///
+    /// The response carries P2 in 1 ms and P2* in 10 ms units.
+    pub fn from_session_response(response: &[u8]) -> Option<Self> {
+        match response {
+            [0x50, _session, p2_hi, p2_lo, star_hi, star_lo, ..] => Some(Self {
+                p2: Duration::from_millis(u16::from_be_bytes([*p2_hi, *p2_lo]).into()),
+                p2_star: Duration::from_millis(
+                    u64::from(u16::from_be_bytes([*star_hi, *star_lo])) * 10,
+                ),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned by [`UdsTransport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdsError<E> {
+    /// The transport failed.
+    Transport(E),
+    /// No (final) response arrived within P2 or P2*.
+    Timeout,
+    /// The request was empty.
+    EmptyRequest,
+}
+
+impl<E> fmt::Display for UdsError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(_) => f.write_str("transport error"),
+            Self::Timeout => f.write_str("no response within P2/P2*"),
+            Self::EmptyRequest => f.write_str("empty request"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for UdsError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Request/response channel with UDS timing supervision.
+#[derive(Debug)]
+pub struct UdsTransport<M, C> {
+    transport: M,
+    clock: C,
+    timing: UdsTiming,
+}
+
+impl<M, C> UdsTransport<M, C> {
+    /// Wrap `transport` with default timing.
+    pub fn new(transport: M, clock: C) -> Self {
+        Self::with_timing(transport, clock, UdsTiming::default())
+    }
+
+    /// Wrap `transport` with explicit timing.
+    pub fn with_timing(transport: M, clock: C, timing: UdsTiming) -> Self {
+        Self {
+            transport,
+            clock,
+            timing,
+        }
+    }
+
+    /// Current timing parameters.
+    pub fn timing(&self) -> &UdsTiming {
+        &self.timing
+    }
+
+    /// Replace the timing parameters, e.g. after a session change.
+    pub fn set_timing(&mut self, timing: UdsTiming) {
+        self.timing = timing;
+    }
+
+    /// Borrow the underlying transport.
+    pub fn transport(&mut self) -> &mut M {
+        &mut self.transport
+    }
+
+    /// Unwrap, returning the underlying transport.
+    pub fn into_inner(self) -> M {
+        self.transport
+    }
+}
+
+impl<M: MessageIo, C: Clock> UdsTransport<M, C> {
+    /// Send `request` without waiting for a response.
+    ///
+    /// Use this for requests with [`SUPPRESS_POSITIVE_RESPONSE`] set, or for functional requests
+    /// whose responses are collected separately.
+    pub fn send(&mut self, request: &[u8]) -> Result<(), UdsError<M::Error>> {
+        if request.is_empty() {
+            return Err(UdsError::EmptyRequest);
+        }
+        self.transport
+            .send_message(request)
+            .map_err(UdsError::Transport)
+    }
+
+    /// Send `request` and return the final response in `buf`.
+    ///
+    /// The response is either a positive response (SID + 0x40) or a negative response other than
+    /// “response pending”; both are returned as raw bytes. Messages that do not answer this
+    /// request's SID are discarded.
+    pub fn request<'b>(
+        &mut self,
+        request: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<&'b [u8], UdsError<M::Error>> {
+        self.send(request)?;
+        let sid = request[0];
+        let mut deadline = self.clock.now() + self.timing.p2;
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Err(UdsError::Timeout);
+            }
+            let len = match self.transport.recv_message(buf, remaining) {
+                Ok(len) => len,
+                Err(_) if self.clock.now() >= deadline => return Err(UdsError::Timeout),
+                Err(e) => return Err(UdsError::Transport(e)),
+            };
+            match buf[..len] {
+                [NEGATIVE_RESPONSE, service, RESPONSE_PENDING, ..] if service == sid => {
+                    deadline = self.clock.now() + self.timing.p2_star;
+                }
+                [NEGATIVE_RESPONSE, service, _, ..] if service == sid => return Ok(&buf[..len]),
+                [service, ..] if service == sid | POSITIVE_RESPONSE_OFFSET => {
+                    return Ok(&buf[..len]);
+                }
+                _ => {}
+            }
+        }
+    }
+}
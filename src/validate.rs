@@ -0,0 +1,159 @@
+//! Frame validation at trust boundaries.
+//!
+//! Gateways, bridges and network backends receive frames from sources they do not control. A
+//! frame type can hold values no controller would put on the bus (a DLC that disagrees with the
+//! data, 10 data bytes, a remote frame with data), and forwarding them moves the problem
+//! somewhere harder to debug. [`validate_frame`] applies one documented set of rules:
+//!
+//! - Data frames carry 0–8 bytes, or a CAN FD length (12, 16, 20, 24, 32, 48 or 64).
+//! - The DLC matches the data: it is either the byte count or the DLC code for it (9–15 for the
+//!   FD lengths); classic frames of 8 bytes may have DLC 9–15, which also means 8 bytes.
+//! - Remote frames have a DLC of 0–8 and no data bytes.
+//!
+//! [`FrameRules`] adds optional rules: rejecting FD lengths, rejecting the standard identifiers
+//! 0x7F0–0x7FF (reserved in CAN 2.0A), and rejecting identifiers matched by a list of filters:
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::validate::FrameRules;
+//!
+//! const RULES: FrameRules<'static> = FrameRules::new().classic_only().reject_can20a_reserved();
+//! if let Err(reason) = RULES.validate(&frame) {
+//!     log::warn!("dropping frame from {peer}: {reason}");
+//!     continue;
+//! }
+//! ```
+
+use core::error::Error;
+use core::fmt;
+
+use embedded_can::{Frame, Id};
+
+use crate::IdMaskFilter;
+use crate::frame_eq::dlc_to_len;
+
+/// Why [`validate_frame`] or [`FrameRules::validate`] rejected a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameInvalidReason {
+    /// The data length is neither a classic nor a CAN FD length.
+    InvalidLength(usize),
+    /// A CAN FD length where only classic frames are allowed.
+    FdNotAllowed(usize),
+    /// The DLC does not describe the data length.
+    DlcMismatch {
+        /// DLC reported by the frame.
+        dlc: usize,
+        /// Number of data bytes.
+        len: usize,
+    },
+    /// A remote frame with a DLC above 8.
+    InvalidRemoteDlc(usize),
+    /// A remote frame carrying data bytes.
+    RemoteWithData,
+    /// The identifier is reserved by the rules.
+    ReservedId(Id),
+}
+
+impl fmt::Display for FrameInvalidReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(len) => write!(f, "invalid data length {len}"),
+            Self::FdNotAllowed(len) => write!(f, "CAN FD data length {len} not allowed"),
+            Self::DlcMismatch { dlc, len } => {
+                write!(f, "DLC {dlc} does not match {len} data bytes")
+            }
+            Self::InvalidRemoteDlc(dlc) => write!(f, "invalid remote frame DLC {dlc}"),
+            Self::RemoteWithData => f.write_str("remote frame with data bytes"),
+            Self::ReservedId(Id::Standard(id)) => {
+                write!(f, "reserved identifier {:03X}", id.as_raw())
+            }
+            Self::ReservedId(Id::Extended(id)) => {
+                write!(f, "reserved identifier {:08X}", id.as_raw())
+            }
+        }
+    }
+}
+
+impl Error for FrameInvalidReason {}
+
+/// Check `frame` against the default rules; see the [module documentation](self).
+pub fn validate_frame<F: Frame>(frame: &F) -> Result<(), FrameInvalidReason> {
+    FrameRules::new().validate(frame)
+}
+
+/// Configurable validation rules; [`FrameRules::new`] (also the default) are the rules of
+/// [`validate_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRules<'a> {
+    allow_fd: bool,
+    reject_can20a_reserved: bool,
+    reserved: &'a [IdMaskFilter],
+}
+
+impl Default for FrameRules<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FrameRules<'a> {
+    /// The default rules: classic and CAN FD lengths, every identifier.
+    pub const fn new() -> Self {
+        Self {
+            allow_fd: true,
+            reject_can20a_reserved: false,
+            reserved: &[],
+        }
+    }
+
+    /// Reject data frames longer than 8 bytes.
+    pub const fn classic_only(mut self) -> Self {
+        self.allow_fd = false;
+        self
+    }
+
+    /// Reject standard identifiers 0x7F0–0x7FF, whose seven most significant bits are all
+    /// recessive; CAN 2.0A controllers were not required to send them.
+    pub const fn reject_can20a_reserved(mut self) -> Self {
+        self.reject_can20a_reserved = true;
+        self
+    }
+
+    /// Reject identifiers that any of `filters` matches, e.g. a diagnostic range that must not
+    /// cross a gateway.
+    pub const fn reserved(mut self, filters: &'a [IdMaskFilter]) -> Self {
+        self.reserved = filters;
+        self
+    }
+
+    /// Check `frame` against the rules.
+    pub fn validate<F: Frame>(&self, frame: &F) -> Result<(), FrameInvalidReason> {
+        let id = frame.id();
+        let reserved_20a = matches!(id, Id::Standard(id) if id.as_raw() >= 0x7F0);
+        if (self.reject_can20a_reserved && reserved_20a)
+            || self.reserved.iter().any(|filter| filter.matches(id))
+        {
+            return Err(FrameInvalidReason::ReservedId(id));
+        }
+        let (dlc, len) = (frame.dlc(), frame.data().len());
+        if frame.is_remote_frame() {
+            if dlc > 8 {
+                return Err(FrameInvalidReason::InvalidRemoteDlc(dlc));
+            }
+            if len != 0 {
+                return Err(FrameInvalidReason::RemoteWithData);
+            }
+            return Ok(());
+        }
+        if !matches!(len, 0..=8 | 12 | 16 | 20 | 24 | 32 | 48 | 64) {
+            return Err(FrameInvalidReason::InvalidLength(len));
+        }
+        if len > 8 && !self.allow_fd {
+            return Err(FrameInvalidReason::FdNotAllowed(len));
+        }
+        let classic_long_dlc = len == 8 && (9..=15).contains(&dlc);
+        if dlc != len && dlc_to_len(dlc) != len && !classic_long_dlc {
+            return Err(FrameInvalidReason::DlcMismatch { dlc, len });
+        }
+        Ok(())
+    }
+}
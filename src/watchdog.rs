@@ -0,0 +1,401 @@
+//! Heartbeat monitoring of other nodes.
+//!
+//! [`Watchdog`] tracks up to `N` identifiers, each with a maximum inter-arrival time. Feed it the
+//! frames you receive with [`Watchdog::observe`] and call [`Watchdog::poll`] periodically (from a
+//! timer tick, for example) to learn when a node stops transmitting and when it comes back.
+//!
+//! Alternatively, [`Watchdog::recv`] and [`Watchdog::recv_async`] wrap a receive call: they wait
+//! for the next frame but return early with an event when a deadline passes, which suits a
+//! dedicated RX task.
+//!
+//! ```rust,ignore
+//! use embedded_can_interface::watchdog::{Monitored, Watchdog, WatchdogEvent};
+//!
+//! let mut watchdog = Watchdog::<4>::new();
+//! watchdog.watch(engine_heartbeat_id, Duration::from_millis(250), clock.now())?;
+//!
+//! loop {
+//!     match watchdog.recv_async(&mut rx, &clock).await? {
+//!         Monitored::Frame(frame) => handle(frame),
+//!         Monitored::Event(WatchdogEvent::Missing { id, .. }) => warn_node_lost(id),
+//!         Monitored::Event(WatchdogEvent::Recovered { id }) => clear_warning(id),
+//!     }
+//! }
+//! ```
+//!
+//! [`MonitoredRx`] is the same receive loop as an interface, so the monitoring can move into the
+//! driver where it supports that: [`SoftwareWatchdog`] implements it with a [`Watchdog`], and the
+//! Linux broadcast manager behind `socketcan::BcmSocket` implements it with kernel timers.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{AsyncRxFrameIo, Clock, RxFrameIo};
+
+/// Change in the liveness of a watched identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// No frame with `id` arrived within its maximum interval.
+    Missing {
+        /// The silent identifier.
+        id: Id,
+        /// When the identifier was last seen (or started being watched).
+        last_seen: Duration,
+    },
+    /// A frame with `id` arrived again after it was reported missing.
+    Recovered {
+        /// The identifier that came back.
+        id: Id,
+    },
+}
+
+/// Error returned by [`Watchdog::watch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogError {
+    /// All `N` slots are in use.
+    Full,
+    /// The identifier is already watched.
+    AlreadyWatched,
+}
+
+impl fmt::Display for WatchdogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("all watchdog slots in use"),
+            Self::AlreadyWatched => f.write_str("identifier already watched"),
+        }
+    }
+}
+
+impl Error for WatchdogError {}
+
+/// Result of [`Watchdog::recv`]: either a received frame or a liveness change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Monitored<F> {
+    /// A frame was received (and has already been observed by the watchdog).
+    Frame(F),
+    /// A watched identifier went missing or recovered.
+    Event(WatchdogEvent),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Watch {
+    id: Id,
+    max_interval: Duration,
+    last_seen: Duration,
+    /// Liveness last reported through [`Watchdog::poll`].
+    reported_missing: bool,
+}
+
+impl Watch {
+    fn deadline(&self) -> Duration {
+        self.last_seen + self.max_interval
+    }
+}
+
+/// Liveness monitor for up to `N` identifiers.
+#[derive(Debug)]
+pub struct Watchdog<const N: usize> {
+    watches: [Option<Watch>; N],
+}
+
+impl<const N: usize> Default for Watchdog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Watchdog<N> {
+    /// Create a watchdog with no watched identifiers.
+    pub const fn new() -> Self {
+        Self { watches: [None; N] }
+    }
+
+    /// Expect a frame with `id` at least every `max_interval`, starting from `now`.
+    pub fn watch(
+        &mut self,
+        id: impl Into<Id>,
+        max_interval: Duration,
+        now: Duration,
+    ) -> Result<(), WatchdogError> {
+        let id = id.into();
+        if self.find(id).is_some() {
+            return Err(WatchdogError::AlreadyWatched);
+        }
+        let slot = self
+            .watches
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(WatchdogError::Full)?;
+        *slot = Some(Watch {
+            id,
+            max_interval,
+            last_seen: now,
+            reported_missing: false,
+        });
+        Ok(())
+    }
+
+    /// Stop watching `id`. Returns `false` if it was not watched.
+    pub fn unwatch(&mut self, id: impl Into<Id>) -> bool {
+        let id = id.into();
+        match self
+            .watches
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(w) if w.id == id))
+        {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `Some(true)` if `id` is watched and has not been reported missing.
+    pub fn is_alive(&self, id: impl Into<Id>) -> Option<bool> {
+        self.find(id.into()).map(|watch| !watch.reported_missing)
+    }
+
+    /// Record that a frame with `id` was received at `now`.
+    pub fn observe_id(&mut self, id: Id, now: Duration) {
+        if let Some(watch) = self
+            .watches
+            .iter_mut()
+            .flatten()
+            .find(|watch| watch.id == id)
+        {
+            watch.last_seen = now;
+        }
+    }
+
+    /// Record that `frame` was received at `now`.
+    pub fn observe<F: Frame>(&mut self, frame: &F, now: Duration) {
+        self.observe_id(frame.id(), now);
+    }
+
+    /// Report the next liveness change as of `now`, if any.
+    ///
+    /// Call repeatedly until it returns `None` to drain all changes.
+    pub fn poll(&mut self, now: Duration) -> Option<WatchdogEvent> {
+        for watch in self.watches.iter_mut().flatten() {
+            let overdue = now > watch.deadline();
+            if overdue && !watch.reported_missing {
+                watch.reported_missing = true;
+                return Some(WatchdogEvent::Missing {
+                    id: watch.id,
+                    last_seen: watch.last_seen,
+                });
+            }
+            if !overdue && watch.reported_missing {
+                watch.reported_missing = false;
+                return Some(WatchdogEvent::Recovered { id: watch.id });
+            }
+        }
+        None
+    }
+
+    /// Earliest time at which a currently alive identifier becomes overdue.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.watches
+            .iter()
+            .flatten()
+            .filter(|watch| !watch.reported_missing)
+            .map(Watch::deadline)
+            .min()
+    }
+
+    fn find(&self, id: Id) -> Option<&Watch> {
+        self.watches.iter().flatten().find(|watch| watch.id == id)
+    }
+
+    fn wait_time(&self, now: Duration) -> Option<Duration> {
+        // Wake just after the deadline: an identifier is overdue only once it has passed.
+        self.next_deadline()
+            .map(|deadline| deadline.saturating_sub(now) + Duration::from_micros(1))
+    }
+
+    /// Receive the next frame from `io`, returning early with a liveness change.
+    ///
+    /// Received frames are observed before being returned; a resulting recovery is reported by the
+    /// following call. Driver errors that occur once a deadline has passed are treated as receive
+    /// timeouts.
+    pub fn recv<R, C>(&mut self, io: &mut R, clock: &C) -> Result<Monitored<R::Frame>, R::Error>
+    where
+        R: RxFrameIo,
+        R::Frame: Frame,
+        C: Clock,
+    {
+        loop {
+            let now = clock.now();
+            if let Some(event) = self.poll(now) {
+                return Ok(Monitored::Event(event));
+            }
+            let result = match self.wait_time(now) {
+                Some(wait) => io.recv_timeout(wait),
+                None => io.recv(),
+            };
+            match result {
+                Ok(frame) => {
+                    self.observe(&frame, clock.now());
+                    return Ok(Monitored::Frame(frame));
+                }
+                Err(_) if self.poll_pending(clock.now()) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Async version of [`Watchdog::recv`].
+    pub async fn recv_async<R, C>(
+        &mut self,
+        io: &mut R,
+        clock: &C,
+    ) -> Result<Monitored<R::Frame>, R::Error>
+    where
+        R: AsyncRxFrameIo,
+        R::Frame: Frame,
+        C: Clock,
+    {
+        loop {
+            let now = clock.now();
+            if let Some(event) = self.poll(now) {
+                return Ok(Monitored::Event(event));
+            }
+            let result = match self.wait_time(now) {
+                Some(wait) => io.recv_timeout(wait).await,
+                None => io.recv().await,
+            };
+            match result {
+                Ok(frame) => {
+                    self.observe(&frame, clock.now());
+                    return Ok(Monitored::Frame(frame));
+                }
+                Err(_) if self.poll_pending(clock.now()) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns `true` if [`Watchdog::poll`] would report a change at `now`.
+    fn poll_pending(&self, now: Duration) -> bool {
+        self.watches
+            .iter()
+            .flatten()
+            .any(|watch| (now > watch.deadline()) != watch.reported_missing)
+    }
+}
+
+/// Error returned by [`MonitoredRx::watch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchError<E> {
+    /// The watch was rejected.
+    Watchdog(WatchdogError),
+    /// The driver reported an error.
+    Io(E),
+}
+
+impl<E> fmt::Display for WatchError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Watchdog(_) => f.write_str("watch rejected"),
+            Self::Io(_) => f.write_str("driver error"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for WatchError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Watchdog(e) => Some(e),
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+/// Reception with liveness monitoring, done in software or by the driver.
+///
+/// Events follow the rules of [`Watchdog::recv`]: each identifier is reported missing once when
+/// it falls silent, and a frame that ends the silence is returned before its
+/// [`WatchdogEvent::Recovered`].
+pub trait MonitoredRx {
+    /// The CAN frame type.
+    type Frame;
+    /// Error returned by the driver implementation.
+    type Error;
+
+    /// Expect a frame with `id` at least every `max_interval`, starting now.
+    fn watch(&mut self, id: Id, max_interval: Duration) -> Result<(), WatchError<Self::Error>>;
+
+    /// Stop watching `id`. Returns `false` if it was not watched.
+    fn unwatch(&mut self, id: Id) -> Result<bool, Self::Error>;
+
+    /// Receive the next frame, returning early with a liveness change.
+    fn recv_monitored(&mut self) -> Result<Monitored<Self::Frame>, Self::Error>;
+}
+
+/// [`MonitoredRx`] in software: a [`Watchdog`] around an [`RxFrameIo`].
+#[derive(Debug)]
+pub struct SoftwareWatchdog<R, C, const N: usize> {
+    inner: R,
+    clock: C,
+    watchdog: Watchdog<N>,
+}
+
+impl<R, C, const N: usize> SoftwareWatchdog<R, C, N> {
+    /// Monitor frames received from `inner`, timed by `clock`.
+    pub fn new(inner: R, clock: C) -> Self {
+        Self {
+            inner,
+            clock,
+            watchdog: Watchdog::new(),
+        }
+    }
+
+    /// Borrow the watchdog.
+    pub fn watchdog(&self) -> &Watchdog<N> {
+        &self.watchdog
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R, C, const N: usize> MonitoredRx for SoftwareWatchdog<R, C, N>
+where
+    R: RxFrameIo,
+    R::Frame: Frame,
+    C: Clock,
+{
+    type Frame = R::Frame;
+    type Error = R::Error;
+
+    fn watch(&mut self, id: Id, max_interval: Duration) -> Result<(), WatchError<R::Error>> {
+        self.watchdog
+            .watch(id, max_interval, self.clock.now())
+            .map_err(WatchError::Watchdog)
+    }
+
+    fn unwatch(&mut self, id: Id) -> Result<bool, R::Error> {
+        Ok(self.watchdog.unwatch(id))
+    }
+
+    fn recv_monitored(&mut self) -> Result<Monitored<R::Frame>, R::Error> {
+        self.watchdog.recv(&mut self.inner, &self.clock)
+    }
+}
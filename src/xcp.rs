@@ -0,0 +1,342 @@
+//! XCP-on-CAN transport (ASAM MCD-1 XCP).
+//!
+//! XCP exchanges two kinds of packets: command transfer objects (CTOs: commands from the master,
+//! and responses, errors, events and service requests from the slave) and data transfer objects
+//! (DTOs: DAQ measurement and STIM stimulation data). On CAN each packet is one frame; the master
+//! sends on the CMD identifier and the slave answers on the RES identifier, with the first byte
+//! (the packet identifier) telling packet kinds apart.
+//!
+//! [`XcpTransport`] handles this framing and the command/response handshake, including both block
+//! transfer modes:
+//!
+//! - master block mode ([`XcpTransport::command_block`]): several commands (e.g. `DOWNLOAD` then
+//!   `DOWNLOAD_NEXT`) are sent back to back, separated by the slave's `MIN_ST`, and only the last
+//!   one is answered;
+//! - slave block mode ([`XcpTransport::command_collect`]): one command (e.g. `UPLOAD`) is answered
+//!   by several response packets whose payloads are concatenated.
+//!
+//! Interpreting command codes and memory layouts is left to the calibration tool on top.
+
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+use crate::{Clock, RxFrameIo, TxFrameIo};
+
+/// Packet identifier of a positive response.
+pub const PID_RES: u8 = 0xFF;
+/// Packet identifier of an error response.
+pub const PID_ERR: u8 = 0xFE;
+/// Packet identifier of an event.
+pub const PID_EV: u8 = 0xFD;
+/// Packet identifier of a service request.
+pub const PID_SERV: u8 = 0xFC;
+
+/// A packet received on the RES identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpPacket<'a> {
+    /// Positive response; the payload excludes the packet identifier.
+    Response(&'a [u8]),
+    /// Error response with its error code and any further bytes.
+    Error {
+        /// The XCP error code (e.g. `0x10`, `ERR_CMD_BUSY`).
+        code: u8,
+        /// Remaining bytes after the code.
+        data: &'a [u8],
+    },
+    /// Asynchronous event.
+    Event {
+        /// The event code.
+        code: u8,
+        /// Remaining bytes after the code.
+        data: &'a [u8],
+    },
+    /// Service request.
+    Service {
+        /// The service request code.
+        code: u8,
+        /// Remaining bytes after the code.
+        data: &'a [u8],
+    },
+    /// DAQ data; `pid` is the absolute or relative ODT number depending on the identification
+    /// field type configured on the slave.
+    Daq {
+        /// Packet identifier (0x00–0xFB).
+        pid: u8,
+        /// Remaining bytes after the packet identifier.
+        data: &'a [u8],
+    },
+}
+
+impl<'a> XcpPacket<'a> {
+    /// Classify the payload of a frame received on the RES identifier.
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let (&pid, rest) = bytes.split_first()?;
+        let coded = |make: fn(u8, &'a [u8]) -> Self| {
+            rest.split_first().map(|(&code, data)| make(code, data))
+        };
+        match pid {
+            PID_RES => Some(Self::Response(rest)),
+            PID_ERR => coded(|code, data| Self::Error { code, data }),
+            PID_EV => coded(|code, data| Self::Event { code, data }),
+            PID_SERV => coded(|code, data| Self::Service { code, data }),
+            pid => Some(Self::Daq { pid, data: rest }),
+        }
+    }
+}
+
+/// Error returned by [`XcpTransport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpError<E> {
+    /// The underlying driver reported an error.
+    Io(E),
+    /// The slave did not answer in time.
+    Timeout,
+    /// The slave answered with an error packet.
+    Slave {
+        /// The XCP error code.
+        code: u8,
+    },
+    /// A command was empty or longer than `MAX_CTO`.
+    InvalidCommand,
+    /// The response buffer is too small.
+    BufferTooSmall,
+}
+
+impl<E> fmt::Display for XcpError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("driver error"),
+            Self::Timeout => f.write_str("XCP slave did not respond in time"),
+            Self::Slave { code } => write!(f, "XCP slave error {code:#04x}"),
+            Self::InvalidCommand => f.write_str("XCP command empty or longer than MAX_CTO"),
+            Self::BufferTooSmall => f.write_str("response buffer too small"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for XcpError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration of an [`XcpTransport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XcpConfig {
+    /// Identifier the master sends commands (and STIM data) on.
+    pub cmd_id: Id,
+    /// Identifier the slave answers (and sends DAQ data) on.
+    pub res_id: Id,
+    /// Maximum CTO length; 8 on classic CAN.
+    pub max_cto: u8,
+    /// Pad outgoing frames to `max_cto` with this value (required by slaves with
+    /// `MAX_DLC_REQUIRED`); `None` sends minimal-length frames.
+    pub padding: Option<u8>,
+    /// Command timeout (XCP `T1`).
+    pub timeout: Duration,
+    /// Separation time between frames in master block mode (the slave's `MIN_ST`).
+    pub min_st: Duration,
+}
+
+impl XcpConfig {
+    /// Configuration with the given identifiers, 8-byte CTOs, no padding and the default
+    /// `T1` of 25 ms.
+    pub fn new(cmd_id: impl Into<Id>, res_id: impl Into<Id>) -> Self {
+        Self {
+            cmd_id: cmd_id.into(),
+            res_id: res_id.into(),
+            max_cto: 8,
+            padding: None,
+            timeout: Duration::from_millis(25),
+            min_st: Duration::ZERO,
+        }
+    }
+}
+
+/// XCP master-side transport over a CAN interface.
+#[derive(Debug)]
+pub struct XcpTransport<T, C> {
+    io: T,
+    clock: C,
+    config: XcpConfig,
+}
+
+impl<T, C> XcpTransport<T, C> {
+    /// Create a transport over `io`.
+    pub fn new(io: T, clock: C, config: XcpConfig) -> Self {
+        Self { io, clock, config }
+    }
+
+    /// The transport configuration.
+    pub fn config(&self) -> &XcpConfig {
+        &self.config
+    }
+
+    /// Mutable access to the configuration, e.g. to apply `MAX_CTO` and `MIN_ST` from the
+    /// slave's `CONNECT` and `GET_COMM_MODE_INFO` responses.
+    pub fn config_mut(&mut self) -> &mut XcpConfig {
+        &mut self.config
+    }
+
+    /// Borrow the wrapped interface.
+    pub fn inner(&self) -> &T {
+        &self.io
+    }
+
+    /// Mutably borrow the wrapped interface.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Unwrap, returning the wrapped interface.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, C, F, E> XcpTransport<T, C>
+where
+    T: TxFrameIo<Frame = F, Error = E> + RxFrameIo<Frame = F, Error = E>,
+    C: Clock,
+    F: Frame,
+{
+    /// Send one command packet (or STIM DTO) without waiting for a response.
+    pub fn send_packet(&mut self, packet: &[u8]) -> Result<(), XcpError<E>> {
+        let max = usize::from(self.config.max_cto);
+        if packet.is_empty() || packet.len() > max || max > 64 {
+            return Err(XcpError::InvalidCommand);
+        }
+        let mut bytes = [self.config.padding.unwrap_or(0); 64];
+        bytes[..packet.len()].copy_from_slice(packet);
+        let len = if self.config.padding.is_some() {
+            max
+        } else {
+            packet.len()
+        };
+        let frame = F::new(self.config.cmd_id, &bytes[..len]).ok_or(XcpError::InvalidCommand)?;
+        self.io
+            .send_timeout(&frame, self.config.timeout)
+            .map_err(XcpError::Io)
+    }
+
+    /// Receive the next frame on the RES identifier before `deadline`.
+    fn recv_res(&mut self, deadline: Duration) -> Result<F, XcpError<E>> {
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Err(XcpError::Timeout);
+            }
+            match self.io.recv_timeout(remaining) {
+                Ok(frame) if frame.id() == self.config.res_id && !frame.is_remote_frame() => {
+                    return Ok(frame);
+                }
+                Ok(_) => {}
+                Err(_) if self.clock.now() >= deadline => return Err(XcpError::Timeout),
+                Err(e) => return Err(XcpError::Io(e)),
+            }
+        }
+    }
+
+    /// Wait for the response to an outstanding command, copying its payload into `res`.
+    ///
+    /// Events, service requests and DAQ packets received meanwhile are passed to `other`.
+    fn await_response(
+        &mut self,
+        res: &mut [u8],
+        other: &mut impl FnMut(XcpPacket<'_>),
+    ) -> Result<usize, XcpError<E>> {
+        let deadline = self.clock.now() + self.config.timeout;
+        loop {
+            let frame = self.recv_res(deadline)?;
+            match XcpPacket::parse(frame.data()) {
+                Some(XcpPacket::Response(payload)) => {
+                    let target = res
+                        .get_mut(..payload.len())
+                        .ok_or(XcpError::BufferTooSmall)?;
+                    target.copy_from_slice(payload);
+                    return Ok(payload.len());
+                }
+                Some(XcpPacket::Error { code, .. }) => return Err(XcpError::Slave { code }),
+                Some(packet) => other(packet),
+                None => {}
+            }
+        }
+    }
+
+    /// Send `cmd` and return the length of the response payload copied into `res`.
+    ///
+    /// The payload excludes the `0xFF` packet identifier. Asynchronous packets received while
+    /// waiting are discarded; use [`XcpTransport::command_with`] to observe them.
+    pub fn command(&mut self, cmd: &[u8], res: &mut [u8]) -> Result<usize, XcpError<E>> {
+        self.command_with(cmd, res, |_| {})
+    }
+
+    /// Like [`XcpTransport::command`], passing events, service requests and DAQ packets received
+    /// while waiting to `other`.
+    pub fn command_with(
+        &mut self,
+        cmd: &[u8],
+        res: &mut [u8],
+        mut other: impl FnMut(XcpPacket<'_>),
+    ) -> Result<usize, XcpError<E>> {
+        self.send_packet(cmd)?;
+        self.await_response(res, &mut other)
+    }
+
+    /// Master block mode: send `cmds` back to back, `MIN_ST` apart, and return the response to
+    /// the last one.
+    pub fn command_block<'c>(
+        &mut self,
+        cmds: impl IntoIterator<Item = &'c [u8]>,
+        res: &mut [u8],
+    ) -> Result<usize, XcpError<E>> {
+        let mut cmds = cmds.into_iter().peekable();
+        while let Some(cmd) = cmds.next() {
+            self.send_packet(cmd)?;
+            if cmds.peek().is_some() {
+                self.clock.spin_until(self.clock.now() + self.config.min_st);
+            }
+        }
+        self.await_response(res, &mut |_| {})
+    }
+
+    /// Slave block mode: send `cmd` and concatenate response payloads into `out` until `len`
+    /// bytes have been received.
+    pub fn command_collect(
+        &mut self,
+        cmd: &[u8],
+        out: &mut [u8],
+        len: usize,
+    ) -> Result<(), XcpError<E>> {
+        if len > out.len() {
+            return Err(XcpError::BufferTooSmall);
+        }
+        self.send_packet(cmd)?;
+        let mut received = 0;
+        let mut chunk = [0u8; 64];
+        while received < len {
+            let n = self.await_response(&mut chunk, &mut |_| {})?;
+            let take = n.min(len - received);
+            out[received..received + take].copy_from_slice(&chunk[..take]);
+            received += take;
+        }
+        Ok(())
+    }
+
+    /// Receive the next packet on the RES identifier (e.g. DAQ data) and pass it to `handle`.
+    pub fn recv_packet<R>(
+        &mut self,
+        timeout: Duration,
+        handle: impl FnOnce(XcpPacket<'_>) -> R,
+    ) -> Result<Option<R>, XcpError<E>> {
+        let deadline = self.clock.now() + timeout;
+        let frame = self.recv_res(deadline)?;
+        Ok(XcpPacket::parse(frame.data()).map(handle))
+    }
+}